@@ -50,6 +50,24 @@ pub fn manifest(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStr
             pub views: Vec<Arc<View>>,
             #[serde(default)]
             pub data_source: Option<DataSource>,
+            #[serde(default)]
+            pub default_table_schema: Option<String>,
+            #[serde(default)]
+            pub constants: Vec<Arc<Constant>>,
+            #[serde(default)]
+            pub cross_metrics: Vec<Arc<CrossMetric>>,
+            #[serde(default)]
+            pub duplicate_alias_policy: Option<DuplicateAliasPolicy>,
+            #[serde(default)]
+            pub curated_views: Vec<Arc<CuratedView>>,
+            #[serde(default)]
+            pub multi_grain_measures: Vec<Arc<MultiGrainMeasure>>,
+            #[serde(default)]
+            pub default_namespace: Option<String>,
+            #[serde(default)]
+            pub period_over_periods: Vec<Arc<PeriodOverPeriod>>,
+            #[serde(default)]
+            pub default_aggregation: Option<DefaultAggregation>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -132,6 +150,32 @@ pub fn model(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream
             pub cached: bool,
             #[serde(default)]
             pub refresh_time: Option<String>,
+            #[serde(default)]
+            pub materialization: Option<Materialization>,
+            #[serde(default)]
+            pub extends: Option<String>,
+            #[serde(default)]
+            pub synonyms: Vec<String>,
+            #[serde(default)]
+            pub fallback_sources: Vec<String>,
+            #[serde(default)]
+            pub routing_hint: Option<String>,
+            #[serde(default)]
+            pub default_sort_key: Option<String>,
+            #[serde(default)]
+            pub latest: Option<Latest>,
+            #[serde(default, with = "bool_from_int")]
+            pub single_row: bool,
+            #[serde(default)]
+            pub partition_template: Option<PartitionTemplate>,
+            #[serde(default)]
+            pub base_filter: Option<String>,
+            #[serde(default)]
+            pub namespace: Option<String>,
+            #[serde(default)]
+            pub required_partition_filter: Option<String>,
+            #[serde(default)]
+            pub table_function: Option<TableFunction>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -153,7 +197,7 @@ pub fn column(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStrea
     let expanded = quote! {
         #python_binding
         #[serde_as]
-        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
         #[serde(rename_all = "camelCase")]
         pub struct Column {
             pub name: String,
@@ -171,6 +215,44 @@ pub fn column(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStrea
             pub is_hidden: bool,
             pub rls: Option<RowLevelSecurity>,
             pub cls: Option<ColumnLevelSecurity>,
+            #[serde(default)]
+            pub semi_additive: Option<SemiAdditive>,
+            #[serde(default)]
+            pub top_n: Option<u32>,
+            #[serde(default)]
+            pub bucket: Option<Bucket>,
+            #[serde(default)]
+            pub surrogate_key: Option<SurrogateKey>,
+            #[serde(default)]
+            pub pivot: Option<Pivot>,
+            #[serde(default)]
+            pub unnest: Option<String>,
+            #[serde(default, with = "bool_from_int")]
+            pub share_of_total: bool,
+            #[serde(default)]
+            pub synonyms: Vec<String>,
+            #[serde(default)]
+            pub empty_value: Option<String>,
+            #[serde(default)]
+            pub materialized_column: Option<String>,
+            #[serde(default, with = "bool_from_int")]
+            pub non_additive: bool,
+            #[serde(default)]
+            pub parent_scope: Option<String>,
+            #[serde(default, with = "bool_from_int")]
+            pub count_null_as_distinct: bool,
+            #[serde(default)]
+            pub weighted_average: Option<WeightedAverage>,
+            #[serde(default)]
+            pub count_if: Option<String>,
+            #[serde(default)]
+            pub properties: std::collections::BTreeMap<String, String>,
+            #[serde(default)]
+            pub relationship_filter: Option<RelationshipFilter>,
+            #[serde(default)]
+            pub allowed_roles: Vec<String>,
+            #[serde(default)]
+            pub default_aggregation: Option<DefaultAggregation>,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -192,13 +274,25 @@ pub fn relationship(python_binding: proc_macro::TokenStream) -> proc_macro::Toke
     let expanded = quote! {
         #python_binding
         #[serde_as]
-        #[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq)]
+        #[derive(Serialize, Deserialize, Debug, Hash, PartialEq, Eq, Clone)]
         #[serde(rename_all = "camelCase")]
         pub struct Relationship {
             pub name: String,
             pub models: Vec<String>,
             pub join_type: JoinType,
             pub condition: String,
+            #[serde(default)]
+            pub lookup: Option<Lookup>,
+            #[serde(default)]
+            pub join_hint: Option<JoinHint>,
+            #[serde(default, with = "bool_from_int")]
+            pub distinct: bool,
+            #[serde(default)]
+            pub as_of: Option<AsOf>,
+            #[serde(default)]
+            pub scope: Option<String>,
+            #[serde(default, with = "bool_from_int")]
+            pub null_safe: bool,
         }
     };
     proc_macro::TokenStream::from(expanded)
@@ -357,7 +451,7 @@ pub fn row_level_security(python_binding: proc_macro::TokenStream) -> proc_macro
     };
     let expanded = quote! {
         #python_binding
-        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
         pub struct RowLevelSecurity {
             pub name: String,
             pub operator: RowLevelOperator,
@@ -408,7 +502,7 @@ pub fn column_level_security(python_binding: proc_macro::TokenStream) -> proc_ma
     };
     let expanded = quote! {
         #python_binding
-        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash)]
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
         pub struct ColumnLevelSecurity {
             pub name: String,
             pub operator: ColumnLevelOperator,
@@ -456,7 +550,7 @@ pub fn normalized_expr(python_binding: proc_macro::TokenStream) -> proc_macro::T
     };
     let expanded = quote! {
         #python_binding
-        #[derive(SerializeDisplay, DeserializeFromStr, Debug, PartialEq, Eq, Hash)]
+        #[derive(SerializeDisplay, DeserializeFromStr, Debug, PartialEq, Eq, Hash, Clone)]
         pub struct NormalizedExpr {
             pub value: String,
             #[serde_with(alias = "type")]
@@ -487,3 +581,603 @@ pub fn normalized_expr_type(python_binding: proc_macro::TokenStream) -> proc_mac
     };
     proc_macro::TokenStream::from(expanded)
 }
+
+/// This macro generates a struct for `SemiAdditive`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn semi_additive(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct SemiAdditive {
+            pub time_column: String,
+            pub entity_keys: Vec<String>,
+            pub aggregation: NonAdditiveAggregation,
+            #[serde(default)]
+            pub time_grain: Option<TimeUnit>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Bucket`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn bucket(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Bucket {
+            pub buckets: u32,
+            pub order_by: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `SurrogateKey`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn surrogate_key(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct SurrogateKey {
+            pub natural_keys: Vec<String>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Materialization`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn materialization(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Materialization {
+            pub target: String,
+            pub partition_column: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Pivot`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn pivot(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Pivot {
+            pub dimension: String,
+            pub values: Vec<String>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `AsOf`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn as_of(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct AsOf {
+            pub dimension_time: String,
+            pub fact_time: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Latest`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn latest(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Latest {
+            pub keys: Vec<String>,
+            pub order_by: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `PartitionTemplate`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn partition_template(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct PartitionTemplate {
+            pub template: String,
+            pub column: String,
+            pub start: String,
+            pub end: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `WeightedAverage`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn weighted_average(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct WeightedAverage {
+            pub value: String,
+            pub weight: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `RelationshipFilter`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn relationship_filter(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct RelationshipFilter {
+            pub relationship: String,
+            #[serde(default)]
+            pub predicate: Option<String>,
+            #[serde(default, with = "bool_from_int")]
+            pub negated: bool,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Constant`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn constant(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Constant {
+            pub name: String,
+            pub r#type: String,
+            pub value: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `CrossMetric`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn cross_metric(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct CrossMetric {
+            pub name: String,
+            pub dimension: String,
+            pub measures: Vec<CrossMeasure>,
+            pub expression: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `CrossMeasure`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn cross_measure(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct CrossMeasure {
+            pub name: String,
+            pub model: String,
+            pub dimension: String,
+            pub expression: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `CuratedView`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn curated_view(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct CuratedView {
+            pub name: String,
+            pub base_model: String,
+            #[serde(default)]
+            pub dimensions: Vec<ViewField>,
+            #[serde(default)]
+            pub measures: Vec<ViewField>,
+            #[serde(default)]
+            pub filters: Vec<String>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `MultiGrainMeasure`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn multi_grain_measure(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct MultiGrainMeasure {
+            pub name: String,
+            pub model: String,
+            #[serde(default)]
+            pub dimensions: Vec<ViewField>,
+            #[serde(default)]
+            pub parent_dimensions: Vec<String>,
+            pub measure: ViewField,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `PeriodOverPeriod`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn period_over_period(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct PeriodOverPeriod {
+            pub name: String,
+            pub model: String,
+            pub time_dimension: String,
+            pub grain: TimeUnit,
+            #[serde(default)]
+            pub offset: Option<u32>,
+            pub measure: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `TableFunction`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn table_function(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct TableFunction {
+            pub name: String,
+            #[serde(default)]
+            pub args: Vec<String>,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `ViewField`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn view_field(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct ViewField {
+            pub name: String,
+            pub expression: String,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates a struct for `Lookup`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn lookup(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone)]
+        #[serde(rename_all = "camelCase")]
+        pub struct Lookup {
+            pub order_by: String,
+            #[serde(default, with = "bool_from_int")]
+            pub descending: bool,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `NonAdditiveAggregation`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn non_additive_aggregation(
+    python_binding: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        pub enum NonAdditiveAggregation {
+            #[serde(alias = "last")]
+            Last,
+            #[serde(alias = "first")]
+            First,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `DefaultAggregation`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn default_aggregation(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        #[serde(rename_all = "UPPERCASE")]
+        pub enum DefaultAggregation {
+            #[serde(alias = "sum")]
+            Sum,
+            #[serde(alias = "avg")]
+            Avg,
+            #[serde(alias = "min")]
+            Min,
+            #[serde(alias = "max")]
+            Max,
+            #[serde(alias = "count")]
+            Count,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `JoinHint`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn join_hint(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Hash, Clone, Copy)]
+        #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+        pub enum JoinHint {
+            #[serde(alias = "broadcast")]
+            Broadcast,
+            #[serde(alias = "shuffle")]
+            Shuffle,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
+
+/// This macro generates an enum for `DuplicateAliasPolicy`
+/// If python_binding is true, it will generate a `pyclass` attribute
+#[proc_macro]
+pub fn duplicate_alias_policy(python_binding: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(python_binding as LitBool);
+    let python_binding = if input.value {
+        quote! {
+            #[pyclass(eq, eq_int)]
+        }
+    } else {
+        quote! {}
+    };
+
+    let expanded = quote! {
+        #python_binding
+        #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+        #[serde(rename_all = "UPPERCASE")]
+        pub enum DuplicateAliasPolicy {
+            #[default]
+            #[serde(alias = "error")]
+            Error,
+            #[serde(alias = "suffix")]
+            Suffix,
+        }
+    };
+    proc_macro::TokenStream::from(expanded)
+}
@@ -23,7 +23,11 @@ use crate::mdl::manifest::{
     Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeGrain, TimeUnit, View,
 };
 use crate::mdl::{
-    ColumnLevelOperator, ColumnLevelSecurity, NormalizedExpr, RowLevelOperator, RowLevelSecurity,
+    AsOf, Bucket, ColumnLevelOperator, ColumnLevelSecurity, Constant, CrossMeasure, CrossMetric,
+    CuratedView, DefaultAggregation, DuplicateAliasPolicy, JoinHint, Latest, Lookup,
+    Materialization, MultiGrainMeasure, NonAdditiveAggregation, NormalizedExpr, PartitionTemplate,
+    PeriodOverPeriod, Pivot, RelationshipFilter, RowLevelOperator, RowLevelSecurity, SemiAdditive,
+    SurrogateKey, TableFunction, ViewField, WeightedAverage,
 };
 use std::sync::Arc;
 
@@ -49,6 +53,15 @@ impl ManifestBuilder {
                 metrics: vec![],
                 views: vec![],
                 data_source: None,
+                default_table_schema: None,
+                constants: vec![],
+                cross_metrics: vec![],
+                duplicate_alias_policy: None,
+                curated_views: vec![],
+                multi_grain_measures: vec![],
+                default_namespace: None,
+                period_over_periods: vec![],
+                default_aggregation: None,
             },
         }
     }
@@ -88,6 +101,55 @@ impl ManifestBuilder {
         self
     }
 
+    pub fn default_table_schema(mut self, schema: &str) -> Self {
+        self.manifest.default_table_schema = Some(schema.to_string());
+        self
+    }
+
+    pub fn default_namespace(mut self, namespace: &str) -> Self {
+        self.manifest.default_namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn duplicate_alias_policy(mut self, policy: DuplicateAliasPolicy) -> Self {
+        self.manifest.duplicate_alias_policy = Some(policy);
+        self
+    }
+
+    pub fn default_aggregation(mut self, aggregation: DefaultAggregation) -> Self {
+        self.manifest.default_aggregation = Some(aggregation);
+        self
+    }
+
+    pub fn constant(mut self, name: &str, r#type: &str, value: &str) -> Self {
+        self.manifest.constants.push(Arc::new(Constant {
+            name: name.to_string(),
+            r#type: r#type.to_string(),
+            value: value.to_string(),
+        }));
+        self
+    }
+
+    pub fn cross_metric(mut self, cross_metric: Arc<CrossMetric>) -> Self {
+        self.manifest.cross_metrics.push(cross_metric);
+        self
+    }
+
+    pub fn curated_view(mut self, curated_view: Arc<CuratedView>) -> Self {
+        self.manifest.curated_views.push(curated_view);
+        self
+    }
+
+    pub fn multi_grain_measure(mut self, multi_grain_measure: Arc<MultiGrainMeasure>) -> Self {
+        self.manifest.multi_grain_measures.push(multi_grain_measure);
+        self
+    }
+
+    pub fn period_over_period(mut self, period_over_period: Arc<PeriodOverPeriod>) -> Self {
+        self.manifest.period_over_periods.push(period_over_period);
+        self
+    }
+
     pub fn build(self) -> Manifest {
         self.manifest
     }
@@ -109,6 +171,19 @@ impl ModelBuilder {
                 primary_key: None,
                 cached: false,
                 refresh_time: None,
+                materialization: None,
+                extends: None,
+                synonyms: vec![],
+                fallback_sources: vec![],
+                routing_hint: None,
+                default_sort_key: None,
+                latest: None,
+                single_row: false,
+                partition_template: None,
+                base_filter: None,
+                namespace: None,
+                required_partition_filter: None,
+                table_function: None,
             },
         }
     }
@@ -148,6 +223,91 @@ impl ModelBuilder {
         self
     }
 
+    pub fn synonyms(mut self, synonyms: &[&str]) -> Self {
+        self.model.synonyms = synonyms.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn extends(mut self, base_model: &str) -> Self {
+        self.model.extends = Some(base_model.to_string());
+        self
+    }
+
+    pub fn fallback_sources(mut self, fallback_sources: &[&str]) -> Self {
+        self.model.fallback_sources = fallback_sources.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn routing_hint(mut self, routing_hint: &str) -> Self {
+        self.model.routing_hint = Some(routing_hint.to_string());
+        self
+    }
+
+    pub fn default_sort_key(mut self, column: &str) -> Self {
+        self.model.default_sort_key = Some(column.to_string());
+        self
+    }
+
+    pub fn latest(mut self, keys: &[&str], order_by: &str) -> Self {
+        self.model.latest = Some(Latest {
+            keys: keys.iter().map(|k| k.to_string()).collect(),
+            order_by: order_by.to_string(),
+        });
+        self
+    }
+
+    pub fn single_row(mut self, single_row: bool) -> Self {
+        self.model.single_row = single_row;
+        self
+    }
+
+    pub fn partition_template(
+        mut self,
+        template: &str,
+        column: &str,
+        start: &str,
+        end: &str,
+    ) -> Self {
+        self.model.partition_template = Some(PartitionTemplate {
+            template: template.to_string(),
+            column: column.to_string(),
+            start: start.to_string(),
+            end: end.to_string(),
+        });
+        self
+    }
+
+    pub fn namespace(mut self, namespace: &str) -> Self {
+        self.model.namespace = Some(namespace.to_string());
+        self
+    }
+
+    pub fn base_filter(mut self, filter: &str) -> Self {
+        self.model.base_filter = Some(filter.to_string());
+        self
+    }
+
+    pub fn required_partition_filter(mut self, column: &str) -> Self {
+        self.model.required_partition_filter = Some(column.to_string());
+        self
+    }
+
+    pub fn table_function(mut self, name: &str, args: &[&str]) -> Self {
+        self.model.table_function = Some(TableFunction {
+            name: name.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn materialization(mut self, target: &str, partition_column: &str) -> Self {
+        self.model.materialization = Some(Materialization {
+            target: target.to_string(),
+            partition_column: partition_column.to_string(),
+        });
+        self
+    }
+
     pub fn build(self) -> Arc<Model> {
         Arc::new(self.model)
     }
@@ -170,6 +330,25 @@ impl ColumnBuilder {
                 expression: None,
                 rls: None,
                 cls: None,
+                semi_additive: None,
+                top_n: None,
+                bucket: None,
+                surrogate_key: None,
+                pivot: None,
+                unnest: None,
+                share_of_total: false,
+                synonyms: vec![],
+                empty_value: None,
+                materialized_column: None,
+                non_additive: false,
+                parent_scope: None,
+                count_null_as_distinct: false,
+                weighted_average: None,
+                count_if: None,
+                properties: Default::default(),
+                relationship_filter: None,
+                allowed_roles: vec![],
+                default_aggregation: None,
             },
         }
     }
@@ -228,6 +407,135 @@ impl ColumnBuilder {
         });
         self
     }
+
+    pub fn semi_additive(
+        mut self,
+        time_column: &str,
+        entity_keys: &[&str],
+        aggregation: NonAdditiveAggregation,
+        time_grain: Option<TimeUnit>,
+    ) -> Self {
+        self.column.semi_additive = Some(SemiAdditive {
+            time_column: time_column.to_string(),
+            entity_keys: entity_keys.iter().map(|key| key.to_string()).collect(),
+            aggregation,
+            time_grain,
+        });
+        self
+    }
+
+    pub fn top_n(mut self, limit: u32) -> Self {
+        self.column.top_n = Some(limit);
+        self
+    }
+
+    pub fn bucket(mut self, buckets: u32, order_by: &str) -> Self {
+        self.column.bucket = Some(Bucket {
+            buckets,
+            order_by: order_by.to_string(),
+        });
+        self
+    }
+
+    pub fn surrogate_key(mut self, natural_keys: &[&str]) -> Self {
+        self.column.surrogate_key = Some(SurrogateKey {
+            natural_keys: natural_keys.iter().map(|key| key.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn pivot(mut self, dimension: &str, values: &[&str]) -> Self {
+        self.column.pivot = Some(Pivot {
+            dimension: dimension.to_string(),
+            values: values.iter().map(|value| value.to_string()).collect(),
+        });
+        self
+    }
+
+    pub fn unnest(mut self, array_column: &str) -> Self {
+        self.column.unnest = Some(array_column.to_string());
+        self
+    }
+
+    pub fn synonyms(mut self, synonyms: &[&str]) -> Self {
+        self.column.synonyms = synonyms.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn share_of_total(mut self, share_of_total: bool) -> Self {
+        self.column.share_of_total = share_of_total;
+        self
+    }
+
+    pub fn empty_value(mut self, empty_value: &str) -> Self {
+        self.column.empty_value = Some(empty_value.to_string());
+        self
+    }
+
+    pub fn materialized_column(mut self, materialized_column: &str) -> Self {
+        self.column.materialized_column = Some(materialized_column.to_string());
+        self
+    }
+
+    pub fn non_additive(mut self, non_additive: bool) -> Self {
+        self.column.non_additive = non_additive;
+        self
+    }
+
+    pub fn parent_scope(mut self, relationship_column: &str) -> Self {
+        self.column.parent_scope = Some(relationship_column.to_string());
+        self
+    }
+
+    pub fn count_null_as_distinct(mut self, count_null_as_distinct: bool) -> Self {
+        self.column.count_null_as_distinct = count_null_as_distinct;
+        self
+    }
+
+    pub fn weighted_average(mut self, value: &str, weight: &str) -> Self {
+        self.column.weighted_average = Some(WeightedAverage {
+            value: value.to_string(),
+            weight: weight.to_string(),
+        });
+        self
+    }
+
+    pub fn count_if(mut self, condition: &str) -> Self {
+        self.column.count_if = Some(condition.to_string());
+        self
+    }
+
+    pub fn relationship_filter(
+        mut self,
+        relationship_column: &str,
+        predicate: Option<&str>,
+        negated: bool,
+    ) -> Self {
+        self.column.relationship_filter = Some(RelationshipFilter {
+            relationship: relationship_column.to_string(),
+            predicate: predicate.map(String::from),
+            negated,
+        });
+        self
+    }
+
+    pub fn allowed_roles(mut self, roles: &[&str]) -> Self {
+        self.column.allowed_roles = roles.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    pub fn default_aggregation(mut self, aggregation: DefaultAggregation) -> Self {
+        self.column.default_aggregation = Some(aggregation);
+        self
+    }
+
+    pub fn property(mut self, key: &str, value: &str) -> Self {
+        self.column
+            .properties
+            .insert(key.to_string(), value.to_string());
+        self
+    }
+
     pub fn build(self) -> Arc<Column> {
         Arc::new(self.column)
     }
@@ -245,6 +553,12 @@ impl RelationshipBuilder {
                 models: vec![],
                 join_type: JoinType::OneToOne,
                 condition: "".to_string(),
+                lookup: None,
+                join_hint: None,
+                distinct: false,
+                as_of: None,
+                scope: None,
+                null_safe: false,
             },
         }
     }
@@ -264,6 +578,42 @@ impl RelationshipBuilder {
         self
     }
 
+    pub fn lookup(mut self, order_by: &str, descending: bool) -> Self {
+        self.relationship.lookup = Some(Lookup {
+            order_by: order_by.to_string(),
+            descending,
+        });
+        self
+    }
+
+    pub fn join_hint(mut self, join_hint: JoinHint) -> Self {
+        self.relationship.join_hint = Some(join_hint);
+        self
+    }
+
+    pub fn distinct(mut self, distinct: bool) -> Self {
+        self.relationship.distinct = distinct;
+        self
+    }
+
+    pub fn as_of(mut self, dimension_time: &str, fact_time: &str) -> Self {
+        self.relationship.as_of = Some(AsOf {
+            dimension_time: dimension_time.to_string(),
+            fact_time: fact_time.to_string(),
+        });
+        self
+    }
+
+    pub fn scope(mut self, predicate: &str) -> Self {
+        self.relationship.scope = Some(predicate.to_string());
+        self
+    }
+
+    pub fn null_safe(mut self, null_safe: bool) -> Self {
+        self.relationship.null_safe = null_safe;
+        self
+    }
+
     pub fn build(self) -> Arc<Relationship> {
         Arc::new(self.relationship)
     }
@@ -348,6 +698,191 @@ impl TimeGrainBuilder {
     }
 }
 
+pub struct CrossMetricBuilder {
+    pub cross_metric: CrossMetric,
+}
+
+impl CrossMetricBuilder {
+    pub fn new(name: &str) -> Self {
+        Self {
+            cross_metric: CrossMetric {
+                name: name.to_string(),
+                dimension: "".to_string(),
+                measures: vec![],
+                expression: "".to_string(),
+            },
+        }
+    }
+
+    /// The name of the dimension shared by the measures
+    pub fn dimension(mut self, dimension: &str) -> Self {
+        self.cross_metric.dimension = dimension.to_string();
+        self
+    }
+
+    /// Add the measure aggregated by the expression over the model, grouped by the dimension of the
+    /// model aligned with the shared one
+    pub fn measure(mut self, name: &str, model: &str, dimension: &str, expression: &str) -> Self {
+        self.cross_metric.measures.push(CrossMeasure {
+            name: name.to_string(),
+            model: model.to_string(),
+            dimension: dimension.to_string(),
+            expression: expression.to_string(),
+        });
+        self
+    }
+
+    /// The expression combining the measures by their names
+    pub fn expression(mut self, expression: &str) -> Self {
+        self.cross_metric.expression = expression.to_string();
+        self
+    }
+
+    pub fn build(self) -> Arc<CrossMetric> {
+        Arc::new(self.cross_metric)
+    }
+}
+
+pub struct PeriodOverPeriodBuilder {
+    pub period_over_period: PeriodOverPeriod,
+}
+
+impl PeriodOverPeriodBuilder {
+    pub fn new(name: &str, model: &str) -> Self {
+        Self {
+            period_over_period: PeriodOverPeriod {
+                name: name.to_string(),
+                model: model.to_string(),
+                time_dimension: "".to_string(),
+                grain: TimeUnit::Month,
+                offset: None,
+                measure: "".to_string(),
+            },
+        }
+    }
+
+    /// The time dimension of the model truncated to the grain, e.g. `o_orderdate`
+    pub fn time_dimension(mut self, time_dimension: &str, grain: TimeUnit) -> Self {
+        self.period_over_period.time_dimension = time_dimension.to_string();
+        self.period_over_period.grain = grain;
+        self
+    }
+
+    /// The number of grains back to the prior period, e.g. 12 months for the year over year. It's
+    /// 1 by default.
+    pub fn offset(mut self, offset: u32) -> Self {
+        self.period_over_period.offset = Some(offset);
+        self
+    }
+
+    /// The base measure aggregated over the model, e.g. `sum(o_totalprice)`
+    pub fn measure(mut self, measure: &str) -> Self {
+        self.period_over_period.measure = measure.to_string();
+        self
+    }
+
+    pub fn build(self) -> Arc<PeriodOverPeriod> {
+        Arc::new(self.period_over_period)
+    }
+}
+
+pub struct CuratedViewBuilder {
+    pub curated_view: CuratedView,
+}
+
+impl CuratedViewBuilder {
+    pub fn new(name: &str, base_model: &str) -> Self {
+        Self {
+            curated_view: CuratedView {
+                name: name.to_string(),
+                base_model: base_model.to_string(),
+                dimensions: vec![],
+                measures: vec![],
+                filters: vec![],
+            },
+        }
+    }
+
+    /// Add the dimension grouping the measures, e.g. `customer.c_name` of a related model
+    pub fn dimension(mut self, name: &str, expression: &str) -> Self {
+        self.curated_view.dimensions.push(ViewField {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        });
+        self
+    }
+
+    /// Add the measure aggregated by the expression, e.g. `sum(orders.o_totalprice)`
+    pub fn measure(mut self, name: &str, expression: &str) -> Self {
+        self.curated_view.measures.push(ViewField {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        });
+        self
+    }
+
+    /// Add the filter applied to the rows before they're aggregated
+    pub fn filter(mut self, filter: &str) -> Self {
+        self.curated_view.filters.push(filter.to_string());
+        self
+    }
+
+    pub fn build(self) -> Arc<CuratedView> {
+        Arc::new(self.curated_view)
+    }
+}
+
+pub struct MultiGrainMeasureBuilder {
+    pub multi_grain_measure: MultiGrainMeasure,
+}
+
+impl MultiGrainMeasureBuilder {
+    pub fn new(name: &str, model: &str) -> Self {
+        Self {
+            multi_grain_measure: MultiGrainMeasure {
+                name: name.to_string(),
+                model: model.to_string(),
+                dimensions: vec![],
+                parent_dimensions: vec![],
+                measure: ViewField {
+                    name: "".to_string(),
+                    expression: "".to_string(),
+                },
+            },
+        }
+    }
+
+    /// Add the dimension of the detail grain
+    pub fn dimension(mut self, name: &str, expression: &str) -> Self {
+        self.multi_grain_measure.dimensions.push(ViewField {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        });
+        self
+    }
+
+    /// Add the dimension kept by the parent grain. It should be one of the dimensions.
+    pub fn parent_dimension(mut self, name: &str) -> Self {
+        self.multi_grain_measure
+            .parent_dimensions
+            .push(name.to_string());
+        self
+    }
+
+    /// Set the measure aggregated at both grains, e.g. `sum(o_totalprice)`
+    pub fn measure(mut self, name: &str, expression: &str) -> Self {
+        self.multi_grain_measure.measure = ViewField {
+            name: name.to_string(),
+            expression: expression.to_string(),
+        };
+        self
+    }
+
+    pub fn build(self) -> Arc<MultiGrainMeasure> {
+        Arc::new(self.multi_grain_measure)
+    }
+}
+
 pub struct ViewBuilder {
     pub view: View,
 }
@@ -375,14 +910,18 @@ impl ViewBuilder {
 #[cfg(test)]
 mod test {
     use crate::mdl::builder::{
-        ColumnBuilder, ManifestBuilder, MetricBuilder, ModelBuilder, RelationshipBuilder,
+        ColumnBuilder, CrossMetricBuilder, CuratedViewBuilder, ManifestBuilder, MetricBuilder,
+        ModelBuilder, MultiGrainMeasureBuilder, PeriodOverPeriodBuilder, RelationshipBuilder,
         TimeGrainBuilder, ViewBuilder,
     };
     use crate::mdl::manifest::DataSource::MySQL;
     use crate::mdl::manifest::{
         Column, DataSource, JoinType, Manifest, Metric, Model, Relationship, TimeUnit, View,
     };
-    use crate::mdl::{ColumnLevelOperator, RowLevelOperator};
+    use crate::mdl::{
+        ColumnLevelOperator, DefaultAggregation, DuplicateAliasPolicy, JoinHint,
+        NonAdditiveAggregation, RowLevelOperator,
+    };
     use std::fs;
     use std::path::PathBuf;
     use std::sync::Arc;
@@ -397,6 +936,29 @@ mod test {
             .expression("test")
             .row_level_security("SESSION_STATUS", RowLevelOperator::Equals)
             .column_level_security("SESSION_LEVEL", ColumnLevelOperator::Equals, "'NORMAL'")
+            .semi_additive(
+                "snapshot_date",
+                &["account"],
+                NonAdditiveAggregation::Last,
+                Some(TimeUnit::Month),
+            )
+            .top_n(10)
+            .bucket(10, "spend")
+            .surrogate_key(&["c_custkey", "c_name"])
+            .pivot("orders.o_month", &["2024-01", "2024-02"])
+            .unnest("tags")
+            .share_of_total(true)
+            .synonyms(&["amount"])
+            .empty_value("0")
+            .materialized_column("total_amount")
+            .non_additive(true)
+            .parent_scope("orders")
+            .count_null_as_distinct(true)
+            .weighted_average("orders.o_price", "orders.o_quantity")
+            .count_if("orders.o_status = 'paid'")
+            .relationship_filter("orders", Some("orders.o_status = 'F'"), true)
+            .allowed_roles(&["finance"])
+            .default_aggregation(DefaultAggregation::Max)
             .build();
 
         let json_str = serde_json::to_string(&expected).unwrap();
@@ -404,6 +966,28 @@ mod test {
         assert_eq!(actual, expected)
     }
 
+    #[test]
+    fn test_semi_additive_deserialize() {
+        let json = r#"
+        {
+            "name": "balance",
+            "type": "integer",
+            "semiAdditive": {
+                "timeColumn": "snapshot_date",
+                "entityKeys": ["account"],
+                "aggregation": "last"
+            }
+        }
+        "#;
+
+        let actual: Arc<Column> = serde_json::from_str(json).unwrap();
+        let semi_additive = actual.semi_additive().unwrap();
+        assert_eq!(semi_additive.time_column, "snapshot_date");
+        assert_eq!(semi_additive.entity_keys, vec!["account"]);
+        assert_eq!(semi_additive.aggregation, NonAdditiveAggregation::Last);
+        assert!(semi_additive.time_grain.is_none());
+    }
+
     #[test]
     fn test_expression_empty_as_none() {
         let expected = ColumnBuilder::new("id", "integer").expression("").build();
@@ -440,6 +1024,43 @@ mod test {
         let actual: Arc<Column> = serde_json::from_str(json).unwrap();
         assert!(actual.is_calculated);
         assert!(!actual.not_null);
+
+        let json = r#"
+        {
+            "name": "open_orders",
+            "type": "integer",
+            "relationshipFilter": {
+                "relationship": "orders",
+                "negated": 1
+            }
+        }
+        "#;
+
+        let actual: Arc<Column> = serde_json::from_str(json).unwrap();
+        assert!(actual
+            .relationship_filter
+            .as_ref()
+            .is_some_and(|filter| filter.negated));
+
+        let json = r#"
+        {
+            "name": "orders_customer",
+            "models": ["orders", "customer"],
+            "joinType": "MANY_TO_ONE",
+            "condition": "orders.o_custkey = customer.c_custkey",
+            "distinct": 1,
+            "nullSafe": 0,
+            "lookup": {
+                "orderBy": "orders.o_orderdate",
+                "descending": 1
+            }
+        }
+        "#;
+
+        let actual: Arc<Relationship> = serde_json::from_str(json).unwrap();
+        assert!(actual.distinct);
+        assert!(!actual.null_safe);
+        assert!(actual.lookup().is_some_and(|lookup| lookup.descending));
     }
 
     #[test]
@@ -452,6 +1073,18 @@ mod test {
             .primary_key("id")
             .cached(true)
             .refresh_time("1h")
+            .materialization("mat.test", "id")
+            .extends("base")
+            .synonyms(&["sample"])
+            .fallback_sources(&["test_backup"])
+            .routing_hint("replica")
+            .default_sort_key("id")
+            .latest(&["id"], "updated_at")
+            .single_row(true)
+            .partition_template("test_{YYYY}{MM}", "created_at", "2024-01-01", "2024-12-31")
+            .base_filter("deleted_at IS NULL")
+            .required_partition_filter("created_at")
+            .table_function("read_json", &["$url", "true"])
             .build();
 
         let json_str = serde_json::to_string(&model).unwrap();
@@ -490,6 +1123,12 @@ mod test {
             .model("testB")
             .join_type(JoinType::OneToMany)
             .condition("test")
+            .join_hint(JoinHint::Broadcast)
+            .distinct(true)
+            .as_of("customer.updated_at", "orders.order_date")
+            .scope("orders.region = $region")
+            .null_safe(true)
+            .lookup("testB.updated_at", true)
             .build();
 
         let json_str = serde_json::to_string(&expected).unwrap();
@@ -604,6 +1243,40 @@ mod test {
             .metric(metric)
             .view(view)
             .data_source(DataSource::Datafusion)
+            .default_table_schema("tpch")
+            .duplicate_alias_policy(DuplicateAliasPolicy::Suffix)
+            .default_aggregation(DefaultAggregation::Sum)
+            .constant("tax_rate", "double", "0.05")
+            .cross_metric(
+                CrossMetricBuilder::new("spend_per_customer")
+                    .dimension("day")
+                    .measure("spend", "marketing", "m_date", "sum(m_spend)")
+                    .measure("new_customers", "signups", "s_date", "count(*)")
+                    .expression("spend / nullif(new_customers, 0)")
+                    .build(),
+            )
+            .curated_view(
+                CuratedViewBuilder::new("customer_revenue", "orders")
+                    .dimension("customer_name", "customer.c_name")
+                    .measure("revenue", "sum(orders.o_totalprice)")
+                    .filter("orders.o_orderstatus = 'F'")
+                    .build(),
+            )
+            .multi_grain_measure(
+                MultiGrainMeasureBuilder::new("region_revenue_share", "orders")
+                    .dimension("region", "o_region")
+                    .dimension("status", "o_orderstatus")
+                    .parent_dimension("region")
+                    .measure("revenue", "sum(o_totalprice)")
+                    .build(),
+            )
+            .period_over_period(
+                PeriodOverPeriodBuilder::new("revenue_yoy", "orders")
+                    .time_dimension("o_orderdate", TimeUnit::Month)
+                    .offset(12)
+                    .measure("sum(o_totalprice)")
+                    .build(),
+            )
             .build();
 
         let json_str = serde_json::to_string(&expected).unwrap();
@@ -636,6 +1309,8 @@ mod test {
                     .column(
                         ColumnBuilder::new("orders", "orders")
                             .relationship("CustomerOrders")
+                            .property("description", "This is a customer orders relationship")
+                            .property("maintainer", "test")
                             .build(),
                     )
                     .primary_key("c_custkey")
@@ -17,6 +17,7 @@
  * under the License.
  */
 use std::fmt::Display;
+use std::future::Future;
 use std::sync::Arc;
 
 #[cfg(not(feature = "python-binding"))]
@@ -24,9 +25,13 @@ mod manifest_impl {
     use crate::mdl::manifest::bool_from_int;
     use crate::mdl::manifest::table_reference;
     use manifest_macro::{
-        column, column_level_operator, column_level_security, data_source, join_type, manifest,
-        metric, model, normalized_expr, normalized_expr_type, relationship, row_level_operator,
-        row_level_security, time_grain, time_unit, view,
+        as_of, bucket, column, column_level_operator, column_level_security, constant,
+        cross_measure, cross_metric, curated_view, data_source, default_aggregation,
+        duplicate_alias_policy, join_hint, join_type, latest, lookup, manifest, materialization,
+        metric, model, multi_grain_measure, non_additive_aggregation, normalized_expr,
+        normalized_expr_type, partition_template, period_over_period, pivot, relationship,
+        relationship_filter, row_level_operator, row_level_security, semi_additive, surrogate_key,
+        table_function, time_grain, time_unit, view, view_field, weighted_average,
     };
     use serde::{Deserialize, Serialize};
     use serde_with::serde_as;
@@ -36,6 +41,8 @@ mod manifest_impl {
     use std::sync::Arc;
     manifest!(false);
     data_source!(false);
+    duplicate_alias_policy!(false);
+    default_aggregation!(false);
     model!(false);
     column!(false);
     relationship!(false);
@@ -50,6 +57,27 @@ mod manifest_impl {
     normalized_expr!(false);
     normalized_expr_type!(false);
     column_level_operator!(false);
+    semi_additive!(false);
+    bucket!(false);
+    surrogate_key!(false);
+    pivot!(false);
+    materialization!(false);
+    lookup!(false);
+    non_additive_aggregation!(false);
+    join_hint!(false);
+    constant!(false);
+    cross_metric!(false);
+    cross_measure!(false);
+    curated_view!(false);
+    multi_grain_measure!(false);
+    view_field!(false);
+    as_of!(false);
+    latest!(false);
+    partition_template!(false);
+    weighted_average!(false);
+    relationship_filter!(false);
+    period_over_period!(false);
+    table_function!(false);
 }
 
 #[cfg(feature = "python-binding")]
@@ -57,9 +85,13 @@ mod manifest_impl {
     use crate::mdl::manifest::bool_from_int;
     use crate::mdl::manifest::table_reference;
     use manifest_macro::{
-        column, column_level_operator, column_level_security, data_source, join_type, manifest,
-        metric, model, normalized_expr, normalized_expr_type, relationship, row_level_operator,
-        row_level_security, time_grain, time_unit, view,
+        as_of, bucket, column, column_level_operator, column_level_security, constant,
+        cross_measure, cross_metric, curated_view, data_source, default_aggregation,
+        duplicate_alias_policy, join_hint, join_type, latest, lookup, manifest, materialization,
+        metric, model, multi_grain_measure, non_additive_aggregation, normalized_expr,
+        normalized_expr_type, partition_template, period_over_period, pivot, relationship,
+        relationship_filter, row_level_operator, row_level_security, semi_additive, surrogate_key,
+        table_function, time_grain, time_unit, view, view_field, weighted_average,
     };
     use pyo3::pyclass;
     use serde::{Deserialize, Serialize};
@@ -70,6 +102,8 @@ mod manifest_impl {
     use std::sync::Arc;
 
     data_source!(true);
+    duplicate_alias_policy!(true);
+    default_aggregation!(true);
     model!(true);
     column!(true);
     relationship!(true);
@@ -85,6 +119,27 @@ mod manifest_impl {
     normalized_expr!(true);
     normalized_expr_type!(true);
     column_level_operator!(true);
+    semi_additive!(true);
+    bucket!(true);
+    surrogate_key!(true);
+    pivot!(true);
+    materialization!(true);
+    lookup!(true);
+    non_additive_aggregation!(true);
+    join_hint!(true);
+    constant!(true);
+    cross_metric!(true);
+    cross_measure!(true);
+    curated_view!(true);
+    multi_grain_measure!(true);
+    view_field!(true);
+    as_of!(true);
+    latest!(true);
+    partition_template!(true);
+    weighted_average!(true);
+    relationship_filter!(true);
+    period_over_period!(true);
+    table_function!(true);
 }
 
 pub use crate::mdl::manifest::manifest_impl::*;
@@ -106,6 +161,27 @@ impl Display for DataSource {
     }
 }
 
+impl Display for DefaultAggregation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DefaultAggregation::Sum => write!(f, "SUM"),
+            DefaultAggregation::Avg => write!(f, "AVG"),
+            DefaultAggregation::Min => write!(f, "MIN"),
+            DefaultAggregation::Max => write!(f, "MAX"),
+            DefaultAggregation::Count => write!(f, "COUNT"),
+        }
+    }
+}
+
+impl Display for DuplicateAliasPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DuplicateAliasPolicy::Error => write!(f, "ERROR"),
+            DuplicateAliasPolicy::Suffix => write!(f, "SUFFIX"),
+        }
+    }
+}
+
 mod table_reference {
     use serde::{self, Deserialize, Deserializer, Serialize, Serializer};
 
@@ -218,6 +294,216 @@ impl Display for JoinType {
     }
 }
 
+impl Display for JoinHint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JoinHint::Broadcast => write!(f, "BROADCAST"),
+            JoinHint::Shuffle => write!(f, "SHUFFLE"),
+        }
+    }
+}
+
+impl Display for TimeUnit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeUnit::Year => write!(f, "year"),
+            TimeUnit::Month => write!(f, "month"),
+            TimeUnit::Day => write!(f, "day"),
+            TimeUnit::Hour => write!(f, "hour"),
+            TimeUnit::Minute => write!(f, "minute"),
+            TimeUnit::Second => write!(f, "second"),
+        }
+    }
+}
+
+impl Manifest {
+    /// Merge the overlay into the base manifest.
+    ///
+    /// The models, relationships, metrics and views only defined by one side are kept. A model
+    /// defined by both sides keeps the columns of the base and adds the new columns of the overlay.
+    /// It's a conflict if both sides define the same object or column differently.
+    pub fn merge(base: Manifest, overlay: Manifest) -> Result<Manifest, String> {
+        if base.catalog != overlay.catalog || base.schema != overlay.schema {
+            return Err(format!(
+                "Can't merge the manifest of {}.{} into {}.{}",
+                overlay.catalog, overlay.schema, base.catalog, base.schema
+            ));
+        }
+        let data_source = match (base.data_source, overlay.data_source) {
+            (Some(base), Some(overlay)) if base != overlay => {
+                return Err(format!(
+                    "The data source {overlay} conflicts with the data source {base}"
+                ));
+            }
+            (base, overlay) => base.or(overlay),
+        };
+        let default_table_schema = match (base.default_table_schema, overlay.default_table_schema) {
+            (Some(base), Some(overlay)) if base != overlay => {
+                return Err(format!(
+                        "The default table schema {overlay} conflicts with the default table schema {base}"
+                    ));
+            }
+            (base, overlay) => base.or(overlay),
+        };
+        let duplicate_alias_policy = match (
+            base.duplicate_alias_policy,
+            overlay.duplicate_alias_policy,
+        ) {
+            (Some(base), Some(overlay)) if base != overlay => {
+                return Err(format!(
+                        "The duplicate alias policy {overlay} conflicts with the duplicate alias policy {base}"
+                    ));
+            }
+            (base, overlay) => base.or(overlay),
+        };
+        let default_aggregation = match (base.default_aggregation, overlay.default_aggregation) {
+            (Some(base), Some(overlay)) if base != overlay => {
+                return Err(format!(
+                    "The default aggregation {overlay} conflicts with the default aggregation {base}"
+                ));
+            }
+            (base, overlay) => base.or(overlay),
+        };
+        let default_namespace = match (base.default_namespace, overlay.default_namespace) {
+            (Some(base), Some(overlay)) if base != overlay => {
+                return Err(format!(
+                    "The default namespace {overlay} conflicts with the default namespace {base}"
+                ));
+            }
+            (base, overlay) => base.or(overlay),
+        };
+
+        let mut models = base.models;
+        for model in overlay.models {
+            match models
+                .iter()
+                .position(|m| m.name == model.name && m.namespace == model.namespace)
+            {
+                Some(index) => models[index] = merge_model(&models[index], &model)?,
+                None => models.push(model),
+            }
+        }
+        Ok(Manifest {
+            catalog: base.catalog,
+            schema: base.schema,
+            models,
+            relationships: merge_named(base.relationships, overlay.relationships, |r| &r.name)
+                .map_err(|name| format!("The relationship {name} is defined differently"))?,
+            metrics: merge_named(base.metrics, overlay.metrics, |m| &m.name)
+                .map_err(|name| format!("The metric {name} is defined differently"))?,
+            views: merge_named(base.views, overlay.views, |v| &v.name)
+                .map_err(|name| format!("The view {name} is defined differently"))?,
+            data_source,
+            default_table_schema,
+            constants: merge_named(base.constants, overlay.constants, |c| &c.name)
+                .map_err(|name| format!("The constant {name} is defined differently"))?,
+            cross_metrics: merge_named(base.cross_metrics, overlay.cross_metrics, |m| &m.name)
+                .map_err(|name| format!("The cross metric {name} is defined differently"))?,
+            duplicate_alias_policy,
+            curated_views: merge_named(base.curated_views, overlay.curated_views, |v| &v.name)
+                .map_err(|name| format!("The curated view {name} is defined differently"))?,
+            multi_grain_measures: merge_named(
+                base.multi_grain_measures,
+                overlay.multi_grain_measures,
+                |m| &m.name,
+            )
+            .map_err(|name| format!("The multi-grain measure {name} is defined differently"))?,
+            default_namespace,
+            period_over_periods: merge_named(
+                base.period_over_periods,
+                overlay.period_over_periods,
+                |m| &m.name,
+            )
+            .map_err(|name| {
+                format!("The period-over-period metric {name} is defined differently")
+            })?,
+            default_aggregation,
+        })
+    }
+
+    /// Load the manifest from the JSON bytes read by the source, e.g. a file or an object
+    /// storage read by the caller's client.
+    pub async fn load(source: impl ManifestSource) -> Result<Manifest, String> {
+        let bytes = source.read().await?;
+        serde_json::from_slice(&bytes).map_err(|e| format!("Invalid manifest: {e}"))
+    }
+}
+
+/// The source of the manifest JSON read by [Manifest::load]. The IO is provided by the caller,
+/// so the source could be a file, an HTTP endpoint or an object storage.
+pub trait ManifestSource {
+    fn read(&self) -> impl Future<Output = Result<Vec<u8>, String>> + Send;
+}
+
+fn merge_model(base: &Arc<Model>, overlay: &Arc<Model>) -> Result<Arc<Model>, String> {
+    if base.ref_sql != overlay.ref_sql
+        || base.base_object != overlay.base_object
+        || base.table_reference != overlay.table_reference
+        || base.primary_key != overlay.primary_key
+        || base.cached != overlay.cached
+        || base.refresh_time != overlay.refresh_time
+        || base.materialization != overlay.materialization
+        || base.extends != overlay.extends
+        || base.synonyms != overlay.synonyms
+        || base.fallback_sources != overlay.fallback_sources
+        || base.routing_hint != overlay.routing_hint
+        || base.default_sort_key != overlay.default_sort_key
+        || base.latest != overlay.latest
+        || base.single_row != overlay.single_row
+        || base.partition_template != overlay.partition_template
+        || base.base_filter != overlay.base_filter
+        || base.required_partition_filter != overlay.required_partition_filter
+        || base.table_function != overlay.table_function
+    {
+        return Err(format!("The model {} is defined differently", base.name));
+    }
+    let columns = merge_named(base.columns.clone(), overlay.columns.clone(), |c| &c.name)
+        .map_err(|name| format!("The column {}.{} is defined differently", base.name, name))?;
+    Ok(Arc::new(Model {
+        name: base.name.clone(),
+        ref_sql: base.ref_sql.clone(),
+        base_object: base.base_object.clone(),
+        table_reference: base.table_reference.clone(),
+        columns,
+        primary_key: base.primary_key.clone(),
+        cached: base.cached,
+        refresh_time: base.refresh_time.clone(),
+        materialization: base.materialization.clone(),
+        extends: base.extends.clone(),
+        synonyms: base.synonyms.clone(),
+        fallback_sources: base.fallback_sources.clone(),
+        routing_hint: base.routing_hint.clone(),
+        default_sort_key: base.default_sort_key.clone(),
+        latest: base.latest.clone(),
+        single_row: base.single_row,
+        partition_template: base.partition_template.clone(),
+        base_filter: base.base_filter.clone(),
+        namespace: base.namespace.clone(),
+        required_partition_filter: base.required_partition_filter.clone(),
+        table_function: base.table_function.clone(),
+    }))
+}
+
+/// Append the objects of the overlay that aren't in the base. The object defined by both sides
+/// should be identical, otherwise return its name.
+fn merge_named<T: PartialEq>(
+    base: Vec<Arc<T>>,
+    overlay: Vec<Arc<T>>,
+    name: impl Fn(&T) -> &String,
+) -> Result<Vec<Arc<T>>, String> {
+    let mut merged = base;
+    for object in overlay {
+        match merged.iter().find(|o| name(o) == name(&object)) {
+            Some(existing) if existing != &object => {
+                return Err(name(&object).clone());
+            }
+            Some(_) => {}
+            None => merged.push(object),
+        }
+    }
+    Ok(merged)
+}
+
 impl Model {
     /// Physical columns are columns that can be selected from the model.
     /// All physical columns are visible columns, but not all visible columns are physical columns
@@ -234,15 +520,24 @@ impl Model {
         &self.name
     }
 
+    /// Return the namespace of the model, e.g. `finance` of `finance.orders`
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
     /// Return the iterator of all visible columns
     pub fn get_visible_columns(&self) -> impl Iterator<Item = Arc<Column>> + '_ {
         self.columns.iter().filter(|f| !f.is_hidden).map(Arc::clone)
     }
 
-    /// Get the specified visible column by name
+    /// Return the visible column by its name or one of its synonyms. The name takes precedence.
     pub fn get_column(&self, column_name: &str) -> Option<Arc<Column>> {
         self.get_visible_columns()
             .find(|c| c.name == column_name)
+            .or_else(|| {
+                self.get_visible_columns()
+                    .find(|c| c.synonyms.iter().any(|s| s == column_name))
+            })
             .map(|c| Arc::clone(&c))
     }
 
@@ -255,6 +550,63 @@ impl Model {
     pub fn table_reference(&self) -> &str {
         self.table_reference.as_deref().unwrap_or("")
     }
+
+    pub fn extends(&self) -> Option<&str> {
+        self.extends.as_deref()
+    }
+
+    pub fn materialization(&self) -> Option<&Materialization> {
+        self.materialization.as_ref()
+    }
+
+    /// Return the tables read in order if the rows are absent from the previous sources
+    pub fn fallback_sources(&self) -> &[String] {
+        &self.fallback_sources
+    }
+
+    /// Return the hint of the connection the scan of the model is routed to, e.g. `replica`
+    pub fn routing_hint(&self) -> Option<&str> {
+        self.routing_hint.as_deref()
+    }
+
+    /// Return the column ordering the paginated queries without an explicit order
+    pub fn default_sort_key(&self) -> Option<&str> {
+        self.default_sort_key.as_deref()
+    }
+
+    /// Return the latest strategy if only the latest row of each key is kept
+    pub fn latest(&self) -> Option<&Latest> {
+        self.latest.as_ref()
+    }
+
+    /// Return true if the model is a scalar source with a single row, e.g. a settings table
+    pub fn is_single_row(&self) -> bool {
+        self.single_row
+    }
+
+    /// Return the template of the dated partitions if the model scans a partitioned table
+    pub fn partition_template(&self) -> Option<&PartitionTemplate> {
+        self.partition_template.as_ref()
+    }
+
+    /// Return the predicate every row of the model source should match, e.g. `deleted_at IS NULL`.
+    /// It's also applied to the model joined by a relationship before the join, so the rows
+    /// soft-deleted from a dimension are never joined.
+    pub fn base_filter(&self) -> Option<&str> {
+        self.base_filter.as_deref()
+    }
+
+    /// Return the partition column every query scanning the model should filter, e.g. the table
+    /// of BigQuery requiring a partition filter
+    pub fn required_partition_filter(&self) -> Option<&str> {
+        self.required_partition_filter.as_deref()
+    }
+
+    /// Return the table-valued function the model reads its rows from, e.g. `read_json(url)`,
+    /// if it isn't a table
+    pub fn table_function(&self) -> Option<&TableFunction> {
+        self.table_function.as_ref()
+    }
 }
 
 impl Column {
@@ -267,6 +619,107 @@ impl Column {
     pub fn expression(&self) -> Option<&str> {
         self.expression.as_deref()
     }
+
+    /// Return the semi-additive definition if the column is a balance-style measure
+    pub fn semi_additive(&self) -> Option<&SemiAdditive> {
+        self.semi_additive.as_ref()
+    }
+
+    /// Return the number of the groups kept if the column is grouped as a top-N dimension
+    pub fn top_n(&self) -> Option<u32> {
+        self.top_n
+    }
+
+    /// Return the bucket definition if the column is a bucketed dimension
+    pub fn bucket(&self) -> Option<&Bucket> {
+        self.bucket.as_ref()
+    }
+
+    /// Return the surrogate key definition if the column is hashed from the natural keys
+    pub fn surrogate_key(&self) -> Option<&SurrogateKey> {
+        self.surrogate_key.as_ref()
+    }
+
+    /// Return the pivot definition if the column is pivoted across the values of a dimension
+    pub fn pivot(&self) -> Option<&Pivot> {
+        self.pivot.as_ref()
+    }
+
+    /// Return the array column if the column exposes its elements as one row per element
+    pub fn unnest(&self) -> Option<&str> {
+        self.unnest.as_deref()
+    }
+
+    /// Return the value of the measure aggregated over no rows instead of NULL
+    pub fn empty_value(&self) -> Option<&str> {
+        self.empty_value.as_deref()
+    }
+
+    /// Return the physical column storing the materialized value of the calculated field
+    pub fn materialized_column(&self) -> Option<&str> {
+        self.materialized_column.as_deref()
+    }
+
+    /// Return the relationship column to the parent if the aggregations of the column are scoped by
+    /// the parent of each row
+    pub fn parent_scope(&self) -> Option<&str> {
+        self.parent_scope.as_deref()
+    }
+
+    /// Return the value and weight columns if the column is their weighted average
+    pub fn weighted_average(&self) -> Option<&WeightedAverage> {
+        self.weighted_average.as_ref()
+    }
+
+    /// Return the condition of the rows the column counts if it's a conditional count
+    pub fn count_if(&self) -> Option<&str> {
+        self.count_if.as_deref()
+    }
+
+    /// Return the related rows the column checks the existence of if it's a relationship filter
+    pub fn relationship_filter(&self) -> Option<&RelationshipFilter> {
+        self.relationship_filter.as_ref()
+    }
+
+    /// Return the roles allowed to query the column. Every role is allowed if it's empty.
+    pub fn allowed_roles(&self) -> &[String] {
+        &self.allowed_roles
+    }
+
+    /// Return the aggregation declared for the calculated field traversing a to-many relationship
+    /// without an aggregate function
+    pub fn default_aggregation(&self) -> Option<DefaultAggregation> {
+        self.default_aggregation
+    }
+
+    /// Return the description of the column in its properties
+    pub fn description(&self) -> Option<&str> {
+        self.properties.get("description").map(String::as_str)
+    }
+
+    /// Return true if the column is calculated on the fly rather than read from a stored column
+    pub fn is_computed(&self) -> bool {
+        self.is_calculated && self.materialized_column.is_none()
+    }
+}
+
+impl Relationship {
+    /// Return the lookup if the related rows are picked one per row of the other model
+    pub fn lookup(&self) -> Option<&Lookup> {
+        self.lookup.as_ref()
+    }
+
+    /// Return the time columns of the as-of join, which picks the latest row of the dimension
+    /// model at or before the time of the fact model
+    pub fn as_of(&self) -> Option<&AsOf> {
+        self.as_of.as_ref()
+    }
+
+    /// Return the predicate scoping the joined rows, e.g. `orders.region = $region`. The
+    /// parameters are bound by the variables of the query context.
+    pub fn scope(&self) -> Option<&str> {
+        self.scope.as_deref()
+    }
 }
 
 impl Metric {
@@ -283,8 +736,12 @@ impl View {
 
 #[cfg(test)]
 mod tests {
-    use crate::mdl::manifest::table_reference;
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder, ViewBuilder};
+    use crate::mdl::manifest::{table_reference, Manifest, ManifestSource};
     use serde_json::Serializer;
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
 
     #[test]
     fn test_table_reference_serialize() {
@@ -310,4 +767,125 @@ mod tests {
             assert_eq!(String::from_utf8(buf).unwrap(), *expected);
         });
     }
+
+    #[test]
+    fn test_merge_overlay() {
+        let base = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let overlay = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .column(ColumnBuilder::new("c_phone", "varchar").build())
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("big_customer")
+                    .statement("SELECT * FROM customer")
+                    .build(),
+            )
+            .build();
+
+        let merged = Manifest::merge(base, overlay).unwrap();
+        let models = merged.models.iter().map(|m| m.name()).collect::<Vec<_>>();
+        assert_eq!(models, ["customer", "orders"]);
+        let columns = merged.models[0]
+            .columns
+            .iter()
+            .map(|c| c.name())
+            .collect::<Vec<_>>();
+        assert_eq!(columns, ["c_custkey", "c_name", "c_phone"]);
+        assert_eq!(merged.views.len(), 1);
+    }
+
+    #[test]
+    fn test_merge_conflict() {
+        let manifest = |r#type: &str| {
+            ManifestBuilder::new()
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", r#type).build())
+                        .build(),
+                )
+                .build()
+        };
+        let err = Manifest::merge(manifest("bigint"), manifest("varchar")).unwrap_err();
+        assert_eq!(err, "The column customer.c_custkey is defined differently");
+
+        let overlay = ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer_v2")
+                    .build(),
+            )
+            .build();
+        let err = Manifest::merge(manifest("bigint"), overlay).unwrap_err();
+        assert_eq!(err, "The model customer is defined differently");
+    }
+
+    #[test]
+    fn test_load_manifest() {
+        struct MockSource(&'static str);
+
+        impl ManifestSource for MockSource {
+            async fn read(&self) -> Result<Vec<u8>, String> {
+                Ok(self.0.as_bytes().to_vec())
+            }
+        }
+
+        let manifest = block_on(Manifest::load(MockSource(
+            r#"{
+                "catalog": "wren",
+                "schema": "test",
+                "models": [
+                    {
+                        "name": "customer",
+                        "tableReference": { "table": "customer" },
+                        "columns": [{ "name": "c_custkey", "type": "bigint" }],
+                        "primaryKey": "c_custkey"
+                    }
+                ]
+            }"#,
+        )))
+        .unwrap();
+        assert_eq!(manifest.catalog, "wren");
+        assert_eq!(manifest.schema, "test");
+        assert_eq!(manifest.models.len(), 1);
+        assert_eq!(manifest.models[0].name(), "customer");
+        assert_eq!(manifest.models[0].primary_key(), Some("c_custkey"));
+
+        let err = block_on(Manifest::load(MockSource("{ not json"))).unwrap_err();
+        assert!(err.starts_with("Invalid manifest"), "{err}");
+    }
+
+    /// Poll the future of a mock source, which is ready without any IO
+    fn block_on<F: Future>(future: F) -> F::Output {
+        match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(output) => output,
+            Poll::Pending => panic!("the mock source should be ready"),
+        }
+    }
 }
@@ -80,6 +80,20 @@ fn extract_manifest(
         metrics: mdl.metrics().to_vec(),
         views: used_views,
         data_source: mdl.data_source(),
+        default_table_schema: mdl.default_table_schema().map(String::from),
+        constants: mdl.constants().to_vec(),
+        // the cross metrics are compiled into the views of the analyzed manifest
+        cross_metrics: vec![],
+        duplicate_alias_policy: mdl.duplicate_alias_policy(),
+        // the curated views are compiled into the views of the analyzed manifest
+        curated_views: vec![],
+        // the multi-grain measures are compiled into the views of the analyzed manifest
+        multi_grain_measures: vec![],
+        // the namespaces are resolved into the model names of the analyzed manifest
+        default_namespace: None,
+        // the period-over-period metrics are compiled into the views of the analyzed manifest
+        period_over_periods: vec![],
+        default_aggregation: mdl.default_aggregation(),
     })
 }
 
@@ -43,6 +43,19 @@ mod tests {
                     primary_key: None,
                     cached: false,
                     refresh_time: None,
+                    materialization: None,
+                    extends: None,
+                    synonyms: vec![],
+                    fallback_sources: vec![],
+                    routing_hint: None,
+                    default_sort_key: None,
+                    latest: None,
+                    single_row: false,
+                    partition_template: None,
+                    base_filter: None,
+                    namespace: None,
+                    required_partition_filter: None,
+                    table_function: None,
                 }),
                 Arc::from(Model {
                     name: "model_2".to_string(),
@@ -53,12 +66,34 @@ mod tests {
                     primary_key: None,
                     cached: false,
                     refresh_time: None,
+                    materialization: None,
+                    extends: None,
+                    synonyms: vec![],
+                    fallback_sources: vec![],
+                    routing_hint: None,
+                    default_sort_key: None,
+                    latest: None,
+                    single_row: false,
+                    partition_template: None,
+                    base_filter: None,
+                    namespace: None,
+                    required_partition_filter: None,
+                    table_function: None,
                 }),
             ],
             relationships: vec![],
             metrics: vec![],
             views: vec![],
             data_source: Some(BigQuery),
+            default_table_schema: None,
+            constants: vec![],
+            cross_metrics: vec![],
+            duplicate_alias_policy: None,
+            curated_views: vec![],
+            multi_grain_measures: vec![],
+            default_namespace: None,
+            period_over_periods: vec![],
+            default_aggregation: None,
         };
         let base64_str = to_json_base64(py_manifest).unwrap();
         let manifest = to_manifest(&base64_str).unwrap();
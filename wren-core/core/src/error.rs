@@ -0,0 +1,214 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ * http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use datafusion::error::DataFusionError;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+/// [WrenError] describes the failures raised by Wren Core itself when generating the plan.
+///
+/// It's wrapped by [DataFusionError::External] when it's returned through the DataFusion API.
+/// Use [WrenError::find_in] to get the typed error back from a [DataFusionError].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WrenError {
+    /// The model required by the plan node isn't defined in the MDL
+    ModelNotFound(String),
+    /// The model doesn't declare the primary key required by the operation
+    MissingPrimaryKey(String),
+    /// The original table scan of a model source isn't a [datafusion::logical_expr::TableScan]
+    InvalidOriginalTableScan { model: String },
+    /// The relation chain doesn't generate any source plan
+    MissingSourcePlan(String),
+    /// The relation chain of a calculation doesn't generate an alias for the source plan
+    MissingCalculationAlias(String),
+    /// The measure of a calculation isn't wrapped in an alias
+    UnaliasedMeasure(String),
+    /// The measure can't be aggregated at the grain of the dimension
+    IncompatibleGrain {
+        measure: String,
+        dimension: String,
+        reason: String,
+    },
+    /// The measure adds up the rows of a non-additive column, e.g. sums an average
+    NonAdditiveMeasure {
+        measure: String,
+        column: String,
+        reason: String,
+    },
+    /// The relation chain traverses more relationships than the configured maximum depth
+    RelationshipDepthExceeded { path: Vec<String>, max_depth: usize },
+    /// The generated plan joins two models without any join condition between them
+    ImplicitCrossJoin { left: String, right: String },
+    /// The generated plan outputs more columns than the configured maximum width
+    ResultWidthExceeded { columns: usize, max_columns: usize },
+    /// The column can't be cast to the type overridden by the query
+    IncompatibleColumnType {
+        column: String,
+        from: String,
+        to: String,
+    },
+    /// The variable required by the row-level security isn't set in the query context
+    MissingQueryVariable { variable: String, column: String },
+    /// The parameter of a relationship scope isn't bound by the query context
+    UnboundParameter { parameter: String, scope: String },
+    /// The parameter of a table function argument isn't bound by the query context
+    UnboundTableFunctionParameter { parameter: String, model: String },
+    /// The query requests a column the session isn't authorized for
+    UnauthorizedColumn { model: String, column: String },
+    /// The query requests a column the role of the session isn't allowed to query
+    DisallowedColumn {
+        role: Option<String>,
+        model: String,
+        column: String,
+    },
+    /// The query scans the model without filtering its required partition column
+    MissingPartitionFilter { model: String, column: String },
+    /// The calculated field calls a function that isn't registered in the session
+    UnknownFunction {
+        function: String,
+        model: String,
+        column: String,
+    },
+}
+
+impl WrenError {
+    /// Find the [WrenError] wrapped in the given [DataFusionError]
+    pub fn find_in(error: &DataFusionError) -> Option<&WrenError> {
+        match error.find_root() {
+            DataFusionError::External(e) => e.downcast_ref::<WrenError>(),
+            _ => None,
+        }
+    }
+}
+
+impl Display for WrenError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            WrenError::ModelNotFound(model) => write!(f, "Model not found: {model}"),
+            WrenError::MissingPrimaryKey(model) => {
+                write!(f, "The model {model} doesn't declare a primary key")
+            }
+            WrenError::InvalidOriginalTableScan { model } => write!(
+                f,
+                "ModelPlanNode should have a TableScan as original_table_scan: {model}"
+            ),
+            WrenError::MissingSourcePlan(name) => {
+                write!(f, "Failed to generate source plan for {name}")
+            }
+            WrenError::MissingCalculationAlias(name) => {
+                write!(f, "calculation plan should have an alias: {name}")
+            }
+            WrenError::UnaliasedMeasure(name) => {
+                write!(f, "measures should have an alias: {name}")
+            }
+            WrenError::IncompatibleGrain {
+                measure,
+                dimension,
+                reason,
+            } => write!(
+                f,
+                "The measure {measure} can't be grouped by the dimension {dimension}: {reason}"
+            ),
+            WrenError::NonAdditiveMeasure {
+                measure,
+                column,
+                reason,
+            } => write!(
+                f,
+                "The measure {measure} can't add up the non-additive column {column}: {reason}"
+            ),
+            WrenError::RelationshipDepthExceeded { path, max_depth } => write!(
+                f,
+                "The relationship path {} exceeds the maximum traversal depth {max_depth}",
+                path.join(" -> ")
+            ),
+            WrenError::IncompatibleColumnType { column, from, to } => write!(
+                f,
+                "The column {column} of type {from} can't be overridden as {to}"
+            ),
+            WrenError::MissingQueryVariable { variable, column } => write!(
+                f,
+                "The variable {variable} required by the row-level security of {column} \
+                isn't set in the query context"
+            ),
+            WrenError::UnboundParameter { parameter, scope } => write!(
+                f,
+                "The parameter {parameter} of the relationship scope {scope} \
+                isn't bound by the query context"
+            ),
+            WrenError::UnboundTableFunctionParameter { parameter, model } => write!(
+                f,
+                "The parameter {parameter} of the table function of the model {model} \
+                isn't bound by the query context"
+            ),
+            WrenError::MissingPartitionFilter { model, column } => write!(
+                f,
+                "The query scanning the model {model} should filter its partition column {column}"
+            ),
+            WrenError::UnauthorizedColumn { model, column } => write!(
+                f,
+                "The session isn't authorized to access the column {model}.{column}"
+            ),
+            WrenError::DisallowedColumn {
+                role: Some(role),
+                model,
+                column,
+            } => write!(
+                f,
+                "The role {role} isn't allowed to query the column {model}.{column}"
+            ),
+            WrenError::DisallowedColumn {
+                role: None,
+                model,
+                column,
+            } => write!(
+                f,
+                "The session without a role isn't allowed to query the column {model}.{column}"
+            ),
+            WrenError::UnknownFunction {
+                function,
+                model,
+                column,
+            } => write!(
+                f,
+                "The calculated field {model}.{column} calls an unknown function {function}"
+            ),
+            WrenError::ImplicitCrossJoin { left, right } => write!(
+                f,
+                "The generated plan contains an implicit cross join between {left} and {right}, \
+                check the relationship between them"
+            ),
+            WrenError::ResultWidthExceeded {
+                columns,
+                max_columns,
+            } => write!(
+                f,
+                "The query outputs {columns} columns, which exceeds the maximum result width {max_columns}"
+            ),
+        }
+    }
+}
+
+impl Error for WrenError {}
+
+impl From<WrenError> for DataFusionError {
+    fn from(e: WrenError) -> Self {
+        DataFusionError::External(Box::new(e))
+    }
+}
@@ -1,3 +1,4 @@
+pub mod error;
 pub mod logical_plan;
 pub mod mdl;
 
@@ -5,4 +6,5 @@ pub use datafusion::error::DataFusionError;
 pub use datafusion::logical_expr::{AggregateUDF, ScalarUDF, WindowUDF};
 pub use datafusion::prelude::SessionContext;
 pub use datafusion::sql::sqlparser::*;
+pub use error::WrenError;
 pub use mdl::AnalyzedWrenMDL;
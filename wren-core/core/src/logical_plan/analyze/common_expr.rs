@@ -0,0 +1,192 @@
+use datafusion::common::tree_node::{
+    Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
+};
+use datafusion::common::{Column, DFSchema, Result, TableReference};
+use datafusion::logical_expr::{Expr, LogicalPlanBuilder, Volatility};
+use std::collections::{HashMap, HashSet};
+
+/// The prefix of the columns computing the subexpressions shared by the projected expressions
+const COMMON_EXPR_PREFIX: &str = "__wren_common_expr_";
+
+/// Project the expressions over the source, computing the function calls shared by several of
+/// them once, e.g. the same JSON extraction of two calculated fields.
+///
+/// The shared calls are computed by a projection under the requested one, which is aliased by
+/// the qualifier of the source, so the other columns are still referenced by the same names. The
+/// operators and the casts are cheap to compute again, so only the function calls are shared.
+/// The volatile calls are never shared because each one should be evaluated on its own.
+pub(crate) fn project_with_common_exprs(
+    source: LogicalPlanBuilder,
+    exprs: Vec<Expr>,
+) -> Result<LogicalPlanBuilder> {
+    let Some(qualifier) = single_qualifier(source.schema()) else {
+        return source.project(exprs);
+    };
+    let common_exprs = find_common_exprs(&exprs)?;
+    if common_exprs.is_empty() {
+        return source.project(exprs);
+    }
+
+    let mut names: HashMap<Expr, String> = HashMap::new();
+    let mut computed = vec![];
+    let exprs = exprs
+        .into_iter()
+        .map(|expr| {
+            expr.transform_down(|e| {
+                if !common_exprs.contains(&e) {
+                    return Ok(Transformed::no(e));
+                }
+                let name = match names.get(&e) {
+                    Some(name) => name.clone(),
+                    None => {
+                        let name = format!("{COMMON_EXPR_PREFIX}{}", names.len() + 1);
+                        names.insert(e.clone(), name.clone());
+                        computed.push(e.alias(&name));
+                        name
+                    }
+                };
+                Ok(Transformed::new(
+                    Expr::Column(Column::new(Some(qualifier.clone()), name)),
+                    true,
+                    TreeNodeRecursion::Jump,
+                ))
+            })
+            .data()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let columns = source
+        .schema()
+        .columns()
+        .into_iter()
+        .map(Expr::Column)
+        .chain(computed)
+        .collect::<Vec<_>>();
+    source.project(columns)?.alias(qualifier)?.project(exprs)
+}
+
+/// Return the function calls appearing in more than one of the expressions in the order they're
+/// found
+fn find_common_exprs(exprs: &[Expr]) -> Result<Vec<Expr>> {
+    let mut counts: Vec<(Expr, usize)> = vec![];
+    for expr in exprs {
+        let mut found = HashSet::new();
+        expr.apply(|e| {
+            if is_shareable(e)? && found.insert(e.clone()) {
+                match counts.iter_mut().find(|(common, _)| common == e) {
+                    Some((_, count)) => *count += 1,
+                    None => counts.push((e.clone(), 1)),
+                }
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+    }
+    Ok(counts
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(expr, _)| expr)
+        .collect())
+}
+
+/// A function call is shareable if it's evaluated row by row without the side effects
+fn is_shareable(expr: &Expr) -> Result<bool> {
+    if !matches!(expr, Expr::ScalarFunction(_)) {
+        return Ok(false);
+    }
+    let not_shareable = expr.exists(|e| {
+        Ok(match e {
+            Expr::ScalarFunction(function) => {
+                function.func.signature().volatility == Volatility::Volatile
+            }
+            Expr::AggregateFunction(_)
+            | Expr::WindowFunction(_)
+            | Expr::ScalarSubquery(_)
+            | Expr::Exists(_)
+            | Expr::InSubquery(_)
+            | Expr::OuterReferenceColumn(_, _)
+            | Expr::Placeholder(_) => true,
+            _ => false,
+        })
+    })?;
+    Ok(!not_shareable)
+}
+
+/// Return the qualifier of the schema if all the fields are qualified by it
+fn single_qualifier(schema: &DFSchema) -> Option<TableReference> {
+    let mut qualifiers = schema.iter().map(|(qualifier, _)| qualifier);
+    let first = qualifiers.next()??;
+    qualifiers
+        .all(|qualifier| qualifier == Some(first))
+        .then(|| first.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_shared_subexpression() -> Result<()> {
+        let ctx = SessionContext::new();
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let email: ArrayRef = Arc::new(StringArray::from_iter_values([
+            "Gura@Hololive.com",
+            "ina@EN.hololive.com",
+        ]));
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![("c_custkey", custkey), ("c_email", email)])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_email", "varchar").build())
+                    .column(
+                        ColumnBuilder::new("email_user", "varchar")
+                            .calculated(true)
+                            .expression("split_part(lower(c_email), '@', 1)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("email_domain", "varchar")
+                            .calculated(true)
+                            .expression("split_part(lower(c_email), '@', 2)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT c_custkey, email_user, email_domain FROM customer ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the shared call is computed once for both fields
+        assert_eq!(actual.matches("lower(").count(), 1, "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+------------+-----------------+",
+                "| c_custkey | email_user | email_domain    |",
+                "+-----------+------------+-----------------+",
+                "| 1         | gura       | hololive.com    |",
+                "| 2         | ina        | en.hololive.com |",
+                "+-----------+------------+-----------------+",
+            ],
+            &result
+        );
+
+        // nothing is shared by one field
+        let sql = "SELECT email_user FROM customer";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("__wren_common_expr"), "{actual}");
+        Ok(())
+    }
+}
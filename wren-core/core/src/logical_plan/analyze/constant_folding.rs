@@ -0,0 +1,150 @@
+use datafusion::common::tree_node::TreeNode;
+use datafusion::common::{DFSchema, Result};
+use datafusion::logical_expr::execution_props::ExecutionProps;
+use datafusion::logical_expr::expr::Alias;
+use datafusion::logical_expr::simplify::SimplifyContext;
+use datafusion::logical_expr::{Expr, ExprSchemable, Volatility};
+use datafusion::optimizer::simplify_expressions::ExprSimplifier;
+use std::sync::Arc;
+
+/// Fold the projected expressions without any column into the literals by the simplifier of
+/// DataFusion, e.g. `concat('tenant', '-', 42)` is projected as `'tenant-42'`.
+///
+/// The literal keeps the type of the expression, and the alias of the projection is kept too.
+/// Only the immutable expressions are folded, so the functions like `now()` are still evaluated
+/// by the data source when the query runs. The expression that can't be folded into a literal is
+/// kept as it is.
+pub(crate) fn fold_constant_exprs(exprs: Vec<Expr>) -> Result<Vec<Expr>> {
+    exprs
+        .into_iter()
+        .map(|expr| match expr {
+            Expr::Alias(Alias {
+                expr,
+                relation,
+                name,
+            }) => Ok(fold_constant_expr(*expr)?.alias_qualified(relation, name)),
+            expr => fold_constant_expr(expr),
+        })
+        .collect()
+}
+
+fn fold_constant_expr(expr: Expr) -> Result<Expr> {
+    if !is_foldable(&expr)? {
+        return Ok(expr);
+    }
+    let schema = Arc::new(DFSchema::empty());
+    let props = ExecutionProps::new();
+    let simplifier = ExprSimplifier::new(
+        SimplifyContext::new(&props).with_schema(Arc::clone(&schema)),
+    );
+    let coerced = simplifier.coerce(expr.clone(), &schema)?;
+    let data_type = coerced.get_type(&schema)?;
+    match simplifier.simplify(coerced)? {
+        Expr::Literal(value) if value.data_type() == data_type => {
+            Ok(Expr::Literal(value))
+        }
+        Expr::Literal(value) => Ok(Expr::Literal(value.cast_to(&data_type)?)),
+        _ => Ok(expr),
+    }
+}
+
+/// The expression is foldable if it's computed from the literals by the immutable functions
+fn is_foldable(expr: &Expr) -> Result<bool> {
+    if matches!(expr, Expr::Literal(_)) {
+        return Ok(false);
+    }
+    let not_foldable = expr.exists(|e| {
+        Ok(match e {
+            Expr::ScalarFunction(function) => {
+                function.func.signature().volatility != Volatility::Immutable
+            }
+            Expr::Column(_)
+            | Expr::OuterReferenceColumn(_, _)
+            | Expr::ScalarVariable(_, _)
+            | Expr::Placeholder(_)
+            | Expr::AggregateFunction(_)
+            | Expr::WindowFunction(_)
+            | Expr::ScalarSubquery(_)
+            | Expr::Exists(_)
+            | Expr::InSubquery(_)
+            | Expr::Unnest(_) => true,
+            #[allow(deprecated)]
+            Expr::Wildcard { .. } => true,
+            _ => false,
+        })
+    })?;
+    Ok(!not_foldable)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch};
+    use datafusion::arrow::datatypes::DataType;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_constant_folding() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![("o_orderkey", orderkey)])?,
+        )?;
+        let manifest = manifest_builder()
+            .constant("tax_rate", "double", "0.05")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("tenant", "varchar")
+                            .expression("concat('acme', '-', 42)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("tax_percent", "double")
+                            .calculated(true)
+                            .expression("tax_rate * 100")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let sql =
+            "SELECT o_orderkey, tenant, tax_percent FROM orders ORDER BY o_orderkey";
+
+        // the constant projections are kept as they're declared by default
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest.clone())?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("concat("), "{actual}");
+
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_constant_folding());
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("concat("), "{actual}");
+        assert!(actual.contains("'acme-42' AS tenant"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        let schema = result[0].schema();
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(2).data_type(), &DataType::Float64);
+        assert_batches_eq!(
+            [
+                "+------------+---------+-------------+",
+                "| o_orderkey | tenant  | tax_percent |",
+                "+------------+---------+-------------+",
+                "| 1          | acme-42 | 5.0         |",
+                "| 2          | acme-42 | 5.0         |",
+                "+------------+---------+-------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+use crate::logical_plan::analyze::plan::{ModelPlanNode, ModelSourceNode};
+use crate::mdl::WrenMDL;
+use crate::WrenError;
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::Result;
+use datafusion::logical_expr::{Join, LogicalPlan};
+use std::collections::HashSet;
+
+/// Collect the model pairs joined by a cartesian product in the plan
+///
+/// A join is a cartesian product if it has no equi-join key and its filter doesn't refer to both
+/// sides. The side of the join is named by the first model found in it.
+pub(super) fn collect_cross_joins(
+    plan: &LogicalPlan,
+    wren_mdl: &WrenMDL,
+) -> Result<HashSet<(String, String)>> {
+    let mut cross_joins = HashSet::new();
+    plan.apply_with_subqueries(|plan| {
+        if let LogicalPlan::Join(join) = plan {
+            if is_cartesian(join) {
+                cross_joins.insert((
+                    model_name(&join.left, wren_mdl)?,
+                    model_name(&join.right, wren_mdl)?,
+                ));
+            }
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(cross_joins)
+}
+
+/// Check that every cross join in the generated plan is requested by the original plan
+pub(super) fn check_implicit_cross_joins(
+    original: &HashSet<(String, String)>,
+    generated: &LogicalPlan,
+    wren_mdl: &WrenMDL,
+) -> Result<()> {
+    let mut implicit = collect_cross_joins(generated, wren_mdl)?
+        .into_iter()
+        .filter(|pair| !original.contains(pair))
+        .collect::<Vec<_>>();
+    implicit.sort();
+    match implicit.into_iter().next() {
+        Some((left, right)) => Err(WrenError::ImplicitCrossJoin { left, right }.into()),
+        None => Ok(()),
+    }
+}
+
+fn is_cartesian(join: &Join) -> bool {
+    if !join.on.is_empty() {
+        return false;
+    }
+    let Some(filter) = &join.filter else {
+        return true;
+    };
+    let columns = filter.column_refs();
+    let refer_left = columns.iter().any(|c| join.left.schema().has_column(c));
+    let refer_right = columns.iter().any(|c| join.right.schema().has_column(c));
+    !(refer_left && refer_right)
+}
+
+fn model_name(plan: &LogicalPlan, wren_mdl: &WrenMDL) -> Result<String> {
+    let mut name = None;
+    plan.apply(|plan| {
+        let found = match plan {
+            LogicalPlan::SubqueryAlias(alias)
+                if wren_mdl.get_model(alias.alias.table()).is_some() =>
+            {
+                Some(alias.alias.table().to_string())
+            }
+            LogicalPlan::Extension(extension) => {
+                let node = extension.node.as_any();
+                if let Some(model_plan) = node.downcast_ref::<ModelPlanNode>() {
+                    Some(model_plan.plan_name.clone())
+                } else {
+                    node.downcast_ref::<ModelSourceNode>()
+                        .map(|source| source.model_name.clone())
+                }
+            }
+            _ => None,
+        };
+        if found.is_some() {
+            name = found;
+            return Ok(TreeNodeRecursion::Stop);
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(name.unwrap_or_else(|| plan.display().to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_strict_join() -> Result<()> {
+        let manifest = |condition: &str| {
+            manifest_builder()
+                .model(
+                    customer_model()
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "orders",
+                                "orders",
+                                "customer_orders",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("order_count", "bigint")
+                                .calculated(true)
+                                .expression("count(orders.o_orderkey)")
+                                .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(orders_model().primary_key("o_orderkey").build())
+                .relationship(
+                    RelationshipBuilder::new("customer_orders")
+                        .model("customer")
+                        .model("orders")
+                        .join_type(JoinType::OneToMany)
+                        .condition(condition)
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT c_name, order_count FROM customer";
+
+        // the condition misses the customer side, so the relation chain joins them without any key
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest("orders.o_custkey = orders.o_orderkey"))?
+                .with_strict_join(),
+        );
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        let Some(WrenError::ImplicitCrossJoin { left, right }) = WrenError::find_in(&err)
+        else {
+            panic!("expected an implicit cross join error: {err}");
+        };
+        let mut models = [left.as_str(), right.as_str()];
+        models.sort();
+        assert_eq!(models, ["customer", "orders"]);
+
+        // the condition without the keys of both models is rejected whatever the mode is
+        for strict_join in [false, true] {
+            let analyzed_mdl =
+                AnalyzedWrenMDL::analyze(manifest("orders.o_custkey = 1"))?;
+            let analyzed_mdl = Arc::new(if strict_join {
+                analyzed_mdl.with_strict_join()
+            } else {
+                analyzed_mdl
+            });
+            let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+            assert!(
+                err.to_string().contains(
+                    "The condition of the relationship customer_orders should compare the keys of both models"
+                ),
+                "{err}"
+            );
+        }
+
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest("customer.c_custkey = orders.o_custkey"))?
+                .with_strict_join(),
+        );
+        transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the cross join requested by the query is allowed
+        let sql = "SELECT c_name, o_orderkey FROM customer CROSS JOIN orders";
+        transform_with_mdl(&analyzed_mdl, sql).await?;
+        Ok(())
+    }
+}
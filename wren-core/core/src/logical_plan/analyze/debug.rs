@@ -0,0 +1,133 @@
+use parking_lot::Mutex;
+
+/// A decision made by [crate::logical_plan::analyze::model_generation::ModelGenerationRule]
+/// while generating the plan of a model
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationDecision {
+    /// The predicate is pushed down to the source of the model, e.g. the base filter, the
+    /// row-level security or the filter of the query pushed down to the scan
+    FilterPushed { model: String, predicate: String },
+    /// The model is joined by the relationship to provide the calculated fields of another model
+    JoinAdded { model: String, condition: String },
+    /// The columns of the model aren't required by the query, so they aren't read from the source
+    ColumnsPruned { model: String, columns: Vec<String> },
+}
+
+impl GenerationDecision {
+    /// The model responsible for the decision
+    pub fn model(&self) -> &str {
+        match self {
+            GenerationDecision::FilterPushed { model, .. }
+            | GenerationDecision::JoinAdded { model, .. }
+            | GenerationDecision::ColumnsPruned { model, .. } => model,
+        }
+    }
+}
+
+/// Collect the [GenerationDecision]s of the model generation, so a surprising plan, e.g. an
+/// unexpected join or a dropped filter, can be explained.
+///
+/// It's registered as an extension of the [datafusion::prelude::SessionConfig] of the query and
+/// passed to the model generation when the context is created. The decisions are recorded in the
+/// order they're made.
+#[derive(Debug, Default)]
+pub struct GenerationDebugCollector {
+    decisions: Mutex<Vec<GenerationDecision>>,
+}
+
+impl GenerationDebugCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record(&self, decision: GenerationDecision) {
+        self.decisions.lock().push(decision);
+    }
+
+    pub fn decisions(&self) -> Vec<GenerationDecision> {
+        self.decisions.lock().clone()
+    }
+
+    /// Clear the recorded decisions, e.g. before generating the next query
+    pub fn clear(&self) {
+        self.decisions.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logical_plan::analyze::debug::{
+        GenerationDebugCollector, GenerationDecision,
+    };
+    use crate::mdl::builder::{ColumnBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{customer_model, manifest_builder, orders_model};
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_generation_debug_collector() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                orders_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("customer_name", "varchar")
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("deleted_at", "date").build())
+                    .base_filter("deleted_at IS NULL")
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey, customer_name FROM orders";
+        let collector = Arc::new(GenerationDebugCollector::new());
+        let session = SessionContext::new_with_config(
+            SessionConfig::new().with_extension(Arc::clone(&collector)),
+        );
+        let _ = transform_sql_with_ctx(&session, analyzed_mdl, &[], sql).await?;
+
+        let decisions = collector.decisions();
+        assert!(decisions.iter().any(|decision| matches!(
+            decision,
+            GenerationDecision::FilterPushed { model, predicate }
+                if model == "customer" && predicate.contains("deleted_at IS NULL")
+        )));
+        assert!(decisions.iter().any(|decision| matches!(
+            decision,
+            GenerationDecision::JoinAdded { model, condition }
+                if model == "customer" && condition.contains("c_custkey")
+        )));
+        // nothing is filtered on the orders
+        assert!(!decisions.iter().any(|decision| matches!(
+            decision,
+            GenerationDecision::FilterPushed { model, .. } if model == "orders"
+        )));
+        Ok(())
+    }
+}
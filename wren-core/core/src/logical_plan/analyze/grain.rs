@@ -0,0 +1,472 @@
+use crate::logical_plan::utils::belong_to_mdl;
+use crate::mdl::manifest::{JoinType, Model};
+use crate::mdl::{AnalyzeOptions, AnalyzedWrenMDL, SessionStateRef, WrenMDL};
+use crate::WrenError;
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::{Column, Result};
+use datafusion::logical_expr::utils::find_aggregate_exprs;
+use datafusion::logical_expr::{Aggregate, Expr, LogicalPlan, SubqueryAlias};
+use datafusion::sql::TableReference;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// The aggregate functions that aren't affected by the duplicated rows of the fan-out join
+const DUPLICATE_INSENSITIVE_AGGREGATIONS: [&str; 4] =
+    ["min", "max", "bool_and", "bool_or"];
+
+/// The aggregate functions adding up the values of the rows
+const ADDITIVE_AGGREGATIONS: [&str; 1] = ["sum"];
+
+/// Check the grain of the measures and the dimensions of each aggregation in the plan.
+///
+/// A measure from model A grouped by a dimension from model B is meaningful only if every hop of
+/// the relationship path from A to B is to-one. Otherwise, the rows of A are duplicated by the join
+/// and the measure is inflated. If both models take part in relationships but there is no path
+/// between them, the grain of the measure can't be determined either.
+///
+/// A non-additive measure, e.g. an average or a ratio, can't be added up across its rows either. It
+/// can be summed by a dimension from model B only if every hop of the path from B to A is to-one,
+/// or the measure is grouped by the primary key of A, so each group keeps a single row of A.
+///
+/// The models without any relationship are skipped because their grain is unknown. The grain of
+/// the measures and the non-additive measures are checked only if they're enabled by
+/// [AnalyzedWrenMDL::with_grain_check] and [AnalyzedWrenMDL::with_non_additive_check].
+pub(crate) fn check_aggregation_grain(
+    plan: &LogicalPlan,
+    analyzed_wren_mdl: &Arc<AnalyzedWrenMDL>,
+    session_state: &SessionStateRef,
+) -> Result<()> {
+    let options = analyzed_wren_mdl.options();
+    if !options.grain_check && !options.non_additive_check {
+        return Ok(());
+    }
+    let wren_mdl = analyzed_wren_mdl.wren_mdl();
+    plan.apply_with_subqueries(|plan| {
+        if let LogicalPlan::Aggregate(aggregate) = plan {
+            check_grain(aggregate, &wren_mdl, options, session_state)?;
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(())
+}
+
+fn check_grain(
+    aggregate: &Aggregate,
+    wren_mdl: &WrenMDL,
+    options: &AnalyzeOptions,
+    session_state: &SessionStateRef,
+) -> Result<()> {
+    let relation_models =
+        collect_relation_models(&aggregate.input, wren_mdl, session_state)?;
+    if relation_models.len() < 2 {
+        return Ok(());
+    }
+    let model_of = |column: &Column| {
+        column
+            .relation
+            .as_ref()
+            .and_then(|relation| relation_models.get(relation))
+    };
+
+    let dimensions = aggregate
+        .group_expr
+        .iter()
+        .flat_map(|expr| expr.column_refs())
+        .filter_map(|column| model_of(column).map(|model| (column, model)))
+        .collect::<Vec<_>>();
+
+    for measure in find_aggregate_exprs(aggregate.aggr_expr.iter()) {
+        let Expr::AggregateFunction(function) = &measure else {
+            continue;
+        };
+        if function.params.distinct
+            || DUPLICATE_INSENSITIVE_AGGREGATIONS.contains(&function.func.name())
+        {
+            continue;
+        }
+        if options.grain_check {
+            let measure_models = measure
+                .column_refs()
+                .into_iter()
+                .filter_map(model_of)
+                .collect::<HashSet<_>>();
+            for measure_model in measure_models {
+                for (dimension, dimension_model) in dimensions.iter() {
+                    if measure_model == *dimension_model {
+                        continue;
+                    }
+                    if let Some(reason) =
+                        incompatible_reason(wren_mdl, measure_model, dimension_model)
+                    {
+                        return Err(WrenError::IncompatibleGrain {
+                            measure: measure.to_string(),
+                            dimension: dimension.flat_name(),
+                            reason,
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+        if !options.non_additive_check {
+            continue;
+        }
+
+        let Some((column, measure_model)) =
+            summed_non_additive_column(&measure, |column| {
+                model_of(column).and_then(|model| wren_mdl.get_model(model))
+            })
+        else {
+            continue;
+        };
+        let grouped_by_key = dimensions.iter().any(|(dimension, dimension_model)| {
+            *dimension_model == measure_model.name()
+                && measure_model.primary_key() == Some(dimension.name.as_str())
+        });
+        if grouped_by_key {
+            continue;
+        }
+        for (_, dimension_model) in dimensions.iter() {
+            if let Some(reason) =
+                fan_out_reason(wren_mdl, measure_model.name(), dimension_model)
+            {
+                return Err(WrenError::NonAdditiveMeasure {
+                    measure: measure.to_string(),
+                    column: column.flat_name(),
+                    reason,
+                }
+                .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Return the non-additive column added up by the measure with its model
+fn summed_non_additive_column<'a>(
+    measure: &'a Expr,
+    model_of: impl Fn(&Column) -> Option<Arc<Model>>,
+) -> Option<(&'a Column, Arc<Model>)> {
+    let Expr::AggregateFunction(function) = measure else {
+        return None;
+    };
+    if !ADDITIVE_AGGREGATIONS.contains(&function.func.name()) {
+        return None;
+    }
+    measure.column_refs().into_iter().find_map(|column| {
+        let model = model_of(column)?;
+        model
+            .get_column(&column.name)
+            .is_some_and(|c| c.non_additive)
+            .then_some((column, model))
+    })
+}
+
+/// Collect the relations (table names or aliases) referring to a model directly
+pub(super) fn collect_relation_models(
+    plan: &LogicalPlan,
+    wren_mdl: &WrenMDL,
+    session_state: &SessionStateRef,
+) -> Result<HashMap<TableReference, String>> {
+    let mut relation_models = HashMap::new();
+    let model_name = |plan: &LogicalPlan| match plan {
+        LogicalPlan::TableScan(table_scan)
+            if belong_to_mdl(
+                wren_mdl,
+                table_scan.table_name.clone(),
+                Arc::clone(session_state),
+            ) =>
+        {
+            wren_mdl
+                .get_model(table_scan.table_name.table())
+                .map(|model| model.name().to_string())
+        }
+        _ => None,
+    };
+    plan.apply(|plan| {
+        match plan {
+            LogicalPlan::TableScan(table_scan) => {
+                if let Some(model) = model_name(plan) {
+                    relation_models.insert(table_scan.table_name.clone(), model);
+                }
+            }
+            LogicalPlan::SubqueryAlias(SubqueryAlias { input, alias, .. }) => {
+                if let Some(model) = model_name(input) {
+                    relation_models.insert(alias.clone(), model);
+                }
+            }
+            _ => {}
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(relation_models)
+}
+
+/// Return the reason if the measure of `measure_model` can't be grouped by the dimension of
+/// `dimension_model`
+fn incompatible_reason(
+    wren_mdl: &WrenMDL,
+    measure_model: &str,
+    dimension_model: &str,
+) -> Option<String> {
+    let links = relationship_links(wren_mdl);
+    if !links.contains_key(measure_model) || !links.contains_key(dimension_model) {
+        return None;
+    }
+    if is_reachable(&links, measure_model, dimension_model, true) {
+        return None;
+    }
+    if is_reachable(&links, measure_model, dimension_model, false) {
+        Some(format!(
+            "the relationship path from {measure_model} to {dimension_model} is to-many, \
+            the rows of {measure_model} would be duplicated by the join"
+        ))
+    } else {
+        Some(format!(
+            "there is no relationship path between {measure_model} and {dimension_model}"
+        ))
+    }
+}
+
+/// Return the reason if the rows of `measure_model` are added up for each row of `dimension_model`
+fn fan_out_reason(
+    wren_mdl: &WrenMDL,
+    measure_model: &str,
+    dimension_model: &str,
+) -> Option<String> {
+    if measure_model == dimension_model {
+        return None;
+    }
+    let links = relationship_links(wren_mdl);
+    if !links.contains_key(measure_model) || !links.contains_key(dimension_model) {
+        return None;
+    }
+    if is_reachable(&links, dimension_model, measure_model, true) {
+        return None;
+    }
+    Some(format!(
+        "the relationship path from {dimension_model} to {measure_model} is to-many, \
+        the rows of {measure_model} would be added up for each row of {dimension_model}"
+    ))
+}
+
+/// The relationship links of each model with the join type from the perspective of the model
+fn relationship_links(wren_mdl: &WrenMDL) -> HashMap<&str, Vec<(&str, JoinType)>> {
+    let mut links: HashMap<&str, Vec<(&str, JoinType)>> = HashMap::new();
+    for relationship in wren_mdl.manifest.relationships.iter() {
+        let [left, right] = relationship.models.as_slice() else {
+            continue;
+        };
+        let reversed = match relationship.join_type {
+            JoinType::OneToMany => JoinType::ManyToOne,
+            JoinType::ManyToOne => JoinType::OneToMany,
+            join_type => join_type,
+        };
+        links
+            .entry(left.as_str())
+            .or_default()
+            .push((right.as_str(), relationship.join_type));
+        links
+            .entry(right.as_str())
+            .or_default()
+            .push((left.as_str(), reversed));
+    }
+    links
+}
+
+fn is_reachable(
+    links: &HashMap<&str, Vec<(&str, JoinType)>>,
+    from: &str,
+    to: &str,
+    to_one_only: bool,
+) -> bool {
+    let mut visited = HashSet::from([from]);
+    let mut queue = VecDeque::from([from]);
+    while let Some(model) = queue.pop_front() {
+        if model == to {
+            return true;
+        }
+        for (next, join_type) in links.get(model).into_iter().flatten() {
+            if (!to_one_only || join_type.is_to_one()) && visited.insert(*next) {
+                queue.push_back(*next);
+            }
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{customer_model, manifest_builder, orders_model};
+    use crate::mdl::manifest::{JoinType, Manifest};
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_incompatible_grain() -> Result<()> {
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest())?.with_grain_check());
+        let ctx = SessionContext::new();
+
+        // orders to customer is many-to-one, so the measure of orders can be grouped by customer
+        let sql = "SELECT c.c_name, sum(o.o_totalprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY c.c_name";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+
+        // the balance of customer is duplicated for each order
+        let sql = "SELECT o.o_orderstatus, sum(c.c_acctbal) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY o.o_orderstatus";
+        let err = transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql)
+            .await
+            .unwrap_err();
+        let Some(WrenError::IncompatibleGrain {
+            measure,
+            dimension,
+            reason,
+        }) = WrenError::find_in(&err)
+        else {
+            panic!("expected an incompatible grain error: {err}");
+        };
+        assert_eq!(measure, "sum(c.c_acctbal)");
+        assert_eq!(dimension, "o.o_orderstatus");
+        assert_eq!(
+            reason,
+            "the relationship path from customer to orders is to-many, \
+            the rows of customer would be duplicated by the join"
+        );
+
+        // the duplicate-insensitive aggregation is allowed
+        let sql = "SELECT o.o_orderstatus, max(c.c_acctbal) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY o.o_orderstatus";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+
+        // there is no relationship path between orders and supplier
+        let sql = "SELECT s.s_name, sum(o.o_totalprice) FROM orders o \
+            JOIN supplier s ON o.o_custkey = s.s_suppkey GROUP BY s.s_name";
+        let err = transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::IncompatibleGrain {
+                measure: "sum(o.o_totalprice)".to_string(),
+                dimension: "s.s_name".to_string(),
+                reason: "there is no relationship path between orders and supplier"
+                    .to_string(),
+            })
+        );
+
+        // the grain isn't checked unless it's enabled
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+        let sql = "SELECT o.o_orderstatus, sum(c.c_acctbal) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY o.o_orderstatus";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_non_additive_measure() -> Result<()> {
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest())?.with_non_additive_check());
+        let ctx = SessionContext::new();
+
+        // the average price of the orders is summed for each customer
+        let sql = "SELECT c.c_name, sum(o.o_avgprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY c.c_name";
+        let err = transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::NonAdditiveMeasure {
+                measure: "sum(o.o_avgprice)".to_string(),
+                column: "o.o_avgprice".to_string(),
+                reason: "the relationship path from customer to orders is to-many, \
+                    the rows of orders would be added up for each row of customer"
+                    .to_string(),
+            })
+        );
+
+        // each group keeps a single order
+        let sql = "SELECT o.o_orderkey, c.c_name, sum(o.o_avgprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY o.o_orderkey, c.c_name";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+
+        // the non-additive measure can be combined by a non-additive aggregation
+        let sql = "SELECT c.c_name, max(o.o_avgprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY c.c_name";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+
+        // the additive measure can be summed for each customer
+        let sql = "SELECT c.c_name, sum(o.o_totalprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY c.c_name";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+
+        // the non-additive measure isn't checked unless it's enabled
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+        let sql = "SELECT c.c_name, sum(o.o_avgprice) FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey GROUP BY c.c_name";
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        Ok(())
+    }
+
+    fn manifest() -> Manifest {
+        manifest_builder()
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("c_acctbal", "bigint").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_orderstatus", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("o_avgprice", "bigint")
+                            .non_additive(true)
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("supplier")
+                    .table_reference("supplier")
+                    .column(ColumnBuilder::new("s_suppkey", "bigint").build())
+                    .column(ColumnBuilder::new("s_name", "varchar").build())
+                    .column(ColumnBuilder::new("s_nationkey", "bigint").build())
+                    .primary_key("s_suppkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("nation")
+                    .table_reference("nation")
+                    .column(ColumnBuilder::new("n_nationkey", "bigint").build())
+                    .column(ColumnBuilder::new("n_name", "varchar").build())
+                    .primary_key("n_nationkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("supplier_nation")
+                    .model("supplier")
+                    .model("nation")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("supplier.s_nationkey = nation.n_nationkey")
+                    .build(),
+            )
+            .build()
+    }
+}
@@ -1,7 +1,18 @@
+mod common_expr;
+mod constant_folding;
+mod cross_join;
+pub mod debug;
 pub mod expand_view;
+mod grain;
 pub mod model_anlayze;
 pub mod model_generation;
+mod pagination;
+mod partition;
 pub mod plan;
+mod projection_folding;
 mod relation_chain;
+pub mod relative_date;
+mod top_n;
+mod user_order;
 
-pub use relation_chain::RelationChain;
+pub use relation_chain::{RelationChain, JOIN_HINT_METADATA_KEY};
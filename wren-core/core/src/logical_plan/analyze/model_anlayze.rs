@@ -1,12 +1,20 @@
+use crate::logical_plan::analyze::grain::check_aggregation_grain;
+use crate::logical_plan::analyze::pagination::apply_default_order;
 use crate::logical_plan::analyze::plan::ModelPlanNode;
-use crate::logical_plan::utils::{belong_to_mdl, expr_to_columns};
+use crate::logical_plan::analyze::relative_date::relative_date_column;
+use crate::logical_plan::analyze::top_n::apply_top_n_dimensions;
+use crate::logical_plan::utils::{
+    belong_to_mdl, expr_to_columns, infer_placeholder_types,
+};
 use crate::mdl::utils::quoted;
 use crate::mdl::{AnalyzedWrenMDL, Dataset, SessionStateRef};
+use crate::WrenError;
 use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
 use datafusion::common::{internal_err, plan_err, Column, DFSchemaRef, Result, Spans};
 use datafusion::config::ConfigOptions;
 use datafusion::error::DataFusionError;
 use datafusion::logical_expr::expr::Alias;
+use datafusion::logical_expr::utils::split_conjunction;
 use datafusion::logical_expr::{
     col, ident, Aggregate, Distinct, DistinctOn, Expr, Extension, Filter, Join,
     LogicalPlan, LogicalPlanBuilder, Projection, Subquery, SubqueryAlias, TableScan,
@@ -43,6 +51,10 @@ impl Debug for ModelAnalyzeRule {
 
 impl AnalyzerRule for ModelAnalyzeRule {
     fn analyze(&self, plan: LogicalPlan, _: &ConfigOptions) -> Result<LogicalPlan> {
+        check_aggregation_grain(&plan, &self.analyzed_wren_mdl, &self.session_state)?;
+        let plan =
+            apply_top_n_dimensions(plan, &self.analyzed_wren_mdl, &self.session_state)?;
+        let plan = apply_default_order(plan, &self.analyzed_wren_mdl)?;
         let root = RefCell::new(Scope::new());
         self.analyze_scope(plan, &root)?
             .map_data(|plan| self.analyze_model(plan, &root).data())?
@@ -179,6 +191,16 @@ impl ModelAnalyzeRule {
                 acuum.into_iter().try_for_each(|expr| {
                     self.collect_required_column(Expr::Column(expr), &mut scope_mut)
                 })?;
+                for conjunct in split_conjunction(&filter.predicate) {
+                    let mut columns = HashSet::new();
+                    expr_to_columns(conjunct, &mut columns)?;
+                    let mut relations = columns.into_iter().map(|c| c.relation);
+                    if let Some(Some(relation)) = relations.next() {
+                        if relations.all(|r| r.as_ref() == Some(&relation)) {
+                            scope_mut.add_filter(relation, conjunct.clone());
+                        }
+                    }
+                }
                 Ok(Transformed::no(plan))
             }
             LogicalPlan::Aggregate(aggregate) => {
@@ -427,6 +449,23 @@ impl ModelAnalyzeRule {
             if let Some(model) = analyzed_wren_mdl.wren_mdl.get_model(table_name) {
                 let table_ref = alias.unwrap_or(table_scan.table_name.clone());
                 let scope = scope.borrow();
+                // the backend rejects the scan without a predicate on the required partition
+                // column, so the query is rejected early
+                if let Some(partition_column) = model.required_partition_filter() {
+                    let filtered = scope.get_filters(&table_ref).iter().any(|filter| {
+                        filter
+                            .column_refs()
+                            .iter()
+                            .any(|column| column.name == partition_column)
+                    });
+                    if !filtered {
+                        return Err(WrenError::MissingPartitionFilter {
+                            model: model.name().to_string(),
+                            column: partition_column.to_string(),
+                        }
+                        .into());
+                    }
+                }
                 let field: Vec<Expr> = if let Some(used_columns) =
                     scope.try_get_required_columns(&table_ref)
                 {
@@ -442,11 +481,35 @@ impl ModelAnalyzeRule {
                     };
                     vec![]
                 };
+                // the filters of the partitioned model prune the partitions to scan, and the
+                // relative date filters of the source columns are pushed down to the scan
+                let table_scan = if model.partition_template().is_some() {
+                    TableScan {
+                        filters: scope.get_filters(&table_ref),
+                        ..table_scan.clone()
+                    }
+                } else {
+                    let source_columns = model.get_physical_columns();
+                    let mut filters = table_scan.filters.clone();
+                    filters.extend(scope.get_filters(&table_ref).into_iter().filter(
+                        |f| {
+                            relative_date_column(f).is_some_and(|column| {
+                                source_columns.iter().any(|c| {
+                                    c.name() == column.name && c.expression().is_none()
+                                })
+                            })
+                        },
+                    ));
+                    TableScan {
+                        filters,
+                        ..table_scan.clone()
+                    }
+                };
                 let model_plan = LogicalPlan::Extension(Extension {
                     node: Arc::new(ModelPlanNode::new(
                         Arc::clone(&model),
                         field,
-                        Some(LogicalPlan::TableScan(table_scan.clone())),
+                        Some(LogicalPlan::TableScan(table_scan)),
                         Arc::clone(&self.analyzed_wren_mdl),
                         Arc::clone(&self.session_state),
                     )?),
@@ -599,6 +662,7 @@ impl ModelAnalyzeRule {
                 input, predicate, ..
             }) => {
                 let Some(alias_model) = Self::find_alias_model(Arc::clone(&input)) else {
+                    let predicate = infer_placeholder_types(predicate, input.schema())?;
                     return Ok(Transformed::no(LogicalPlan::Filter(Filter::try_new(
                         predicate, input,
                     )?)));
@@ -610,6 +674,7 @@ impl ModelAnalyzeRule {
                         input.schema().clone(),
                     )?
                     .data;
+                let expr = infer_placeholder_types(expr, input.schema())?;
                 Ok(Transformed::yes(LogicalPlan::Filter(Filter::try_new(
                     expr, input,
                 )?)))
@@ -779,6 +844,8 @@ pub struct Scope {
     visited_dataset: HashMap<TableReference, Dataset>,
     /// The table name visited in the scope (not only the Wren dataset)
     visited_tables: HashSet<TableReference>,
+    /// The conjuncts of the filters referring to a single table, e.g. to prune its partitions
+    filters: HashMap<TableReference, Vec<Expr>>,
     /// The parent scope
     parent: Option<Box<RefCell<Scope>>>,
     childs: VecDeque<RefCell<Scope>>,
@@ -790,6 +857,7 @@ impl Scope {
             required_columns: HashMap::new(),
             visited_dataset: HashMap::new(),
             visited_tables: HashSet::new(),
+            filters: HashMap::new(),
             parent: None,
             childs: VecDeque::new(),
         }
@@ -800,6 +868,7 @@ impl Scope {
             required_columns: HashMap::new(),
             visited_dataset: HashMap::new(),
             visited_tables: HashSet::new(),
+            filters: HashMap::new(),
             parent: Some(Box::new(parent)),
             childs: VecDeque::new(),
         }
@@ -855,6 +924,16 @@ impl Scope {
         self.visited_tables.insert(table_ref);
     }
 
+    pub fn add_filter(&mut self, table_ref: TableReference, expr: Expr) {
+        self.filters.entry(table_ref).or_default().push(expr);
+    }
+
+    /// Return the filters of the table in the current scope. The filters of the parent scope
+    /// don't filter the rows of the table in the subquery.
+    pub fn get_filters(&self, table_ref: &TableReference) -> Vec<Expr> {
+        self.filters.get(table_ref).cloned().unwrap_or_default()
+    }
+
     pub fn try_get_required_columns(
         &self,
         table_ref: &TableReference,
@@ -1,40 +1,799 @@
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::sync::Arc;
 
+use crate::logical_plan::analyze::common_expr::project_with_common_exprs;
+use crate::logical_plan::analyze::constant_folding::fold_constant_exprs;
+use crate::logical_plan::analyze::cross_join::{
+    check_implicit_cross_joins, collect_cross_joins,
+};
+use crate::logical_plan::analyze::debug::{GenerationDebugCollector, GenerationDecision};
+use crate::logical_plan::analyze::partition::{resolve_partitions, Partition};
 use crate::logical_plan::analyze::plan::{
     CalculationPlanNode, ModelPlanNode, ModelSourceNode, PartialModelPlanNode,
 };
+use crate::logical_plan::analyze::projection_folding::fold_redundant_projections;
+use crate::logical_plan::analyze::relation_chain::RelationChain;
+use crate::logical_plan::analyze::relative_date::{
+    resolve_relative_date, resolve_relative_date_filters,
+};
+use crate::logical_plan::analyze::user_order::anchor_user_order;
 use crate::logical_plan::utils::{
-    create_remote_table_source, eliminate_ambiguous_columns, rebase_column,
+    create_remote_table_source, eliminate_ambiguous_columns, from_qualified_name,
+    map_data_type, rebase_column,
 };
-use crate::mdl::manifest::Model;
-use crate::mdl::utils::quoted;
+use crate::mdl::cache::cache_table_name;
+use crate::mdl::manifest::{Model, RelationshipFilter, RowLevelOperator};
+use crate::mdl::query_context::QueryContext;
+use crate::mdl::type_override::ColumnTypeOverrides;
+use crate::mdl::utils::{collect_identifiers, create_remote_expr_for_model, quoted};
 use crate::mdl::{AnalyzedWrenMDL, SessionStateRef};
-use crate::DataFusionError;
+use crate::{DataFusionError, WrenError};
+use datafusion::arrow::compute::can_cast_types;
+use datafusion::arrow::datatypes::DataType;
 use datafusion::common::alias::AliasGenerator;
 use datafusion::common::config::ConfigOptions;
-use datafusion::common::tree_node::{Transformed, TransformedResult};
-use datafusion::common::{plan_err, Result};
-use datafusion::logical_expr::{col, ident, Extension, UserDefinedLogicalNodeCore};
-use datafusion::logical_expr::{Expr, LogicalPlan, LogicalPlanBuilder};
+use datafusion::common::tree_node::{
+    Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
+};
+use datafusion::common::{
+    plan_err, Column, DFSchema, Result, ScalarValue, UnnestOptions,
+};
+use datafusion::datasource::provider_as_source;
+use datafusion::functions::expr_fn::{concat_ws, md5, random, substring};
+use datafusion::functions_aggregate::expr_fn::count;
+use datafusion::functions_aggregate::sum::sum_udaf;
+use datafusion::functions_window::expr_fn::row_number;
+use datafusion::logical_expr::expr::WindowFunction;
+use datafusion::logical_expr::type_coercion::binary::comparison_coercion;
+use datafusion::logical_expr::utils::{
+    conjunction, find_aggregate_exprs, find_window_exprs, split_conjunction_owned,
+};
+use datafusion::logical_expr::{
+    cast, col, exists, ident, lit, not_exists, out_ref_col, ExprFunctionExt, Extension,
+    UserDefinedLogicalNodeCore,
+};
+use datafusion::logical_expr::{
+    Expr, LogicalPlan, LogicalPlanBuilder, Projection, TableSource,
+};
 use datafusion::optimizer::analyzer::AnalyzerRule;
-use datafusion::physical_plan::internal_err;
 use datafusion::sql::TableReference;
 
 pub const SOURCE_ALIAS: &str = "__source";
+/// The hidden column counting the rows of each group of the calculation
+pub const ROW_COUNT_COLUMN: &str = "__wren_row_count";
+const LATEST_RANK: &str = "__latest_rank";
+/// The range of the first 8 hex digits of the seeded hash
+const HASH_PREFIX_RANGE: f64 = 4294967296.0;
+
+/// Divide the grouped measure by its total over the whole grouped result.
+///
+/// The total is a `sum` window without any partition, so it's computed after the aggregation
+/// and the filtered-out groups don't contribute to it.
+fn share_of_total(plan: LogicalPlan, measure: &str) -> Result<LogicalPlan> {
+    let total_name = format!("__total_{measure}");
+    let measure_col = col(Column::from_name(measure));
+    let total =
+        Expr::WindowFunction(WindowFunction::new(sum_udaf(), vec![measure_col.clone()]))
+            .alias(&total_name);
+    let mut project = plan
+        .schema()
+        .columns()
+        .into_iter()
+        .filter(|column| column.name() != measure)
+        .map(Expr::Column)
+        .collect::<Vec<_>>();
+    project.push(
+        (cast(measure_col, DataType::Float64)
+            / cast(col(Column::from_name(total_name)), DataType::Float64))
+        .alias(measure),
+    );
+    LogicalPlanBuilder::from(plan)
+        .window(vec![total])?
+        .project(project)?
+        .build()
+}
+
+/// The output column of the grouped expression. The aliased expression is referenced by its
+/// alias and the column by its qualified name.
+fn grouped_column(expr: &Expr) -> Expr {
+    let (relation, name) = expr.qualified_name();
+    Expr::Column(Column::new(relation, name))
+}
 
 /// [ModelGenerationRule] is responsible for generating the model plan node.
+#[derive(Clone)]
 pub struct ModelGenerationRule {
     analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
     session_state: SessionStateRef,
+    use_model_cache: bool,
+    /// How many times each [PartialModelPlanNode] is referenced in the analyzed plan
+    partial_model_usage: Arc<HashMap<PartialModelPlanNode, usize>>,
+    /// The values of the session issuing the query, e.g. for the row-level security
+    query_context: Arc<QueryContext>,
+    /// Records the decisions of the generation if the debugging is requested
+    debug_collector: Option<Arc<GenerationDebugCollector>>,
 }
 
 impl ModelGenerationRule {
-    pub fn new(mdl: Arc<AnalyzedWrenMDL>, session_state: SessionStateRef) -> Self {
+    pub fn new(
+        mdl: Arc<AnalyzedWrenMDL>,
+        session_state: SessionStateRef,
+        query_context: Arc<QueryContext>,
+    ) -> Self {
         Self {
             analyzed_wren_mdl: mdl,
             session_state,
+            use_model_cache: false,
+            partial_model_usage: Arc::new(HashMap::new()),
+            query_context,
+            debug_collector: None,
+        }
+    }
+
+    pub(crate) fn session_state(&self) -> SessionStateRef {
+        Arc::clone(&self.session_state)
+    }
+
+    pub(crate) fn query_context(&self) -> Arc<QueryContext> {
+        Arc::clone(&self.query_context)
+    }
+
+    fn with_partial_model_usage(
+        &self,
+        partial_model_usage: HashMap<PartialModelPlanNode, usize>,
+    ) -> Self {
+        Self {
+            partial_model_usage: Arc::new(partial_model_usage),
+            ..self.clone()
+        }
+    }
+
+    /// Record the decisions of the generation to the collector, e.g. the joins added for the
+    /// calculated fields and the filters pushed down to the model sources.
+    pub fn with_debug_collector(
+        mut self,
+        collector: Arc<GenerationDebugCollector>,
+    ) -> Self {
+        self.debug_collector = Some(collector);
+        self
+    }
+
+    pub(crate) fn record(&self, decision: impl FnOnce() -> GenerationDecision) {
+        if let Some(collector) = &self.debug_collector {
+            collector.record(decision());
+        }
+    }
+
+    /// Read the model from the model cache of [AnalyzedWrenMDL] if it's available.
+    /// It should only be enabled for the local runtime.
+    pub fn with_model_cache(mut self) -> Self {
+        self.use_model_cache = true;
+        self
+    }
+
+    /// Build the plan reading the cached relation if the cache provides all the required columns.
+    /// Only the relation cached in the scope of the [QueryContext] of the query is read.
+    fn plan_from_cache(&self, model_plan: &ModelPlanNode) -> Result<Option<LogicalPlan>> {
+        if !self.use_model_cache || model_plan.required_exprs.is_empty() {
+            return Ok(None);
+        }
+        let Some(cache) = self.analyzed_wren_mdl.model_cache() else {
+            return Ok(None);
+        };
+        let required_columns: Vec<String> =
+            UserDefinedLogicalNodeCore::schema(model_plan)
+                .fields()
+                .iter()
+                .map(|f| f.name().clone())
+                .collect();
+        let Some(table) = cache.get(
+            &model_plan.plan_name,
+            &self.query_context.cache_scope(),
+            &required_columns,
+        ) else {
+            return Ok(None);
+        };
+        let plan = LogicalPlanBuilder::scan(
+            TableReference::bare(cache_table_name(&model_plan.plan_name)),
+            provider_as_source(table),
+            None,
+        )?
+        .project(required_columns.iter().map(ident))?
+        .build()?;
+        Ok(Some(plan))
+    }
+
+    /// Reconcile the nullability of the output fields with the manifest and the joins.
+    ///
+    /// The physical columns of the model itself are nullable as they're declared, whatever the
+    /// source infers. The calculated fields reading the related models come from the outer side
+    /// of the relationship joins, so they're nullable even if the read columns are declared not
+    /// null. The other calculated fields keep the nullability inferred by DataFusion.
+    fn reconcile_nullability(
+        &self,
+        model_name: &str,
+        plan: LogicalPlan,
+    ) -> Result<LogicalPlan> {
+        let LogicalPlan::Projection(projection) = plan else {
+            return Ok(plan);
+        };
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        let Some(model) = wren_mdl.get_model(model_name) else {
+            return Err(WrenError::ModelNotFound(model_name.to_string()).into());
+        };
+        let required_dataset_topo =
+            &self.analyzed_wren_mdl.lineage().required_dataset_topo;
+        let schema = Arc::clone(&projection.schema);
+        let mut changed = false;
+        let fields = schema
+            .iter()
+            .map(|(qualifier, field)| {
+                let nullable = match model.get_column(field.name()) {
+                    Some(c)
+                        if !c.is_calculated
+                            && c.relationship.is_none()
+                            && c.semi_additive.is_none() =>
+                    {
+                        !c.not_null
+                    }
+                    Some(c) if c.is_calculated => {
+                        let qualified_column =
+                            from_qualified_name(&wren_mdl, model.name(), c.name());
+                        // the related models, including the instances of a self
+                        // relationship, are joined as the other vertices
+                        let reads_related_model = required_dataset_topo
+                            .get(&qualified_column)
+                            .is_some_and(|graph| graph.node_count() > 1);
+                        reads_related_model || field.is_nullable()
+                    }
+                    _ => field.is_nullable(),
+                };
+                if nullable != field.is_nullable() {
+                    changed = true;
+                    let field = field.as_ref().clone().with_nullable(nullable);
+                    (qualifier.cloned(), Arc::new(field))
+                } else {
+                    (qualifier.cloned(), Arc::clone(field))
+                }
+            })
+            .collect::<Vec<_>>();
+        if !changed {
+            return Ok(LogicalPlan::Projection(projection));
+        }
+        let reconciled = DFSchema::new_with_metadata(fields, schema.metadata().clone())?
+            .with_functional_dependencies(schema.functional_dependencies().clone())?;
+        Ok(LogicalPlan::Projection(Projection::try_new_with_schema(
+            projection.expr,
+            projection.input,
+            Arc::new(reconciled),
+        )?))
+    }
+
+    /// Cast the columns to the types overridden by the query. The column that can't be cast to
+    /// the overridden type is rejected.
+    fn override_column_types(
+        &self,
+        model_name: &str,
+        plan: LogicalPlan,
+    ) -> Result<LogicalPlan> {
+        let Some(overrides) = ColumnTypeOverrides::from_session(&self.session_state)
+        else {
+            return Ok(plan);
+        };
+        let mut changed = false;
+        let exprs = plan
+            .schema()
+            .iter()
+            .map(|(qualifier, field)| {
+                let column = Expr::Column(Column::from((qualifier, field)));
+                let Some(r#type) = overrides.get(model_name, field.name()) else {
+                    return Ok(column);
+                };
+                let data_type = map_data_type(r#type)?;
+                if !can_cast_types(field.data_type(), &data_type) {
+                    return Err(WrenError::IncompatibleColumnType {
+                        column: format!("{}.{}", model_name, field.name()),
+                        from: field.data_type().to_string(),
+                        to: r#type.to_string(),
+                    }
+                    .into());
+                }
+                changed = true;
+                Ok(cast(column, data_type)
+                    .alias_qualified(qualifier.cloned(), field.name()))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if !changed {
+            return Ok(plan);
+        }
+        LogicalPlanBuilder::from(plan).project(exprs)?.build()
+    }
+
+    /// Filter the source of the model by all of its predicates at once. The filters of the
+    /// original scan, the base filter of the model and the row-level security are split into
+    /// their conjuncts and combined into one conjunction in that order. A conjunct repeated by
+    /// the sources is applied only once. The relative date filters of the scan are resolved to
+    /// their bounds.
+    fn filter_source(
+        &self,
+        model: &Arc<Model>,
+        source: LogicalPlanBuilder,
+        scan_filters: &[Expr],
+    ) -> Result<LogicalPlanBuilder> {
+        let mut predicates = scan_filters
+            .iter()
+            .map(|filter| {
+                let filter = resolve_relative_date(filter.clone(), &self.query_context)?;
+                rebase_column(&filter.data, SOURCE_ALIAS)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if let Some(base_filter) = model.base_filter() {
+            let expr = create_remote_expr_for_model(
+                base_filter,
+                Arc::clone(model),
+                Arc::clone(&self.analyzed_wren_mdl),
+                Arc::clone(&self.session_state),
+            )?;
+            predicates.push(rebase_column(&expr, SOURCE_ALIAS)?);
+        }
+        predicates.extend(self.row_level_security_predicates(model)?);
+        let mut conjuncts: Vec<Expr> = vec![];
+        for conjunct in predicates.into_iter().flat_map(split_conjunction_owned) {
+            if !conjuncts.contains(&conjunct) {
+                self.record(|| GenerationDecision::FilterPushed {
+                    model: model.name().to_string(),
+                    predicate: conjunct.to_string(),
+                });
+                conjuncts.push(conjunct);
+            }
+        }
+        match conjunction(conjuncts) {
+            Some(predicate) => source.filter(predicate),
+            None => Ok(source),
+        }
+    }
+
+    /// The predicates of the row-level security of the model columns. The column is compared
+    /// with the variable of the [QueryContext] named by the security.
+    fn row_level_security_predicates(&self, model: &Arc<Model>) -> Result<Vec<Expr>> {
+        let mut predicates = vec![];
+        for column in model.get_physical_columns() {
+            let Some(rls) = &column.rls else {
+                continue;
+            };
+            let Some(value) = self.query_context.variable(&rls.name) else {
+                return Err(WrenError::MissingQueryVariable {
+                    variable: rls.name.clone(),
+                    column: format!("{}.{}", model.name(), column.name()),
+                }
+                .into());
+            };
+            let expr = create_remote_expr_for_model(
+                column.expression().unwrap_or(&quoted(column.name())),
+                Arc::clone(model),
+                Arc::clone(&self.analyzed_wren_mdl),
+                Arc::clone(&self.session_state),
+            )?;
+            let expr = rebase_column(&expr, SOURCE_ALIAS)?;
+            predicates.push(row_level_predicate(expr, &rls.operator, value));
+        }
+        Ok(predicates)
+    }
+
+    /// Keep only the latest row of each key of the model, e.g.
+    /// `row_number() OVER (PARTITION BY id ORDER BY updated_at DESC) = 1`. The rows are filtered
+    /// by the row-level security first, so the latest row visible to the session is kept.
+    fn latest_per_key(
+        &self,
+        model: &Arc<Model>,
+        source: LogicalPlanBuilder,
+    ) -> Result<LogicalPlanBuilder> {
+        let Some(latest) = model.latest() else {
+            return Ok(source);
+        };
+        if latest.keys.is_empty() {
+            return plan_err!("The latest row of model {} requires keys", model.name());
+        }
+        let source_expr = |name: &str| {
+            let Some(column) = model.get_column(name) else {
+                return plan_err!("Column {} not found in model {}", name, model.name());
+            };
+            let expr = create_remote_expr_for_model(
+                column.expression().unwrap_or(&quoted(column.name())),
+                Arc::clone(model),
+                Arc::clone(&self.analyzed_wren_mdl),
+                Arc::clone(&self.session_state),
+            )?;
+            rebase_column(&expr, SOURCE_ALIAS)
+        };
+        let keys = latest
+            .keys
+            .iter()
+            .map(|key| source_expr(key))
+            .collect::<Result<Vec<_>>>()?;
+        let rank = row_number()
+            .partition_by(keys)
+            .order_by(vec![source_expr(&latest.order_by)?.sort(false, false)])
+            .build()?
+            .alias(LATEST_RANK);
+        let source = source.build()?;
+        let columns = source
+            .schema()
+            .columns()
+            .into_iter()
+            .map(Expr::Column)
+            .collect::<Vec<_>>();
+        LogicalPlanBuilder::from(source)
+            .window(vec![rank])?
+            .filter(col(LATEST_RANK).eq(lit(1_u64)))?
+            .project(columns)
+    }
+
+    /// Union the rows of the fallback sources of the model. The rows of a fallback source are read
+    /// only if their primary keys are absent from the previous sources, e.g.
+    /// ```sql
+    /// SELECT * FROM orders UNION ALL
+    /// SELECT * FROM orders_backup WHERE NOT EXISTS
+    ///   (SELECT 1 FROM orders WHERE orders.o_orderkey = orders_backup.o_orderkey)
+    /// ```
+    ///
+    /// The fallback sources share the columns of the primary source. If a column differs in type
+    /// across the sources, e.g. `INT` and `BIGINT`, it's cast to their common super type, see
+    /// [union_with_super_types]. The filters of the source are applied over the union, see
+    /// [Self::filter_source].
+    fn union_fallback_sources(
+        &self,
+        model: &Arc<Model>,
+        source: LogicalPlanBuilder,
+    ) -> Result<LogicalPlanBuilder> {
+        if model.fallback_sources().is_empty() {
+            return Ok(source);
+        }
+        let Some(primary_key) = model.primary_key() else {
+            return plan_err!(
+                "Model {} should have primary key for the fallback sources",
+                model.name()
+            );
+        };
+        let table_source = self.remote_table_source(model)?;
+        let mut previous = vec![(
+            self.table_reference(model.table_reference()),
+            Arc::clone(&table_source),
+        )];
+        let mut inputs = vec![source.build()?];
+        for fallback in model.fallback_sources() {
+            let table = self.table_reference(fallback);
+            // the registered fallback source is read by its own schema
+            let fallback_source =
+                match self.analyzed_wren_mdl.wren_mdl().get_table(fallback) {
+                    Some(provider) => provider_as_source(provider),
+                    None => Arc::clone(&table_source),
+                };
+            let key_type = fallback_source
+                .schema()
+                .field_with_name(primary_key)?
+                .data_type()
+                .clone();
+            let guards = previous
+                .iter()
+                .map(|(prev, prev_source)| {
+                    let subquery = LogicalPlanBuilder::scan(
+                        prev.clone(),
+                        Arc::clone(prev_source),
+                        None,
+                    )?
+                    .filter(col(Column::new(Some(prev.clone()), primary_key)).eq(
+                        out_ref_col(
+                            key_type.clone(),
+                            Column::new(Some(table.clone()), primary_key),
+                        ),
+                    ))?
+                    .project(vec![lit(1)])?
+                    .build()?;
+                    Ok(not_exists(Arc::new(subquery)))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let fallback_scan = LogicalPlanBuilder::scan(
+                table.clone(),
+                Arc::clone(&fallback_source),
+                None,
+            )?;
+            let fallback_scan = match conjunction(guards) {
+                Some(guard) => fallback_scan.filter(guard)?,
+                None => fallback_scan,
+            };
+            inputs.push(fallback_scan.build()?);
+            previous.push((table, fallback_source));
+        }
+        union_with_super_types(model, inputs)
+    }
+
+    /// Plan the relationship filters of the model as the `EXISTS` (or `NOT EXISTS`) subqueries
+    /// over the related model correlated with the model source, e.g. the customers without the
+    /// recent orders: `NOT EXISTS (SELECT 1 FROM orders WHERE orders.o_custkey =
+    /// __source.c_custkey AND orders.o_orderdate > ...)`.
+    ///
+    /// The model source projects the relationship filter as a placeholder, which is replaced by
+    /// the subquery here.
+    fn relationship_filters(
+        &self,
+        model: &Arc<Model>,
+        source: &DFSchema,
+        exprs: Vec<Expr>,
+    ) -> Result<Vec<Expr>> {
+        exprs
+            .into_iter()
+            .map(|expr| {
+                let Expr::Alias(alias) = &expr else {
+                    return Ok(expr);
+                };
+                match model
+                    .get_column(&alias.name)
+                    .and_then(|column| column.relationship_filter().cloned())
+                {
+                    Some(filter) => Ok(self
+                        .relationship_filter_expr(model, &filter, source)?
+                        .alias(&alias.name)),
+                    None => Ok(expr),
+                }
+            })
+            .collect()
+    }
+
+    fn relationship_filter_expr(
+        &self,
+        model: &Arc<Model>,
+        filter: &RelationshipFilter,
+        source: &DFSchema,
+    ) -> Result<Expr> {
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        let Some((relationship, related)) =
+            model.get_column(&filter.relationship).and_then(|column| {
+                let relationship =
+                    wren_mdl.get_relationship(column.relationship.as_deref()?)?;
+                Some((relationship, wren_mdl.get_model(&column.r#type)?))
+            })
+        else {
+            return plan_err!(
+                "Relationship column {} of the relationship filter not found in model {}",
+                filter.relationship,
+                model.name()
+            );
+        };
+        if related.name() == model.name() {
+            return plan_err!(
+                "The relationship filter of model {} should be over another model",
+                model.name()
+            );
+        }
+        let keys = collect_identifiers(&relationship.condition)?;
+        let key_of = |model: &str| {
+            keys.iter().find_map(|key| {
+                let (qualifier, name) = key.name.split_once('.')?;
+                (qualifier == model).then(|| name.to_string())
+            })
+        };
+        let (Some(key), Some(related_key)) =
+            (key_of(model.name()), key_of(related.name()))
+        else {
+            return plan_err!(
+                "The condition of relationship {} should join the keys of {} and {}",
+                relationship.name,
+                model.name(),
+                related.name()
+            );
+        };
+        let Some(key_column) = model.get_column(&key).filter(|c| !c.is_calculated) else {
+            return plan_err!(
+                "Key {} of the relationship filter should be a physical column of model {}",
+                key,
+                model.name()
+            );
+        };
+
+        // the key of the model is referenced from the subquery by the source columns
+        let outer_key = create_remote_expr_for_model(
+            &key_column
+                .expression()
+                .map(String::from)
+                .unwrap_or_else(|| quoted(key_column.name())),
+            Arc::clone(model),
+            Arc::clone(&self.analyzed_wren_mdl),
+            Arc::clone(&self.session_state),
+        )?;
+        let outer_key = rebase_column(&outer_key, SOURCE_ALIAS)?
+            .transform_up(|e| match e {
+                Expr::Column(column) => {
+                    let data_type =
+                        source.field_from_column(&column)?.data_type().clone();
+                    Ok(Transformed::yes(out_ref_col(data_type, column)))
+                }
+                e => Ok(Transformed::no(e)),
+            })
+            .data()?;
+
+        // the predicate is scoped to the related model, so only its columns are read
+        let mut required_columns = BTreeSet::from([related_key.clone()]);
+        if let Some(predicate) = &filter.predicate {
+            for identifier in collect_identifiers(predicate)? {
+                if let Some((qualifier, name)) = identifier.name.split_once('.') {
+                    if qualifier == related.name() {
+                        required_columns.insert(name.to_string());
+                    }
+                }
+            }
+        }
+        let related_source = ModelSourceNode::new(
+            Arc::clone(&related),
+            required_columns
+                .into_iter()
+                .map(|name| Expr::Column(Column::from_name(name)))
+                .collect(),
+            Arc::clone(&self.analyzed_wren_mdl),
+            Arc::clone(&self.session_state),
+            None,
+        )?;
+        let related_source = self
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(related_source),
+            }))?
+            .data;
+        let condition = col(Column::new(
+            Some(TableReference::bare(related.name())),
+            related_key,
+        ))
+        .eq(outer_key);
+        let condition = match &filter.predicate {
+            Some(predicate) => condition.and(
+                self.session_state
+                    .read()
+                    .create_logical_expr(predicate, related_source.schema())?,
+            ),
+            None => condition,
+        };
+        let subquery = LogicalPlanBuilder::from(related_source)
+            .filter(condition)?
+            .project(vec![lit(1)])?
+            .build()?;
+        Ok(if filter.negated {
+            not_exists(Arc::new(subquery))
+        } else {
+            exists(Arc::new(subquery))
+        })
+    }
+
+    /// Scan the table of the model
+    fn scan(&self, model: &Arc<Model>) -> Result<LogicalPlanBuilder> {
+        LogicalPlanBuilder::scan(
+            self.table_reference(model.table_reference()),
+            self.remote_table_source(model)?,
+            None,
+        )
+    }
+
+    /// Qualify the bare table reference by the default table schema of the manifest. The table
+    /// reference with a schema is kept.
+    fn table_reference(&self, table: &str) -> TableReference {
+        match (
+            TableReference::from(table),
+            self.analyzed_wren_mdl.wren_mdl().default_table_schema(),
+        ) {
+            (TableReference::Bare { table }, Some(schema)) => {
+                TableReference::partial(schema, table)
+            }
+            (table_reference, _) => table_reference,
+        }
+    }
+
+    /// Scan the partitions of the template matching the filters of the original scan and union
+    /// them. The filters only prune the partitions, so the rows are still filtered by the query.
+    fn scan_partitions(
+        &self,
+        model: &Arc<Model>,
+        original_table_scan: Option<&LogicalPlan>,
+    ) -> Result<LogicalPlanBuilder> {
+        let Some(template) = model.partition_template() else {
+            return plan_err!(
+                "Model {} doesn't declare the partition template",
+                model.name()
+            );
+        };
+        let filters = match original_table_scan {
+            Some(LogicalPlan::TableScan(scan)) => scan.filters.as_slice(),
+            _ => &[],
+        };
+        let table_source = self.remote_table_source(model)?;
+        let scan = |partition: &Partition| {
+            LogicalPlanBuilder::scan(
+                self.table_reference(&partition.table),
+                Arc::clone(&table_source),
+                None,
+            )
+        };
+        let partitions = resolve_partitions(template, filters)?;
+        let mut scans = partitions.iter().map(&scan);
+        let Some(first) = scans.next() else {
+            // none of the partitions matches, the first one is scanned for the schema only
+            let partitions = resolve_partitions(template, &[])?;
+            return scan(&partitions[0])?.filter(lit(false));
+        };
+        scans.try_fold(first?, |union, scan| union.union(scan?.build()?))
+    }
+
+    /// Fold the constant projections if it's enabled by the [AnalyzedWrenMDL]
+    fn fold_constants(&self, exprs: Vec<Expr>) -> Result<Vec<Expr>> {
+        if self.analyzed_wren_mdl.options().constant_folding {
+            fold_constant_exprs(exprs)
+        } else {
+            Ok(exprs)
+        }
+    }
+
+    /// Create the table source of the model scan by the routing hint and the query tag
+    fn remote_table_source(&self, model: &Arc<Model>) -> Result<Arc<dyn TableSource>> {
+        create_remote_table_source(
+            Arc::clone(model),
+            &self.analyzed_wren_mdl.wren_mdl(),
+            Arc::clone(&self.session_state),
+            self.routing_hint(model),
+            self.query_context.query_tag(),
+        )
+    }
+
+    /// The routing hint of the [QueryContext] takes precedence over the one of the model
+    fn routing_hint<'a>(&'a self, model: &'a Model) -> Option<&'a str> {
+        self.query_context
+            .routing_hint()
+            .or_else(|| model.routing_hint())
+    }
+
+    /// Sample the rows of the model source by the fraction of the [QueryContext]. The sampling is
+    /// planned as the filter `random() < fraction` because the logical plan can't carry a
+    /// `TABLESAMPLE` clause of the backends.
+    ///
+    /// If the seed of the [QueryContext] is set, the rows are sampled by the seeded hash of the
+    /// primary key instead, so the same rows are sampled across the runs.
+    fn sample(
+        &self,
+        model: &Model,
+        source: LogicalPlanBuilder,
+    ) -> Result<LogicalPlanBuilder> {
+        match self.query_context.sample_fraction() {
+            Some(fraction) if fraction <= 0.0 || fraction > 1.0 => {
+                plan_err!("The sample fraction should be in (0, 1]: {fraction}")
+            }
+            Some(fraction) if fraction < 1.0 => match self.query_context.seed() {
+                Some(seed) => {
+                    let hash = seeded_hash(seed, model, source.schema())?;
+                    // the prefix of the hash is compared as the hex digits of the fraction
+                    let threshold = (fraction * HASH_PREFIX_RANGE) as u64;
+                    source.filter(hash.lt(lit(format!("{threshold:08x}"))))
+                }
+                None => source.filter(random().lt(lit(fraction))),
+            },
+            _ => Ok(source),
+        }
+    }
+
+    /// A [PartialModelPlanNode] referenced only once can be replaced by its model plan directly
+    /// if the model plan provides the same fields. The projection and alias over it are redundant.
+    fn is_inlinable(&self, partial_model: &PartialModelPlanNode) -> bool {
+        if self.partial_model_usage.get(partial_model) != Some(&1) {
+            return false;
         }
+        let model_fields = UserDefinedLogicalNodeCore::schema(&partial_model.model_node)
+            .fields()
+            .iter()
+            .map(|f| f.name())
+            .collect::<HashSet<_>>();
+        let partial_fields = partial_model
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| f.name())
+            .collect::<HashSet<_>>();
+        model_fields == partial_fields
     }
 
     pub(crate) fn generate_model_internal(
@@ -47,13 +806,13 @@ impl ModelGenerationRule {
                 if let Some(model_plan) =
                     extension.node.as_any().downcast_ref::<ModelPlanNode>()
                 {
-                    let (source_plan, alias) = model_plan.relation_chain.clone().plan(
-                        ModelGenerationRule::new(
-                            Arc::clone(&self.analyzed_wren_mdl),
-                            Arc::clone(&self.session_state),
-                        ),
-                        &alias_generator,
-                    )?;
+                    if let Some(plan) = self.plan_from_cache(model_plan)? {
+                        return Ok(Transformed::yes(plan));
+                    }
+                    let (source_plan, alias) = model_plan
+                        .relation_chain
+                        .clone()
+                        .plan(self.clone(), &alias_generator)?;
 
                     let projections = if let Some(alias) = alias {
                         model_plan
@@ -65,18 +824,32 @@ impl ModelGenerationRule {
                         model_plan.required_exprs.clone()
                     };
                     let projections = eliminate_ambiguous_columns(projections);
+                    let projections = self.fold_constants(projections)?;
                     let result = match source_plan {
                         Some(plan) => {
                             if model_plan.required_exprs.is_empty() {
                                 plan
                             } else {
-                                LogicalPlanBuilder::from(plan)
-                                    .project(projections)?
-                                    .build()?
+                                let plan = project_with_common_exprs(
+                                    LogicalPlanBuilder::from(plan),
+                                    projections,
+                                )?;
+                                // collapse the duplicated rows fanned out by a distinct relationship
+                                let plan = if model_plan.distinct {
+                                    plan.distinct()?.build()?
+                                } else {
+                                    plan.build()?
+                                };
+                                let plan = self
+                                    .reconcile_nullability(&model_plan.plan_name, plan)?;
+                                self.override_column_types(&model_plan.plan_name, plan)?
                             }
                         }
                         _ => {
-                            return plan_err!("Failed to generate source plan");
+                            return Err(WrenError::MissingSourcePlan(
+                                model_plan.plan_name.clone(),
+                            )
+                            .into());
                         }
                     };
                     // calculated field scope
@@ -84,53 +857,78 @@ impl ModelGenerationRule {
                 } else if let Some(model_plan) =
                     extension.node.as_any().downcast_ref::<ModelSourceNode>()
                 {
-                    let model: Arc<Model> = Arc::clone(
-                        &self
-                            .analyzed_wren_mdl
-                            .wren_mdl()
-                            .get_model(&model_plan.model_name)
-                            .expect("Model not found"),
-                    );
+                    let Some(model) = self
+                        .analyzed_wren_mdl
+                        .wren_mdl()
+                        .get_model(&model_plan.model_name)
+                    else {
+                        return Err(WrenError::ModelNotFound(
+                            model_plan.model_name.clone(),
+                        )
+                        .into());
+                    };
                     let mut required_exprs = model_plan.required_exprs.clone();
                     required_exprs.iter_mut().try_for_each(|expr| {
                         *expr = rebase_column(expr, SOURCE_ALIAS)?;
                         Ok::<(), DataFusionError>(())
                     })?;
+                    let required_exprs = self.fold_constants(required_exprs)?;
+                    if self.debug_collector.is_some() {
+                        let schema = UserDefinedLogicalNodeCore::schema(model_plan);
+                        let pruned: Vec<_> = model
+                            .get_physical_columns()
+                            .iter()
+                            .filter(|c| {
+                                !schema.fields().iter().any(|f| f.name() == c.name())
+                            })
+                            .map(|c| c.name().to_string())
+                            .collect();
+                        if !pruned.is_empty() {
+                            self.record(|| GenerationDecision::ColumnsPruned {
+                                model: model.name().to_string(),
+                                columns: pruned,
+                            });
+                        }
+                    }
                     // support table reference
-                    let table_scan = match &model_plan.original_table_scan {
+                    let (source, scan_filters) = match &model_plan.original_table_scan {
+                        original_scan if model.partition_template().is_some() => (
+                            self.scan_partitions(&model, original_scan.as_ref())?,
+                            vec![],
+                        ),
                         Some(LogicalPlan::TableScan(original_scan)) => {
-                            LogicalPlanBuilder::scan_with_filters(
-                                TableReference::from(model.table_reference()),
-                                create_remote_table_source(
-                                    Arc::clone(&model),
-                                    &self.analyzed_wren_mdl.wren_mdl(),
-                                    Arc::clone(&self.session_state),
-                                )?,
-                                None,
-                                original_scan.filters.clone(),
-                            )?
-                                .alias(SOURCE_ALIAS)?
-                            .project(required_exprs)?
-                            .build()
+                            (self.scan(&model)?, original_scan.filters.clone())
+                        }
+                        Some(_) => {
+                            return Err(WrenError::InvalidOriginalTableScan {
+                                model: model_plan.model_name.clone(),
+                            }
+                            .into())
                         }
-                        Some(_) => Err(datafusion::error::DataFusionError::Internal(
-                            "ModelPlanNode should have a TableScan as original_table_scan"
-                                .to_string(),
-                        )),
-                        None => {
-                            LogicalPlanBuilder::scan(
-                                TableReference::from(model.table_reference()),
-                                create_remote_table_source(
-                                    Arc::clone(&model),
-                                    &self.analyzed_wren_mdl.wren_mdl(),
-                                    Arc::clone(&self.session_state))?,
-                                None,
-                            )?
-                                .alias(SOURCE_ALIAS)?
-                                .project(required_exprs)?
-                                .build()
-                        },
-                    }?;
+                        None => (self.scan(&model)?, vec![]),
+                    };
+                    let source = self.union_fallback_sources(&model, source)?;
+                    let source = self.filter_source(
+                        &model,
+                        source.alias(SOURCE_ALIAS)?,
+                        &scan_filters,
+                    )?;
+                    let source = self.latest_per_key(&model, source)?;
+                    // the single-row model is read as a scalar source, so it never fans out
+                    // the rows joined with it
+                    let source = if model.is_single_row() {
+                        source.limit(0, Some(1))?
+                    } else {
+                        source
+                    };
+                    let source = self.sample(&model, source)?;
+                    let required_exprs = self.relationship_filters(
+                        &model,
+                        source.schema(),
+                        required_exprs,
+                    )?;
+                    let table_scan = project_with_window(source, required_exprs)?;
+                    let table_scan = unnest_columns(table_scan, &model)?.build()?;
 
                     // it could be count(*) query
                     if model_plan.required_exprs.is_empty() {
@@ -145,40 +943,81 @@ impl ModelGenerationRule {
                     .as_any()
                     .downcast_ref::<CalculationPlanNode>(
                 ) {
-                    let (source_plan, plan_alias) =
-                        calculation_plan.relation_chain.clone().plan(
-                            ModelGenerationRule::new(
-                                Arc::clone(&self.analyzed_wren_mdl),
-                                Arc::clone(&self.session_state),
-                            ),
-                            &alias_generator,
-                        )?;
+                    let (source_plan, plan_alias) = calculation_plan
+                        .relation_chain
+                        .clone()
+                        .plan(self.clone(), &alias_generator)?;
 
                     let plan_alias = if let Some(alias) = plan_alias {
                         alias
                     } else {
-                        return internal_err!("calculation plan should have an alias");
+                        return Err(WrenError::MissingCalculationAlias(
+                            calculation_plan.calculation.column.name().to_string(),
+                        )
+                        .into());
                     };
 
                     if let Expr::Alias(alias) = calculation_plan.measures[0].clone() {
                         let measure: Expr = *alias.expr.clone();
                         let rebased_measure = rebase_column(&measure, &plan_alias)?;
                         let name = alias.name.clone();
-                        let ident =
-                            ident(rebased_measure.to_string()).alias(name.clone());
-                        let rebased_dimension =
-                            rebase_column(&calculation_plan.dimensions[0], &plan_alias)?;
-                        let project = vec![rebased_dimension.clone(), ident];
+                        // The measure could combine several aggregations, e.g. the filtered and
+                        // unfiltered ones. They are computed by one aggregate and the measure is
+                        // projected over their output. The `FILTER (WHERE ...)` clause is kept as
+                        // the native filter of the aggregate function.
+                        let mut aggregations = find_aggregate_exprs([&rebased_measure]);
+                        let output = rebased_measure
+                            .transform_down(|e| {
+                                if aggregations.contains(&e) {
+                                    let name = e.schema_name().to_string();
+                                    Ok(Transformed::yes(Expr::Column(Column::from_name(
+                                        name,
+                                    ))))
+                                } else {
+                                    Ok(Transformed::no(e))
+                                }
+                            })
+                            .data()?
+                            .alias(name.clone());
+                        let rebased_dimensions = calculation_plan
+                            .dimensions
+                            .iter()
+                            .map(|dimension| rebase_column(dimension, &plan_alias))
+                            .collect::<Result<Vec<_>>>()?;
+                        // The dimension could be an expression, e.g. `date_trunc('month', ...)`.
+                        // The projection references the grouped output by its name instead of
+                        // evaluating the expression again over the aggregate.
+                        let mut project = rebased_dimensions
+                            .iter()
+                            .map(grouped_column)
+                            .collect::<Vec<_>>();
+                        project.push(output);
+                        // the rows of each group are counted for the data-quality checks
+                        if self.analyzed_wren_mdl.options().row_count_validation {
+                            aggregations.push(count(lit(1)).alias(ROW_COUNT_COLUMN));
+                            project.push(col(ROW_COUNT_COLUMN));
+                        }
                         let result = match source_plan {
-                            Some(plan) => LogicalPlanBuilder::from(plan)
-                                .aggregate(
-                                    vec![rebased_dimension],
-                                    vec![rebased_measure],
-                                )?
-                                .project(project)?
-                                .build()?,
+                            Some(plan) => {
+                                let plan = LogicalPlanBuilder::from(plan)
+                                    .aggregate(rebased_dimensions, aggregations)?
+                                    .project(project)?
+                                    .build()?;
+                                if calculation_plan.calculation.column.share_of_total {
+                                    share_of_total(plan, &name)?
+                                } else {
+                                    plan
+                                }
+                            }
                             _ => {
-                                return plan_err!("Failed to generate source plan");
+                                return Err(WrenError::MissingSourcePlan(
+                                    calculation_plan
+                                        .calculation
+                                        .column
+                                        .name()
+                                        .to_string(),
+                                )
+                                .into());
                             }
                         };
                         let alias = LogicalPlanBuilder::from(result)
@@ -186,7 +1025,10 @@ impl ModelGenerationRule {
                             .build()?;
                         Ok(Transformed::yes(alias))
                     } else {
-                        return plan_err!("measures should have an alias");
+                        return Err(WrenError::UnaliasedMeasure(
+                            calculation_plan.calculation.column.name().to_string(),
+                        )
+                        .into());
                     }
                 } else if let Some(partial_model) = extension
                     .node
@@ -200,6 +1042,9 @@ impl ModelGenerationRule {
                     let subquery = LogicalPlanBuilder::from(plan)
                         .alias(quoted(partial_model.model_node.plan_name()))?
                         .build()?;
+                    if self.is_inlinable(partial_model) {
+                        return Ok(Transformed::yes(subquery));
+                    }
                     let source_plan = self.generate_model_internal(subquery)?.data;
                     let projection: Vec<_> = partial_model
                         .schema()
@@ -230,19 +1075,773 @@ impl Debug for ModelGenerationRule {
 
 impl AnalyzerRule for ModelGenerationRule {
     fn analyze(&self, plan: LogicalPlan, _: &ConfigOptions) -> Result<LogicalPlan> {
+        let rule = self.with_partial_model_usage(count_partial_models(&plan)?);
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        // the cross joins requested by the query are allowed in the strict mode
+        let requested_cross_joins = if self.analyzed_wren_mdl.options().strict_join {
+            Some(collect_cross_joins(&plan, &wren_mdl)?)
+        } else {
+            None
+        };
         let transformed_up = plan
             .transform_up_with_subqueries(&|plan| -> Result<Transformed<LogicalPlan>> {
-                self.generate_model_internal(plan)
+                rule.generate_model_internal(plan)
             })
             .data()?;
-        transformed_up
+        let generated = transformed_up
             .transform_down_with_subqueries(&|plan| -> Result<Transformed<LogicalPlan>> {
-                self.generate_model_internal(plan)
+                rule.generate_model_internal(plan)
             })
-            .data()
+            .data()?;
+        let generated = if self.analyzed_wren_mdl.options().projection_folding {
+            fold_redundant_projections(generated)?
+        } else {
+            generated
+        };
+        let generated = resolve_relative_date_filters(generated, &self.query_context)?;
+        // the sort of the query stays at the top of the expanded plan
+        let generated = anchor_user_order(generated)?;
+        if let Some(requested_cross_joins) = requested_cross_joins {
+            check_implicit_cross_joins(&requested_cross_joins, &generated, &wren_mdl)?;
+        }
+        if let Some(max_columns) = self.analyzed_wren_mdl.options().max_result_width {
+            let columns = generated.schema().fields().len();
+            if columns > max_columns {
+                return Err(WrenError::ResultWidthExceeded {
+                    columns,
+                    max_columns,
+                }
+                .into());
+            }
+        }
+        Ok(generated)
     }
 
     fn name(&self) -> &str {
         "ModelGenerationRule"
     }
 }
+
+/// Project the expressions over the source. The window functions in the expressions (e.g. the
+/// bucket of a bucketed dimension) are planned by a [LogicalPlan::Window] first, and the
+/// expressions refer to its output.
+fn project_with_window(
+    source: LogicalPlanBuilder,
+    exprs: Vec<Expr>,
+) -> Result<LogicalPlanBuilder> {
+    let window_exprs = find_window_exprs(&exprs);
+    if window_exprs.is_empty() {
+        return project_with_common_exprs(source, exprs);
+    }
+    let exprs = exprs
+        .into_iter()
+        .map(|expr| {
+            expr.transform_down(|e| {
+                if window_exprs.contains(&e) {
+                    let name = e.schema_name().to_string();
+                    Ok(Transformed::yes(Expr::Column(Column::from_name(name))))
+                } else {
+                    Ok(Transformed::no(e))
+                }
+            })
+            .data()
+        })
+        .collect::<Result<Vec<_>>>()?;
+    source.window(window_exprs)?.project(exprs)
+}
+
+fn row_level_predicate(
+    expr: Expr,
+    operator: &RowLevelOperator,
+    value: ScalarValue,
+) -> Expr {
+    // the value of IN and NOT IN is a comma-separated list
+    let list = || {
+        value
+            .to_string()
+            .split(',')
+            .map(|v| lit(v.trim().to_string()))
+            .collect::<Vec<_>>()
+    };
+    match operator {
+        RowLevelOperator::Equals => expr.eq(lit(value)),
+        RowLevelOperator::NotEquals => expr.not_eq(lit(value)),
+        RowLevelOperator::GreaterThan => expr.gt(lit(value)),
+        RowLevelOperator::LessThan => expr.lt(lit(value)),
+        RowLevelOperator::GreaterThanOrEquals => expr.gt_eq(lit(value)),
+        RowLevelOperator::LessThanOrEquals => expr.lt_eq(lit(value)),
+        RowLevelOperator::IN => expr.in_list(list(), false),
+        RowLevelOperator::NotIn => expr.in_list(list(), true),
+        RowLevelOperator::LIKE => expr.like(lit(value)),
+        RowLevelOperator::NotLike => expr.not_like(lit(value)),
+    }
+}
+
+/// Union the inputs by the columns of the first one. The columns differing in type are cast to
+/// their common super type, e.g. `INT` and `BIGINT` are widened to `BIGINT`. It fails if the types
+/// are incompatible, e.g. a text and a number.
+fn union_with_super_types(
+    model: &Model,
+    inputs: Vec<LogicalPlan>,
+) -> Result<LogicalPlanBuilder> {
+    let Some((first, others)) = inputs.split_first() else {
+        return plan_err!("Model {} has no source to union", model.name());
+    };
+    let mut columns = vec![];
+    for field in first.schema().fields() {
+        let mut data_type = field.data_type().clone();
+        for other in others {
+            let Ok(other) = other.schema().field_with_unqualified_name(field.name())
+            else {
+                return plan_err!(
+                    "Column {} of model {} is missing from one of its sources",
+                    field.name(),
+                    model.name()
+                );
+            };
+            let Some(super_type) = union_super_type(&data_type, other.data_type()) else {
+                return plan_err!(
+                    "Column {} of model {} can't be unioned from the types {} and {}",
+                    field.name(),
+                    model.name(),
+                    data_type,
+                    other.data_type()
+                );
+            };
+            data_type = super_type;
+        }
+        columns.push((field.name().clone(), data_type));
+    }
+
+    // the inputs are projected to the columns of the super types if they're not aligned yet
+    let align = |input: LogicalPlan| -> Result<LogicalPlan> {
+        let fields = input.schema().fields();
+        if fields.len() == columns.len()
+            && fields
+                .iter()
+                .zip(&columns)
+                .all(|(field, (name, data_type))| {
+                    field.name() == name && field.data_type() == data_type
+                })
+        {
+            return Ok(input);
+        }
+        let exprs = columns
+            .iter()
+            .map(|(name, data_type)| {
+                let (qualifier, field) =
+                    input.schema().qualified_field_with_unqualified_name(name)?;
+                let column = Expr::Column(Column::new(qualifier.cloned(), name));
+                Ok(if field.data_type() == data_type {
+                    column
+                } else {
+                    cast(column, data_type.clone()).alias(name)
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        LogicalPlanBuilder::from(input).project(exprs)?.build()
+    };
+    let mut inputs = inputs.into_iter();
+    let first = LogicalPlanBuilder::from(align(inputs.next().unwrap())?);
+    inputs.try_fold(first, |union, input| union.union(align(input)?))
+}
+
+/// Return the common super type of the column types of the union, or `None` if they're
+/// incompatible. Only the numbers, the strings or the temporal types are widened among themselves.
+fn union_super_type(left: &DataType, right: &DataType) -> Option<DataType> {
+    let is_string = |t: &DataType| {
+        matches!(t, DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View)
+    };
+    if left == right {
+        return Some(left.clone());
+    }
+    let compatible = (left.is_numeric() && right.is_numeric())
+        || (is_string(left) && is_string(right))
+        || (left.is_temporal() && right.is_temporal())
+        || left.is_null()
+        || right.is_null();
+    if compatible {
+        comparison_coercion(left, right)
+    } else {
+        None
+    }
+}
+
+/// Return the first 8 hex digits of the md5 hash of the row salted by the seed, e.g.
+/// `substr(md5(concat_ws('|', '42', CAST(__source.c_custkey AS VARCHAR))), 1, 8)`. The row is
+/// identified by the primary key of the model, or all the columns of the source if it isn't
+/// available.
+fn seeded_hash(seed: i64, model: &Model, schema: &DFSchema) -> Result<Expr> {
+    let mut columns = schema.columns();
+    if let Some(key) = model.primary_key() {
+        if columns.iter().any(|c| c.name == key) {
+            columns.retain(|c| c.name == key);
+        }
+    }
+    let mut values = vec![lit(seed.to_string())];
+    for column in columns {
+        values.push(match schema.field_from_column(&column)?.data_type() {
+            DataType::Utf8 => Expr::Column(column),
+            _ => cast(Expr::Column(column), DataType::Utf8),
+        });
+    }
+    Ok(substring(
+        md5(concat_ws(lit("|"), values)),
+        lit(1_i64),
+        lit(8_i64),
+    ))
+}
+
+/// Unnest the array columns of the model into one row per element. The unnested columns of the
+/// same model are zipped by the element position.
+fn unnest_columns(
+    source: LogicalPlanBuilder,
+    model: &Model,
+) -> Result<LogicalPlanBuilder> {
+    let columns = model
+        .get_physical_columns()
+        .into_iter()
+        .filter(|column| column.unnest().is_some())
+        .map(|column| Column::from_name(column.name()))
+        .collect::<Vec<_>>();
+    if columns.is_empty() {
+        return Ok(source);
+    }
+    source.unnest_columns_with_options(columns, UnnestOptions::default())
+}
+
+/// Count the references of each [PartialModelPlanNode] in the plan, including the ones in the
+/// relation chains of the model plan nodes.
+pub(crate) fn count_partial_models(
+    plan: &LogicalPlan,
+) -> Result<HashMap<PartialModelPlanNode, usize>> {
+    let mut usage = HashMap::new();
+    collect_partial_models(plan, &mut usage)?;
+    Ok(usage)
+}
+
+fn collect_partial_models(
+    plan: &LogicalPlan,
+    usage: &mut HashMap<PartialModelPlanNode, usize>,
+) -> Result<()> {
+    plan.apply_with_subqueries(|plan| {
+        if let LogicalPlan::Extension(extension) = plan {
+            let node = extension.node.as_any();
+            if let Some(model_plan) = node.downcast_ref::<ModelPlanNode>() {
+                collect_partial_models_in_chain(&model_plan.relation_chain, usage)?;
+            } else if let Some(calculation) = node.downcast_ref::<CalculationPlanNode>() {
+                collect_partial_models_in_chain(&calculation.relation_chain, usage)?;
+            } else if let Some(partial_model) =
+                node.downcast_ref::<PartialModelPlanNode>()
+            {
+                *usage.entry(partial_model.clone()).or_default() += 1;
+                collect_partial_models_in_chain(
+                    &partial_model.model_node.relation_chain,
+                    usage,
+                )?;
+            }
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(())
+}
+
+fn collect_partial_models_in_chain(
+    relation_chain: &RelationChain,
+    usage: &mut HashMap<PartialModelPlanNode, usize>,
+) -> Result<()> {
+    match relation_chain {
+        RelationChain::Chain { plan, next, .. } => {
+            collect_partial_models(plan, usage)?;
+            collect_partial_models_in_chain(next, usage)
+        }
+        RelationChain::Start(plan) => collect_partial_models(plan, usage),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::logical_plan::analyze::model_generation::{
+        count_partial_models, ModelGenerationRule, ROW_COUNT_COLUMN, SOURCE_ALIAS,
+    };
+    use crate::logical_plan::analyze::plan::{
+        CalculationPlanNode, ModelPlanNode, ModelSourceNode, PartialModelPlanNode,
+    };
+    use crate::logical_plan::analyze::relative_date::RelativeDate;
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{manifest_builder, mdl_json_manifest, orders_model};
+    use crate::mdl::manifest::{JoinType, RowLevelOperator};
+    use crate::mdl::query_context::QueryContext;
+    use crate::mdl::AnalyzedWrenMDL;
+    use crate::WrenError;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema, TimeUnit};
+    use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+    use datafusion::common::{Column, Result, ScalarValue};
+    use datafusion::logical_expr::builder::LogicalTableSource;
+    use datafusion::logical_expr::utils::split_conjunction_owned;
+    use datafusion::logical_expr::{
+        col, lit, Expr, Extension, LogicalPlan, LogicalPlanBuilder, ScalarUDF,
+        UserDefinedLogicalNodeCore,
+    };
+    use datafusion::prelude::SessionContext;
+
+    #[test]
+    fn test_invalid_original_table_scan() -> Result<()> {
+        let mdl = mdl_json_manifest();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(mdl)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        let source = ModelSourceNode::new(
+            model,
+            vec![col("c_custkey")],
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Some(LogicalPlanBuilder::empty(false).build()?),
+        )?;
+        let rule = ModelGenerationRule::new(
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Arc::new(QueryContext::new()),
+        );
+        let err = rule
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(source),
+            }))
+            .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::InvalidOriginalTableScan {
+                model: "customer".to_string()
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_source_fingerprint() -> Result<()> {
+        let mdl = mdl_json_manifest();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(mdl)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        let table_source =
+            Arc::new(LogicalTableSource::new(Arc::new(Schema::new(vec![
+                Field::new("c_custkey", DataType::Int32, true),
+                Field::new("c_name", DataType::Utf8, true),
+            ]))));
+        let source = |required: Vec<Expr>, filters: Vec<Expr>| {
+            let table_scan = LogicalPlanBuilder::scan_with_filters(
+                "customer",
+                Arc::clone(&table_source) as _,
+                None,
+                filters,
+            )?
+            .build()?;
+            ModelSourceNode::new(
+                Arc::clone(&model),
+                required,
+                Arc::clone(&analyzed_mdl),
+                ctx.state_ref(),
+                Some(table_scan),
+            )
+        };
+
+        // the structurally identical scans share the fingerprint
+        let scan = source(vec![col("c_custkey")], vec![])?;
+        let same_scan = source(vec![col("c_custkey")], vec![])?;
+        assert_eq!(scan.fingerprint, same_scan.fingerprint);
+
+        let filtered = source(vec![col("c_custkey")], vec![col("c_custkey").eq(lit(1))])?;
+        assert_ne!(scan.fingerprint, filtered.fingerprint);
+        let more_columns = source(vec![col("c_custkey"), col("c_name")], vec![])?;
+        assert_ne!(scan.fingerprint, more_columns.fingerprint);
+        Ok(())
+    }
+
+    #[test]
+    fn test_combine_source_filters() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("c_name", "varchar")
+                            .row_level_security("session_name", RowLevelOperator::Equals)
+                            .build(),
+                    )
+                    .base_filter("c_custkey > 0 AND c_name IS NOT NULL")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        let table_source =
+            Arc::new(LogicalTableSource::new(Arc::new(Schema::new(vec![
+                Field::new("c_custkey", DataType::Int64, true),
+                Field::new("c_name", DataType::Utf8, true),
+            ]))));
+        // the filters of the scan repeat the base filter and the row-level security
+        let table_scan = LogicalPlanBuilder::scan_with_filters(
+            "customer",
+            table_source as _,
+            None,
+            vec![
+                col("c_custkey").gt(lit(0_i64)),
+                col("c_name").eq(lit("Gura")),
+            ],
+        )?
+        .build()?;
+        let source = ModelSourceNode::new(
+            model,
+            vec![col("c_custkey"), col("c_name")],
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Some(table_scan),
+        )?;
+        let rule = ModelGenerationRule::new(
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Arc::new(QueryContext::new().with_variable("session_name", "Gura")),
+        );
+        let plan = rule
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(source),
+            }))?
+            .data;
+
+        let mut predicates = vec![];
+        plan.apply(|node| {
+            if let LogicalPlan::Filter(filter) = node {
+                predicates.push(filter.predicate.clone());
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        let [predicate] = predicates.as_slice() else {
+            panic!("expected a single filter of the source, found {predicates:?}");
+        };
+        let source_column = |name: &str| col(Column::new(Some(SOURCE_ALIAS), name));
+        assert_eq!(
+            split_conjunction_owned(predicate.clone()),
+            vec![
+                source_column("c_custkey").gt(lit(0_i64)),
+                source_column("c_name").eq(lit("Gura")),
+                source_column("c_name").is_not_null(),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_date_filter() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("orders").unwrap();
+        let table_source =
+            Arc::new(LogicalTableSource::new(Arc::new(Schema::new(vec![
+                Field::new("o_orderkey", DataType::Int64, true),
+                Field::new(
+                    "o_orderdate",
+                    DataType::Timestamp(TimeUnit::Microsecond, None),
+                    true,
+                ),
+            ]))));
+        let last_7_days = ScalarUDF::new_from_impl(RelativeDate::new())
+            .call(vec![col("o_orderdate"), lit("last 7 days")]);
+        let table_scan = LogicalPlanBuilder::scan_with_filters(
+            "orders",
+            table_source as _,
+            None,
+            vec![last_7_days],
+        )?
+        .build()?;
+        let source = ModelSourceNode::new(
+            model,
+            vec![col("o_orderkey")],
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Some(table_scan),
+        )?;
+        // 2024-03-10 02:00:00 UTC is still 2024-03-09 in New York
+        let query_context = QueryContext::new()
+            .with_now(1_710_036_000_000_000)
+            .with_timezone("America/New_York");
+        let rule = ModelGenerationRule::new(
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Arc::new(query_context),
+        );
+        let plan = rule
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(source),
+            }))?
+            .data;
+
+        let mut predicates = vec![];
+        plan.apply(|node| {
+            if let LogicalPlan::Filter(filter) = node {
+                predicates.push(filter.predicate.clone());
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        let [predicate] = predicates.as_slice() else {
+            panic!("expected a single filter of the source, found {predicates:?}");
+        };
+        // from 2024-03-03 00:00:00 to 2024-03-10 00:00:00 in New York (EST, UTC-5)
+        let timestamp =
+            |micros| lit(ScalarValue::TimestampMicrosecond(Some(micros), None));
+        let order_date = col(Column::new(Some(SOURCE_ALIAS), "o_orderdate"));
+        assert_eq!(
+            split_conjunction_owned(predicate.clone()),
+            vec![
+                order_date.clone().gt_eq(timestamp(1_709_442_000_000_000)),
+                order_date.lt(timestamp(1_710_046_800_000_000)),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_row_count_validation() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("totalprice", "bigint")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let calculation_fields = |analyzed_mdl: AnalyzedWrenMDL| {
+            let analyzed_mdl = Arc::new(analyzed_mdl);
+            let ctx = SessionContext::new();
+            let calculation = CalculationPlanNode::for_column(
+                "customer",
+                "totalprice",
+                Arc::clone(&analyzed_mdl),
+                ctx.state_ref(),
+            )?;
+            let rule = ModelGenerationRule::new(
+                analyzed_mdl,
+                ctx.state_ref(),
+                Arc::new(QueryContext::new()),
+            );
+            let plan = rule
+                .generate_model_internal(LogicalPlan::Extension(Extension {
+                    node: Arc::new(calculation),
+                }))?
+                .data;
+            Ok::<_, datafusion::common::DataFusionError>(
+                plan.schema()
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().to_string())
+                    .collect::<Vec<_>>(),
+            )
+        };
+
+        let fields = calculation_fields(AnalyzedWrenMDL::analyze(manifest.clone())?)?;
+        assert!(!fields.iter().any(|f| f == ROW_COUNT_COLUMN), "{fields:?}");
+
+        // the hidden count is aggregated alongside the measure
+        let fields = calculation_fields(
+            AnalyzedWrenMDL::analyze(manifest)?.with_row_count_validation(),
+        )?;
+        assert!(fields.iter().any(|f| f == "totalprice"), "{fields:?}");
+        assert!(fields.iter().any(|f| f == ROW_COUNT_COLUMN), "{fields:?}");
+        Ok(())
+    }
+
+    #[test]
+    fn test_reconcile_nullability() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("c_name", "varchar")
+                            .not_null(true)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(
+                        ColumnBuilder::new("o_orderkey", "bigint")
+                            .not_null(true)
+                            .build(),
+                    )
+                    .column(ColumnBuilder::new("o_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("customer_name", "varchar")
+                            .calculated(true)
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("next_orderkey", "bigint")
+                            .calculated(true)
+                            .expression("o_orderkey + 1")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("orders").unwrap();
+        let model_plan = ModelPlanNode::new(
+            model,
+            vec![
+                col("o_orderkey"),
+                col("o_custkey"),
+                col("customer_name"),
+                col("next_orderkey"),
+            ],
+            None,
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+        )?;
+        let rule = ModelGenerationRule::new(
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Arc::new(QueryContext::new()),
+        );
+        let plan = rule
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(model_plan),
+            }))?
+            .data;
+        let schema = plan.schema();
+        // the physical columns are nullable as they're declared
+        assert!(!schema
+            .field_with_unqualified_name("o_orderkey")?
+            .is_nullable());
+        assert!(schema
+            .field_with_unqualified_name("o_custkey")?
+            .is_nullable());
+        // the calculated field from the outer side of the join is nullable, even if the read
+        // column is declared not null
+        assert!(schema
+            .field_with_unqualified_name("customer_name")?
+            .is_nullable());
+        // the calculated field of the model itself keeps the inferred nullability
+        assert!(!schema
+            .field_with_unqualified_name("next_orderkey")?
+            .is_nullable());
+        Ok(())
+    }
+
+    #[test]
+    fn test_inline_single_use_partial_model() -> Result<()> {
+        let mdl = mdl_json_manifest();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(mdl)?);
+        let ctx = SessionContext::new();
+        let model = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        let model_node = ModelPlanNode::new(
+            model,
+            vec![col("c_custkey")],
+            None,
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+        )?;
+        let schema = Arc::clone(UserDefinedLogicalNodeCore::schema(&model_node));
+        let partial_model = PartialModelPlanNode::new(model_node, schema);
+        let plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(partial_model.clone()),
+        });
+        let rule = ModelGenerationRule::new(
+            Arc::clone(&analyzed_mdl),
+            ctx.state_ref(),
+            Arc::new(QueryContext::new()),
+        );
+
+        // the single-use partial model is inlined without the extra projection
+        let usage = count_partial_models(&plan)?;
+        assert_eq!(usage.get(&partial_model), Some(&1));
+        let inlined = rule
+            .with_partial_model_usage(usage)
+            .generate_model_internal(plan.clone())?
+            .data;
+        let LogicalPlan::SubqueryAlias(alias) = inlined else {
+            panic!("expected a subquery alias");
+        };
+        assert!(matches!(alias.input.as_ref(), LogicalPlan::Extension(_)));
+
+        // the multi-use partial model is preserved
+        let union = LogicalPlanBuilder::from(plan.clone())
+            .union(plan.clone())?
+            .build()?;
+        let usage = count_partial_models(&union)?;
+        assert_eq!(usage.get(&partial_model), Some(&2));
+        let preserved = rule
+            .with_partial_model_usage(usage)
+            .generate_model_internal(plan)?
+            .data;
+        let LogicalPlan::SubqueryAlias(alias) = preserved else {
+            panic!("expected a subquery alias");
+        };
+        assert!(matches!(alias.input.as_ref(), LogicalPlan::Projection(_)));
+        Ok(())
+    }
+}
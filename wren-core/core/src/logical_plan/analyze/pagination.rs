@@ -0,0 +1,155 @@
+use crate::mdl::{AnalyzedWrenMDL, WrenMDL};
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{Column, Result};
+use datafusion::logical_expr::{Expr, Limit, LogicalPlan, LogicalPlanBuilder};
+use std::sync::Arc;
+
+/// Order the paginated query of a model by the default sort key of the model if the query doesn't
+/// order the rows, so the pages of `LIMIT` and `OFFSET` are stable.
+///
+/// Only the query scanning one model directly is ordered, e.g.
+/// `SELECT o_orderkey FROM orders LIMIT 10 OFFSET 20`. The sort is placed under the projection, so
+/// the sort key doesn't need to be projected.
+pub(crate) fn apply_default_order(
+    plan: LogicalPlan,
+    analyzed_wren_mdl: &Arc<AnalyzedWrenMDL>,
+) -> Result<LogicalPlan> {
+    let wren_mdl = analyzed_wren_mdl.wren_mdl();
+    plan.transform_up_with_subqueries(|plan| match plan {
+        LogicalPlan::Limit(limit) => order_limit(limit, &wren_mdl),
+        _ => Ok(Transformed::no(plan)),
+    })
+    .data()
+}
+
+fn order_limit(limit: Limit, wren_mdl: &WrenMDL) -> Result<Transformed<LogicalPlan>> {
+    let source = match limit.input.as_ref() {
+        LogicalPlan::Projection(projection) => projection.input.as_ref(),
+        input => input,
+    };
+    let Some(sort_key) = scanned_model_sort_key(source, wren_mdl) else {
+        return Ok(Transformed::no(LogicalPlan::Limit(limit)));
+    };
+    let (qualifier, field) = source
+        .schema()
+        .qualified_field_with_unqualified_name(&sort_key)?;
+    let sort_expr = Expr::Column(Column::from((qualifier, field))).sort(true, false);
+    let input = match limit.input.as_ref() {
+        LogicalPlan::Projection(projection) => {
+            LogicalPlanBuilder::from(projection.input.as_ref().clone())
+                .sort(vec![sort_expr])?
+                .project(projection.expr.clone())?
+                .build()?
+        }
+        input => LogicalPlanBuilder::from(input.clone())
+            .sort(vec![sort_expr])?
+            .build()?,
+    };
+    Ok(Transformed::yes(LogicalPlan::Limit(Limit {
+        input: Arc::new(input),
+        ..limit
+    })))
+}
+
+/// Return the default sort key of the model if the plan scans the model without ordering it
+fn scanned_model_sort_key(plan: &LogicalPlan, wren_mdl: &WrenMDL) -> Option<String> {
+    match plan {
+        LogicalPlan::Filter(filter) => scanned_model_sort_key(&filter.input, wren_mdl),
+        LogicalPlan::SubqueryAlias(alias) => {
+            scanned_model_sort_key(&alias.input, wren_mdl)
+        }
+        LogicalPlan::TableScan(scan) => wren_mdl
+            .get_model(scan.table_name.table())
+            .and_then(|model| model.default_sort_key().map(String::from)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_pagination_default_sort_key() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![5, 3, 1, 4, 2]));
+        let totalprice: ArrayRef =
+            Arc::new(Float64Array::from(vec![500.0, 300.0, 100.0, 400.0, 200.0]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_totalprice", totalprice),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .default_sort_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let page = |offset: usize| {
+            let analyzed_mdl = Arc::clone(&analyzed_mdl);
+            let ctx = ctx.clone();
+            async move {
+                let sql =
+                    format!("SELECT o_totalprice FROM orders LIMIT 2 OFFSET {offset}");
+                let actual = transform_sql_with_ctx(
+                    &SessionContext::new(),
+                    analyzed_mdl,
+                    &[],
+                    &sql,
+                )
+                .await?;
+                assert!(actual.contains("ORDER BY"), "{actual}");
+                ctx.sql(&actual).await?.collect().await
+            }
+        };
+
+        // the pages are ordered by the default sort key though it isn't projected
+        assert_batches_eq!(
+            [
+                "+--------------+",
+                "| o_totalprice |",
+                "+--------------+",
+                "| 100.0        |",
+                "| 200.0        |",
+                "+--------------+",
+            ],
+            &page(0).await?
+        );
+        assert_batches_eq!(
+            [
+                "+--------------+",
+                "| o_totalprice |",
+                "+--------------+",
+                "| 300.0        |",
+                "| 400.0        |",
+                "+--------------+",
+            ],
+            &page(2).await?
+        );
+
+        // the explicit order is kept
+        let actual = transform_with_mdl(
+            &analyzed_mdl,
+            "SELECT o_totalprice FROM orders ORDER BY o_totalprice DESC LIMIT 2",
+        )
+        .await?;
+        assert!(!actual.contains("o_orderkey"), "{actual}");
+        Ok(())
+    }
+}
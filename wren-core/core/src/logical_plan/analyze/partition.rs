@@ -0,0 +1,243 @@
+use crate::mdl::manifest::PartitionTemplate;
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::{plan_err, Result, ScalarValue};
+use datafusion::logical_expr::expr::{Between, Cast, TryCast};
+use datafusion::logical_expr::utils::split_conjunction;
+use datafusion::logical_expr::{BinaryExpr, Expr, Operator};
+
+/// The dated partition of a partitioned table, covering the days in `[first, last]`
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Partition {
+    pub table: String,
+    first: i32,
+    last: i32,
+}
+
+/// Resolve the partitions of the template whose dates may match the filters.
+///
+/// The template names the partitions by the date placeholders `{YYYY}`, `{MM}` and `{DD}`, e.g.
+/// `events_{YYYY}{MM}` names a partition for each month from the start to the end date. The
+/// comparisons of the partition column with a date literal in the filters prune the partitions
+/// out of the range. The other filters can't prune the partitions and are ignored, so the
+/// filters should still be applied to the rows of the scanned partitions.
+pub(crate) fn resolve_partitions(
+    template: &PartitionTemplate,
+    filters: &[Expr],
+) -> Result<Vec<Partition>> {
+    let (Some(start), Some(end)) =
+        (parse_date(&template.start), parse_date(&template.end))
+    else {
+        return plan_err!(
+            "The range {} to {} of the partition template {} should be the dates of YYYY-MM-DD",
+            template.start,
+            template.end,
+            template.template
+        );
+    };
+    if start > end {
+        return plan_err!(
+            "The start {} of the partition template {} is after the end {}",
+            template.start,
+            template.template,
+            template.end
+        );
+    }
+    let (lower, upper) = filters
+        .iter()
+        .flat_map(split_conjunction)
+        .filter_map(|expr| date_bound(expr, &template.column))
+        .fold((start, end), |(lower, upper), (low, high)| {
+            (
+                low.map_or(lower, |low| lower.max(low)),
+                high.map_or(upper, |high| upper.min(high)),
+            )
+        });
+    let mut partitions: Vec<Partition> = vec![];
+    for day in start..=end {
+        let table = render(&template.template, day);
+        match partitions.last_mut() {
+            Some(last) if last.table == table => last.last = day,
+            _ => partitions.push(Partition {
+                table,
+                first: day,
+                last: day,
+            }),
+        }
+    }
+    partitions.retain(|p| p.last >= lower && p.first <= upper);
+    Ok(partitions)
+}
+
+/// Return the inclusive lower or upper bound of the partition column in days since the epoch
+fn date_bound(expr: &Expr, column: &str) -> Option<(Option<i32>, Option<i32>)> {
+    match expr {
+        Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+            let (op, date) = match (left.as_ref(), right.as_ref()) {
+                (Expr::Column(c), other) if c.name == column => {
+                    (*op, date_literal(other)?)
+                }
+                (other, Expr::Column(c)) if c.name == column => {
+                    (op.swap()?, date_literal(other)?)
+                }
+                _ => return None,
+            };
+            match op {
+                Operator::Eq => Some((Some(date), Some(date))),
+                Operator::Gt => Some((Some(date + 1), None)),
+                Operator::GtEq => Some((Some(date), None)),
+                Operator::Lt => Some((None, Some(date - 1))),
+                Operator::LtEq => Some((None, Some(date))),
+                _ => None,
+            }
+        }
+        Expr::Between(Between {
+            expr,
+            negated: false,
+            low,
+            high,
+        }) => match expr.as_ref() {
+            Expr::Column(c) if c.name == column => {
+                Some((Some(date_literal(low)?), Some(date_literal(high)?)))
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn date_literal(expr: &Expr) -> Option<i32> {
+    match expr {
+        Expr::Literal(ScalarValue::Date32(Some(days))) => Some(*days),
+        Expr::Literal(
+            ScalarValue::Utf8(Some(s))
+            | ScalarValue::LargeUtf8(Some(s))
+            | ScalarValue::Utf8View(Some(s)),
+        ) => parse_date(s),
+        Expr::Cast(Cast { expr, data_type })
+        | Expr::TryCast(TryCast { expr, data_type })
+            if data_type == &DataType::Date32 =>
+        {
+            date_literal(expr)
+        }
+        _ => None,
+    }
+}
+
+/// Parse the date of `YYYY-MM-DD` into the days since the epoch
+fn parse_date(date: &str) -> Option<i32> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next()?.parse::<u32>().ok()?;
+    let day = parts.next()?.parse::<u32>().ok()?;
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+fn render(template: &str, days: i32) -> String {
+    let (year, month, day) = civil_from_days(days);
+    template
+        .replace("{YYYY}", &format!("{year:04}"))
+        .replace("{MM}", &format!("{month:02}"))
+        .replace("{DD}", &format!("{day:02}"))
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// The conversions between the civil dates and the days since 1970-01-01 in the proleptic
+// Gregorian calendar. See http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i32, month: u32, day: u32) -> i32 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i32;
+    let day_of_year =
+        (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i32 - 1;
+    let day_of_era =
+        year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i32) -> (i32, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year =
+        day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = (if mp < 10 { mp + 3 } else { mp - 9 }) as u32;
+    let year = year_of_era + era * 400 + i32::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Date32Array, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_partition_template() -> Result<()> {
+        let ctx = SessionContext::new();
+        // the monthly partitions of 2024-01, 2024-02 and 2024-03
+        for (partition, ids, dates) in [
+            ("events_202401", vec![1, 2], vec![19730, 19740]),
+            ("events_202402", vec![3], vec![19763]),
+            ("events_202403", vec![4], vec![19787]),
+        ] {
+            ctx.register_batch(
+                partition,
+                RecordBatch::try_from_iter(vec![
+                    ("e_id", Arc::new(Int64Array::from(ids)) as ArrayRef),
+                    ("e_date", Arc::new(Date32Array::from(dates)) as ArrayRef),
+                ])?,
+            )?;
+        }
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("events")
+                    .table_reference("events")
+                    .column(ColumnBuilder::new("e_id", "bigint").build())
+                    .column(ColumnBuilder::new("e_date", "date").build())
+                    .partition_template(
+                        "events_{YYYY}{MM}",
+                        "e_date",
+                        "2024-01-01",
+                        "2024-03-31",
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT e_id FROM events WHERE e_date >= DATE '2024-02-01' ORDER BY e_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the partition of 2024-01 is pruned by the date filter
+        assert!(!actual.contains("events_202401"), "{actual}");
+        assert!(actual.contains("events_202402"), "{actual}");
+        assert!(actual.contains("UNION ALL"), "{actual}");
+        assert!(actual.contains("events_202403"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            ["+------+", "| e_id |", "+------+", "| 3    |", "| 4    |", "+------+",],
+            &result
+        );
+        Ok(())
+    }
+}
@@ -2,34 +2,42 @@ use std::cmp::Ordering;
 use std::collections::{BTreeSet, HashMap, VecDeque};
 use std::fmt;
 use std::fmt::{Debug, Formatter};
-use std::hash::Hash;
+use std::hash::{DefaultHasher, Hash, Hasher};
 use std::sync::Arc;
 
 use datafusion::arrow::datatypes::Field;
 use datafusion::common::{
-    internal_err, plan_err, Column, DFSchema, DFSchemaRef, TableReference,
+    internal_err, plan_err, Column, DFSchema, DFSchemaRef, ScalarValue, TableReference,
 };
 use datafusion::error::Result;
+use datafusion::functions::expr_fn::coalesce;
+use datafusion::functions_aggregate::expr_fn::{avg, count, max, min, sum};
 use datafusion::logical_expr::expr::WildcardOptions;
 use datafusion::logical_expr::utils::find_aggregate_exprs;
 use datafusion::logical_expr::{
-    col, Expr, Extension, LogicalPlan, UserDefinedLogicalNode, UserDefinedLogicalNodeCore,
+    col, lit, Expr, Extension, LogicalPlan, UserDefinedLogicalNode,
+    UserDefinedLogicalNodeCore,
 };
 use log::debug;
 use petgraph::Graph;
 
 use crate::logical_plan::analyze::RelationChain;
 use crate::logical_plan::analyze::RelationChain::Start;
-use crate::logical_plan::utils::{from_qualified_name, map_data_type};
+use crate::logical_plan::utils::{from_qualified_name, map_data_type, with_description};
 use crate::mdl;
 use crate::mdl::lineage::DatasetLink;
-use crate::mdl::manifest::{JoinType, Model};
+use crate::mdl::manifest::{DefaultAggregation, JoinType, Model};
+use crate::mdl::query_context::QueryContext;
+use crate::mdl::type_override::ColumnTypeOverrides;
 use crate::mdl::utils::{
-    create_remote_expr_for_model, create_wren_calculated_field_expr,
-    create_wren_expr_for_model, is_dag, quoted,
+    bucket_expr, count_null_as_distinct, create_remote_expr_for_model,
+    create_wren_calculated_field_expr, create_wren_expr_for_model, is_dag,
+    parent_scope_key, quoted, scope_aggregations, semi_additive_expr, surrogate_key_expr,
+    unnest_expr,
 };
 use crate::mdl::Dataset;
 use crate::mdl::{AnalyzedWrenMDL, ColumnReference, SessionStateRef};
+use crate::WrenError;
 
 #[derive(Debug)]
 pub(crate) enum WrenPlan {
@@ -60,6 +68,8 @@ pub(crate) struct ModelPlanNode {
     pub(crate) relation_chain: Box<RelationChain>,
     schema_ref: DFSchemaRef,
     pub(crate) original_table_scan: Option<LogicalPlan>,
+    /// De-duplicate the rows of the model fanned out by a distinct relationship
+    pub(crate) distinct: bool,
 }
 
 impl ModelPlanNode {
@@ -89,6 +99,7 @@ impl ModelPlanNode {
 /// model_required_fields: The required fields for the source models.
 /// required_calculation: The required calculation plan for the target model.
 /// fields: The fields for the target model to build the schema of this plan.
+/// distinct: Whether the required fields fan out the model by a distinct relationship.
 /// analyzed_wren_mdl: The analyzed Wren MDL.
 struct ModelPlanNodeBuilder {
     required_exprs_buffer: BTreeSet<OrdExpr>,
@@ -96,6 +107,7 @@ struct ModelPlanNodeBuilder {
     model_required_fields: HashMap<TableReference, BTreeSet<OrdExpr>>,
     required_calculation: Vec<WrenPlan>,
     fields: VecDeque<(Option<TableReference>, Arc<Field>)>,
+    distinct: bool,
     analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
     session_state: SessionStateRef,
 }
@@ -111,6 +123,7 @@ impl ModelPlanNodeBuilder {
             model_required_fields: HashMap::new(),
             required_calculation: vec![],
             fields: VecDeque::new(),
+            distinct: false,
             analyzed_wren_mdl,
             session_state,
         }
@@ -128,6 +141,7 @@ impl ModelPlanNodeBuilder {
             model.name(),
         );
 
+        let type_overrides = ColumnTypeOverrides::from_session(&self.session_state);
         let required_columns =
             model.get_physical_columns().into_iter().filter(|column| {
                 required_fields
@@ -135,36 +149,10 @@ impl ModelPlanNodeBuilder {
                     .any(|expr| is_required_column(expr, column.name()))
             });
         for column in required_columns {
-            if column.is_calculated {
-                let expr = if column.expression.is_some() {
-                    let column_rf = self
-                        .analyzed_wren_mdl
-                        .wren_mdl()
-                        .get_column_reference(&from_qualified_name(
-                            &self.analyzed_wren_mdl.wren_mdl(),
-                            model.name(),
-                            column.name(),
-                        ));
-                    let Some(column_rf) = column_rf else {
-                        return plan_err!("Column reference not found for {:?}", column);
-                    };
-                    let expr = create_wren_calculated_field_expr(
-                        column_rf,
-                        Arc::clone(&self.analyzed_wren_mdl),
-                        Arc::clone(&self.session_state),
-                    )?;
-                    let expr_plan = expr.alias(column.name());
-                    expr_plan
-                } else {
-                    return plan_err!("Only support calculated field with expression");
-                };
-
-                let qualified_column = from_qualified_name(
-                    &self.analyzed_wren_mdl.wren_mdl(),
-                    model.name(),
-                    column.name(),
-                );
-
+            if column.is_computed() {
+                let (expr, qualified_column) =
+                    self.calculated_field_expr(&model, &column)?;
+                self.check_required_columns(&qualified_column)?;
                 let Some(column_graph) = self
                     .analyzed_wren_mdl
                     .lineage()
@@ -176,7 +164,6 @@ impl ModelPlanNodeBuilder {
                         qualified_column
                     );
                 };
-
                 if self.is_to_many_calculation(expr.clone()) {
                     let calculation = self.create_partial_calculation(
                         model_ref.clone(),
@@ -185,8 +172,16 @@ impl ModelPlanNodeBuilder {
                         expr,
                     )?;
                     self.required_calculation.push(calculation);
+                } else if column.share_of_total {
+                    return plan_err!(
+                        "The share of total {} should aggregate over a to-many relationship",
+                        column.name()
+                    );
                 } else {
                     merge_graph(&mut self.directed_graph, column_graph)?;
+                    self.distinct |= column_graph
+                        .edge_weights()
+                        .any(|link| !link.join_type.is_to_one() && link.distinct);
                     if self.is_contain_calculation_source(&qualified_column) {
                         collect_partial_model_plan(
                             Arc::clone(&self.analyzed_wren_mdl),
@@ -223,12 +218,16 @@ impl ModelPlanNodeBuilder {
                 self.required_exprs_buffer
                     .insert(OrdExpr::new(expr_plan.clone()));
             }
+            // the overridden column is cast by the model generation
+            let r#type = type_overrides
+                .as_ref()
+                .and_then(|overrides| overrides.get(model.name(), column.name()))
+                .unwrap_or(&column.r#type);
             self.fields.push_front((
                 Some(TableReference::bare(quoted(model.name()))),
-                Arc::new(Field::new(
-                    column.name(),
-                    map_data_type(&column.r#type)?,
-                    column.not_null,
+                Arc::new(with_description(
+                    Field::new(column.name(), map_data_type(r#type)?, !column.not_null),
+                    &column,
                 )),
             ));
         }
@@ -285,34 +284,48 @@ impl ModelPlanNodeBuilder {
             .map(|c| c.iter().cloned().map(|c| c.expr).collect())
             .unwrap_or_default();
 
+        self.order_calculations();
         let mut calculate_iter = self.required_calculation.iter();
-        let source_chain =
-            if !source_required_fields.is_empty() || required_fields.is_empty() {
-                if required_fields.is_empty() {
-                    source_required_fields.insert(
-                        0,
-                        // TODO: remove deprecated wildcard
-                        #[allow(deprecated)]
-                        Expr::Wildcard {
-                            qualifier: None,
-                            options: Box::new(WildcardOptions::default()),
-                        },
-                    );
+        let source_chain = if !source_required_fields.is_empty()
+            || required_fields.is_empty()
+        {
+            if required_fields.is_empty() {
+                source_required_fields.insert(
+                    0,
+                    // TODO: remove deprecated wildcard
+                    #[allow(deprecated)]
+                    Expr::Wildcard {
+                        qualifier: None,
+                        options: Box::new(WildcardOptions::default()),
+                    },
+                );
+            }
+            // the filters pushed down to the original scan apply to the model itself, e.g.
+            // to prune its partitions
+            let source_table_scan = match (source, &original_table_scan) {
+                (Dataset::Model(source_model), Some(LogicalPlan::TableScan(scan)))
+                    if source_model.name() == model.name()
+                        && !scan.filters.is_empty() =>
+                {
+                    original_table_scan.clone()
                 }
-                RelationChain::source(
-                    source,
-                    source_required_fields,
-                    Arc::clone(&self.analyzed_wren_mdl),
-                    Arc::clone(&self.session_state),
-                )?
-            } else {
-                let Some(first_calculation) = calculate_iter.next() else {
-                    return plan_err!("Calculation not found and no any required field");
-                };
-                Start(LogicalPlan::Extension(Extension {
-                    node: first_calculation.as_ref(),
-                }))
+                _ => None,
             };
+            RelationChain::source(
+                source,
+                source_required_fields,
+                Arc::clone(&self.analyzed_wren_mdl),
+                Arc::clone(&self.session_state),
+                source_table_scan,
+            )?
+        } else {
+            let Some(first_calculation) = calculate_iter.next() else {
+                return plan_err!("Calculation not found and no any required field");
+            };
+            Start(LogicalPlan::Extension(Extension {
+                node: first_calculation.as_ref(),
+            }))
+        };
 
         let mut relation_chain = RelationChain::with_chain(
             source_chain,
@@ -332,20 +345,27 @@ impl ModelPlanNodeBuilder {
                     model.name()
                 );
             };
-            relation_chain = RelationChain::Chain(
-                LogicalPlan::Extension(Extension {
+            relation_chain = RelationChain::Chain {
+                plan: LogicalPlan::Extension(Extension {
                     node: calculation_plan.as_ref(),
                 }),
-                JoinType::OneToOne,
-                format!(
+                relationship: calculation_plan.name().to_string(),
+                join_type: JoinType::OneToOne,
+                condition: format!(
                     "{}.{} = {}.{}",
                     quoted(model_ref.table()),
                     quoted(join_key),
                     quoted(target_ref.table()),
                     quoted(join_key),
                 ),
-                Box::new(relation_chain),
-            );
+                lookup: None,
+                self_alias: None,
+                join_hint: None,
+                as_of: None,
+                scope: None,
+                null_safe: false,
+                next: Box::new(relation_chain),
+            };
         }
 
         Ok(ModelPlanNode {
@@ -359,13 +379,201 @@ impl ModelPlanNodeBuilder {
             relation_chain: Box::new(relation_chain),
             schema_ref,
             original_table_scan,
+            distinct: self.distinct,
         })
     }
 
+    /// Order the calculations by the estimated row counts of their related models in the
+    /// statistics of the [AnalyzedWrenMDL], the smaller first. The calculations over the models
+    /// without statistics are ordered last, and the declared order is kept for the ties.
+    fn order_calculations(&mut self) {
+        let analyzed_wren_mdl = Arc::clone(&self.analyzed_wren_mdl);
+        let statistics = analyzed_wren_mdl.statistics();
+        if statistics.is_empty() {
+            return;
+        }
+        let wren_mdl = analyzed_wren_mdl.wren_mdl();
+        self.required_calculation.sort_by_cached_key(|calculation| {
+            let WrenPlan::Calculation(node) = calculation;
+            let model_name = node.calculation.dataset.name();
+            let qualified_column = from_qualified_name(
+                &wren_mdl,
+                model_name,
+                node.calculation.column.name(),
+            );
+            analyzed_wren_mdl
+                .lineage()
+                .required_dataset_topo
+                .get(&qualified_column)
+                .map(|graph| {
+                    graph
+                        .node_weights()
+                        .filter(|dataset| dataset.name() != model_name)
+                        .map(|dataset| {
+                            statistics.row_count(dataset.name()).unwrap_or(usize::MAX)
+                        })
+                        .fold(0_usize, usize::saturating_add)
+                })
+                .unwrap_or(usize::MAX)
+        });
+    }
+
+    /// Create the expression of the calculated field, aggregated by default if it's required.
+    /// Return it with the qualified name of the column.
+    fn calculated_field_expr(
+        &self,
+        model: &Model,
+        column: &Arc<mdl::manifest::Column>,
+    ) -> Result<(Expr, Column)> {
+        if column.expression.is_none() {
+            return plan_err!("Only support calculated field with expression");
+        }
+        let qualified_column = from_qualified_name(
+            &self.analyzed_wren_mdl.wren_mdl(),
+            model.name(),
+            column.name(),
+        );
+        let Some(column_rf) = self
+            .analyzed_wren_mdl
+            .wren_mdl()
+            .get_column_reference(&qualified_column)
+        else {
+            return plan_err!("Column reference not found for {:?}", column);
+        };
+        let expr = create_wren_calculated_field_expr(
+            column_rf,
+            Arc::clone(&self.analyzed_wren_mdl),
+            Arc::clone(&self.session_state),
+        )?
+        .alias(column.name());
+        let expr = if column.count_null_as_distinct {
+            count_null_as_distinct(expr)?
+        } else {
+            expr
+        };
+        let Some(column_graph) = self
+            .analyzed_wren_mdl
+            .lineage()
+            .required_dataset_topo
+            .get(&qualified_column)
+        else {
+            return plan_err!("Required dataset not found for {}", qualified_column);
+        };
+        let expr = self.with_default_aggregation(column, expr, column_graph)?;
+        Ok((expr, qualified_column))
+    }
+
+    /// Check the session is authorized for every column the calculated field is computed from, and
+    /// its role is allowed to query them. The column hidden from the session can't be read through
+    /// a calculated field either, e.g. `sum(customer.c_secret)`.
+    fn check_required_columns(&self, qualified_column: &Column) -> Result<()> {
+        let Some(required_columns) = self
+            .analyzed_wren_mdl
+            .lineage()
+            .required_fields_map
+            .get(qualified_column)
+        else {
+            return Ok(());
+        };
+        let query_context = QueryContext::from_config(self.session_state.read().config());
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        let mut required_columns = required_columns.iter().collect::<Vec<_>>();
+        required_columns.sort_by_key(|c| c.flat_name());
+        for required in required_columns {
+            let Some(ColumnReference {
+                dataset: Dataset::Model(model),
+                column,
+            }) = wren_mdl.get_column_reference(required)
+            else {
+                continue;
+            };
+            if !query_context.is_allowed(&column) {
+                return Err(WrenError::DisallowedColumn {
+                    role: query_context.role().map(String::from),
+                    model: model.name().to_string(),
+                    column: column.name().to_string(),
+                }
+                .into());
+            }
+            if !query_context.is_authorized(&column) {
+                return Err(WrenError::UnauthorizedColumn {
+                    model: model.name().to_string(),
+                    column: column.name().to_string(),
+                }
+                .into());
+            }
+        }
+        Ok(())
+    }
+
     fn is_to_many_calculation(&self, expr: Expr) -> bool {
         !find_aggregate_exprs(&[expr]).is_empty()
     }
 
+    /// Aggregate the calculated field by its default aggregation if it traverses a to-many
+    /// relationship without any aggregate function. The distinct relationship keeps the fanned out
+    /// rows, which are de-duplicated by the model instead.
+    ///
+    /// The default aggregation is declared by the column or the manifest. The field without any
+    /// declared aggregation is left as it is. The identifiers (the primary keys and the join keys
+    /// of the relationships) can't be aggregated by default, and only the numeric fields can be
+    /// summed or averaged.
+    fn with_default_aggregation(
+        &self,
+        column: &mdl::manifest::Column,
+        expr: Expr,
+        column_graph: &Graph<Dataset, DatasetLink>,
+    ) -> Result<Expr> {
+        if self.is_to_many_calculation(expr.clone())
+            || column_graph
+                .edge_weights()
+                .all(|link| link.join_type.is_to_one() || link.distinct)
+        {
+            return Ok(expr);
+        }
+        let wren_mdl = self.analyzed_wren_mdl.wren_mdl();
+        let Some(aggregation) = column
+            .default_aggregation()
+            .or_else(|| wren_mdl.default_aggregation())
+        else {
+            return Ok(expr);
+        };
+        for source in expr.column_refs() {
+            if is_identifier(&wren_mdl, source)? {
+                return plan_err!(
+                    "The calculated field {} traverses a to-many relationship without an aggregation. \
+                    {} is an identifier and can't be aggregated by default",
+                    column.name(),
+                    source.flat_name()
+                );
+            }
+        }
+        let aggregate = match aggregation {
+            DefaultAggregation::Sum => sum,
+            DefaultAggregation::Avg => avg,
+            DefaultAggregation::Min => min,
+            DefaultAggregation::Max => max,
+            DefaultAggregation::Count => count,
+        };
+        if matches!(
+            aggregation,
+            DefaultAggregation::Sum | DefaultAggregation::Avg
+        ) && !map_data_type(&column.r#type)?.is_numeric()
+        {
+            return plan_err!(
+                "The calculated field {} traverses a to-many relationship without an aggregation. \
+                The type {} can't be aggregated by {} by default",
+                column.name(),
+                column.r#type,
+                aggregation
+            );
+        }
+        Ok(match expr {
+            Expr::Alias(alias) => aggregate(*alias.expr).alias(alias.name),
+            expr => aggregate(expr),
+        })
+    }
+
     fn is_contain_calculation_source(&self, qualified_column: &Column) -> bool {
         self.analyzed_wren_mdl
             .lineage()
@@ -399,12 +607,26 @@ impl ModelPlanNodeBuilder {
             return plan_err!("Required dataset not found for {}", qualified_column);
         };
 
-        // The calculation column is provided by the CalculationPlanNode.
-        let _ = &self.required_exprs_buffer.insert(OrdExpr::new(col(format!(
+        // The calculation column is provided by the CalculationPlanNode. The measure aggregated
+        // over no rows takes its empty value after the aggregation instead of NULL.
+        let calculation_column = col(format!(
             "{}.{}",
             quoted(column.name()),
-            quoted(column.name()),
-        ))));
+            quoted(column.name())
+        ));
+        let calculation_column = match column.empty_value() {
+            Some(empty_value) => {
+                let empty_value = self
+                    .session_state
+                    .read()
+                    .create_logical_expr(empty_value, &DFSchema::empty())?;
+                coalesce(vec![calculation_column, empty_value]).alias(column.name())
+            }
+            None => calculation_column,
+        };
+        let _ = &self
+            .required_exprs_buffer
+            .insert(OrdExpr::new(calculation_column));
 
         let mut partial_model_required_fields = HashMap::new();
 
@@ -438,6 +660,7 @@ impl ModelPlanNodeBuilder {
             source_required_fields,
             Arc::clone(&self.analyzed_wren_mdl),
             Arc::clone(&self.session_state),
+            None,
         )?;
 
         let partial_chain = RelationChain::with_chain(
@@ -465,6 +688,30 @@ impl ModelPlanNodeBuilder {
     }
 }
 
+/// Check if the column is the primary key of its model or a join key of any relationship
+fn is_identifier(wren_mdl: &mdl::WrenMDL, column: &Column) -> Result<bool> {
+    let Some(model) = column
+        .relation
+        .as_ref()
+        .and_then(|relation| wren_mdl.get_model(relation.table()))
+    else {
+        return Ok(false);
+    };
+    if model.primary_key() == Some(column.name.as_str()) {
+        return Ok(true);
+    }
+    let join_key = format!("{}.{}", model.name(), column.name);
+    for relationship in wren_mdl.manifest.relationships.iter() {
+        if mdl::utils::collect_identifiers(&relationship.condition)?
+            .iter()
+            .any(|identifier| identifier.name == join_key)
+        {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
 #[inline]
 fn is_required_column(expr: &Expr, name: &str) -> bool {
     match expr {
@@ -541,7 +788,7 @@ fn collect_model_required_fields(
             return plan_err!("Column reference not found for {}", c);
         };
         if let Dataset::Model(m) = dataset {
-            if column.is_calculated {
+            if column.is_computed() {
                 let expr_plan = if let Some(expression) = &column.expression {
                     let Ok(expr) = create_wren_expr_for_model(
                         expression,
@@ -591,20 +838,55 @@ fn get_remote_column_exp(
     analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
     session_state_ref: SessionStateRef,
 ) -> Result<Expr> {
-    let expr = if let Some(expression) = &column.expression {
-        create_remote_expr_for_model(
-            expression,
-            model,
-            analyzed_wren_mdl,
-            session_state_ref,
-        )?
+    // the relationship filter is a subquery correlated with the model source, which is planned
+    // by the model generation
+    if column.relationship_filter().is_some() {
+        return Ok(lit(ScalarValue::Boolean(None)).alias(column.name.clone()));
+    }
+    let expression = column
+        .expression
+        .clone()
+        .unwrap_or_else(|| quoted(&column.name));
+    let expression = if let Some(materialized_column) = column.materialized_column() {
+        quoted(materialized_column)
+    } else if let Some(semi_additive) = column.semi_additive() {
+        semi_additive_expr(&expression, semi_additive, &model)?
+    } else if let Some(bucket) = column.bucket() {
+        bucket_expr(bucket, &model)?
+    } else if let Some(array_column) = column.unnest() {
+        unnest_expr(array_column, &model)?
+    } else if let Some(surrogate_key) = column.surrogate_key() {
+        let data_source = analyzed_wren_mdl
+            .wren_mdl()
+            .data_source()
+            .unwrap_or_default();
+        surrogate_key_expr(surrogate_key, &model, &data_source)?
     } else {
-        create_remote_expr_for_model(
-            quoted(&column.name).as_str(),
+        expression
+    };
+    let expr = match column.parent_scope() {
+        Some(scope) => {
+            let key = parent_scope_key(scope, &model, &analyzed_wren_mdl.wren_mdl())?;
+            let partition_by = create_remote_expr_for_model(
+                &key,
+                Arc::clone(&model),
+                Arc::clone(&analyzed_wren_mdl),
+                Arc::clone(&session_state_ref),
+            )?;
+            let expr = create_remote_expr_for_model(
+                &expression,
+                model,
+                analyzed_wren_mdl,
+                session_state_ref,
+            )?;
+            scope_aggregations(expr, partition_by)?
+        }
+        None => create_remote_expr_for_model(
+            &expression,
             model,
             analyzed_wren_mdl,
             session_state_ref,
-        )?
+        )?,
     };
     Ok(expr.alias(column.name.clone()))
 }
@@ -716,6 +998,7 @@ impl UserDefinedLogicalNodeCore for ModelPlanNode {
             relation_chain: self.relation_chain.clone(),
             schema_ref: self.schema_ref.clone(),
             original_table_scan: self.original_table_scan.clone(),
+            distinct: self.distinct,
         })
     }
 }
@@ -729,6 +1012,8 @@ pub struct ModelSourceNode {
     pub required_exprs: Vec<Expr>,
     pub schema_ref: DFSchemaRef,
     pub original_table_scan: Option<LogicalPlan>,
+    /// The deterministic identity of the scan, e.g. to key the cached query results
+    pub fingerprint: u64,
 }
 
 impl ModelSourceNode {
@@ -765,7 +1050,7 @@ impl ModelSourceNode {
                         Arc::new(Field::new(
                             column.name(),
                             map_data_type(&column.r#type)?,
-                            column.not_null,
+                            !column.not_null,
                         )),
                     ));
                     required_exprs_buffer.insert(OrdExpr::new(get_remote_column_exp(
@@ -788,7 +1073,7 @@ impl ModelSourceNode {
                 else {
                     return plan_err!("Field not found {}", expr);
                 };
-                if column.is_calculated {
+                if column.is_computed() {
                     return plan_err!("should not use calculated field in source plan");
                 } else {
                     let expr_plan = get_remote_column_exp(
@@ -805,12 +1090,34 @@ impl ModelSourceNode {
                     Arc::new(Field::new(
                         column.name(),
                         map_data_type(&column.r#type)?,
-                        column.not_null,
+                        !column.not_null,
                     )),
                 ));
             }
         }
 
+        // the unnested columns fan out the rows of the model, so they're always required
+        for column in model
+            .get_physical_columns()
+            .into_iter()
+            .filter(|column| column.unnest().is_some())
+        {
+            required_exprs_buffer.insert(OrdExpr::new(get_remote_column_exp(
+                &column,
+                Arc::clone(&model),
+                Arc::clone(&analyzed_wren_mdl),
+                Arc::clone(&session_state_ref),
+            )?));
+            fields_buffer.insert((
+                Some(TableReference::bare(quoted(model.name()))),
+                Arc::new(Field::new(
+                    column.name(),
+                    map_data_type(&column.r#type)?,
+                    !column.not_null,
+                )),
+            ));
+        }
+
         let fields = fields_buffer.into_iter().collect::<Vec<_>>();
         let schema_ref = DFSchemaRef::new(
             DFSchema::new_with_metadata(fields, HashMap::new())
@@ -820,15 +1127,34 @@ impl ModelSourceNode {
             .into_iter()
             .map(|e| e.expr)
             .collect::<Vec<_>>();
+        let fingerprint =
+            scan_fingerprint(&model, &required_exprs, original_table_scan.as_ref());
         Ok(ModelSourceNode {
             model_name: model.name().to_string(),
             required_exprs,
             schema_ref,
             original_table_scan,
+            fingerprint,
         })
     }
 }
 
+/// Fingerprint the scan of the model by the model definition, the required columns and the
+/// filters pushed down to the scan. Any change of them changes the fingerprint.
+fn scan_fingerprint(
+    model: &Model,
+    required_exprs: &[Expr],
+    original_table_scan: Option<&LogicalPlan>,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    model.hash(&mut hasher);
+    required_exprs.hash(&mut hasher);
+    if let Some(LogicalPlan::TableScan(scan)) = original_table_scan {
+        scan.filters.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
 impl PartialOrd for ModelSourceNode {
     fn partial_cmp(&self, _other: &Self) -> Option<Ordering> {
         None
@@ -857,7 +1183,11 @@ impl UserDefinedLogicalNodeCore for ModelSourceNode {
     }
 
     fn fmt_for_explain(&self, f: &mut Formatter) -> fmt::Result {
-        write!(f, "ModelSource: name={}", self.model_name)
+        write!(
+            f,
+            "ModelSource: name={}, fingerprint={:016x}",
+            self.model_name, self.fingerprint
+        )
     }
 
     fn with_exprs_and_inputs(&self, _: Vec<Expr>, _: Vec<LogicalPlan>) -> Result<Self> {
@@ -866,6 +1196,7 @@ impl UserDefinedLogicalNodeCore for ModelSourceNode {
             required_exprs: self.required_exprs.clone(),
             schema_ref: self.schema_ref.clone(),
             original_table_scan: self.original_table_scan.clone(),
+            fingerprint: self.fingerprint,
         })
     }
 }
@@ -899,12 +1230,12 @@ impl CalculationPlanNode {
             Arc::new(Field::new(
                 calculation.column.name(),
                 map_data_type(&calculation.column.r#type)?,
-                calculation.column.not_null,
+                !calculation.column.not_null,
             )),
             Arc::new(Field::new(
                 pk_column.name(),
                 map_data_type(&pk_column.r#type)?,
-                pk_column.not_null,
+                !pk_column.not_null,
             )),
         ]
         .into_iter()
@@ -928,6 +1259,43 @@ impl CalculationPlanNode {
             schema_ref,
         })
     }
+
+    /// Create the calculation of the calculated field traversing a to-many relationship of the
+    /// model, the same as the one required by the [ModelPlanNode] of the model.
+    pub fn for_column(
+        model_name: &str,
+        column_name: &str,
+        analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
+        session_state: SessionStateRef,
+    ) -> Result<Self> {
+        let wren_mdl = analyzed_wren_mdl.wren_mdl();
+        let Some(model) = wren_mdl.get_model(model_name) else {
+            return plan_err!("Model not found {model_name}");
+        };
+        let Some(column) = model.get_column(column_name).filter(|c| c.is_calculated)
+        else {
+            return plan_err!(
+                "The calculated field {model_name}.{column_name} isn't found"
+            );
+        };
+        let model_ref =
+            TableReference::full(wren_mdl.catalog(), wren_mdl.schema(), model.name());
+        let mut builder = ModelPlanNodeBuilder::new(analyzed_wren_mdl, session_state);
+        let (expr, qualified_column) = builder.calculated_field_expr(&model, &column)?;
+        if !builder.is_to_many_calculation(expr.clone()) {
+            return plan_err!(
+                "The calculated field {model_name}.{column_name} doesn't aggregate over a to-many relationship"
+            );
+        }
+        match builder.create_partial_calculation(
+            model_ref,
+            column,
+            &qualified_column,
+            expr,
+        )? {
+            WrenPlan::Calculation(calculation) => Ok(Arc::unwrap_or_clone(calculation)),
+        }
+    }
 }
 
 impl PartialOrd for CalculationPlanNode {
@@ -0,0 +1,168 @@
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{Column, Result};
+use datafusion::logical_expr::expr::Alias;
+use datafusion::logical_expr::{Expr, LogicalPlan, Projection, SubqueryAlias};
+use std::sync::Arc;
+
+/// Fold the redundant projections stacked by the model expansion, e.g. the projection of a
+/// partial model re-selecting the columns of the model plan under the same alias.
+///
+/// - A projection re-selecting all the columns of its input as they are is removed, unless it's
+///   the select list of the query.
+/// - An alias directly over another alias replaces it.
+/// - A projection selecting only the columns of the projection under it is merged into it, so
+///   the expressions are computed by one projection. An expression used more than once isn't
+///   merged unless it's a column or a literal, so nothing is computed twice.
+///
+/// The folded plan keeps the same output schema.
+pub(crate) fn fold_redundant_projections(plan: LogicalPlan) -> Result<LogicalPlan> {
+    let plan = plan
+        .map_children(|child| {
+            child.transform_up_with_subqueries(|plan| match plan {
+                LogicalPlan::Projection(projection) => fold_projection(projection, true),
+                LogicalPlan::SubqueryAlias(alias) => fold_alias(alias),
+                plan => Ok(Transformed::no(plan)),
+            })
+        })
+        .data()?;
+    match plan {
+        LogicalPlan::Projection(projection) => fold_projection(projection, false).data(),
+        plan => Ok(plan),
+    }
+}
+
+fn fold_projection(
+    projection: Projection,
+    remove_identity: bool,
+) -> Result<Transformed<LogicalPlan>> {
+    if remove_identity && is_identity(&projection) {
+        return Ok(Transformed::yes(Arc::unwrap_or_clone(projection.input)));
+    }
+    let LogicalPlan::Projection(inner) = projection.input.as_ref() else {
+        return Ok(Transformed::no(LogicalPlan::Projection(projection)));
+    };
+    #[allow(deprecated)]
+    if inner
+        .expr
+        .iter()
+        .any(|expr| matches!(expr, Expr::Wildcard { .. }))
+    {
+        return Ok(Transformed::no(LogicalPlan::Projection(projection)));
+    }
+
+    // the outer expressions should be the columns of the inner projection
+    let mut indices = vec![];
+    for expr in &projection.expr {
+        let column = match expr {
+            Expr::Column(column) => column,
+            Expr::Alias(Alias { expr, .. }) => match expr.as_ref() {
+                Expr::Column(column) => column,
+                _ => return Ok(Transformed::no(LogicalPlan::Projection(projection))),
+            },
+            _ => return Ok(Transformed::no(LogicalPlan::Projection(projection))),
+        };
+        match inner.schema.maybe_index_of_column(column) {
+            Some(index) => indices.push(index),
+            None => return Ok(Transformed::no(LogicalPlan::Projection(projection))),
+        }
+    }
+    let computed_twice = inner.expr.iter().enumerate().any(|(index, expr)| {
+        !matches!(expr.clone().unalias(), Expr::Column(_) | Expr::Literal(_))
+            && indices.iter().filter(|i| **i == index).count() > 1
+    });
+    if computed_twice {
+        return Ok(Transformed::no(LogicalPlan::Projection(projection)));
+    }
+
+    let exprs = indices
+        .into_iter()
+        .zip(projection.schema.iter())
+        .map(
+            |(index, (qualifier, field))| match inner.expr[index].clone().unalias() {
+                Expr::Column(column)
+                    if column.relation.as_ref() == qualifier
+                        && &column.name == field.name() =>
+                {
+                    Expr::Column(column)
+                }
+                expr => expr.alias_qualified(qualifier.cloned(), field.name()),
+            },
+        )
+        .collect();
+    Ok(Transformed::yes(LogicalPlan::Projection(
+        Projection::try_new_with_schema(
+            exprs,
+            Arc::clone(&inner.input),
+            Arc::clone(&projection.schema),
+        )?,
+    )))
+}
+
+/// The projection is an identity if it selects all the columns of its input in the same order
+/// without changing the schema. Only the input already generated as a select list is considered,
+/// so the removal never leaves a bare scan or join to be unparsed as `SELECT *`.
+fn is_identity(projection: &Projection) -> bool {
+    let input_schema = projection.input.schema();
+    matches!(
+        projection.input.as_ref(),
+        LogicalPlan::Projection(_) | LogicalPlan::SubqueryAlias(_)
+    ) && projection.expr.len() == input_schema.fields().len()
+        && projection
+            .expr
+            .iter()
+            .zip(input_schema.iter())
+            .all(|(expr, field)| match expr {
+                Expr::Column(column) => *column == Column::from(field),
+                _ => false,
+            })
+        && projection.schema.as_ref() == input_schema.as_ref()
+}
+
+fn fold_alias(alias: SubqueryAlias) -> Result<Transformed<LogicalPlan>> {
+    match alias.input.as_ref() {
+        LogicalPlan::SubqueryAlias(inner) => {
+            Ok(Transformed::yes(LogicalPlan::SubqueryAlias(
+                SubqueryAlias::try_new(Arc::clone(&inner.input), alias.alias)?,
+            )))
+        }
+        _ => Ok(Transformed::no(LogicalPlan::SubqueryAlias(alias))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logical_plan::analyze::projection_folding::fold_redundant_projections;
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::common::Result;
+    use datafusion::logical_expr::{cast, col, lit, table_scan};
+
+    #[test]
+    fn test_fold_redundant_projections() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Int64, true),
+            Field::new("b", DataType::Utf8, true),
+        ]);
+        // the model plan, the partial model re-selecting it and the casts over the model
+        let plan = table_scan(Some("t"), &schema, None)?
+            .project(vec![(col("t.a") + lit(1_i64)).alias("x"), col("t.b")])?
+            .alias("m")?
+            .project(vec![col("m.x"), col("m.b")])?
+            .alias("m")?
+            .project(vec![
+                cast(col("m.x"), DataType::Utf8).alias("x"),
+                col("m.b"),
+            ])?
+            .project(vec![col("x"), col("m.b")])?
+            .build()?;
+        let folded = fold_redundant_projections(plan.clone())?;
+        assert_eq!(
+            format!("{}", folded.display_indent()),
+            "Projection: CAST(m.x AS Utf8) AS x, m.b\
+            \n  SubqueryAlias: m\
+            \n    Projection: t.a + Int64(1) AS x, t.b\
+            \n      TableScan: t"
+        );
+        assert_eq!(folded.schema(), plan.schema());
+        Ok(())
+    }
+}
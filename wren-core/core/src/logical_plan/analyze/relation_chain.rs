@@ -1,3 +1,4 @@
+use crate::logical_plan::analyze::debug::GenerationDecision;
 use crate::logical_plan::analyze::model_generation::ModelGenerationRule;
 use crate::logical_plan::analyze::plan::{
     CalculationPlanNode, ModelPlanNode, ModelSourceNode, OrdExpr, PartialModelPlanNode,
@@ -7,43 +8,123 @@ use crate::logical_plan::utils::{
     create_schema, eliminate_ambiguous_columns, rebase_column,
 };
 use crate::mdl::lineage::DatasetLink;
-use crate::mdl::manifest::JoinType;
-use crate::mdl::utils::{qualify_name_from_column_name, quoted};
+use crate::mdl::manifest::{AsOf, JoinHint, JoinType, Lookup};
+use crate::mdl::query_context::QueryContext;
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::utils::{
+    qualify_name_from_column_name, quoted, quoted_ident, self_join_column_name,
+};
 use crate::mdl::Dataset;
 use crate::mdl::{AnalyzedWrenMDL, SessionStateRef};
-use crate::{mdl, DataFusionError};
+use crate::{mdl, DataFusionError, WrenError};
 use datafusion::common::alias::AliasGenerator;
-use datafusion::common::TableReference;
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
 use datafusion::common::{
     internal_err, not_impl_err, plan_err, DFSchema, DFSchemaRef, Result,
 };
+use datafusion::common::{Column, TableReference};
+use datafusion::functions_window::expr_fn::row_number;
+use datafusion::logical_expr::expr::Placeholder;
 use datafusion::logical_expr::{
-    col, Expr, Extension, LogicalPlan, LogicalPlanBuilder, SubqueryAlias,
-    UserDefinedLogicalNodeCore,
+    binary_expr, col, ident, lit, Expr, ExprFunctionExt, Extension, LogicalPlan,
+    LogicalPlanBuilder, Operator, SortExpr, SubqueryAlias, UserDefinedLogicalNodeCore,
 };
+use datafusion::sql::sqlparser::ast::{self, visit_expressions_mut};
 use petgraph::graph::NodeIndex;
 use petgraph::Graph;
 use std::collections::{BTreeSet, HashMap};
+use std::ops::ControlFlow;
 use std::sync::Arc;
 
 const ALIAS: &str = "__relation_";
+const LOOKUP_RANK: &str = "__lookup_rank";
+const AS_OF_RANK: &str = "__as_of_rank";
+/// The metadata key of the join schema keeping the join hint of the relationship
+pub const JOIN_HINT_METADATA_KEY: &str = "wren.join_hint";
 
 /// RelationChain is a chain of models that are connected by the relationship.
 /// The chain is used to generate the join plan for the model.
 /// The physical layout will be looked like:
 /// (((Model3, Model2), Model1), Nil)
+///
+/// The join hint of the relationship is kept to annotate the join with the joined model.
 #[derive(Eq, PartialEq, Debug, Hash, Clone)]
 pub enum RelationChain {
-    Chain(LogicalPlan, JoinType, String, Box<RelationChain>),
+    Chain {
+        /// The plan of the joined model
+        plan: LogicalPlan,
+        /// The relationship joining the model, or the calculated field joined by the primary key
+        relationship: String,
+        join_type: JoinType,
+        condition: String,
+        /// The order picking the first row of the joined model for each join key
+        lookup: Option<SortExpr>,
+        /// The alias of the joined instance of a self relationship
+        self_alias: Option<String>,
+        join_hint: Option<JoinHint>,
+        as_of: Option<AsOfJoin>,
+        scope: Option<String>,
+        null_safe: bool,
+        next: Box<RelationChain>,
+    },
     Start(LogicalPlan),
 }
 
+/// The as-of join picks the latest row of the joined model at or before the time of each row of
+/// the fact model, e.g. the latest quote of the symbol when the trade happened.
+#[derive(Eq, PartialEq, Debug, Hash, Clone)]
+pub struct AsOfJoin {
+    /// The time column of the joined model
+    dimension_time: String,
+    /// The time column of the fact model
+    fact_time: Column,
+    /// The primary key of the fact model identifying each of its rows
+    fact_key: Column,
+}
+
+impl AsOfJoin {
+    fn new(
+        as_of: &AsOf,
+        target: &str,
+        analyzed_wren_mdl: &AnalyzedWrenMDL,
+    ) -> Result<Self> {
+        let (Some((dimension, dimension_time)), Some((fact, fact_time))) = (
+            as_of.dimension_time.split_once('.'),
+            as_of.fact_time.split_once('.'),
+        ) else {
+            return plan_err!(
+                "The time columns of the as-of join should be qualified by the models: {}, {}",
+                as_of.dimension_time,
+                as_of.fact_time
+            );
+        };
+        if dimension != target {
+            return plan_err!(
+                "The as-of join should join the dimension model {dimension} instead of {target}"
+            );
+        }
+        let Some(fact_key) = analyzed_wren_mdl
+            .wren_mdl()
+            .get_model(fact)
+            .and_then(|model| model.primary_key().map(String::from))
+        else {
+            return Err(WrenError::MissingPrimaryKey(fact.to_string()).into());
+        };
+        Ok(Self {
+            dimension_time: dimension_time.to_string(),
+            fact_time: Column::new(Some(TableReference::bare(fact)), fact_time),
+            fact_key: Column::new(Some(TableReference::bare(fact)), fact_key),
+        })
+    }
+}
+
 impl RelationChain {
     pub(crate) fn source(
         dataset: &Dataset,
         required_fields: Vec<Expr>,
         analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
         session_state_ref: SessionStateRef,
+        original_table_scan: Option<LogicalPlan>,
     ) -> Result<Self> {
         match dataset {
             Dataset::Model(source_model) => {
@@ -53,7 +134,7 @@ impl RelationChain {
                         required_fields,
                         analyzed_wren_mdl,
                         session_state_ref,
-                        None,
+                        original_table_scan,
                     )?),
                 })))
             }
@@ -73,6 +154,10 @@ impl RelationChain {
         session_state_ref: SessionStateRef,
     ) -> Result<Self> {
         let mut relation_chain = source;
+        let mut path = directed_graph
+            .node_weight(start)
+            .map(|dataset| vec![dataset.name().to_string()])
+            .unwrap_or_default();
 
         for next in iter {
             let target = directed_graph.node_weight(next).unwrap();
@@ -90,6 +175,8 @@ impl RelationChain {
             };
             match target {
                 Dataset::Model(target_model) => {
+                    // the joined model is read from its source, so its base filter, e.g. the
+                    // soft-delete predicate, is applied before the join
                     let node = if fields.iter().any(|e| {
                         e.column.is_some() && e.column.clone().unwrap().is_calculated
                     }) {
@@ -120,17 +207,53 @@ impl RelationChain {
                             )?),
                         })
                     };
-                    relation_chain = RelationChain::Chain(
-                        node,
-                        link.join_type,
-                        link.condition.clone(),
-                        Box::new(relation_chain),
-                    );
+                    // The first row of each join key is picked by the lookup when the chain is
+                    // planned.
+                    let lookup = match &link.lookup {
+                        Some(lookup) if is_lookup_target(lookup, target.name()) => Some(
+                            ident(lookup_column(lookup)).sort(!lookup.descending, false),
+                        ),
+                        _ => None,
+                    };
+                    let as_of = link
+                        .as_of
+                        .as_ref()
+                        .map(|as_of| {
+                            AsOfJoin::new(as_of, target.name(), &analyzed_wren_mdl)
+                        })
+                        .transpose()?;
+                    relation_chain = RelationChain::Chain {
+                        plan: node,
+                        relationship: link.relationship.clone(),
+                        join_type: link.join_type,
+                        condition: link.condition.clone(),
+                        lookup,
+                        // The joined instance of a self relationship is aliased by its path of
+                        // the hops
+                        self_alias: link.alias.clone(),
+                        join_hint: link.join_hint,
+                        as_of,
+                        scope: link.scope.clone(),
+                        null_safe: link.null_safe,
+                        next: Box::new(relation_chain),
+                    };
                 }
                 _ => return plan_err!("Only support model as source dataset"),
             }
+            path.push(
+                link.alias
+                    .clone()
+                    .unwrap_or_else(|| target.name().to_string()),
+            );
             start = next;
         }
+        if let Some(max_depth) = analyzed_wren_mdl.options().max_relationship_depth {
+            if path.len() > max_depth + 1 {
+                return Err(
+                    WrenError::RelationshipDepthExceeded { path, max_depth }.into()
+                );
+            }
+        }
         Ok(relation_chain)
     }
 
@@ -140,21 +263,47 @@ impl RelationChain {
         alias_generator: &AliasGenerator,
     ) -> Result<(Option<LogicalPlan>, Option<String>)> {
         match self {
-            RelationChain::Chain(plan, _, condition, ref mut next) => {
+            RelationChain::Chain {
+                plan,
+                relationship,
+                condition,
+                lookup,
+                self_alias,
+                join_hint,
+                as_of,
+                scope,
+                null_safe,
+                ref mut next,
+                ..
+            } => {
                 let left = rule.generate_model_internal(plan.clone())?.data;
+                // replace the model alias with the alias of the self relationship
+                let left = match (&self_alias, left) {
+                    (
+                        Some(self_alias),
+                        LogicalPlan::SubqueryAlias(SubqueryAlias { input, .. }),
+                    ) => LogicalPlanBuilder::from(Arc::unwrap_or_clone(input))
+                        .alias(TableReference::bare(self_alias.as_str()))?
+                        .build()?,
+                    (_, left) => left,
+                };
                 let left_alias = if let LogicalPlan::SubqueryAlias(SubqueryAlias {
                     alias,
                     ..
                 }) = &left
                 {
-                    alias.table()
+                    alias.table().to_string()
                 } else {
                     return internal_err!(
                         "model plan should be wrapped in a subquery alias"
                     );
                 };
 
-                let (Some(right), right_alias) = next.plan(rule, alias_generator)? else {
+                let session_state = rule.session_state();
+                let query_context = rule.query_context();
+                let (Some(right), right_alias) =
+                    next.plan(rule.clone(), alias_generator)?
+                else {
                     return plan_err!("Nil relation chain");
                 };
 
@@ -182,10 +331,76 @@ impl RelationChain {
                         _ => Ok::<_, DataFusionError>(expr),
                     })
                     .collect::<Result<Vec<_>>>()?;
-                let join_condition = join_keys[0].clone().eq(join_keys[1].clone());
+                let left = match lookup {
+                    Some(order_by) => lookup_first_row(
+                        left,
+                        &left_alias,
+                        &join_keys,
+                        vec![order_by.clone()],
+                    )?,
+                    None => left,
+                };
+                // The NULL-safe relationship matches the NULL keys of both sides too.
+                let join_condition = match join_keys.as_slice() {
+                    [left_key, right_key, ..] if *null_safe => binary_expr(
+                        left_key.clone(),
+                        Operator::IsNotDistinctFrom,
+                        right_key.clone(),
+                    ),
+                    [left_key, right_key, ..] => left_key.clone().eq(right_key.clone()),
+                    _ => {
+                        return plan_err!(
+                            "The condition of the relationship {relationship} should compare the keys of both models: {condition}"
+                        )
+                    }
+                };
+                // the as-of join only matches the rows of the joined model up to the fact time
+                let as_of = as_of
+                    .as_ref()
+                    .map(|as_of| {
+                        let rebase = |column: &Column| match &right_alias {
+                            Some(right_alias) => {
+                                rebase_column(&Expr::Column(column.clone()), right_alias)
+                            }
+                            None => Ok(Expr::Column(column.clone())),
+                        };
+                        Ok::<_, DataFusionError>((
+                            col(format!(
+                                "{}.{}",
+                                quoted(&left_alias),
+                                quoted(&as_of.dimension_time)
+                            )),
+                            rebase(&as_of.fact_time)?,
+                            rebase(&as_of.fact_key)?,
+                        ))
+                    })
+                    .transpose()?;
+                let join_condition = match &as_of {
+                    Some((dimension_time, fact_time, _)) => join_condition
+                        .and(dimension_time.clone().lt_eq(fact_time.clone())),
+                    None => join_condition,
+                };
+                // the scope of the relationship is folded into the join condition
+                let join_condition = match scope.as_deref() {
+                    Some(scope) => join_condition.and(scope_predicate(
+                        scope,
+                        &left_alias,
+                        right_alias.as_deref(),
+                        &left.schema().join(right.schema())?,
+                        &session_state,
+                        &query_context,
+                    )?),
+                    None => join_condition,
+                };
+                rule.record(|| GenerationDecision::JoinAdded {
+                    model: left_alias.clone(),
+                    condition: join_condition.to_string(),
+                });
                 let mut required_exprs = BTreeSet::new();
                 // collect the output calculated fields
                 match plan {
+                    // the columns of the aliased instance are collected from the left table
+                    LogicalPlan::Extension(_) if self_alias.is_some() => {}
                     LogicalPlan::Extension(plan) => {
                         if let Some(model_plan) =
                             plan.node.as_any().downcast_ref::<ModelPlanNode>()
@@ -266,7 +481,13 @@ impl RelationChain {
                     };
                     let qualified_name =
                         format!("{}.{}", table_rf.to_quoted_string(), quoted(f.name()));
-                    required_exprs.insert(OrdExpr::new(col(qualified_name)));
+                    let column = match &self_alias {
+                        // avoid the collision with the columns of the owning model
+                        Some(self_alias) => col(qualified_name)
+                            .alias(self_join_column_name(self_alias, f.name())),
+                        None => col(qualified_name),
+                    };
+                    required_exprs.insert(OrdExpr::new(column));
                 }
 
                 // collect the column of the right table
@@ -286,14 +507,26 @@ impl RelationChain {
                     .collect();
                 let required_field = eliminate_ambiguous_columns(required_field);
                 let alias = alias_generator.next(ALIAS);
+                let join = LogicalPlanBuilder::from(left)
+                    .join_on(
+                        right,
+                        datafusion::logical_expr::JoinType::Right,
+                        vec![join_condition],
+                    )?
+                    .build()?;
+                let join = match join_hint {
+                    Some(join_hint) => annotate_join_hint(join, *join_hint, &left_alias)?,
+                    None => join,
+                };
+                let join = match as_of {
+                    Some((dimension_time, _, fact_key)) => {
+                        latest_as_of(join, dimension_time, fact_key)?
+                    }
+                    None => join,
+                };
                 Ok((
                     Some(
-                        LogicalPlanBuilder::from(left)
-                            .join_on(
-                                right,
-                                datafusion::logical_expr::JoinType::Right,
-                                vec![join_condition],
-                            )?
+                        LogicalPlanBuilder::from(join)
                             .project(required_field)?
                             .alias(&alias)?
                             .build()?,
@@ -307,3 +540,251 @@ impl RelationChain {
         }
     }
 }
+
+/// Build the predicate of the relationship scope. The columns of the other side are rebased to its
+/// alias, and the parameters, e.g. `$region`, are bound by the variables of the query context.
+fn scope_predicate(
+    scope: &str,
+    left_alias: &str,
+    right_alias: Option<&str>,
+    schema: &DFSchema,
+    session_state: &SessionStateRef,
+    query_context: &QueryContext,
+) -> Result<Expr> {
+    let mut expr = parse_expr_snippet(scope)?;
+    if let Some(right_alias) = right_alias {
+        let _ = visit_expressions_mut(&mut expr, |e| {
+            if let ast::Expr::CompoundIdentifier(ids) = e {
+                if let [qualifier, _] = ids.as_mut_slice() {
+                    if qualifier.value != left_alias {
+                        *qualifier = quoted_ident(right_alias);
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+    }
+    let predicate = session_state
+        .read()
+        .create_logical_expr(&expr.to_string(), schema)?;
+    predicate
+        .transform_up(|e| match e {
+            Expr::Placeholder(Placeholder { id, .. }) => {
+                match query_context.variable(id.trim_start_matches('$')) {
+                    Some(value) => Ok(Transformed::yes(lit(value))),
+                    None => Err(WrenError::UnboundParameter {
+                        parameter: id,
+                        scope: scope.to_string(),
+                    }
+                    .into()),
+                }
+            }
+            _ => Ok(Transformed::no(e)),
+        })
+        .data()
+}
+
+/// Annotate the join with the distribution hint of the joined model, e.g. `BROADCAST(customer)`.
+///
+/// DataFusion doesn't plan the distribution of the join, so the hint is kept in the metadata of the
+/// join schema with the key [JOIN_HINT_METADATA_KEY] for the distributed backends.
+fn annotate_join_hint(
+    join: LogicalPlan,
+    join_hint: JoinHint,
+    alias: &str,
+) -> Result<LogicalPlan> {
+    let LogicalPlan::Join(mut join) = join else {
+        return internal_err!("the relation chain should be planned as a join");
+    };
+    let mut metadata = join.schema.metadata().clone();
+    metadata.insert(
+        JOIN_HINT_METADATA_KEY.to_string(),
+        format!("{join_hint}({alias})"),
+    );
+    let schema = DFSchema::new_with_metadata(
+        join.schema
+            .iter()
+            .map(|(qualifier, field)| (qualifier.cloned(), Arc::clone(field)))
+            .collect(),
+        metadata,
+    )?
+    .with_functional_dependencies(join.schema.functional_dependencies().clone())?;
+    join.schema = Arc::new(schema);
+    Ok(LogicalPlan::Join(join))
+}
+
+/// Keep the latest matched row of the joined model for each row of the fact model. The fact row
+/// without any matched row is kept as it is.
+fn latest_as_of(
+    join: LogicalPlan,
+    dimension_time: Expr,
+    fact_key: Expr,
+) -> Result<LogicalPlan> {
+    let columns = join
+        .schema()
+        .columns()
+        .into_iter()
+        .map(Expr::Column)
+        .collect::<Vec<_>>();
+    let rank = row_number()
+        .partition_by(vec![fact_key])
+        .order_by(vec![dimension_time.sort(false, false)])
+        .build()?
+        .alias(AS_OF_RANK);
+    LogicalPlanBuilder::from(join)
+        .window(vec![rank])?
+        .filter(col(AS_OF_RANK).eq(lit(1_u64)))?
+        .project(columns)?
+        .build()
+}
+
+/// The lookup column is qualified by the model looked up, e.g. `orders.o_orderdate`
+fn is_lookup_target(lookup: &Lookup, model: &str) -> bool {
+    lookup
+        .order_by
+        .split_once('.')
+        .is_some_and(|(relation, _)| relation == model)
+}
+
+fn lookup_column(lookup: &Lookup) -> &str {
+    lookup
+        .order_by
+        .split_once('.')
+        .map(|(_, column)| column)
+        .unwrap_or(&lookup.order_by)
+}
+
+/// Keep the first row of the model for each of its join keys, like a `LATERAL` subquery
+/// ordered by the lookup column and limited to one row.
+fn lookup_first_row(
+    plan: LogicalPlan,
+    alias: &str,
+    join_keys: &[Expr],
+    order_by: Vec<SortExpr>,
+) -> Result<LogicalPlan> {
+    let partition_by = join_keys
+        .iter()
+        .filter(|key| {
+            matches!(key, Expr::Column(column)
+                if column.relation.as_ref().is_some_and(|r| r.table() == alias))
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    let columns = plan
+        .schema()
+        .columns()
+        .into_iter()
+        .map(Expr::Column)
+        .collect::<Vec<_>>();
+    let rank = row_number()
+        .partition_by(partition_by)
+        .order_by(order_by)
+        .build()?
+        .alias(LOOKUP_RANK);
+    LogicalPlanBuilder::from(plan)
+        .window(vec![rank])?
+        .filter(col(LOOKUP_RANK).eq(lit(1_u64)))?
+        .project(columns)?
+        .alias(alias)?
+        .build()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_max_relationship_depth() -> Result<()> {
+        let manifest = || {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("lineitem")
+                        .table_reference("lineitem")
+                        .column(ColumnBuilder::new("l_linenumber", "bigint").build())
+                        .column(ColumnBuilder::new("l_orderkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "orders",
+                                "orders",
+                                "lineitem_orders",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("customer_name", "varchar")
+                                .calculated(true)
+                                .expression("orders.customer.c_name")
+                                .build(),
+                        )
+                        .primary_key("l_linenumber")
+                        .build(),
+                )
+                .model(
+                    orders_model()
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "customer",
+                                "customer",
+                                "orders_customer",
+                            )
+                            .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .model(customer_model().primary_key("c_custkey").build())
+                .relationship(
+                    RelationshipBuilder::new("lineitem_orders")
+                        .model("lineitem")
+                        .model("orders")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("lineitem.l_orderkey = orders.o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("orders_customer")
+                        .model("orders")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("orders.o_custkey = customer.c_custkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT customer_name FROM lineitem";
+
+        // the path lineitem -> orders -> customer traverses 2 relationships
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest())?.with_max_relationship_depth(1),
+        );
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::RelationshipDepthExceeded {
+                path: vec![
+                    "lineitem".to_string(),
+                    "orders".to_string(),
+                    "customer".to_string()
+                ],
+                max_depth: 1,
+            })
+        );
+        assert!(err.to_string().contains(
+            "The relationship path lineitem -> orders -> customer exceeds the maximum traversal depth 1"
+        ));
+
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest())?.with_max_relationship_depth(2),
+        );
+        transform_with_mdl(&analyzed_mdl, sql).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::any::Any;
+use std::str::FromStr;
+
+use chrono::{DateTime, Days, NaiveDate, TimeZone};
+use datafusion::arrow::array::timezone::Tz;
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{plan_err, Column, Result, ScalarValue};
+use datafusion::logical_expr::expr::ScalarFunction;
+use datafusion::logical_expr::{
+    lit, ColumnarValue, Expr, LogicalPlan, ScalarUDFImpl, Signature, Volatility,
+};
+
+use crate::mdl::query_context::QueryContext;
+
+/// The name of the relative date filter function
+pub const RELATIVE_DATE: &str = "relative_date";
+
+/// `relative_date(column, range)` filters the rows whose column is in the date range relative to
+/// the reference time of the [QueryContext], e.g. `relative_date(o_orderdate, 'last 7 days')`.
+/// The range is one of `today`, `yesterday` and `last N days`, where `last N days` covers today
+/// and the N - 1 days before it. The days are the calendar days in the timezone of the
+/// [QueryContext].
+///
+/// The function is never evaluated. The model generation resolves it to the bounds of the range,
+/// see [resolve_relative_date].
+#[derive(Debug)]
+pub struct RelativeDate {
+    signature: Signature,
+}
+
+impl RelativeDate {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::any(2, Volatility::Stable),
+        }
+    }
+}
+
+impl Default for RelativeDate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScalarUDFImpl for RelativeDate {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        RELATIVE_DATE
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    fn return_type(&self, _arg_types: &[DataType]) -> Result<DataType> {
+        Ok(DataType::Boolean)
+    }
+
+    fn invoke(&self, _args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        plan_err!("{RELATIVE_DATE} is only allowed in the filters of the query")
+    }
+}
+
+/// Return the column filtered by the relative date filter, e.g. `o_orderdate` of
+/// `relative_date(o_orderdate, 'last 7 days')`
+pub(crate) fn relative_date_column(expr: &Expr) -> Option<&Column> {
+    match expr {
+        Expr::ScalarFunction(ScalarFunction { func, args })
+            if func.name() == RELATIVE_DATE =>
+        {
+            match args.first() {
+                Some(Expr::Column(column)) => Some(column),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Resolve the relative date filters of the filter plans. See [resolve_relative_date].
+pub(crate) fn resolve_relative_date_filters(
+    plan: LogicalPlan,
+    query_context: &QueryContext,
+) -> Result<LogicalPlan> {
+    plan.transform_up_with_subqueries(|plan| match plan {
+        LogicalPlan::Filter(mut filter) => {
+            let predicate =
+                resolve_relative_date(filter.predicate.clone(), query_context)?;
+            if !predicate.transformed {
+                return Ok(Transformed::no(LogicalPlan::Filter(filter)));
+            }
+            filter.predicate = predicate.data;
+            Ok(Transformed::yes(LogicalPlan::Filter(filter)))
+        }
+        _ => Ok(Transformed::no(plan)),
+    })
+    .data()
+}
+
+/// Resolve the relative date filters of the expression to the comparisons with the UTC timestamps
+/// of the range bounds. e.g. for the reference time `2024-03-10 02:00:00` UTC in
+/// `America/New_York`, `relative_date(o_orderdate, 'last 7 days')` is resolved to
+/// ```sql
+/// o_orderdate >= TIMESTAMP '2024-03-03 05:00:00' AND o_orderdate < TIMESTAMP '2024-03-10 05:00:00'
+/// ```
+pub(crate) fn resolve_relative_date(
+    expr: Expr,
+    query_context: &QueryContext,
+) -> Result<Transformed<Expr>> {
+    expr.transform_up(|expr| {
+        let Expr::ScalarFunction(ScalarFunction { func, args }) = &expr else {
+            return Ok(Transformed::no(expr));
+        };
+        if func.name() != RELATIVE_DATE {
+            return Ok(Transformed::no(expr));
+        }
+        let [target, Expr::Literal(
+            ScalarValue::Utf8(Some(range))
+            | ScalarValue::LargeUtf8(Some(range))
+            | ScalarValue::Utf8View(Some(range)),
+        )] = args.as_slice()
+        else {
+            return plan_err!(
+                "{RELATIVE_DATE} expects an expression and a range literal, e.g. 'last 7 days'"
+            );
+        };
+        let (lower, upper) = range_bounds(range, query_context)?;
+        let timestamp = |micros| lit(ScalarValue::TimestampMicrosecond(Some(micros), None));
+        Ok(Transformed::yes(
+            target
+                .clone()
+                .gt_eq(timestamp(lower))
+                .and(target.clone().lt(timestamp(upper))),
+        ))
+    })
+}
+
+/// Return the UTC bounds `[lower, upper)` of the range in microseconds since the epoch
+fn range_bounds(range: &str, query_context: &QueryContext) -> Result<(i64, i64)> {
+    let timezone = query_context.timezone();
+    let Ok(tz) = Tz::from_str(timezone) else {
+        return plan_err!("Invalid timezone {timezone} of the relative date filter");
+    };
+    let Some(now) = DateTime::from_timestamp_micros(query_context.now()) else {
+        return plan_err!(
+            "Invalid reference time {} of the relative date filter",
+            query_context.now()
+        );
+    };
+    let Some((days_ago, days)) = parse_range(range) else {
+        return plan_err!(
+            "Unsupported relative date range '{range}', expected today, yesterday or last N days"
+        );
+    };
+    let today = now.with_timezone(&tz).date_naive();
+    let first = today.checked_sub_days(Days::new(days_ago));
+    let end = first.and_then(|first| first.checked_add_days(Days::new(days)));
+    let (Some(first), Some(end)) = (first, end) else {
+        return plan_err!("The relative date range '{range}' is out of range");
+    };
+    Ok((local_midnight(&tz, first)?, local_midnight(&tz, end)?))
+}
+
+/// Parse the range into the days from its first day to today and the days it covers
+fn parse_range(range: &str) -> Option<(u64, u64)> {
+    let range = range.to_lowercase();
+    match range.split_whitespace().collect::<Vec<_>>().as_slice() {
+        ["today"] => Some((0, 1)),
+        ["yesterday"] => Some((1, 1)),
+        ["last", days, "day" | "days"] => {
+            let days = days.parse::<u64>().ok().filter(|days| *days > 0)?;
+            Some((days - 1, days))
+        }
+        _ => None,
+    }
+}
+
+/// Return the UTC time of the local midnight starting the date. If the midnight is skipped by the
+/// daylight saving time, the date starts at the first hour that exists.
+fn local_midnight(tz: &Tz, date: NaiveDate) -> Result<i64> {
+    match (0..24).find_map(|hour| {
+        tz.from_local_datetime(&date.and_hms_opt(hour, 0, 0)?)
+            .earliest()
+    }) {
+        Some(midnight) => Ok(midnight.timestamp_micros()),
+        None => plan_err!("The start of the date {date} isn't found in the timezone"),
+    }
+}
@@ -0,0 +1,250 @@
+use crate::logical_plan::analyze::grain::collect_relation_models;
+use crate::mdl::{AnalyzedWrenMDL, SessionStateRef, WrenMDL};
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{plan_err, Column, Result, ScalarValue};
+use datafusion::functions_aggregate::expr_fn::{max, min, sum};
+use datafusion::functions_window::expr_fn::row_number;
+use datafusion::logical_expr::{
+    col, lit, when, Aggregate, Expr, ExprFunctionExt, LogicalPlan, LogicalPlanBuilder,
+};
+use std::sync::Arc;
+
+/// The label of the group collecting the rows out of the top-N groups of a string dimension
+pub const OTHER_BUCKET: &str = "other";
+const RANK_COLUMN: &str = "__top_n_rank";
+
+/// Limit the groups of the aggregation grouped by a top-N dimension.
+///
+/// If the only dimension of the aggregation declares `topN`, the groups are ranked by the first
+/// measure. The top-N groups are kept and the remainder is aggregated into the [OTHER_BUCKET] group,
+/// so the totals of the measures are the same as the exact aggregation. The dimension keeps its
+/// type: the remainder of a string dimension is labeled [OTHER_BUCKET], and the remainder of the
+/// other dimensions is the NULL group, merged with the NULL values of the dimension if any.
+///
+/// The measures are merged by `sum` (for `sum` and `count`), `min` or `max`. The other aggregations
+/// can't be merged from the partial results and will fail.
+pub(crate) fn apply_top_n_dimensions(
+    plan: LogicalPlan,
+    analyzed_wren_mdl: &Arc<AnalyzedWrenMDL>,
+    session_state: &SessionStateRef,
+) -> Result<LogicalPlan> {
+    let wren_mdl = analyzed_wren_mdl.wren_mdl();
+    plan.transform_up_with_subqueries(|plan| match plan {
+        LogicalPlan::Aggregate(aggregate) => {
+            top_n_aggregate(aggregate, &wren_mdl, session_state)
+        }
+        _ => Ok(Transformed::no(plan)),
+    })
+    .data()
+}
+
+fn top_n_aggregate(
+    aggregate: Aggregate,
+    wren_mdl: &WrenMDL,
+    session_state: &SessionStateRef,
+) -> Result<Transformed<LogicalPlan>> {
+    let limit = match aggregate.group_expr.as_slice() {
+        [Expr::Column(dimension)] if !aggregate.aggr_expr.is_empty() => {
+            top_n_limit(dimension, &aggregate, wren_mdl, session_state)?
+        }
+        _ => None,
+    };
+    let Some(limit) = limit else {
+        return Ok(Transformed::no(LogicalPlan::Aggregate(aggregate)));
+    };
+    let merges = aggregate
+        .aggr_expr
+        .iter()
+        .map(merge_function)
+        .collect::<Result<Vec<_>>>()?;
+
+    let schema = Arc::clone(&aggregate.schema);
+    let dimension = Column::from(schema.qualified_field(0));
+    let remainder = match schema.field(0).data_type() {
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View => lit(OTHER_BUCKET),
+        data_type => lit(ScalarValue::try_from(data_type)?),
+    };
+    let measures = (1..schema.fields().len())
+        .map(|i| Column::from(schema.qualified_field(i)))
+        .collect::<Vec<_>>();
+
+    let rank = row_number()
+        .order_by(vec![col(measures[0].clone()).sort(false, false)])
+        .build()?
+        .alias(RANK_COLUMN);
+    let bucket = when(
+        col(RANK_COLUMN).lt_eq(lit(limit as u64)),
+        col(dimension.clone()),
+    )
+    .otherwise(remainder)?
+    .alias_qualified(dimension.relation.clone(), &dimension.name);
+    let projection = std::iter::once(bucket)
+        .chain(measures.iter().cloned().map(col))
+        .collect::<Vec<_>>();
+    let merged_measures = merges
+        .into_iter()
+        .zip(measures)
+        .map(|(merge, measure)| {
+            merge(col(measure.clone())).alias_qualified(measure.relation, measure.name)
+        })
+        .collect::<Vec<_>>();
+
+    let plan = LogicalPlanBuilder::from(LogicalPlan::Aggregate(aggregate))
+        .window(vec![rank])?
+        .project(projection)?
+        .aggregate(vec![col(dimension)], merged_measures)?
+        .build()?;
+    Ok(Transformed::yes(plan))
+}
+
+/// Return the top-N limit if the dimension is a column of the model declaring `topN`
+fn top_n_limit(
+    dimension: &Column,
+    aggregate: &Aggregate,
+    wren_mdl: &WrenMDL,
+    session_state: &SessionStateRef,
+) -> Result<Option<u32>> {
+    let Some(relation) = &dimension.relation else {
+        return Ok(None);
+    };
+    let relation_models =
+        collect_relation_models(&aggregate.input, wren_mdl, session_state)?;
+    Ok(relation_models
+        .get(relation)
+        .and_then(|model| wren_mdl.get_model(model))
+        .and_then(|model| model.get_column(&dimension.name))
+        .and_then(|column| column.top_n()))
+}
+
+/// Return the aggregation merging the partial results of the measure
+fn merge_function(measure: &Expr) -> Result<fn(Expr) -> Expr> {
+    let function = match measure {
+        Expr::Alias(alias) => alias.expr.as_ref(),
+        _ => measure,
+    };
+    let Expr::AggregateFunction(function) = function else {
+        return plan_err!("Invalid measure of the top-N dimension: {}", measure);
+    };
+    if function.params.distinct || function.params.filter.is_some() {
+        return plan_err!(
+            "The measure {} can't be merged into the {} group",
+            measure,
+            OTHER_BUCKET
+        );
+    }
+    match function.func.name() {
+        "sum" | "count" => Ok(sum),
+        "min" => Ok(min),
+        "max" => Ok(max),
+        _ => plan_err!(
+            "The measure {} can't be merged into the {} group",
+            measure,
+            OTHER_BUCKET
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logical_plan::analyze::top_n::OTHER_BUCKET;
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{
+        ArrayRef, AsArray, Int64Array, RecordBatch, StringArray,
+    };
+    use datafusion::arrow::datatypes::{DataType, Int64Type};
+    use datafusion::assert_batches_sorted_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_top_n_dimension() -> Result<()> {
+        let ctx = SessionContext::new();
+        let customer: ArrayRef = Arc::new(StringArray::from_iter_values(
+            (1..=15).map(|i| format!("c{i:02}")),
+        ));
+        let store: ArrayRef =
+            Arc::new(Int64Array::from_iter_values((1..=15).map(|i| i % 3)));
+        let amount: ArrayRef =
+            Arc::new(Int64Array::from_iter_values((1..=15).map(|i| i * 10)));
+        ctx.register_batch(
+            "sales",
+            RecordBatch::try_from_iter(vec![
+                ("customer", customer),
+                ("store", store),
+                ("amount", amount),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("sales")
+                    .table_reference("sales")
+                    .column(ColumnBuilder::new("customer", "varchar").top_n(10).build())
+                    .column(ColumnBuilder::new("store", "bigint").top_n(2).build())
+                    .column(ColumnBuilder::new("amount", "bigint").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        let sql = "SELECT customer, sum(amount) AS total FROM sales GROUP BY customer";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        let mut groups = batches
+            .iter()
+            .flat_map(|batch| {
+                let customer = batch.column(0).as_string::<i32>();
+                let total = batch.column(1).as_primitive::<Int64Type>();
+                (0..batch.num_rows())
+                    .map(|i| (customer.value(i).to_string(), total.value(i)))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        groups.sort();
+        // the top-10 customers and the other bucket
+        assert_eq!(groups.len(), 11);
+        assert_eq!(groups[0], ("c06".to_string(), 60));
+        assert_eq!(
+            groups[10],
+            (OTHER_BUCKET.to_string(), 10 + 20 + 30 + 40 + 50)
+        );
+
+        // the total reconciles with the exact sum
+        let exact = ctx
+            .sql("SELECT sum(amount) FROM sales")
+            .await?
+            .collect()
+            .await?;
+        let exact = exact[0].column(0).as_primitive::<Int64Type>().value(0);
+        assert_eq!(groups.iter().map(|(_, total)| total).sum::<i64>(), exact);
+
+        // the other dimensions keep their type, the remainder is the NULL group
+        let sql = "SELECT store, sum(amount) AS total FROM sales GROUP BY store";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let batches = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(batches[0].schema().field(0).data_type(), &DataType::Int64);
+        assert_batches_sorted_eq!(
+            [
+                "+-------+-------+",
+                "| store | total |",
+                "+-------+-------+",
+                "|       | 350   |",
+                "| 0     | 450   |",
+                "| 2     | 400   |",
+                "+-------+-------+",
+            ],
+            &batches
+        );
+
+        // the aggregation can't be merged from the partial results
+        let sql = "SELECT customer, avg(amount) FROM sales GROUP BY customer";
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert!(err.to_string().contains(
+            "The measure avg(sales.amount) can't be merged into the other group"
+        ));
+        Ok(())
+    }
+}
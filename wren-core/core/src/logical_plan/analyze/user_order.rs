@@ -0,0 +1,222 @@
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{Column, Result};
+use datafusion::logical_expr::{Expr, Limit, LogicalPlan, Projection, Sort, SortExpr};
+use std::sync::Arc;
+
+/// Anchor the `ORDER BY` of the query to the output of the generated plan, so the sort stays at
+/// the top of the plan after the models are expanded.
+///
+/// The sort under the select list, e.g. `ORDER BY o_totalprice DESC` of
+/// `SELECT o_totalprice AS price FROM orders`, is moved over it if every sort key is an output
+/// of the select list. The sort keys are compared by the expressions they're computed from, so
+/// the aliased outputs are matched too. The sort of a key that isn't selected is kept as it is.
+pub(crate) fn anchor_user_order(plan: LogicalPlan) -> Result<LogicalPlan> {
+    match plan {
+        LogicalPlan::Limit(limit) => {
+            let input = anchor_user_order(Arc::unwrap_or_clone(limit.input))?;
+            Ok(LogicalPlan::Limit(Limit {
+                input: Arc::new(input),
+                ..limit
+            }))
+        }
+        LogicalPlan::Projection(projection) => anchor_projection(projection),
+        plan => Ok(plan),
+    }
+}
+
+fn anchor_projection(projection: Projection) -> Result<LogicalPlan> {
+    let LogicalPlan::Sort(sort) = projection.input.as_ref() else {
+        return Ok(LogicalPlan::Projection(projection));
+    };
+    let sort = sort.clone();
+    let definitions = match sort.input.as_ref() {
+        LogicalPlan::Projection(inner) => Some(inner),
+        _ => None,
+    };
+    let outputs = projection
+        .expr
+        .iter()
+        .map(|expr| resolve_definition(expr.clone().unalias(), definitions))
+        .collect::<Result<Vec<_>>>()?;
+    let mut anchored = vec![];
+    for sort_expr in &sort.expr {
+        let key = resolve_definition(sort_expr.expr.clone(), definitions)?;
+        let Some(index) = outputs.iter().position(|output| *output == key) else {
+            return Ok(LogicalPlan::Projection(projection));
+        };
+        anchored.push(SortExpr {
+            expr: Expr::Column(Column::from(projection.schema.qualified_field(index))),
+            ..sort_expr.clone()
+        });
+    }
+    let projection = Projection::try_new_with_schema(
+        projection.expr,
+        Arc::clone(&sort.input),
+        projection.schema,
+    )?;
+    Ok(LogicalPlan::Sort(Sort {
+        expr: anchored,
+        input: Arc::new(LogicalPlan::Projection(projection)),
+        fetch: sort.fetch,
+    }))
+}
+
+/// Replace the columns of the projection under the sort with the expressions computing them
+fn resolve_definition(expr: Expr, definitions: Option<&Projection>) -> Result<Expr> {
+    let Some(definitions) = definitions else {
+        return Ok(expr);
+    };
+    expr.transform_up(|expr| match &expr {
+        Expr::Column(column) => match definitions.schema.maybe_index_of_column(column) {
+            Some(index) => {
+                Ok(Transformed::yes(definitions.expr[index].clone().unalias()))
+            }
+            None => Ok(Transformed::no(expr)),
+        },
+        _ => Ok(Transformed::no(expr)),
+    })
+    .data()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::logical_plan::analyze::user_order::anchor_user_order;
+    use crate::mdl::builder::{ColumnBuilder, RelationshipBuilder};
+    use crate::mdl::context::create_ctx_with_mdl;
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+    use datafusion::arrow::datatypes::{DataType, Field, Schema};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::logical_expr::{col, lit, table_scan, LogicalPlan};
+    use datafusion::prelude::SessionContext;
+
+    #[test]
+    fn test_anchor_user_order() -> Result<()> {
+        let schema = Schema::new(vec![
+            Field::new("a", DataType::Utf8, true),
+            Field::new("b", DataType::Int64, true),
+        ]);
+        // the sort key is selected with an alias
+        let plan = table_scan(Some("t"), &schema, None)?
+            .project(vec![col("t.a"), (col("t.b") * lit(2_i64)).alias("score")])?
+            .sort(vec![col("score").sort(false, true)])?
+            .project(vec![col("t.a"), col("score").alias("s")])?
+            .limit(0, Some(10))?
+            .build()?;
+        let anchored = anchor_user_order(plan.clone())?;
+        assert_eq!(
+            format!("{}", anchored.display_indent()),
+            "Limit: skip=0, fetch=10\
+            \n  Sort: s DESC NULLS FIRST\
+            \n    Projection: t.a, score AS s\
+            \n      Projection: t.a, t.b * Int64(2) AS score\
+            \n        TableScan: t"
+        );
+        assert_eq!(anchored.schema(), plan.schema());
+
+        // the key that isn't selected stays under the select list
+        let plan = table_scan(Some("t"), &schema, None)?
+            .sort(vec![col("t.b").sort(true, false)])?
+            .project(vec![col("t.a")])?
+            .build()?;
+        assert_eq!(anchor_user_order(plan.clone())?, plan);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preserve_user_order() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Float64Array::from(vec![100.0, 50.0, 80.0, 20.0]))
+                        as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("total_spend", "double")
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT c_name, total_spend AS spend FROM customer ORDER BY total_spend DESC";
+
+        // the sort by the measure is at the top of the expanded plan
+        let wren_ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let plan = wren_ctx.state().create_logical_plan(sql).await?;
+        let plan = wren_ctx.state().optimize(&plan)?;
+        let LogicalPlan::Sort(sort) = &plan else {
+            panic!("expected the sort at the top of the plan:\n{plan}");
+        };
+        assert_eq!(sort.expr.len(), 1);
+        assert!(!sort.expr[0].asc);
+        assert_eq!(sort.expr[0].expr.to_string(), "spend");
+
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+-------+",
+                "| c_name | spend |",
+                "+--------+-------+",
+                "| Azki   | 130.0 |",
+                "| Gura   | 100.0 |",
+                "| Ina    | 20.0  |",
+                "+--------+-------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
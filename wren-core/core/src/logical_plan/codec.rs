@@ -0,0 +1,446 @@
+use std::fmt;
+use std::fmt::{Debug, Formatter};
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::catalog::TableProvider;
+use datafusion::common::{
+    internal_err, not_impl_err, plan_err, Column, DFSchema, TableReference,
+};
+use datafusion::error::{DataFusionError, Result};
+use datafusion::logical_expr::{
+    col, Expr, Extension, LogicalPlan, LogicalPlanBuilder, UserDefinedLogicalNode,
+    UserDefinedLogicalNodeCore,
+};
+use datafusion::prelude::SessionContext;
+use datafusion_proto::logical_plan::from_proto::parse_exprs;
+use datafusion_proto::logical_plan::to_proto::serialize_exprs;
+use datafusion_proto::logical_plan::LogicalExtensionCodec;
+use datafusion_proto::protobuf::LogicalExprNode;
+use parking_lot::RwLock;
+use prost::{Message, Oneof};
+
+use crate::logical_plan::analyze::plan::{
+    CalculationPlanNode, ModelPlanNode, ModelSourceNode, PartialModelPlanNode,
+};
+use crate::logical_plan::utils::{create_remote_table_source, create_schema};
+use crate::mdl::manifest::Model;
+use crate::mdl::{AnalyzedWrenMDL, SessionStateRef};
+
+/// [WrenExtensionCodec] serializes the extension nodes generated by the model analysis, so the
+/// plan can be shipped by `datafusion-proto` before it's expanded by the model generation.
+///
+/// The node isn't serialized as is. Only the model, the required columns and the filters pushed
+/// down to the table scan are encoded, and the node is rebuilt from the same MDL when decoding.
+pub struct WrenExtensionCodec {
+    analyzed_wren_mdl: Arc<AnalyzedWrenMDL>,
+}
+
+impl Debug for WrenExtensionCodec {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WrenExtensionCodec")
+            .field("catalog", &self.analyzed_wren_mdl.wren_mdl().catalog())
+            .field("schema", &self.analyzed_wren_mdl.wren_mdl().schema())
+            .finish()
+    }
+}
+
+/// The encoded form of the extension nodes
+#[derive(Clone, PartialEq, Message)]
+struct WrenNode {
+    #[prost(oneof = "WrenNodeType", tags = "1, 2, 3, 4")]
+    node_type: Option<WrenNodeType>,
+}
+
+#[derive(Clone, PartialEq, Oneof)]
+enum WrenNodeType {
+    #[prost(message, tag = "1")]
+    Model(ModelNode),
+    #[prost(message, tag = "2")]
+    ModelSource(ModelNode),
+    #[prost(message, tag = "3")]
+    Calculation(CalculationNode),
+    #[prost(message, tag = "4")]
+    PartialModel(PartialModelNode),
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct ModelNode {
+    #[prost(string, tag = "1")]
+    model: String,
+    #[prost(string, repeated, tag = "2")]
+    required_fields: Vec<String>,
+    /// The original table scan, if the node has one
+    #[prost(message, optional, tag = "3")]
+    table_scan: Option<TableScanNode>,
+}
+
+/// The table scan of the model is rebuilt from the MDL, so only its pushed-down filters are encoded
+#[derive(Clone, PartialEq, Message)]
+struct TableScanNode {
+    #[prost(message, repeated, tag = "1")]
+    filters: Vec<LogicalExprNode>,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct CalculationNode {
+    #[prost(string, tag = "1")]
+    model: String,
+    #[prost(string, tag = "2")]
+    column: String,
+}
+
+#[derive(Clone, PartialEq, Message)]
+struct PartialModelNode {
+    #[prost(message, optional, tag = "1")]
+    model: Option<ModelNode>,
+    #[prost(string, repeated, tag = "2")]
+    columns: Vec<String>,
+}
+
+impl LogicalExtensionCodec for WrenExtensionCodec {
+    fn try_decode(
+        &self,
+        buf: &[u8],
+        _inputs: &[LogicalPlan],
+        ctx: &SessionContext,
+    ) -> Result<Extension> {
+        let node = WrenNode::decode(buf).map_err(|e| {
+            DataFusionError::Internal(format!("failed to decode the Wren node: {e:?}"))
+        })?;
+        let session_state: SessionStateRef = Arc::new(RwLock::new(ctx.state()));
+        let node: Arc<dyn UserDefinedLogicalNode> = match node.node_type {
+            Some(WrenNodeType::Model(model)) => {
+                Arc::new(self.decode_model(&model, &session_state)?)
+            }
+            Some(WrenNodeType::ModelSource(source)) => {
+                let original_table_scan = self.decode_table_scan(
+                    &source.model,
+                    source.table_scan.as_ref(),
+                    &session_state,
+                )?;
+                Arc::new(ModelSourceNode::new(
+                    self.get_model(&source.model)?,
+                    fields(&source.required_fields),
+                    Arc::clone(&self.analyzed_wren_mdl),
+                    session_state,
+                    original_table_scan,
+                )?)
+            }
+            Some(WrenNodeType::Calculation(calculation)) => {
+                Arc::new(CalculationPlanNode::for_column(
+                    &calculation.model,
+                    &calculation.column,
+                    Arc::clone(&self.analyzed_wren_mdl),
+                    session_state,
+                )?)
+            }
+            Some(WrenNodeType::PartialModel(PartialModelNode {
+                model: Some(model),
+                columns,
+            })) => {
+                let model_node = self.decode_model(&model, &session_state)?;
+                let model = self.get_model(&model.model)?;
+                let columns = columns
+                    .iter()
+                    .map(|name| match model.get_column(name) {
+                        Some(column) => Ok(column),
+                        None => {
+                            plan_err!("The column {name} isn't found in {}", model.name())
+                        }
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                let schema = Arc::new(DFSchema::try_from(create_schema(columns)?)?);
+                Arc::new(PartialModelPlanNode::new(model_node, schema))
+            }
+            Some(WrenNodeType::PartialModel(PartialModelNode {
+                model: None, ..
+            })) => {
+                return internal_err!(
+                    "The partial model node should have its model node"
+                );
+            }
+            None => return internal_err!("The Wren node should have its type"),
+        };
+        Ok(Extension { node })
+    }
+
+    fn try_encode(&self, node: &Extension, buf: &mut Vec<u8>) -> Result<()> {
+        let any = node.node.as_any();
+        let node_type = if let Some(model) = any.downcast_ref::<ModelPlanNode>() {
+            WrenNodeType::Model(self.encode_model(model)?)
+        } else if let Some(source) = any.downcast_ref::<ModelSourceNode>() {
+            WrenNodeType::ModelSource(ModelNode {
+                model: source.model_name.clone(),
+                required_fields: field_names(UserDefinedLogicalNodeCore::schema(source)),
+                table_scan: self
+                    .encode_table_scan(source.original_table_scan.as_ref())?,
+            })
+        } else if let Some(calculation) = any.downcast_ref::<CalculationPlanNode>() {
+            WrenNodeType::Calculation(CalculationNode {
+                model: calculation.calculation.dataset.name().to_string(),
+                column: calculation.calculation.column.name().to_string(),
+            })
+        } else if let Some(partial) = any.downcast_ref::<PartialModelPlanNode>() {
+            WrenNodeType::PartialModel(PartialModelNode {
+                model: Some(self.encode_model(&partial.model_node)?),
+                columns: field_names(UserDefinedLogicalNodeCore::schema(partial)),
+            })
+        } else {
+            return not_impl_err!("Unsupported extension node: {}", node.node.name());
+        };
+        WrenNode {
+            node_type: Some(node_type),
+        }
+        .encode(buf)
+        .map_err(|e| {
+            DataFusionError::Internal(format!("failed to encode the Wren node: {e:?}"))
+        })
+    }
+
+    fn try_decode_table_provider(
+        &self,
+        _buf: &[u8],
+        table_ref: &TableReference,
+        _schema: SchemaRef,
+        _ctx: &SessionContext,
+    ) -> Result<Arc<dyn TableProvider>> {
+        not_impl_err!("Decoding the table provider of {table_ref} isn't supported")
+    }
+
+    fn try_encode_table_provider(
+        &self,
+        table_ref: &TableReference,
+        _node: Arc<dyn TableProvider>,
+        _buf: &mut Vec<u8>,
+    ) -> Result<()> {
+        not_impl_err!("Encoding the table provider of {table_ref} isn't supported")
+    }
+}
+
+impl WrenExtensionCodec {
+    pub fn new(analyzed_wren_mdl: Arc<AnalyzedWrenMDL>) -> Self {
+        Self { analyzed_wren_mdl }
+    }
+
+    fn get_model(&self, model_name: &str) -> Result<Arc<Model>> {
+        match self.analyzed_wren_mdl.wren_mdl().get_model(model_name) {
+            Some(model) => Ok(model),
+            None => plan_err!("Model not found {model_name}"),
+        }
+    }
+
+    fn encode_model(&self, model: &ModelPlanNode) -> Result<ModelNode> {
+        Ok(ModelNode {
+            model: model.plan_name.clone(),
+            required_fields: field_names(UserDefinedLogicalNodeCore::schema(model)),
+            table_scan: self.encode_table_scan(model.original_table_scan.as_ref())?,
+        })
+    }
+
+    fn decode_model(
+        &self,
+        model: &ModelNode,
+        session_state: &SessionStateRef,
+    ) -> Result<ModelPlanNode> {
+        let original_table_scan = self.decode_table_scan(
+            &model.model,
+            model.table_scan.as_ref(),
+            session_state,
+        )?;
+        ModelPlanNode::new(
+            self.get_model(&model.model)?,
+            fields(&model.required_fields),
+            original_table_scan,
+            Arc::clone(&self.analyzed_wren_mdl),
+            Arc::clone(session_state),
+        )
+    }
+
+    fn encode_table_scan(
+        &self,
+        table_scan: Option<&LogicalPlan>,
+    ) -> Result<Option<TableScanNode>> {
+        match table_scan {
+            Some(LogicalPlan::TableScan(scan)) => Ok(Some(TableScanNode {
+                filters: serialize_exprs(&scan.filters, self)?,
+            })),
+            Some(plan) => {
+                internal_err!("The original table scan should be a TableScan: {plan}")
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Rebuild the table scan of the model with the pushed-down filters
+    fn decode_table_scan(
+        &self,
+        model_name: &str,
+        table_scan: Option<&TableScanNode>,
+        session_state: &SessionStateRef,
+    ) -> Result<Option<LogicalPlan>> {
+        let Some(table_scan) = table_scan else {
+            return Ok(None);
+        };
+        let filters = {
+            let state = session_state.read();
+            parse_exprs(&table_scan.filters, &*state, self)?
+        };
+        let model = self.get_model(model_name)?;
+        let source = create_remote_table_source(
+            Arc::clone(&model),
+            &self.analyzed_wren_mdl.wren_mdl(),
+            Arc::clone(session_state),
+            model.routing_hint(),
+            None,
+        )?;
+        let scan = LogicalPlanBuilder::scan_with_filters(
+            TableReference::bare(model.name()),
+            source,
+            None,
+            filters,
+        )?
+        .build()?;
+        Ok(Some(scan))
+    }
+}
+
+fn field_names(schema: &DFSchema) -> Vec<String> {
+    schema.fields().iter().map(|f| f.name().clone()).collect()
+}
+
+fn fields(names: &[String]) -> Vec<Expr> {
+    names
+        .iter()
+        .map(|name| col(Column::from_name(name)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use datafusion::common::{DFSchema, Result, TableReference};
+    use datafusion::logical_expr::{
+        col, lit, Extension, LogicalPlan, LogicalPlanBuilder,
+    };
+    use datafusion::prelude::SessionContext;
+    use datafusion_proto::bytes::{
+        logical_plan_from_bytes_with_extension_codec,
+        logical_plan_to_bytes_with_extension_codec,
+    };
+    use parking_lot::RwLock;
+
+    use crate::logical_plan::analyze::plan::{
+        CalculationPlanNode, ModelPlanNode, PartialModelPlanNode,
+    };
+    use crate::logical_plan::codec::WrenExtensionCodec;
+    use crate::logical_plan::utils::{create_remote_table_source, create_schema};
+    use crate::mdl::builder::{ColumnBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{customer_model, manifest_builder, orders_model};
+    use crate::mdl::manifest::{JoinType, Manifest};
+    use crate::mdl::{AnalyzedWrenMDL, SessionStateRef};
+
+    fn manifest() -> Manifest {
+        manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("totalprice", "bigint")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_calculation_plan() -> Result<()> {
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+        let ctx = SessionContext::new();
+        let calculation = CalculationPlanNode::for_column(
+            "customer",
+            "totalprice",
+            Arc::clone(&analyzed_mdl),
+            Arc::new(RwLock::new(ctx.state())),
+        )?;
+        let plan = LogicalPlanBuilder::from(LogicalPlan::Extension(Extension {
+            node: Arc::new(calculation),
+        }))
+        .project(vec![col("totalprice")])?
+        .build()?;
+
+        let codec = WrenExtensionCodec::new(Arc::clone(&analyzed_mdl));
+        let bytes = logical_plan_to_bytes_with_extension_codec(&plan, &codec)?;
+        let decoded = logical_plan_from_bytes_with_extension_codec(&bytes, &ctx, &codec)?;
+        assert_eq!(format!("{decoded}"), format!("{plan}"));
+        assert_eq!(decoded, plan);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_roundtrip_partial_model_plan() -> Result<()> {
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+        let ctx = SessionContext::new();
+        let session_state: SessionStateRef = Arc::new(RwLock::new(ctx.state()));
+        let model = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        let source = create_remote_table_source(
+            Arc::clone(&model),
+            &analyzed_mdl.wren_mdl(),
+            Arc::clone(&session_state),
+            None,
+            None,
+        )?;
+        // the filter pushed down to the original table scan is kept
+        let table_scan = LogicalPlanBuilder::scan_with_filters(
+            TableReference::bare("customer"),
+            source,
+            None,
+            vec![col("c_custkey").gt(lit(1_i64))],
+        )?
+        .build()?;
+        let model_node = ModelPlanNode::new(
+            Arc::clone(&model),
+            vec![col("c_custkey"), col("c_name")],
+            Some(table_scan),
+            Arc::clone(&analyzed_mdl),
+            session_state,
+        )?;
+        let columns = vec![model.get_column("c_custkey").unwrap()];
+        let schema = Arc::new(DFSchema::try_from(create_schema(columns)?)?);
+        let plan = LogicalPlan::Extension(Extension {
+            node: Arc::new(PartialModelPlanNode::new(model_node, schema)),
+        });
+
+        let codec = WrenExtensionCodec::new(Arc::clone(&analyzed_mdl));
+        let bytes = logical_plan_to_bytes_with_extension_codec(&plan, &codec)?;
+        let decoded = logical_plan_from_bytes_with_extension_codec(&bytes, &ctx, &codec)?;
+        assert_eq!(decoded, plan);
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 pub mod analyze;
+pub mod codec;
 pub mod context_provider;
 pub mod optimize;
 pub mod utils;
@@ -0,0 +1,238 @@
+use std::any::Any;
+use std::sync::Arc;
+
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{plan_err, DFSchema, Result};
+use datafusion::config::ConfigOptions;
+use datafusion::logical_expr::expr::ScalarFunction;
+use datafusion::logical_expr::{
+    lit, ColumnarValue, Expr, ExprSchemable, LogicalPlan, ScalarUDF, ScalarUDFImpl,
+    Signature, TypeSignature, Volatility,
+};
+use datafusion::optimizer::AnalyzerRule;
+
+/// The name of the timezone conversion function
+pub const AT_TIME_ZONE: &str = "at_time_zone";
+
+/// `at_time_zone(timestamp, timezone[, source_timezone])` converts the timestamp to the
+/// wall-clock time of the timezone. It's unparsed to `timestamp AT TIME ZONE 'timezone'`, or the
+/// conversion function of the data source, by [crate::mdl::dialect::WrenDialect] and never
+/// evaluated locally.
+///
+/// The timestamp without timezone is the wall-clock time of `source_timezone`, so it's localized
+/// before the conversion. e.g. `(timestamp AT TIME ZONE 'UTC') AT TIME ZONE 'timezone'`.
+#[derive(Debug)]
+pub struct AtTimeZone {
+    signature: Signature,
+}
+
+impl AtTimeZone {
+    pub fn new() -> Self {
+        Self {
+            signature: Signature::one_of(
+                vec![TypeSignature::Any(2), TypeSignature::Any(3)],
+                Volatility::Immutable,
+            ),
+        }
+    }
+}
+
+impl Default for AtTimeZone {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ScalarUDFImpl for AtTimeZone {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn name(&self) -> &str {
+        AT_TIME_ZONE
+    }
+
+    fn signature(&self) -> &Signature {
+        &self.signature
+    }
+
+    // the converted timestamp keeps the type, so the schemas of the plans aren't changed
+    fn return_type(&self, arg_types: &[DataType]) -> Result<DataType> {
+        Ok(arg_types[0].clone())
+    }
+
+    fn invoke(&self, _args: &[ColumnarValue]) -> Result<ColumnarValue> {
+        plan_err!("{AT_TIME_ZONE} is only evaluated by the backend")
+    }
+}
+
+/// Convert the timestamps truncated by `date_trunc` to the timezone of the
+/// [crate::mdl::query_context::QueryContext] explicitly, so the backend buckets them by the same
+/// calendar regardless of its session timezone. e.g. in `Asia/Taipei`,
+/// `date_trunc('month', o_orderdate)` is generated as
+/// ```sql
+/// date_trunc('month', (o_orderdate AT TIME ZONE 'UTC') AT TIME ZONE 'Asia/Taipei')
+/// ```
+/// The timestamp without timezone, like `o_orderdate`, is stored in UTC, so it's localized to
+/// UTC before the conversion. The dates have no timezone, so only the timestamps are converted.
+/// In UTC the plan is left unchanged.
+#[derive(Debug)]
+pub struct DateTruncTimezone {
+    timezone: String,
+    at_time_zone: Arc<ScalarUDF>,
+}
+
+impl DateTruncTimezone {
+    pub fn new(timezone: &str) -> Self {
+        Self {
+            timezone: timezone.to_string(),
+            at_time_zone: Arc::new(ScalarUDF::new_from_impl(AtTimeZone::new())),
+        }
+    }
+
+    fn convert_date_trunc(
+        &self,
+        expr: Expr,
+        schema: &DFSchema,
+    ) -> Result<Transformed<Expr>> {
+        expr.transform_up(|expr| match expr {
+            Expr::ScalarFunction(ScalarFunction { func, args })
+                if func.name() == "date_trunc"
+                    && args.len() == 2
+                    && is_timestamp(&args[1], schema) =>
+            {
+                let [grain, timestamp] = <[Expr; 2]>::try_from(args).unwrap();
+                let naive = is_timestamp_without_timezone(&timestamp, schema);
+                let mut at_time_zone_args = vec![timestamp, lit(self.timezone.as_str())];
+                if naive {
+                    at_time_zone_args.push(lit("UTC"));
+                }
+                let converted = Expr::ScalarFunction(ScalarFunction::new_udf(
+                    Arc::clone(&self.at_time_zone),
+                    at_time_zone_args,
+                ));
+                Ok(Transformed::yes(Expr::ScalarFunction(ScalarFunction {
+                    func,
+                    args: vec![grain, converted],
+                })))
+            }
+            _ => Ok(Transformed::no(expr)),
+        })
+    }
+}
+
+impl AnalyzerRule for DateTruncTimezone {
+    fn analyze(&self, plan: LogicalPlan, _config: &ConfigOptions) -> Result<LogicalPlan> {
+        if self.timezone.eq_ignore_ascii_case("UTC") {
+            return Ok(plan);
+        }
+        plan.transform_up_with_subqueries(|plan| {
+            let schema = expression_schema(&plan);
+            plan.map_expressions(|expr| self.convert_date_trunc(expr, &schema))
+        })
+        .data()
+    }
+
+    fn name(&self) -> &str {
+        "date_trunc_timezone"
+    }
+}
+
+/// The schema the expressions of the plan are resolved against
+fn expression_schema(plan: &LogicalPlan) -> DFSchema {
+    let mut schema = DFSchema::empty();
+    let inputs = plan.inputs();
+    if inputs.is_empty() {
+        schema.merge(plan.schema());
+    }
+    inputs.iter().for_each(|input| schema.merge(input.schema()));
+    schema
+}
+
+fn is_timestamp(expr: &Expr, schema: &DFSchema) -> bool {
+    matches!(expr.get_type(schema), Ok(DataType::Timestamp(_, _)))
+}
+
+fn is_timestamp_without_timezone(expr: &Expr, schema: &DFSchema) -> bool {
+    matches!(expr.get_type(schema), Ok(DataType::Timestamp(_, None)))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::manifest_builder;
+    use crate::mdl::manifest::DataSource;
+    use crate::mdl::query_context::QueryContext;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_date_trunc_timezone() -> Result<()> {
+        let manifest = |data_source: DataSource| {
+            manifest_builder()
+                .data_source(data_source)
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_orderdate", "timestamp").build())
+                        .column(ColumnBuilder::new("o_shipdate", "date").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .build()
+        };
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::Postgres))?);
+        let sql = "SELECT date_trunc('month', o_orderdate) AS month, count(*) AS orders \
+            FROM orders GROUP BY 1";
+        let session = |query_context: QueryContext| {
+            SessionContext::new_with_config(
+                SessionConfig::new().with_extension(Arc::new(query_context)),
+            )
+        };
+
+        // the timestamps are bucketed by the calendar of the timezone
+        let taipei = session(QueryContext::new().with_timezone("Asia/Taipei"));
+        let actual =
+            transform_sql_with_ctx(&taipei, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(actual
+            .contains("o_orderdate AT TIME ZONE 'UTC') AT TIME ZONE 'Asia/Taipei')"));
+
+        // the timestamp with timezone is converted directly
+        let tz_sql = "SELECT date_trunc('month', \
+            CAST(o_orderdate AS TIMESTAMP WITH TIME ZONE)) AS month FROM orders";
+        let actual =
+            transform_sql_with_ctx(&taipei, Arc::clone(&analyzed_mdl), &[], tz_sql)
+                .await?;
+        assert!(actual.contains(") AT TIME ZONE 'Asia/Taipei')"));
+        assert!(!actual.contains("AT TIME ZONE 'UTC'"));
+
+        // the dates have no timezone to convert
+        let date_sql = "SELECT date_trunc('month', o_shipdate) AS month FROM orders";
+        let actual =
+            transform_sql_with_ctx(&taipei, Arc::clone(&analyzed_mdl), &[], date_sql)
+                .await?;
+        assert!(!actual.contains("AT TIME ZONE"));
+
+        // the data sources without AT TIME ZONE convert the timestamps by their functions
+        let mysql = Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::MySQL))?);
+        let actual = transform_sql_with_ctx(&taipei, mysql, &[], sql).await?;
+        assert!(actual.contains("convert_tz("));
+        assert!(actual.contains("o_orderdate, '+00:00', 'Asia/Taipei')"));
+        let bigquery =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::BigQuery))?);
+        let actual = transform_sql_with_ctx(&taipei, bigquery, &[], sql).await?;
+        assert!(actual.contains("datetime("));
+        assert!(actual.contains("o_orderdate, 'Asia/Taipei')"));
+
+        // the plan is left unchanged in UTC
+        let utc = session(QueryContext::new());
+        let actual = transform_sql_with_ctx(&utc, analyzed_mdl, &[], sql).await?;
+        assert!(!actual.contains("AT TIME ZONE"));
+        Ok(())
+    }
+}
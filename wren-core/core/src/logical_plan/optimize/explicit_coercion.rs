@@ -0,0 +1,165 @@
+use datafusion::arrow::datatypes::DataType;
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{DFSchema, Result};
+use datafusion::config::ConfigOptions;
+use datafusion::logical_expr::{
+    cast, BinaryExpr, Expr, ExprSchemable, Filter, LogicalPlan,
+};
+use datafusion::optimizer::AnalyzerRule;
+
+/// How a comparison between a boolean and a text expression is coerced for the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BooleanTextCoercion {
+    /// Cast the text side to boolean, e.g. `CAST(text_column AS BOOLEAN) = true`
+    #[default]
+    CastToBoolean,
+    /// Cast the boolean side to text for the backend without the boolean cast,
+    /// e.g. `text_column = CAST(true AS VARCHAR)`
+    CastToText,
+}
+
+/// Insert the explicit cast into the comparisons of the filters whose sides are boolean and text.
+///
+/// The backends disagree on the implicit coercion between boolean and text, so the generated SQL
+/// shouldn't rely on it. The types are resolved from the declared column types of the models.
+/// It should be placed before [datafusion::optimizer::analyzer::type_coercion::TypeCoercion].
+#[derive(Debug, Default)]
+pub struct ExplicitCoercion {
+    boolean_text: BooleanTextCoercion,
+}
+
+impl ExplicitCoercion {
+    pub fn new(boolean_text: BooleanTextCoercion) -> Self {
+        Self { boolean_text }
+    }
+
+    fn coerce_predicate(
+        &self,
+        predicate: Expr,
+        schema: &DFSchema,
+    ) -> Result<Transformed<Expr>> {
+        predicate.transform_up(|expr| match expr {
+            Expr::BinaryExpr(BinaryExpr { left, op, right })
+                if op.is_comparison_operator() =>
+            {
+                let left_type = left.get_type(schema)?;
+                let right_type = right.get_type(schema)?;
+                let (left, right) = match (self.boolean_text, &left_type, &right_type) {
+                    (BooleanTextCoercion::CastToBoolean, DataType::Boolean, t)
+                        if is_text(t) =>
+                    {
+                        (*left, cast(*right, DataType::Boolean))
+                    }
+                    (BooleanTextCoercion::CastToBoolean, t, DataType::Boolean)
+                        if is_text(t) =>
+                    {
+                        (cast(*left, DataType::Boolean), *right)
+                    }
+                    (BooleanTextCoercion::CastToText, DataType::Boolean, t)
+                        if is_text(t) =>
+                    {
+                        (cast(*left, DataType::Utf8), *right)
+                    }
+                    (BooleanTextCoercion::CastToText, t, DataType::Boolean)
+                        if is_text(t) =>
+                    {
+                        (*left, cast(*right, DataType::Utf8))
+                    }
+                    _ => {
+                        return Ok(Transformed::no(Expr::BinaryExpr(BinaryExpr {
+                            left,
+                            op,
+                            right,
+                        })))
+                    }
+                };
+                Ok(Transformed::yes(Expr::BinaryExpr(BinaryExpr::new(
+                    Box::new(left),
+                    op,
+                    Box::new(right),
+                ))))
+            }
+            _ => Ok(Transformed::no(expr)),
+        })
+    }
+}
+
+impl AnalyzerRule for ExplicitCoercion {
+    fn analyze(&self, plan: LogicalPlan, _config: &ConfigOptions) -> Result<LogicalPlan> {
+        plan.transform_up_with_subqueries(|plan| match plan {
+            LogicalPlan::Filter(filter) => {
+                let input = filter.input;
+                self.coerce_predicate(filter.predicate, input.schema())?
+                    .map_data(|predicate| {
+                        Filter::try_new(predicate, input).map(LogicalPlan::Filter)
+                    })
+            }
+            _ => Ok(Transformed::no(plan)),
+        })
+        .data()
+    }
+
+    fn name(&self) -> &str {
+        "explicit_coercion"
+    }
+}
+
+fn is_text(data_type: &DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::Utf8 | DataType::LargeUtf8 | DataType::Utf8View
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::manifest::DataSource::MySQL;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_explicit_boolean_text_coercion() -> Result<()> {
+        let ctx = SessionContext::new();
+        let manifest = |builder: ManifestBuilder| {
+            builder
+                .catalog("wren")
+                .schema("test")
+                .model(
+                    ModelBuilder::new("users")
+                        .table_reference("users")
+                        .column(ColumnBuilder::new("id", "int").build())
+                        .column(ColumnBuilder::new("is_active", "varchar").build())
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT id FROM users WHERE is_active = true";
+
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(ManifestBuilder::new()))?);
+        let result =
+            transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert_eq!(
+            result,
+            "SELECT users.id FROM \
+            (SELECT users.id, users.is_active FROM \
+            (SELECT __source.id AS id, __source.is_active AS is_active FROM users AS __source) AS users) AS users \
+            WHERE CAST(users.is_active AS BOOLEAN) = true"
+        );
+
+        // MySQL doesn't support casting to boolean, so the boolean side is cast to text
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(
+            ManifestBuilder::new().data_source(MySQL),
+        ))?);
+        let result =
+            transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(
+            result.ends_with("WHERE users.is_active = CAST(true AS VARCHAR)"),
+            "{result}"
+        );
+        Ok(())
+    }
+}
@@ -17,4 +17,7 @@
  * under the License.
  */
 
+pub mod date_trunc_timezone;
+pub mod explicit_coercion;
+pub mod pattern_escape;
 pub mod simplify_timestamp;
@@ -0,0 +1,203 @@
+use datafusion::common::tree_node::{Transformed, TransformedResult, TreeNode};
+use datafusion::common::{Result, ScalarValue};
+use datafusion::config::ConfigOptions;
+use datafusion::logical_expr::expr::Like;
+use datafusion::logical_expr::{lit, BinaryExpr, Expr, LogicalPlan, Operator};
+use datafusion::optimizer::AnalyzerRule;
+
+/// How the backslash escaping the wildcards of a pattern is written for the backend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PatternEscape {
+    /// The backend escapes the wildcards by the backslash by default, as DataFusion does
+    #[default]
+    Backslash,
+    /// The backend doesn't have a default escape character, so the backslash is declared,
+    /// e.g. `name LIKE '%x\_y%' ESCAPE '\'`
+    ExplicitEscape,
+    /// The backend reads the backslash of a string literal as an escape sequence, so the backslash
+    /// is doubled to reach the pattern, e.g. `name LIKE '%x\\_y%'`
+    DoubledBackslash,
+}
+
+/// Rewrite the escape sequences of the `LIKE` and regex patterns for the backend.
+///
+/// The patterns are parsed by DataFusion, where the backslash escapes `%` and `_` of a `LIKE`
+/// pattern by default. The patterns of every plan node are rewritten, e.g. the predicates of the
+/// filters, the filters pushed down to the table scans and the `CASE` of the projections, so the
+/// backend matches the same rows.
+#[derive(Debug, Default)]
+pub struct EscapePattern {
+    escape: PatternEscape,
+}
+
+impl EscapePattern {
+    pub fn new(escape: PatternEscape) -> Self {
+        Self { escape }
+    }
+
+    fn escape_expr(&self, expr: Expr) -> Result<Transformed<Expr>> {
+        expr.transform_up(|expr| match expr {
+            Expr::Like(like) => Ok(self.escape_like(like)),
+            Expr::BinaryExpr(BinaryExpr { left, op, right })
+                if self.escape == PatternEscape::DoubledBackslash
+                    && is_regex_operator(op) =>
+            {
+                match doubled_backslash(&right) {
+                    Some(pattern) => Ok(Transformed::yes(Expr::BinaryExpr(
+                        BinaryExpr::new(left, op, Box::new(pattern)),
+                    ))),
+                    None => Ok(Transformed::no(Expr::BinaryExpr(BinaryExpr {
+                        left,
+                        op,
+                        right,
+                    }))),
+                }
+            }
+            _ => Ok(Transformed::no(expr)),
+        })
+    }
+
+    fn escape_like(&self, like: Like) -> Transformed<Expr> {
+        match self.escape {
+            PatternEscape::ExplicitEscape if like.escape_char.is_none() => {
+                Transformed::yes(Expr::Like(Like {
+                    escape_char: Some('\\'),
+                    ..like
+                }))
+            }
+            PatternEscape::DoubledBackslash => match doubled_backslash(&like.pattern) {
+                Some(pattern) => Transformed::yes(Expr::Like(Like {
+                    pattern: Box::new(pattern),
+                    ..like
+                })),
+                None => Transformed::no(Expr::Like(like)),
+            },
+            _ => Transformed::no(Expr::Like(like)),
+        }
+    }
+}
+
+impl AnalyzerRule for EscapePattern {
+    fn analyze(&self, plan: LogicalPlan, _config: &ConfigOptions) -> Result<LogicalPlan> {
+        if self.escape == PatternEscape::Backslash {
+            return Ok(plan);
+        }
+        plan.transform_up_with_subqueries(|plan| {
+            plan.map_expressions(|expr| self.escape_expr(expr))
+        })
+        .data()
+    }
+
+    fn name(&self) -> &str {
+        "escape_pattern"
+    }
+}
+
+fn is_regex_operator(op: Operator) -> bool {
+    matches!(
+        op,
+        Operator::RegexMatch
+            | Operator::RegexIMatch
+            | Operator::RegexNotMatch
+            | Operator::RegexNotIMatch
+    )
+}
+
+/// Double the backslashes of the literal pattern, keeping its string type. The pattern computed by
+/// an expression is kept.
+fn doubled_backslash(pattern: &Expr) -> Option<Expr> {
+    let Expr::Literal(pattern) = pattern else {
+        return None;
+    };
+    let doubled = |pattern: &str| Some(pattern.replace('\\', "\\\\"));
+    let pattern = match pattern {
+        ScalarValue::Utf8(Some(pattern)) if pattern.contains('\\') => {
+            ScalarValue::Utf8(doubled(pattern))
+        }
+        ScalarValue::Utf8View(Some(pattern)) if pattern.contains('\\') => {
+            ScalarValue::Utf8View(doubled(pattern))
+        }
+        ScalarValue::LargeUtf8(Some(pattern)) if pattern.contains('\\') => {
+            ScalarValue::LargeUtf8(doubled(pattern))
+        }
+        _ => return None,
+    };
+    Some(lit(pattern))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logical_plan::optimize::pattern_escape::doubled_backslash;
+    use crate::mdl::fixture::{customer_model, manifest_builder, transform_with_mdl};
+    use crate::mdl::manifest::DataSource;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::logical_expr::{lit, Expr};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_pattern_escape() -> Result<()> {
+        let manifest = |data_source: DataSource| {
+            manifest_builder()
+                .data_source(data_source)
+                .model(customer_model().build())
+                .build()
+        };
+        let transform = |data_source: DataSource, sql: &'static str| async move {
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(data_source))?);
+            transform_with_mdl(&analyzed_mdl, sql).await
+        };
+        let sql = r"SELECT c_custkey FROM customer WHERE c_name LIKE '%x\_y%'";
+
+        // the backslash escapes the underscore by default
+        let actual = transform(DataSource::Postgres, sql).await?;
+        assert!(
+            actual.ends_with(r"WHERE customer.c_name LIKE '%x\_y%'"),
+            "{actual}"
+        );
+        // BigQuery reads the backslash of the literal as an escape sequence
+        let actual = transform(DataSource::BigQuery, sql).await?;
+        assert!(
+            actual.ends_with(r"WHERE customer.c_name LIKE '%x\\_y%'"),
+            "{actual}"
+        );
+        let actual = transform(
+            DataSource::BigQuery,
+            r"SELECT c_custkey FROM customer WHERE c_name ~ '^x\d+'",
+        )
+        .await?;
+        assert!(actual.ends_with(r"'^x\\d+'"), "{actual}");
+        // the patterns out of the filters are rewritten too
+        let actual = transform(
+            DataSource::BigQuery,
+            r"SELECT CASE WHEN c_name LIKE '%x\_y%' THEN 1 ELSE 0 END AS matched FROM customer",
+        )
+        .await?;
+        assert!(actual.contains(r"LIKE '%x\\_y%'"), "{actual}");
+        // Trino doesn't have a default escape character
+        let actual = transform(DataSource::Trino, sql).await?;
+        assert!(
+            actual.ends_with(r"WHERE customer.c_name LIKE '%x\_y%' ESCAPE '\'"),
+            "{actual}"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_doubled_backslash() {
+        let cases = [
+            ScalarValue::Utf8(Some(r"x\_y".to_string())),
+            ScalarValue::Utf8View(Some(r"x\_y".to_string())),
+            ScalarValue::LargeUtf8(Some(r"x\_y".to_string())),
+        ];
+        for pattern in cases {
+            let data_type = pattern.data_type();
+            let Some(Expr::Literal(actual)) = doubled_backslash(&lit(pattern)) else {
+                panic!("expected a doubled pattern");
+            };
+            assert_eq!(actual.data_type(), data_type);
+            assert_eq!(actual.to_string(), r"x\\_y");
+        }
+        assert!(doubled_backslash(&lit("x_y")).is_none());
+    }
+}
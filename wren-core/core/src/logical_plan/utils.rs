@@ -1,20 +1,24 @@
 use crate::mdl::lineage::DatasetLink;
 use crate::mdl::manifest::Column;
+use crate::mdl::table_source::TableSourceFactoryRef;
 use crate::mdl::utils::quoted;
 use crate::mdl::{manifest::Model, WrenMDL};
 use crate::mdl::{Dataset, SessionStateRef};
 use datafusion::arrow::datatypes::{
     DataType, Field, IntervalUnit, Schema, SchemaBuilder, SchemaRef, TimeUnit,
 };
-use datafusion::common::plan_err;
 use datafusion::common::tree_node::{
     Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
 };
+use datafusion::common::{plan_err, DFSchema};
 use datafusion::datasource::DefaultTableSource;
 use datafusion::error::Result;
+use datafusion::logical_expr::expr::{Between, BinaryExpr, Placeholder};
 use datafusion::logical_expr::sqlparser::ast::ArrayElemTypeDef;
 use datafusion::logical_expr::sqlparser::dialect::GenericDialect;
-use datafusion::logical_expr::{builder::LogicalTableSource, Expr, TableSource};
+use datafusion::logical_expr::{
+    builder::LogicalTableSource, Expr, ExprSchemable, TableSource,
+};
 use datafusion::sql::sqlparser::ast;
 use datafusion::sql::sqlparser::parser::Parser;
 use datafusion::sql::TableReference;
@@ -131,12 +135,13 @@ pub fn map_data_type(data_type: &str) -> Result<DataType> {
             DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into()))
         }
         "date" => DataType::Date32,
-        "interval" => DataType::Interval(IntervalUnit::DayTime),
+        "interval" | "duration" => DataType::Interval(IntervalUnit::MonthDayNano),
         "json" => DataType::Utf8, // we don't have a JSON type, so we map it to Utf8
         "oid" => DataType::Int32,
         "bytea" => DataType::Binary,
         "uuid" => DataType::Utf8, // we don't have a UUID type, so we map it to Utf8
         "inet" => DataType::Utf8, // we don't have a INET type, so we map it to Utf8
+        "point" | "geometry" | "geography" => DataType::Utf8, // the geospatial values are kept as WKT
         "unknown" => DataType::Utf8, // we don't have a UNKNOWN type, so we map it to Utf8
         // BigQuery Compatible Types
         "bignumeric" => DataType::Decimal128(38, 10), // set the default precision and scale
@@ -164,7 +169,10 @@ pub fn create_schema(columns: Vec<Arc<Column>>) -> Result<SchemaRef> {
         .iter()
         .map(|column| {
             let data_type = map_data_type(&column.r#type)?;
-            Ok(Field::new(&column.name, data_type, column.not_null))
+            Ok(with_description(
+                Field::new(&column.name, data_type, !column.not_null),
+                column,
+            ))
         })
         .collect::<Result<Vec<_>>>()?;
     Ok(SchemaRef::new(Schema::new_with_metadata(
@@ -173,20 +181,47 @@ pub fn create_schema(columns: Vec<Arc<Column>>) -> Result<SchemaRef> {
     )))
 }
 
+/// The key of the field metadata carrying the description of the column
+pub const DESCRIPTION_METADATA_KEY: &str = "wren.description";
+
+/// Keep the description of the column in the metadata of its field, so the clients can read it
+/// from the schema of the result.
+pub fn with_description(field: Field, column: &Column) -> Field {
+    match column.description() {
+        Some(description) => field.with_metadata(HashMap::from([(
+            DESCRIPTION_METADATA_KEY.to_string(),
+            description.to_string(),
+        )])),
+        None => field,
+    }
+}
+
+/// Create the table source of the model scan. The [crate::mdl::table_source::TableSourceFactory]
+/// registered in the session creates the source by the routing hint and the query tag of the scan.
 pub fn create_remote_table_source(
     model: Arc<Model>,
     mdl: &WrenMDL,
     session_state_ref: SessionStateRef,
+    routing_hint: Option<&str>,
+    query_tag: Option<&str>,
 ) -> Result<Arc<dyn TableSource>> {
-    if let Some(table_provider) = mdl.get_table(model.table_reference()) {
-        Ok(Arc::new(DefaultTableSource::new(table_provider)))
-    } else {
-        let dataset = Dataset::Model(model);
-        let schema = dataset
-            .to_remote_schema(Some(mdl.get_register_tables()), session_state_ref)?;
-        Ok(Arc::new(LogicalTableSource::new(Arc::new(
-            schema.as_arrow().clone(),
-        ))))
+    let factory = TableSourceFactoryRef::from_session(&session_state_ref);
+    let source: Arc<dyn TableSource> =
+        if let Some(table_provider) = mdl.get_table(model.table_reference()) {
+            Arc::new(DefaultTableSource::new(table_provider))
+        } else {
+            let dataset = Dataset::Model(Arc::clone(&model));
+            let schema = dataset
+                .to_remote_schema(Some(mdl.get_register_tables()), session_state_ref)?;
+            Arc::new(LogicalTableSource::new(Arc::new(schema.as_arrow().clone())))
+        };
+    match factory {
+        Some(factory) => {
+            factory
+                .0
+                .create_table_source(&model, source, routing_hint, query_tag)
+        }
+        None => Ok(source),
     }
 }
 
@@ -341,6 +376,64 @@ pub fn eliminate_ambiguous_columns(expr: Vec<Expr>) -> Vec<Expr> {
     columns.into_values().collect()
 }
 
+/// Infer the data type of the untyped [Expr::Placeholder] from the other side of the comparison
+///
+/// e.g. `$1` in `c_custkey + 1 > $1` will be typed as the type of `c_custkey + 1`.
+/// The placeholder is kept in the plan, so the parameter can be bound at execution time.
+pub fn infer_placeholder_types(expr: Expr, schema: &DFSchema) -> Result<Expr> {
+    expr.transform_up(|expr| match expr {
+        Expr::BinaryExpr(BinaryExpr { left, op, right }) => {
+            let left = typed_placeholder(*left, &right, schema);
+            let right = typed_placeholder(*right, &left.data, schema);
+            let transformed = left.transformed || right.transformed;
+            Ok(Transformed::new_transformed(
+                Expr::BinaryExpr(BinaryExpr::new(
+                    Box::new(left.data),
+                    op,
+                    Box::new(right.data),
+                )),
+                transformed,
+            ))
+        }
+        Expr::Between(Between {
+            expr,
+            negated,
+            low,
+            high,
+        }) => {
+            let low = typed_placeholder(*low, &expr, schema);
+            let high = typed_placeholder(*high, &expr, schema);
+            let transformed = low.transformed || high.transformed;
+            Ok(Transformed::new_transformed(
+                Expr::Between(Between::new(
+                    expr,
+                    negated,
+                    Box::new(low.data),
+                    Box::new(high.data),
+                )),
+                transformed,
+            ))
+        }
+        _ => Ok(Transformed::no(expr)),
+    })
+    .data()
+}
+
+fn typed_placeholder(expr: Expr, other: &Expr, schema: &DFSchema) -> Transformed<Expr> {
+    match expr {
+        Expr::Placeholder(Placeholder {
+            id,
+            data_type: None,
+        }) => match other.get_type(schema) {
+            Ok(data_type) if !matches!(other, Expr::Placeholder(_)) => {
+                Transformed::yes(Expr::Placeholder(Placeholder::new(id, Some(data_type))))
+            }
+            _ => Transformed::no(Expr::Placeholder(Placeholder::new(id, None))),
+        },
+        _ => Transformed::no(expr),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use crate::logical_plan::utils::{
@@ -387,7 +480,8 @@ mod test {
                 DataType::Timestamp(TimeUnit::Nanosecond, Some("UTC".into())),
             ),
             ("date", DataType::Date32),
-            ("interval", DataType::Interval(IntervalUnit::DayTime)),
+            ("interval", DataType::Interval(IntervalUnit::MonthDayNano)),
+            ("duration", DataType::Interval(IntervalUnit::MonthDayNano)),
             ("json", DataType::Utf8),
             ("oid", DataType::Int32),
             ("bytea", DataType::Binary),
@@ -0,0 +1,358 @@
+use crate::mdl::context::create_ctx_with_mdl;
+use crate::mdl::query_context::QueryContext;
+use crate::mdl::utils::quoted;
+use crate::mdl::AnalyzedWrenMDL;
+use datafusion::arrow::array::RecordBatch;
+use datafusion::arrow::datatypes::SchemaRef;
+use datafusion::common::{plan_err, Result};
+use datafusion::datasource::MemTable;
+use datafusion::prelude::SessionContext;
+use parking_lot::Mutex;
+use std::collections::{BTreeSet, VecDeque};
+use std::sync::Arc;
+
+/// [ModelCache] keeps the output of the model plan in memory for the local runtime.
+///
+/// An entry can be reused by a query if its columns are a superset of the required columns of the model.
+/// The rows of a model may depend on the session, e.g. its row-level security, so an entry is only
+/// reused by the session of the same scope, see [QueryContext::cache_scope].
+/// The total size of the cached batches is limited by the memory budget. The least recently used entry
+/// will be evicted first if the budget is exceeded.
+#[derive(Debug)]
+pub struct ModelCache {
+    budget: usize,
+    state: Mutex<CacheState>,
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    /// The least recently used entry is at the front
+    entries: VecDeque<CacheEntry>,
+    memory_used: usize,
+    hits: usize,
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    model: String,
+    scope: String,
+    columns: BTreeSet<String>,
+    table: Arc<MemTable>,
+    size: usize,
+}
+
+impl ModelCache {
+    /// Create a cache with the memory budget in bytes
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    /// Get the cached relation of the model in the scope if it provides all the required columns
+    pub fn get(
+        &self,
+        model: &str,
+        scope: &str,
+        required_columns: &[String],
+    ) -> Option<Arc<MemTable>> {
+        let mut state = self.state.lock();
+        let index = state.entries.iter().position(|entry| {
+            entry.model == model
+                && entry.scope == scope
+                && required_columns.iter().all(|c| entry.columns.contains(c))
+        })?;
+        let entry = state.entries.remove(index)?;
+        let table = Arc::clone(&entry.table);
+        state.entries.push_back(entry);
+        state.hits += 1;
+        Some(table)
+    }
+
+    /// Cache the output of the model in the scope. The previous entry of the model in the same
+    /// scope will be replaced. Return false if the batches can't fit in the memory budget.
+    pub fn put(
+        &self,
+        model: &str,
+        scope: &str,
+        schema: SchemaRef,
+        batches: Vec<RecordBatch>,
+    ) -> Result<bool> {
+        let size = batches.iter().map(|b| b.get_array_memory_size()).sum();
+        if size > self.budget {
+            return Ok(false);
+        }
+        let columns = schema.fields().iter().map(|f| f.name().clone()).collect();
+        let table = Arc::new(MemTable::try_new(schema, vec![batches])?);
+
+        let mut state = self.state.lock();
+        if let Some(index) = state
+            .entries
+            .iter()
+            .position(|e| e.model == model && e.scope == scope)
+        {
+            if let Some(replaced) = state.entries.remove(index) {
+                state.memory_used -= replaced.size;
+            }
+        }
+        while state.memory_used + size > self.budget {
+            let Some(evicted) = state.entries.pop_front() else {
+                break;
+            };
+            state.memory_used -= evicted.size;
+        }
+        state.entries.push_back(CacheEntry {
+            model: model.to_string(),
+            scope: scope.to_string(),
+            columns,
+            table,
+            size,
+        });
+        state.memory_used += size;
+        Ok(true)
+    }
+
+    /// Return true if the model is cached in the scope
+    pub fn contains(&self, model: &str, scope: &str) -> bool {
+        self.state
+            .lock()
+            .entries
+            .iter()
+            .any(|e| e.model == model && e.scope == scope)
+    }
+
+    /// Return the total size of the cached batches in bytes
+    pub fn memory_used(&self) -> usize {
+        self.state.lock().memory_used
+    }
+
+    /// Return how many times the cached relations are reused
+    pub fn hits(&self) -> usize {
+        self.state.lock().hits
+    }
+}
+
+/// The name of the table scan that reads the cached relation of the model
+pub(crate) fn cache_table_name(model: &str) -> String {
+    format!("__wren_cache_{model}")
+}
+
+/// Execute the model with the given columns locally and put the result to the model cache
+/// of the [AnalyzedWrenMDL]. The result is cached in the scope of the [QueryContext] of the session.
+pub async fn cache_model(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    model: &str,
+    columns: &[&str],
+) -> Result<bool> {
+    let Some(cache) = analyzed_mdl.model_cache() else {
+        return plan_err!("Model cache isn't enabled");
+    };
+    let wren_mdl = analyzed_mdl.wren_mdl();
+    let projection = columns
+        .iter()
+        .map(|c| quoted(c))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let sql = format!(
+        "SELECT {} FROM {}.{}.{}",
+        projection,
+        quoted(wren_mdl.catalog()),
+        quoted(wren_mdl.schema()),
+        quoted(model)
+    );
+    let scope = QueryContext::from_config(&ctx.copied_config()).cache_scope();
+    let ctx = create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), true).await?;
+    let df = ctx.sql(&sql).await?;
+    let schema = Arc::new(df.schema().as_arrow().clone());
+    let batches = df.collect().await?;
+    let schema = batches.first().map(|b| b.schema()).unwrap_or(schema);
+    cache.put(model, &scope, schema, batches)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::cache::{cache_model, ModelCache};
+    use crate::mdl::context::create_ctx_with_mdl;
+    use crate::mdl::fixture::{customer, manifest_builder};
+    use crate::mdl::manifest::RowLevelOperator;
+    use crate::mdl::query_context::QueryContext;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_reuse_cached_model() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.customer".to_string(), provider);
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let cache = Arc::new(ModelCache::new(1024 * 1024));
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?
+                .with_model_cache(Arc::clone(&cache)),
+        );
+        assert!(
+            cache_model(
+                &ctx,
+                Arc::clone(&analyzed_mdl),
+                "customer",
+                &["c_custkey", "c_name"]
+            )
+            .await?
+        );
+        assert!(cache.contains("customer", &QueryContext::new().cache_scope()));
+        assert_eq!(cache.hits(), 0);
+
+        let wren_ctx = create_ctx_with_mdl(&ctx, Arc::clone(&analyzed_mdl), true).await?;
+        let result = wren_ctx
+            .sql("SELECT c_name FROM wren.test.customer WHERE c_custkey > 1 ORDER BY c_name")
+            .await?
+            .collect()
+            .await?;
+        assert_eq!(cache.hits(), 1);
+        assert_batches_eq!(
+            [
+                "+--------+",
+                "| c_name |",
+                "+--------+",
+                "| Azki   |",
+                "| Ina    |",
+                "+--------+",
+            ],
+            &result
+        );
+
+        // the relation cached by another session isn't reused
+        let tenant_ctx = SessionContext::new_with_config(
+            SessionConfig::new()
+                .with_extension(Arc::new(QueryContext::new().with_tenant_id("b"))),
+        );
+        let wren_ctx = create_ctx_with_mdl(&tenant_ctx, analyzed_mdl, true).await?;
+        let _ = wren_ctx
+            .sql("SELECT c_name FROM wren.test.customer")
+            .await?
+            .collect()
+            .await?;
+        assert_eq!(cache.hits(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cached_row_level_security() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.customer".to_string(), provider);
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(
+                        ColumnBuilder::new("c_custkey", "bigint")
+                            .row_level_security("custkey", RowLevelOperator::Equals)
+                            .build(),
+                    )
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let cache = Arc::new(ModelCache::new(1024 * 1024));
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?
+                .with_model_cache(Arc::clone(&cache)),
+        );
+        let session = |custkey: i64| {
+            SessionContext::new_with_config(SessionConfig::new().with_extension(
+                Arc::new(QueryContext::new().with_variable("custkey", custkey)),
+            ))
+        };
+        assert!(
+            cache_model(
+                &session(1),
+                Arc::clone(&analyzed_mdl),
+                "customer",
+                &["c_custkey", "c_name"]
+            )
+            .await?
+        );
+
+        // the rows visible to another session are read from the source again
+        let wren_ctx = create_ctx_with_mdl(&session(2), analyzed_mdl, true).await?;
+        let result = wren_ctx
+            .sql("SELECT c_name FROM wren.test.customer")
+            .await?
+            .collect()
+            .await?;
+        assert_eq!(cache.hits(), 0);
+        assert_batches_eq!(
+            [
+                "+--------+",
+                "| c_name |",
+                "+--------+",
+                "| Azki   |",
+                "+--------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_evict_under_budget() -> Result<()> {
+        let batch = customer();
+        let size = batch.get_array_memory_size();
+        let cache = ModelCache::new(size * 2);
+        assert!(cache.put("a", "", batch.schema(), vec![batch.clone()])?);
+        assert!(cache.put("b", "", batch.schema(), vec![batch.clone()])?);
+        assert_eq!(cache.memory_used(), size * 2);
+
+        // touch `a` to make `b` the least recently used entry
+        assert!(cache.get("a", "", &["c_custkey".to_string()]).is_some());
+        assert!(cache.get("a", "", &["unknown".to_string()]).is_none());
+        assert!(cache
+            .get("a", "other", &["c_custkey".to_string()])
+            .is_none());
+        assert!(cache.put("c", "", batch.schema(), vec![batch.clone()])?);
+        assert_eq!(cache.memory_used(), size * 2);
+        assert!(cache.contains("a", ""));
+        assert!(!cache.contains("b", ""));
+        assert!(cache.contains("c", ""));
+
+        // the entry larger than the budget won't be cached
+        let cache = ModelCache::new(size - 1);
+        assert!(!cache.put("a", "", batch.schema(), vec![batch])?);
+        assert_eq!(cache.memory_used(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use crate::mdl::WrenMDL;
+use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+use datafusion::common::{DataFusionError, Result};
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::sql::TableReference;
+use std::collections::BTreeSet;
+
+/// Create the comment listing the models and the measures queried by the plan, e.g.
+/// `/* wren lineage: models=["orders"] measures=["orders.revenue"] */`. The measures are the
+/// calculated fields referenced by the query.
+///
+/// The names are escaped as JSON strings, and their slashes are escaped too, so a name can't close
+/// the comment or open a nested one.
+pub(crate) fn lineage_comment(wren_mdl: &WrenMDL, plan: &LogicalPlan) -> Result<String> {
+    let mut models = BTreeSet::new();
+    let mut measures = BTreeSet::new();
+    plan.apply_with_subqueries(|plan| {
+        if let LogicalPlan::TableScan(scan) = plan {
+            if let Some(model) = wren_mdl.get_model(scan.table_name.table()) {
+                models.insert(model.name().to_string());
+            }
+        }
+        for expr in plan.expressions() {
+            for column in expr.column_refs() {
+                let Some(model) = column
+                    .relation
+                    .as_ref()
+                    .map(TableReference::table)
+                    .and_then(|table| wren_mdl.get_model(table))
+                else {
+                    continue;
+                };
+                if model
+                    .get_column(&column.name)
+                    .is_some_and(|c| c.is_calculated)
+                {
+                    measures.insert(format!("{}.{}", model.name(), column.name));
+                }
+            }
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(format!(
+        "/* wren lineage: models={} measures={} */",
+        escape(&models)?,
+        escape(&measures)?
+    ))
+}
+
+fn escape(names: &BTreeSet<String>) -> Result<String> {
+    let json = serde_json::to_string(names)
+        .map_err(|e| DataFusionError::External(Box::new(e)))?;
+    Ok(json.replace('/', "\\/"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::comment::escape;
+    use crate::mdl::fixture::{manifest_builder, orders_model, transform_with_mdl};
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::common::Result;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_escape() -> Result<()> {
+        let names = BTreeSet::from([
+            "orders".to_string(),
+            "a */ DROP TABLE orders; /* b".to_string(),
+            "c\"\nd".to_string(),
+        ]);
+        assert_eq!(
+            escape(&names)?,
+            r#"["a *\/ DROP TABLE orders; \/* b","c\"\nd","orders"]"#
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lineage_comment() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("totalprice", "double")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let sql = "SELECT c_custkey, totalprice FROM customer";
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest.clone())?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("/*"), "{actual}");
+
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_lineage_comment());
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let Some((comment, _)) = actual.split_once('\n') else {
+            panic!("expected a leading comment: {actual}");
+        };
+        assert_eq!(
+            comment,
+            r#"/* wren lineage: models=["customer"] measures=["customer.totalprice"] */"#
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Column, Manifest};
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// Compile the conditional counts into their calculated expressions.
+///
+/// e.g. the count of the paid orders, `count_if(orders.o_status = 'paid')`, is compiled to
+/// `sum(CASE WHEN orders.o_status = 'paid' THEN 1 ELSE 0 END)`
+///
+/// The sum of the case expression is generated instead of the native `FILTER (WHERE ...)`
+/// clause, so the backends without the clause count the same rows.
+pub(crate) fn compile_conditional_counts(mut manifest: Manifest) -> Result<Manifest> {
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model.columns.iter().all(|c| c.count_if().is_none()) {
+                return Ok(model);
+            }
+            let mut model = Arc::unwrap_or_clone(model);
+            model.columns = model
+                .columns
+                .iter()
+                .map(|column| match column.count_if() {
+                    Some(condition) => {
+                        conditional_count_column(model.name(), column, condition)
+                    }
+                    None => Ok(Arc::clone(column)),
+                })
+                .collect::<Result<_>>()?;
+            Ok(Arc::new(model))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+fn conditional_count_column(
+    model: &str,
+    column: &Column,
+    condition: &str,
+) -> Result<Arc<Column>> {
+    if !column.is_calculated {
+        return plan_err!(
+            "The conditional count {model}.{} should be a calculated field",
+            column.name()
+        );
+    }
+    if column.expression().is_some() {
+        return plan_err!(
+            "The conditional count {model}.{} can't have an expression",
+            column.name()
+        );
+    }
+    let condition = parse_expr_snippet(condition)?;
+    let mut compiled = column.clone();
+    compiled.expression = Some(format!("sum(CASE WHEN {condition} THEN 1 ELSE 0 END)"));
+    compiled.count_if = None;
+    Ok(Arc::new(compiled))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, RelationshipBuilder,
+    };
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_conditional_count() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 2, 1])) as ArrayRef,
+                ),
+                (
+                    "o_status",
+                    Arc::new(StringArray::from_iter_values([
+                        "paid", "open", "open", "paid",
+                    ])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("paid_orders", "bigint")
+                            .calculated(true)
+                            .count_if("orders.o_status = 'paid'")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_status", "varchar").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, paid_orders FROM customer \
+            WHERE c_custkey IN (1, 2) ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // only the matching rows are counted
+        assert!(actual.contains("sum(CASE WHEN "), "{actual}");
+        assert!(
+            actual.contains("o_status = 'paid' THEN 1 ELSE 0 END)"),
+            "{actual}"
+        );
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+-------------+",
+                "| c_name | paid_orders |",
+                "+--------+-------------+",
+                "| Azki   | 0           |",
+                "| Gura   | 2           |",
+                "+--------+-------------+",
+            ],
+            &result
+        );
+
+        // the condition replaces the expression of the measure
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_status", "varchar").build())
+                    .column(
+                        ColumnBuilder::new("paid_orders", "bigint")
+                            .calculated(true)
+                            .expression("count(*)")
+                            .count_if("o_status = 'paid'")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let Err(err) = AnalyzedWrenMDL::analyze(manifest) else {
+            panic!("expected the conditional count with an expression to be rejected");
+        };
+        assert!(err.to_string().contains(
+            "The conditional count orders.paid_orders can't have an expression"
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result, ScalarValue};
+use datafusion::sql::sqlparser::ast::{visit_expressions_mut, Expr};
+
+use crate::logical_plan::utils::map_data_type;
+use crate::mdl::manifest::{Constant, Manifest, Model};
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// Substitute the named constants used by the calculated fields with their literal values.
+///
+/// e.g. `o_totalprice * tax_rate` with the constant `tax_rate = 0.05` of type `double` is
+/// resolved to `o_totalprice * 0.05`. The value should be valid for the declared type, and the
+/// literal keeps the type, so its use is checked when the calculated field is planned. A column of
+/// the model takes precedence over a constant of the same name.
+pub(crate) fn resolve_constants(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.constants.is_empty() {
+        return Ok(manifest);
+    }
+    let literals = manifest
+        .constants
+        .iter()
+        .map(|constant| Ok((constant.name.clone(), constant_literal(constant)?)))
+        .collect::<Result<HashMap<_, _>>>()?;
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model
+                .columns
+                .iter()
+                .all(|c| !c.is_calculated || c.expression.is_none())
+            {
+                return Ok(model);
+            }
+            let mut resolved = Model::clone(&model);
+            resolved.columns = model
+                .columns
+                .iter()
+                .map(|column| match column.expression() {
+                    Some(expression) if column.is_calculated => {
+                        let mut expr = parse_expr_snippet(expression)?;
+                        let _ = visit_expressions_mut(&mut expr, |e| {
+                            if let Expr::Identifier(id) = e {
+                                if model.get_column(&id.value).is_none() {
+                                    if let Some(literal) = literals.get(&id.value) {
+                                        *e = literal.clone();
+                                    }
+                                }
+                            }
+                            ControlFlow::<()>::Continue(())
+                        });
+                        let mut column = column.as_ref().clone();
+                        column.expression = Some(expr.to_string());
+                        Ok(Arc::new(column))
+                    }
+                    _ => Ok(Arc::clone(column)),
+                })
+                .collect::<Result<_>>()?;
+            Ok(Arc::new(resolved))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+/// Create the literal of the constant typed by its declared type
+fn constant_literal(constant: &Constant) -> Result<Expr> {
+    let data_type = map_data_type(&constant.r#type)?;
+    if ScalarValue::try_from_string(constant.value.clone(), &data_type).is_err() {
+        return plan_err!(
+            "The value {} of the constant {} isn't a valid {}",
+            constant.value,
+            constant.name,
+            constant.r#type
+        );
+    }
+    let literal = format!("'{}'", constant.value.replace('\'', "''"));
+    if data_type.is_numeric() {
+        parse_expr_snippet(&constant.value)
+    } else {
+        parse_expr_snippet(&format!("CAST({literal} AS {})", constant.r#type))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{
+        ArrayRef, Date32Array, Float64Array, Int64Array, RecordBatch,
+    };
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_constant() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let totalprice: ArrayRef = Arc::new(Float64Array::from(vec![100.0, 200.0]));
+        let orderdate: ArrayRef = Arc::new(Date32Array::from(vec![19800, 19900]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_totalprice", totalprice),
+                ("o_orderdate", orderdate),
+            ])?,
+        )?;
+        let manifest = |tax_rate: &str| {
+            manifest_builder()
+                .constant("tax_rate", "double", tax_rate)
+                .constant("fiscal_year_start", "date", "2024-04-01")
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_totalprice", "double").build())
+                        .column(ColumnBuilder::new("o_orderdate", "date").build())
+                        .column(
+                            ColumnBuilder::new("tax", "double")
+                                .calculated(true)
+                                .expression("o_totalprice * tax_rate")
+                                .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("in_fiscal_year", "boolean")
+                                .calculated(true)
+                                .expression("o_orderdate >= fiscal_year_start")
+                                .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .build()
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest("0.05"))?);
+        let sql =
+            "SELECT o_orderkey, tax, in_fiscal_year FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("0.05"), "{actual}");
+        assert!(actual.contains("2024-04-01"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+------+----------------+",
+                "| o_orderkey | tax  | in_fiscal_year |",
+                "+------------+------+----------------+",
+                "| 1          | 5.0  | false          |",
+                "| 2          | 10.0 | true           |",
+                "+------------+------+----------------+",
+            ],
+            &result
+        );
+
+        // the value should be valid for the declared type
+        let Err(e) = AnalyzedWrenMDL::analyze(manifest("five percent")) else {
+            panic!("expected the invalid constant to fail");
+        };
+        assert!(
+            e.to_string().contains(
+                "The value five percent of the constant tax_rate isn't a valid double"
+            ),
+            "{e}"
+        );
+        Ok(())
+    }
+}
@@ -2,12 +2,19 @@ use std::any::Any;
 use std::ops::Deref;
 use std::sync::Arc;
 
+use crate::logical_plan::analyze::debug::GenerationDebugCollector;
 use crate::logical_plan::analyze::expand_view::ExpandWrenViewRule;
 use crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule;
 use crate::logical_plan::analyze::model_generation::ModelGenerationRule;
+use crate::logical_plan::analyze::relative_date::RelativeDate;
+use crate::logical_plan::optimize::date_trunc_timezone::DateTruncTimezone;
+use crate::logical_plan::optimize::explicit_coercion::ExplicitCoercion;
+use crate::logical_plan::optimize::pattern_escape::EscapePattern;
 use crate::logical_plan::optimize::simplify_timestamp::TimestampSimplify;
 use crate::logical_plan::utils::create_schema;
+use crate::mdl::dialect::get_inner_dialect;
 use crate::mdl::manifest::Model;
+use crate::mdl::query_context::QueryContext;
 use crate::mdl::{AnalyzedWrenMDL, SessionStateRef, WrenMDL};
 use async_trait::async_trait;
 use datafusion::arrow::datatypes::SchemaRef;
@@ -17,7 +24,8 @@ use datafusion::catalog_common::CatalogProvider;
 use datafusion::common::Result;
 use datafusion::datasource::{TableProvider, TableType, ViewTable};
 use datafusion::execution::session_state::SessionStateBuilder;
-use datafusion::logical_expr::Expr;
+use datafusion::logical_expr::registry::FunctionRegistry;
+use datafusion::logical_expr::{Expr, ScalarUDF};
 use datafusion::optimizer::analyzer::expand_wildcard_rule::ExpandWildcardRule;
 use datafusion::optimizer::analyzer::inline_table_scan::InlineTableScan;
 use datafusion::optimizer::analyzer::type_coercion::TypeCoercion;
@@ -55,11 +63,17 @@ pub async fn create_ctx_with_mdl(
             analyzed_mdl.wren_mdl.catalog(),
             analyzed_mdl.wren_mdl.schema(),
         );
-    let reset_default_catalog_schema = Arc::new(RwLock::new(
-        SessionStateBuilder::new_from_existing(ctx.state())
-            .with_config(config.clone())
-            .build(),
-    ));
+    let query_context = QueryContext::from_config(&config);
+    let debug_collector = config.get_extension::<GenerationDebugCollector>();
+    let mut state = SessionStateBuilder::new_from_existing(ctx.state())
+        .with_config(config.clone())
+        .build();
+    // the custom functions are resolved by the calculated fields, so they're registered to the
+    // state of the analyzer rules too
+    for udf in analyzed_mdl.scalar_udfs() {
+        state.register_udf(Arc::clone(udf))?;
+    }
+    let reset_default_catalog_schema = Arc::new(RwLock::new(state));
 
     let new_state = SessionStateBuilder::new_from_existing(
         reset_default_catalog_schema.clone().read().deref().clone(),
@@ -69,6 +83,8 @@ pub async fn create_ctx_with_mdl(
         new_state.with_analyzer_rules(analyze_rule_for_local_runtime(
             Arc::clone(&analyzed_mdl),
             reset_default_catalog_schema.clone(),
+            query_context,
+            debug_collector,
         ))
         //  The plan will be executed locally, so apply the default optimizer rules
     } else {
@@ -76,20 +92,40 @@ pub async fn create_ctx_with_mdl(
             .with_analyzer_rules(analyze_rule_for_unparsing(
                 Arc::clone(&analyzed_mdl),
                 reset_default_catalog_schema.clone(),
+                query_context,
+                debug_collector,
             ))
             .with_optimizer_rules(optimize_rule_for_unparsing())
     };
 
     let new_state = new_state.with_config(config).build();
     let ctx = SessionContext::new_with_state(new_state);
+    // the relative date filters are resolved by the model generation
+    ctx.register_udf(ScalarUDF::new_from_impl(RelativeDate::new()));
     register_table_with_mdl(&ctx, analyzed_mdl.wren_mdl()).await?;
     Ok(ctx)
 }
 
+/// Create the [ModelGenerationRule] recording its decisions to the collector of the session if any
+fn model_generation_rule(
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    session_state_ref: SessionStateRef,
+    query_context: Arc<QueryContext>,
+    debug_collector: Option<Arc<GenerationDebugCollector>>,
+) -> ModelGenerationRule {
+    let rule = ModelGenerationRule::new(analyzed_mdl, session_state_ref, query_context);
+    match debug_collector {
+        Some(collector) => rule.with_debug_collector(collector),
+        None => rule,
+    }
+}
+
 // Analyzer rules for local runtime
 fn analyze_rule_for_local_runtime(
     analyzed_mdl: Arc<AnalyzedWrenMDL>,
     session_state_ref: SessionStateRef,
+    query_context: Arc<QueryContext>,
+    debug_collector: Option<Arc<GenerationDebugCollector>>,
 ) -> Vec<Arc<dyn AnalyzerRule + Send + Sync>> {
     vec![
         // expand the view should be the first rule
@@ -101,10 +137,15 @@ fn analyze_rule_for_local_runtime(
             Arc::clone(&analyzed_mdl),
             Arc::clone(&session_state_ref),
         )),
-        Arc::new(ModelGenerationRule::new(
-            Arc::clone(&analyzed_mdl),
-            session_state_ref,
-        )),
+        Arc::new(
+            model_generation_rule(
+                Arc::clone(&analyzed_mdl),
+                session_state_ref,
+                query_context,
+                debug_collector,
+            )
+            .with_model_cache(),
+        ),
         Arc::new(InlineTableScan::new()),
         // Every rule that will generate [Expr::Wildcard] should be placed in front of [ExpandWildcardRule].
         Arc::new(ExpandWildcardRule::new()),
@@ -117,7 +158,12 @@ fn analyze_rule_for_local_runtime(
 fn analyze_rule_for_unparsing(
     analyzed_mdl: Arc<AnalyzedWrenMDL>,
     session_state_ref: SessionStateRef,
+    query_context: Arc<QueryContext>,
+    debug_collector: Option<Arc<GenerationDebugCollector>>,
 ) -> Vec<Arc<dyn AnalyzerRule + Send + Sync>> {
+    let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
+    let inner_dialect = get_inner_dialect(&data_source);
+    let timezone = query_context.timezone().to_string();
     vec![
         // expand the view should be the first rule
         Arc::new(ExpandWrenViewRule::new(
@@ -128,9 +174,11 @@ fn analyze_rule_for_unparsing(
             Arc::clone(&analyzed_mdl),
             Arc::clone(&session_state_ref),
         )),
-        Arc::new(ModelGenerationRule::new(
+        Arc::new(model_generation_rule(
             Arc::clone(&analyzed_mdl),
             session_state_ref,
+            query_context,
+            debug_collector,
         )),
         Arc::new(InlineTableScan::new()),
         // Every rule that will generate [Expr::Wildcard] should be placed in front of [ExpandWildcardRule].
@@ -138,6 +186,12 @@ fn analyze_rule_for_unparsing(
         // TimestampSimplify should be placed before TypeCoercion because the simplified timestamp should
         // be casted to the target type if needed
         Arc::new(TimestampSimplify::new()),
+        // The explicit casts should be inserted before TypeCoercion applies the implicit ones
+        Arc::new(ExplicitCoercion::new(inner_dialect.boolean_text_coercion())),
+        // The patterns are escaped for the backend, including the filters pushed down to the scans
+        Arc::new(EscapePattern::new(inner_dialect.pattern_escape())),
+        // The pushed date_trunc buckets the timestamps in the timezone of the query
+        Arc::new(DateTruncTimezone::new(&timezone)),
         // [Expr::Wildcard] should be expanded before [TypeCoercion]
         Arc::new(TypeCoercion::new()),
         // Disable it to avoid generate the alias name, `count(*)` because BigQuery doesn't allow
@@ -201,8 +255,18 @@ pub async fn register_table_with_mdl(
     catalog.register_schema(&wren_mdl.manifest.schema, Arc::new(schema))?;
     ctx.register_catalog(&wren_mdl.manifest.catalog, Arc::new(catalog));
 
+    // the columns the session isn't authorized for or its role isn't allowed to query are
+    // invisible to the query
+    let query_context = QueryContext::from_config(ctx.state().config());
     for model in wren_mdl.manifest.models.iter() {
-        let table = WrenDataSource::new(Arc::clone(model))?;
+        let columns = model
+            .get_physical_columns()
+            .into_iter()
+            .filter(|column| {
+                query_context.is_authorized(column) && query_context.is_allowed(column)
+            })
+            .collect();
+        let table = WrenDataSource::new_with_schema(create_schema(columns)?);
         ctx.register_table(
             TableReference::full(wren_mdl.catalog(), wren_mdl.schema(), model.name()),
             Arc::new(table),
@@ -0,0 +1,188 @@
+use std::collections::HashSet;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{CrossMetric, Manifest};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::utils::{push_compiled_view, quoted};
+
+/// Compile the cross-model metrics into the views combining their measures.
+///
+/// Each measure is aggregated over its own model grouped by the dimension aligned with the shared
+/// one, so the measures keep their own grain. The expressions are parsed and validated before
+/// they're embedded in the statement. The aggregated measures are full joined on the shared
+/// dimension and combined by the expression of the metric. e.g. the marketing spend per new
+/// customer is compiled to
+/// ```sql
+/// SELECT coalesce(__measure_0."day", __measure_1."day") AS "day",
+///   spend / nullif(new_customers, 0) AS "spend_per_customer"
+/// FROM (SELECT m_date AS "day", sum(m_spend) AS "spend" FROM marketing GROUP BY 1) AS __measure_0
+/// FULL JOIN (SELECT s_date AS "day", count(*) AS "new_customers" FROM signups GROUP BY 1) AS __measure_1
+/// ON __measure_0."day" = __measure_1."day"
+/// ```
+pub(crate) fn compile_cross_metrics(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.cross_metrics.is_empty() {
+        return Ok(manifest);
+    }
+    for cross_metric in std::mem::take(&mut manifest.cross_metrics) {
+        let statement = cross_metric_statement(&manifest, &cross_metric)?;
+        push_compiled_view(&mut manifest, &cross_metric.name, statement)?;
+    }
+    Ok(manifest)
+}
+
+fn cross_metric_statement(
+    manifest: &Manifest,
+    cross_metric: &CrossMetric,
+) -> Result<String> {
+    let name = &cross_metric.name;
+    if cross_metric.measures.is_empty() {
+        return plan_err!("The cross metric {name} should have at least one measure");
+    }
+    let mut names = HashSet::from([cross_metric.dimension.as_str()]);
+    for measure in &cross_metric.measures {
+        if !names.insert(measure.name.as_str()) {
+            return plan_err!(
+                "The measure {} of the cross metric {name} is defined more than once",
+                measure.name
+            );
+        }
+        if !manifest.models.iter().any(|m| m.name == measure.model) {
+            return plan_err!(
+                "The model {} of the cross metric {name} isn't found",
+                measure.model
+            );
+        }
+    }
+    let dimension = quoted(&cross_metric.dimension);
+    let aliases = (0..cross_metric.measures.len())
+        .map(|i| format!("__measure_{i}"))
+        .collect::<Vec<_>>();
+    let dimensions = aliases
+        .iter()
+        .map(|alias| format!("{alias}.{dimension}"))
+        .collect::<Vec<_>>();
+    let mut from = String::new();
+    for (i, measure) in cross_metric.measures.iter().enumerate() {
+        let aggregated = format!(
+            "(SELECT {} AS {dimension}, {} AS {} FROM {} GROUP BY 1) AS {}",
+            parse_expr_snippet(&measure.dimension)?,
+            parse_expr_snippet(&measure.expression)?,
+            quoted(&measure.name),
+            quoted(&measure.model),
+            aliases[i]
+        );
+        // the rows of the dimension absent from the previous measures are kept
+        let aligned = match &dimensions[..i] {
+            [] => String::new(),
+            [previous] => format!(" ON {previous} = {}", dimensions[i]),
+            previous => {
+                format!(" ON coalesce({}) = {}", previous.join(", "), dimensions[i])
+            }
+        };
+        if i == 0 {
+            from.push_str(&aggregated);
+        } else {
+            from.push_str(&format!(" FULL JOIN {aggregated}{aligned}"));
+        }
+    }
+    let shared_dimension = match dimensions.as_slice() {
+        [dimension] => dimension.clone(),
+        dimensions => format!("coalesce({})", dimensions.join(", ")),
+    };
+    Ok(format!(
+        "SELECT {shared_dimension} AS {dimension}, {} AS {} FROM {from}",
+        parse_expr_snippet(&cross_metric.expression)?,
+        quoted(name)
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, CrossMetricBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{
+        ArrayRef, Date32Array, Float64Array, Int64Array, RecordBatch,
+    };
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_cross_metric() -> Result<()> {
+        let ctx = SessionContext::new();
+        // 2024-01-15, 2024-01-16
+        ctx.register_batch(
+            "marketing",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "m_date",
+                    Arc::new(Date32Array::from(vec![19737, 19737, 19738])) as ArrayRef,
+                ),
+                (
+                    "m_spend",
+                    Arc::new(Float64Array::from(vec![100.0, 50.0, 80.0])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "signups",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "s_id",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5])) as ArrayRef,
+                ),
+                (
+                    "s_date",
+                    Arc::new(Date32Array::from(vec![19737, 19737, 19737, 19738, 19738]))
+                        as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("marketing")
+                    .table_reference("marketing")
+                    .column(ColumnBuilder::new("m_date", "date").build())
+                    .column(ColumnBuilder::new("m_spend", "double").build())
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("signups")
+                    .table_reference("signups")
+                    .column(ColumnBuilder::new("s_id", "bigint").build())
+                    .column(ColumnBuilder::new("s_date", "date").build())
+                    .primary_key("s_id")
+                    .build(),
+            )
+            .cross_metric(
+                CrossMetricBuilder::new("spend_per_customer")
+                    .dimension("report_date")
+                    .measure("spend", "marketing", "m_date", "sum(m_spend)")
+                    .measure("new_customers", "signups", "s_date", "count(*)")
+                    .expression("spend / nullif(new_customers, 0)")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT report_date, spend_per_customer FROM spend_per_customer \
+            ORDER BY report_date";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // each measure is aggregated at its own grain before joined on the shared date
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-------------+--------------------+",
+                "| report_date | spend_per_customer |",
+                "+-------------+--------------------+",
+                "| 2024-01-15  | 50.0               |",
+                "| 2024-01-16  | 40.0               |",
+                "+-------------+--------------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
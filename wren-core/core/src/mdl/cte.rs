@@ -0,0 +1,264 @@
+use datafusion::common::{internal_err, Result};
+use datafusion::sql::sqlparser::ast::{
+    Ident, ObjectName, Query, SetExpr, Statement, TableAlias, TableFactor, TableWithJoins,
+};
+
+use crate::mdl::snippet::parse_query_snippet;
+
+/// The prefix of the names of the factored CTEs
+const CTE_PREFIX: &str = "__cte_";
+
+/// Factor the derived tables of the statement into the CTEs of a `WITH` clause.
+///
+/// The derived tables are factored from the innermost one, so a CTE only refers to the CTEs defined
+/// before it. The derived tables with the same body share a single CTE. The CTE is named by the
+/// alias of the derived table prefixed by [CTE_PREFIX], so it doesn't shadow a physical table of the
+/// same name, and suffixed by a number if the name is taken by another body.
+pub(crate) fn factor_ctes(statement: Statement) -> Result<Statement> {
+    let Statement::Query(mut query) = statement else {
+        return Ok(statement);
+    };
+    if query.with.is_some() {
+        return Ok(Statement::Query(query));
+    }
+    let mut factor = CteFactor::default();
+    factor.factor_set_expr(&mut query.body)?;
+    if factor.ctes.is_empty() {
+        return Ok(Statement::Query(query));
+    }
+
+    let Statement::Query(template) =
+        parse_query_snippet("WITH template AS (SELECT 1) SELECT 1")?
+    else {
+        return internal_err!("Failed to parse the template of the CTE");
+    };
+    let Some(mut with) = template.with else {
+        return internal_err!("Failed to parse the template of the CTE");
+    };
+    let Some(cte_template) = with.cte_tables.pop() else {
+        return internal_err!("Failed to parse the template of the CTE");
+    };
+    with.cte_tables = factor
+        .ctes
+        .into_iter()
+        .map(|(name, body)| {
+            let mut cte = cte_template.clone();
+            cte.alias.name = name;
+            cte.query = Box::new(body);
+            cte
+        })
+        .collect();
+    query.with = Some(with);
+    Ok(Statement::Query(query))
+}
+
+#[derive(Default)]
+struct CteFactor {
+    ctes: Vec<(Ident, Query)>,
+}
+
+impl CteFactor {
+    fn factor_query(&mut self, query: &mut Query) -> Result<()> {
+        self.factor_set_expr(&mut query.body)
+    }
+
+    fn factor_set_expr(&mut self, body: &mut SetExpr) -> Result<()> {
+        match body {
+            SetExpr::Select(select) => {
+                for table in select.from.iter_mut() {
+                    self.factor_table_with_joins(table)?;
+                }
+                Ok(())
+            }
+            SetExpr::Query(query) => self.factor_query(query),
+            SetExpr::SetOperation { left, right, .. } => {
+                self.factor_set_expr(left)?;
+                self.factor_set_expr(right)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn factor_table_with_joins(&mut self, table: &mut TableWithJoins) -> Result<()> {
+        self.factor_table(&mut table.relation)?;
+        for join in table.joins.iter_mut() {
+            self.factor_table(&mut join.relation)?;
+        }
+        Ok(())
+    }
+
+    fn factor_table(&mut self, table: &mut TableFactor) -> Result<()> {
+        match table {
+            TableFactor::Derived {
+                lateral: false,
+                subquery,
+                alias: Some(alias),
+            } => {
+                self.factor_query(subquery)?;
+                let name = self.register(subquery, &alias.name);
+                *table = cte_reference(name, alias.clone())?;
+                Ok(())
+            }
+            TableFactor::NestedJoin {
+                table_with_joins, ..
+            } => self.factor_table_with_joins(table_with_joins),
+            _ => Ok(()),
+        }
+    }
+
+    /// Return the name of the CTE of the body. The same body is registered only once.
+    fn register(&mut self, body: &Query, alias: &Ident) -> Ident {
+        if let Some((name, _)) = self.ctes.iter().find(|(_, cte)| cte == body) {
+            return name.clone();
+        }
+        let prefixed = format!("{CTE_PREFIX}{}", alias.value);
+        let mut name = alias.clone();
+        name.value = prefixed.clone();
+        let mut suffix = 1;
+        while self.ctes.iter().any(|(cte, _)| cte.value == name.value) {
+            name.value = format!("{prefixed}_{suffix}");
+            suffix += 1;
+        }
+        self.ctes.push((name.clone(), body.clone()));
+        name
+    }
+}
+
+/// Refer to the CTE by the alias of the replaced derived table
+fn cte_reference(name: Ident, alias: TableAlias) -> Result<TableFactor> {
+    let Statement::Query(query) = parse_query_snippet("SELECT 1 FROM template")? else {
+        return internal_err!("Failed to parse the template of the CTE reference");
+    };
+    let SetExpr::Select(select) = *query.body else {
+        return internal_err!("Failed to parse the template of the CTE reference");
+    };
+    let Some(mut table) = select.from.into_iter().next().map(|t| t.relation) else {
+        return internal_err!("Failed to parse the template of the CTE reference");
+    };
+    if let TableFactor::Table {
+        name: table_name,
+        alias: table_alias,
+        ..
+    } = &mut table
+    {
+        *table_name = ObjectName(vec![name]);
+        *table_alias = Some(alias);
+    }
+    Ok(table)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashSet;
+    use std::sync::Arc;
+
+    use crate::mdl::builder::ColumnBuilder;
+    use crate::mdl::cte::factor_ctes;
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, orders_of,
+        transform_with_mdl,
+    };
+    use crate::mdl::snippet::parse_query_snippet;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Float64Array};
+    use datafusion::arrow::util::pretty::pretty_format_batches;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use datafusion::sql::sqlparser::ast::Statement;
+    use datafusion::sql::sqlparser::dialect::GenericDialect;
+    use datafusion::sql::sqlparser::parser::Parser;
+
+    #[test]
+    fn test_factor_ctes() -> Result<()> {
+        let sql = "SELECT customer.c_name, o.o_totalprice \
+            FROM (SELECT c_custkey, c_name FROM customer) AS customer \
+            JOIN (SELECT o_custkey, o_totalprice \
+                FROM (SELECT o_custkey, o_totalprice FROM orders WHERE o_totalprice > 0) AS o) AS o \
+            ON customer.c_custkey = o.o_custkey \
+            JOIN (SELECT c_custkey, c_name FROM customer) AS buyer \
+            ON buyer.c_custkey = o.o_custkey";
+        let actual = factor_ctes(parse_query_snippet(sql)?)?;
+        // the CTE doesn't shadow the physical table, the inner derived table is factored first
+        // and the same body shares the CTE
+        assert_eq!(
+            actual.to_string(),
+            "WITH __cte_customer AS (SELECT c_custkey, c_name FROM customer), \
+            __cte_o AS (SELECT o_custkey, o_totalprice FROM orders WHERE o_totalprice > 0), \
+            __cte_o_1 AS (SELECT o_custkey, o_totalprice FROM __cte_o AS o) \
+            SELECT customer.c_name, o.o_totalprice FROM __cte_customer AS customer \
+            JOIN __cte_o_1 AS o ON customer.c_custkey = o.o_custkey \
+            JOIN __cte_customer AS buyer ON buyer.c_custkey = o.o_custkey"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_cte_output() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let totalprice: ArrayRef = Arc::new(Float64Array::from(vec![100.0, 300.0, 50.0]));
+        ctx.register_batch(
+            "orders",
+            orders_of(&[1, 1, 2], vec![("o_totalprice", totalprice)]),
+        )?;
+        let manifest = || {
+            manifest_builder()
+                .model(customer_model().primary_key("c_custkey").build())
+                .model(
+                    orders_model()
+                        .column(ColumnBuilder::new("o_totalprice", "double").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .build()
+        };
+        let nested_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+        let cte_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?.with_cte_output());
+        // return the bodies of the CTEs
+        let cte_bodies = |sql: &str| -> Result<Vec<String>> {
+            let statement = Parser::new(&GenericDialect {})
+                .try_with_sql(sql)?
+                .parse_statement()?;
+            let Statement::Query(query) = statement else {
+                panic!("expected a query: {sql}");
+            };
+            let Some(with) = query.with else {
+                panic!("expected a WITH clause: {sql}");
+            };
+            Ok(with
+                .cte_tables
+                .iter()
+                .map(|cte| cte.query.to_string())
+                .collect())
+        };
+
+        let sql = "SELECT c.c_name, o.o_totalprice FROM customer c \
+            JOIN orders o ON c.c_custkey = o.o_custkey ORDER BY o.o_totalprice";
+        let actual = transform_with_mdl(&cte_mdl, sql).await?;
+        assert!(actual.starts_with("WITH "), "{actual}");
+        let bodies = cte_bodies(&actual)?;
+        let unique_bodies = bodies.iter().collect::<HashSet<_>>();
+        assert_eq!(bodies.len(), unique_bodies.len(), "{actual}");
+        // the CTE-based SQL returns the same rows as the nested one
+        let nested = transform_with_mdl(&nested_mdl, sql).await?;
+        let expected = ctx.sql(&nested).await?.collect().await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(
+            pretty_format_batches(&expected)?.to_string(),
+            pretty_format_batches(&result)?.to_string()
+        );
+
+        // the expansions of the same model share the CTE
+        let single = "SELECT c_name FROM customer";
+        let single = transform_with_mdl(&cte_mdl, single).await?;
+        let sql = "SELECT a.c_name, b.c_name FROM customer a \
+            JOIN customer b ON a.c_custkey = b.c_custkey";
+        let actual = transform_with_mdl(&cte_mdl, sql).await?;
+        assert_eq!(
+            cte_bodies(&actual)?.len(),
+            cte_bodies(&single)?.len(),
+            "{actual}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::collections::BTreeSet;
+use std::ops::ControlFlow;
+
+use datafusion::common::{plan_err, DataFusionError, Result};
+use datafusion::sql::sqlparser::ast::{visit_expressions, Expr};
+
+use crate::mdl::manifest::{CuratedView, Manifest};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::utils::{push_compiled_view, quoted};
+
+/// Compile the curated views into the views querying their base models.
+///
+/// The dimensions, measures and filters refer to the columns of the base model or of the models
+/// related to it, e.g. `customer.c_name` of the orders. The related models are joined by the
+/// relationship with the base model, so the users query one flat view without knowing the
+/// relationships. The rows of the base model without a related row are kept. e.g. the revenue of
+/// the customers is compiled to
+/// ```sql
+/// SELECT customer.c_name AS "customer_name", sum(orders.o_totalprice) AS "revenue"
+/// FROM "orders" LEFT JOIN "customer" ON orders.o_custkey = customer.c_custkey
+/// WHERE (orders.o_orderstatus = 'F') GROUP BY 1
+/// ```
+/// The models of the view are expanded by the model generation as the models of a query.
+pub(crate) fn compile_curated_views(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.curated_views.is_empty() {
+        return Ok(manifest);
+    }
+    for curated_view in std::mem::take(&mut manifest.curated_views) {
+        let statement = curated_view_statement(&manifest, &curated_view)?;
+        push_compiled_view(&mut manifest, &curated_view.name, statement)?;
+    }
+    Ok(manifest)
+}
+
+fn curated_view_statement(
+    manifest: &Manifest,
+    curated_view: &CuratedView,
+) -> Result<String> {
+    let name = &curated_view.name;
+    let base = &curated_view.base_model;
+    if !manifest.models.iter().any(|m| &m.name == base) {
+        return plan_err!("The base model {base} of the curated view {name} isn't found");
+    }
+    if curated_view.dimensions.is_empty() && curated_view.measures.is_empty() {
+        return plan_err!("The curated view {name} should have a dimension or a measure");
+    }
+    let mut related = BTreeSet::new();
+    let mut parse = |sql: &str| {
+        let expr = parse_expr_snippet(sql)?;
+        let _ = visit_expressions(&expr, |e| {
+            if let Expr::CompoundIdentifier(ids) = e {
+                if let Some(model) = ids.first().filter(|id| &id.value != base) {
+                    if manifest.models.iter().any(|m| m.name == model.value) {
+                        related.insert(model.value.clone());
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        Ok::<_, DataFusionError>(expr)
+    };
+    let fields = curated_view
+        .dimensions
+        .iter()
+        .chain(curated_view.measures.iter())
+        .map(|field| {
+            Ok(format!(
+                "{} AS {}",
+                parse(&field.expression)?,
+                quoted(&field.name)
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let filters = curated_view
+        .filters
+        .iter()
+        .map(|filter| Ok(format!("({})", parse(filter)?)))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut statement = format!("SELECT {} FROM {}", fields.join(", "), quoted(base));
+    for model in related {
+        let Some(relationship) = manifest.relationships.iter().find(|r| {
+            r.models.len() == 2 && r.models.contains(base) && r.models.contains(&model)
+        }) else {
+            return plan_err!(
+                "The model {model} of the curated view {name} isn't related to the base model {base}"
+            );
+        };
+        statement.push_str(&format!(
+            " LEFT JOIN {} ON {}",
+            quoted(&model),
+            parse_expr_snippet(&relationship.condition)?
+        ));
+    }
+    if !filters.is_empty() {
+        statement.push_str(&format!(" WHERE {}", filters.join(" AND ")));
+    }
+    if !curated_view.measures.is_empty() && !curated_view.dimensions.is_empty() {
+        let group_by = (1..=curated_view.dimensions.len())
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        statement.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+    }
+    Ok(statement)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, CuratedViewBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders, orders_model,
+        transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_curated_view() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch("orders", orders())?;
+        let manifest = manifest_builder()
+            .model(customer_model().primary_key("c_custkey").build())
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .curated_view(
+                CuratedViewBuilder::new("customer_revenue", "orders")
+                    .dimension("customer_name", "customer.c_name")
+                    .measure("revenue", "sum(orders.o_totalprice)")
+                    .filter("orders.o_totalprice > 100")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT customer_name, revenue FROM customer_revenue ORDER BY customer_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the related customer is joined by the relationship with the base model
+        assert!(actual.contains("JOIN"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------+---------+",
+                "| customer_name | revenue |",
+                "+---------------+---------+",
+                "| Azki          | 200     |",
+                "| Ina           | 300     |",
+                "+---------------+---------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
@@ -62,7 +62,7 @@ impl Dataset {
                     let fields: Vec<Field> = model
                         .get_physical_columns()
                         .iter()
-                        .filter(|c| !c.is_calculated)
+                        .filter(|c| !c.is_computed())
                         .map(|c| to_remote_field(c, Arc::clone(&session_state)))
                         .collect::<Result<Vec<Vec<Field>>>>()?
                         .iter()
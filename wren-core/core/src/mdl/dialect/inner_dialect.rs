@@ -17,10 +17,12 @@
  * under the License.
  */
 
-use crate::mdl::dialect::utils::scalar_function_to_sql_internal;
+use crate::logical_plan::optimize::explicit_coercion::BooleanTextCoercion;
+use crate::logical_plan::optimize::pattern_escape::PatternEscape;
+use crate::mdl::dialect::utils::{is_reserved_keyword, scalar_function_to_sql_internal};
 use crate::mdl::manifest::DataSource;
 use datafusion::common::Result;
-use datafusion::logical_expr::Expr;
+use datafusion::logical_expr::{lit, Expr};
 use datafusion::sql::sqlparser::ast;
 use datafusion::sql::unparser::Unparser;
 
@@ -36,12 +38,59 @@ pub trait InnerDialect: Send + Sync {
     ) -> Result<Option<ast::Expr>> {
         Ok(None)
     }
+
+    /// The explicit coercion for the comparison between a boolean and a text expression.
+    fn boolean_text_coercion(&self) -> BooleanTextCoercion {
+        BooleanTextCoercion::CastToBoolean
+    }
+
+    /// The hash function used to generate the surrogate key from the natural keys.
+    fn surrogate_key_function(&self) -> &'static str {
+        "md5"
+    }
+
+    /// How the backslash escaping the wildcards of a `LIKE` or regex pattern is written.
+    fn pattern_escape(&self) -> PatternEscape {
+        PatternEscape::Backslash
+    }
+
+    /// The character quoting an identifier when the identifiers are quoted minimally.
+    fn identifier_quote(&self) -> char {
+        '"'
+    }
+
+    /// Return true if the word is reserved by the backend, so it should be quoted as an identifier.
+    fn is_reserved_word(&self, word: &str) -> bool {
+        is_reserved_keyword(word)
+    }
+
+    /// Return true if the backend folds the unquoted identifiers to uppercase rather than
+    /// lowercase.
+    fn folds_to_uppercase(&self) -> bool {
+        false
+    }
+
+    /// Convert the UTC timestamp to the wall-clock time of the timezone. If the backend
+    /// supports `timestamp AT TIME ZONE 'timezone'`, it should return `None`, and the timestamp
+    /// without timezone is localized by `AT TIME ZONE 'UTC'` first.
+    fn at_time_zone_to_sql(
+        &self,
+        _unparser: &Unparser,
+        _timestamp: &Expr,
+        _timezone: &Expr,
+    ) -> Result<Option<ast::Expr>> {
+        Ok(None)
+    }
 }
 
 /// [get_inner_dialect] returns the suitable InnerDialect for the given data source.
 pub fn get_inner_dialect(data_source: &DataSource) -> Box<dyn InnerDialect> {
     match data_source {
+        DataSource::Postgres => Box::new(PostgresDialect {}),
         DataSource::MySQL => Box::new(MySQLDialect {}),
+        DataSource::BigQuery => Box::new(BigQueryDialect {}),
+        DataSource::Trino => Box::new(TrinoDialect {}),
+        DataSource::Snowflake => Box::new(SnowflakeDialect {}),
         _ => Box::new(GenericDialect {}),
     }
 }
@@ -52,6 +101,121 @@ pub struct GenericDialect {}
 
 impl InnerDialect for GenericDialect {}
 
+/// [PostgresDialect] is a dialect that overrides the SQL generation for PostgreSQL dialect.
+pub struct PostgresDialect {}
+
+impl InnerDialect for PostgresDialect {
+    /// PostgreSQL reserves fewer words than the keywords it knows, e.g. `name` isn't reserved
+    fn is_reserved_word(&self, word: &str) -> bool {
+        POSTGRES_RESERVED_KEYWORDS.contains(&word.to_uppercase().as_str())
+    }
+}
+
+/// The reserved keywords of PostgreSQL, including the ones allowed as a function or type name
+const POSTGRES_RESERVED_KEYWORDS: [&str; 101] = [
+    "ALL",
+    "ANALYSE",
+    "ANALYZE",
+    "AND",
+    "ANY",
+    "ARRAY",
+    "AS",
+    "ASC",
+    "ASYMMETRIC",
+    "AUTHORIZATION",
+    "BINARY",
+    "BOTH",
+    "CASE",
+    "CAST",
+    "CHECK",
+    "COLLATE",
+    "COLLATION",
+    "COLUMN",
+    "CONCURRENTLY",
+    "CONSTRAINT",
+    "CREATE",
+    "CROSS",
+    "CURRENT_CATALOG",
+    "CURRENT_DATE",
+    "CURRENT_ROLE",
+    "CURRENT_SCHEMA",
+    "CURRENT_TIME",
+    "CURRENT_TIMESTAMP",
+    "CURRENT_USER",
+    "DEFAULT",
+    "DEFERRABLE",
+    "DESC",
+    "DISTINCT",
+    "DO",
+    "ELSE",
+    "END",
+    "EXCEPT",
+    "FALSE",
+    "FETCH",
+    "FOR",
+    "FOREIGN",
+    "FREEZE",
+    "FROM",
+    "FULL",
+    "GRANT",
+    "GROUP",
+    "HAVING",
+    "ILIKE",
+    "IN",
+    "INITIALLY",
+    "INNER",
+    "INTERSECT",
+    "INTO",
+    "IS",
+    "ISNULL",
+    "JOIN",
+    "LATERAL",
+    "LEADING",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "LOCALTIME",
+    "LOCALTIMESTAMP",
+    "NATURAL",
+    "NOT",
+    "NOTNULL",
+    "NULL",
+    "OFFSET",
+    "ON",
+    "ONLY",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVERLAPS",
+    "PLACING",
+    "PRIMARY",
+    "REFERENCES",
+    "RETURNING",
+    "RIGHT",
+    "SELECT",
+    "SESSION_USER",
+    "SIMILAR",
+    "SOME",
+    "SYMMETRIC",
+    "SYSTEM_USER",
+    "TABLE",
+    "TABLESAMPLE",
+    "THEN",
+    "TO",
+    "TRAILING",
+    "TRUE",
+    "UNION",
+    "UNIQUE",
+    "USER",
+    "USING",
+    "VARIADIC",
+    "VERBOSE",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+];
+
 /// [MySQLDialect] is a dialect that overrides the SQL generation for MySQL dialect.
 pub struct MySQLDialect {}
 
@@ -67,4 +231,192 @@ impl InnerDialect for MySQLDialect {
             _ => Ok(None),
         }
     }
+
+    /// MySQL doesn't support `CAST(... AS BOOLEAN)`
+    fn boolean_text_coercion(&self) -> BooleanTextCoercion {
+        BooleanTextCoercion::CastToText
+    }
+
+    /// MySQL quotes the identifiers by the backtick unless `ANSI_QUOTES` is enabled
+    fn identifier_quote(&self) -> char {
+        '`'
+    }
+
+    /// MySQL doesn't support `AT TIME ZONE`, the timestamp is converted by `CONVERT_TZ`
+    fn at_time_zone_to_sql(
+        &self,
+        unparser: &Unparser,
+        timestamp: &Expr,
+        timezone: &Expr,
+    ) -> Result<Option<ast::Expr>> {
+        let args = [timestamp.clone(), lit("+00:00"), timezone.clone()];
+        scalar_function_to_sql_internal(unparser, "convert_tz", &args)
+    }
+}
+
+/// [BigQueryDialect] is a dialect that overrides the SQL generation for BigQuery dialect.
+pub struct BigQueryDialect {}
+
+impl InnerDialect for BigQueryDialect {
+    /// BigQuery recommends `farm_fingerprint` for the surrogate key
+    fn surrogate_key_function(&self) -> &'static str {
+        "farm_fingerprint"
+    }
+
+    /// BigQuery reads the backslash of a string literal as an escape sequence
+    fn pattern_escape(&self) -> PatternEscape {
+        PatternEscape::DoubledBackslash
+    }
+
+    /// BigQuery quotes the identifiers by the backtick
+    fn identifier_quote(&self) -> char {
+        '`'
+    }
+
+    /// BigQuery reserves its own keywords rather than the ANSI ones
+    fn is_reserved_word(&self, word: &str) -> bool {
+        BIGQUERY_RESERVED_KEYWORDS.contains(&word.to_uppercase().as_str())
+    }
+
+    /// BigQuery converts a timestamp to the civil time of the timezone by `DATETIME`
+    fn at_time_zone_to_sql(
+        &self,
+        unparser: &Unparser,
+        timestamp: &Expr,
+        timezone: &Expr,
+    ) -> Result<Option<ast::Expr>> {
+        let args = [timestamp.clone(), timezone.clone()];
+        scalar_function_to_sql_internal(unparser, "datetime", &args)
+    }
+}
+
+/// The reserved keywords of the BigQuery GoogleSQL
+const BIGQUERY_RESERVED_KEYWORDS: [&str; 96] = [
+    "ALL",
+    "AND",
+    "ANY",
+    "ARRAY",
+    "AS",
+    "ASC",
+    "ASSERT_ROWS_MODIFIED",
+    "AT",
+    "BETWEEN",
+    "BY",
+    "CASE",
+    "CAST",
+    "COLLATE",
+    "CONTAINS",
+    "CREATE",
+    "CROSS",
+    "CUBE",
+    "CURRENT",
+    "DEFAULT",
+    "DEFINE",
+    "DESC",
+    "DISTINCT",
+    "ELSE",
+    "END",
+    "ENUM",
+    "ESCAPE",
+    "EXCEPT",
+    "EXCLUDE",
+    "EXISTS",
+    "EXTRACT",
+    "FALSE",
+    "FETCH",
+    "FOLLOWING",
+    "FOR",
+    "FROM",
+    "FULL",
+    "GROUP",
+    "GROUPING",
+    "GROUPS",
+    "HASH",
+    "HAVING",
+    "IF",
+    "IGNORE",
+    "IN",
+    "INNER",
+    "INTERSECT",
+    "INTERVAL",
+    "INTO",
+    "IS",
+    "JOIN",
+    "LATERAL",
+    "LEFT",
+    "LIKE",
+    "LIMIT",
+    "LOOKUP",
+    "MERGE",
+    "NATURAL",
+    "NEW",
+    "NO",
+    "NOT",
+    "NULL",
+    "NULLS",
+    "OF",
+    "ON",
+    "OR",
+    "ORDER",
+    "OUTER",
+    "OVER",
+    "PARTITION",
+    "PRECEDING",
+    "PROTO",
+    "QUALIFY",
+    "RANGE",
+    "RECURSIVE",
+    "RESPECT",
+    "RIGHT",
+    "ROLLUP",
+    "ROWS",
+    "SELECT",
+    "SET",
+    "SOME",
+    "STRUCT",
+    "TABLESAMPLE",
+    "THEN",
+    "TO",
+    "TREAT",
+    "TRUE",
+    "UNBOUNDED",
+    "UNION",
+    "UNNEST",
+    "USING",
+    "WHEN",
+    "WHERE",
+    "WINDOW",
+    "WITH",
+    "WITHIN",
+];
+
+/// [TrinoDialect] is a dialect that overrides the SQL generation for Trino dialect.
+pub struct TrinoDialect {}
+
+impl InnerDialect for TrinoDialect {
+    /// Trino doesn't have a default escape character for `LIKE`
+    fn pattern_escape(&self) -> PatternEscape {
+        PatternEscape::ExplicitEscape
+    }
+}
+
+/// [SnowflakeDialect] is a dialect that overrides the SQL generation for Snowflake dialect.
+pub struct SnowflakeDialect {}
+
+impl InnerDialect for SnowflakeDialect {
+    /// Snowflake resolves the unquoted identifiers as uppercase
+    fn folds_to_uppercase(&self) -> bool {
+        true
+    }
+
+    /// Snowflake converts the timestamp between timezones by `CONVERT_TIMEZONE`
+    fn at_time_zone_to_sql(
+        &self,
+        unparser: &Unparser,
+        timestamp: &Expr,
+        timezone: &Expr,
+    ) -> Result<Option<ast::Expr>> {
+        let args = [lit("UTC"), timezone.clone(), timestamp.clone()];
+        scalar_function_to_sql_internal(unparser, "convert_timezone", &args)
+    }
 }
@@ -21,4 +21,5 @@ mod inner_dialect;
 mod utils;
 mod wren_dialect;
 
+pub(crate) use inner_dialect::get_inner_dialect;
 pub use wren_dialect::WrenDialect;
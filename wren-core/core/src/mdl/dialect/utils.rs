@@ -20,6 +20,7 @@ use datafusion::common::Result;
 use datafusion::logical_expr::Expr;
 use datafusion::sql::sqlparser::ast;
 use datafusion::sql::sqlparser::ast::{Function, Ident, ObjectName};
+use datafusion::sql::sqlparser::keywords::ALL_KEYWORDS;
 use datafusion::sql::sqlparser::tokenizer::Span;
 use datafusion::sql::unparser::Unparser;
 
@@ -73,3 +74,11 @@ pub(crate) fn scalar_function_to_sql_internal(
         uses_odbc_syntax: false,
     })))
 }
+
+/// Return true if the word is any keyword known by the SQL parser, e.g. `order` or `user`.
+/// It's the conservative choice for the backends without their own reserved words.
+pub(crate) fn is_reserved_keyword(word: &str) -> bool {
+    ALL_KEYWORDS
+        .binary_search(&word.to_uppercase().as_str())
+        .is_ok()
+}
@@ -16,6 +16,7 @@
  * specific language governing permissions and limitations
  * under the License.
  */
+use crate::logical_plan::optimize::date_trunc_timezone::AT_TIME_ZONE;
 use crate::mdl::dialect::inner_dialect::{get_inner_dialect, InnerDialect};
 use crate::mdl::manifest::DataSource;
 use datafusion::common::{internal_err, plan_err, Result, ScalarValue};
@@ -23,22 +24,36 @@ use datafusion::logical_expr::sqlparser::ast::{Ident, Subscript};
 use datafusion::logical_expr::sqlparser::keywords::ALL_KEYWORDS;
 use datafusion::logical_expr::Expr;
 use datafusion::sql::sqlparser::ast;
-use datafusion::sql::sqlparser::ast::{AccessExpr, Array, Value};
+use datafusion::sql::sqlparser::ast::{
+    AccessExpr, Array, ObjectName, Query, SelectItem, SetExpr, TableAlias, TableFactor,
+    Value, VisitMut, VisitorMut,
+};
 use datafusion::sql::sqlparser::tokenizer::Span;
 use datafusion::sql::unparser::dialect::{Dialect, IntervalStyle};
 use datafusion::sql::unparser::Unparser;
 use regex::Regex;
+use std::ops::ControlFlow;
 
 /// WrenDialect is a dialect for Wren engine. Handle the identifier quote style based on the
 /// original Datafusion Dialect implementation but with more strict rules.
 /// If the identifier isn't lowercase, it will be quoted.
+///
+/// With the minimal quoting, only the words reserved by the data source are quoted among the
+/// keywords, and the identifiers are quoted by the quote character of the data source.
 pub struct WrenDialect {
     inner_dialect: Box<dyn InnerDialect>,
+    minimal_quoting: bool,
 }
 
 impl Dialect for WrenDialect {
     fn identifier_quote_style(&self, identifier: &str) -> Option<char> {
         let identifier_regex = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
+        if self.minimal_quoting {
+            return (self.inner_dialect.is_reserved_word(identifier)
+                || !identifier_regex.is_match(identifier)
+                || non_lowercase(identifier))
+            .then(|| self.inner_dialect.identifier_quote());
+        }
         if ALL_KEYWORDS.contains(&identifier.to_uppercase().as_str())
             || !identifier_regex.is_match(identifier)
             || non_lowercase(identifier)
@@ -80,6 +95,10 @@ impl Dialect for WrenDialect {
                 let sql = self.named_struct_to_sql(args, unparser)?;
                 Ok(Some(sql))
             }
+            AT_TIME_ZONE => {
+                let sql = self.at_time_zone_to_sql(args, unparser)?;
+                Ok(Some(sql))
+            }
             _ => Ok(None),
         }
     }
@@ -95,7 +114,27 @@ impl WrenDialect {
     pub fn new(data_source: &DataSource) -> Self {
         Self {
             inner_dialect: get_inner_dialect(data_source),
+            minimal_quoting: false,
+        }
+    }
+
+    /// Quote only the identifiers reserved by the data source or containing special characters
+    pub fn with_minimal_quoting(mut self, minimal_quoting: bool) -> Self {
+        self.minimal_quoting = minimal_quoting;
+        self
+    }
+
+    /// Fold the quoted identifiers of the generated SQL by the rule the data source folds the
+    /// unquoted ones, so they resolve against the physical catalog. e.g. `"name"` is folded to
+    /// `"NAME"` for Snowflake.
+    ///
+    /// Only the lowercase identifiers are folded, they're quoted because of the keywords rather than
+    /// their case. The identifier with any uppercase letter is case-sensitive and kept as it is.
+    pub fn fold_identifiers(&self, mut statement: ast::Statement) -> ast::Statement {
+        if self.inner_dialect.folds_to_uppercase() {
+            let _ = statement.visit(&mut IdentifierFolder::new());
         }
+        statement
     }
 
     fn make_array_to_sql(&self, args: &[Expr], unparser: &Unparser) -> Result<ast::Expr> {
@@ -157,6 +196,35 @@ impl WrenDialect {
         Ok(ast::Expr::Dictionary(fields))
     }
 
+    fn at_time_zone_to_sql(
+        &self,
+        args: &[Expr],
+        unparser: &Unparser,
+    ) -> Result<ast::Expr> {
+        if args.len() != 2 && args.len() != 3 {
+            return internal_err!("{AT_TIME_ZONE} must have 2 or 3 arguments");
+        }
+        if let Some(sql) = self
+            .inner_dialect
+            .at_time_zone_to_sql(unparser, &args[0], &args[1])?
+        {
+            return Ok(sql);
+        }
+        let timestamp = unparser.expr_to_sql(&args[0])?;
+        // the timestamp without timezone is localized to the source timezone first
+        let timestamp = match args.get(2) {
+            Some(source_timezone) => ast::Expr::Nested(Box::new(ast::Expr::AtTimeZone {
+                timestamp: Box::new(timestamp),
+                time_zone: Box::new(unparser.expr_to_sql(source_timezone)?),
+            })),
+            None => timestamp,
+        };
+        Ok(ast::Expr::AtTimeZone {
+            timestamp: Box::new(timestamp),
+            time_zone: Box::new(unparser.expr_to_sql(&args[1])?),
+        })
+    }
+
     fn get_fields_to_sql(
         &self,
         args: &[Expr],
@@ -194,3 +262,206 @@ fn non_lowercase(sql: &str) -> bool {
     let lowercase = sql.to_lowercase();
     lowercase != sql
 }
+
+/// Fold the quoted lowercase identifiers of a statement to uppercase. Only the identifiers are
+/// visited, so the string literals are kept as they are.
+struct IdentifierFolder {
+    foldable: Regex,
+}
+
+impl IdentifierFolder {
+    fn new() -> Self {
+        Self {
+            foldable: Regex::new(r"^[a-z_][a-z0-9_]*$").unwrap(),
+        }
+    }
+
+    fn fold(&self, ident: &mut Ident) {
+        if ident.quote_style.is_some() && self.foldable.is_match(&ident.value) {
+            ident.value = ident.value.to_uppercase();
+        }
+    }
+
+    fn fold_alias(&self, alias: &mut TableAlias) {
+        self.fold(&mut alias.name);
+        alias
+            .columns
+            .iter_mut()
+            .for_each(|column| self.fold(&mut column.name));
+    }
+
+    /// The aliases of the projections aren't visited as expressions
+    fn fold_set_expr(&self, body: &mut SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for item in select.projection.iter_mut() {
+                    match item {
+                        SelectItem::ExprWithAlias { alias, .. } => self.fold(alias),
+                        SelectItem::QualifiedWildcard(name, _) => {
+                            name.0.iter_mut().for_each(|ident| self.fold(ident))
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.fold_set_expr(left);
+                self.fold_set_expr(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl VisitorMut for IdentifierFolder {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &mut Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = query.with.as_mut() {
+            with.cte_tables
+                .iter_mut()
+                .for_each(|cte| self.fold_alias(&mut cte.alias));
+        }
+        self.fold_set_expr(&mut query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_relation(
+        &mut self,
+        relation: &mut ObjectName,
+    ) -> ControlFlow<Self::Break> {
+        relation.0.iter_mut().for_each(|ident| self.fold(ident));
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        match table_factor {
+            TableFactor::Table { alias, .. }
+            | TableFactor::Derived { alias, .. }
+            | TableFactor::UNNEST { alias, .. } => {
+                if let Some(alias) = alias {
+                    self.fold_alias(alias);
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &mut ast::Expr) -> ControlFlow<Self::Break> {
+        match expr {
+            ast::Expr::Identifier(ident) => self.fold(ident),
+            ast::Expr::CompoundIdentifier(idents) => {
+                idents.iter_mut().for_each(|ident| self.fold(ident))
+            }
+            _ => {}
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::manifest::DataSource;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_minimal_quoting() -> Result<()> {
+        let manifest = |data_source: DataSource| {
+            manifest_builder()
+                .data_source(data_source)
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                        .column(ColumnBuilder::new("name", "varchar").build())
+                        .column(ColumnBuilder::new("order", "bigint").build())
+                        .column(ColumnBuilder::new("user", "varchar").build())
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = r#"SELECT c_custkey, name, "order", "user" FROM customer"#;
+
+        // all the keywords are quoted by default
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::Postgres))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains(r#"customer."name""#), "{actual}");
+
+        // the simple identifier is left bare and the reserved word is quoted
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest(DataSource::Postgres))?
+                .with_minimal_quoting(),
+        );
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("customer.name"), "{actual}");
+        assert!(!actual.contains(r#""name""#), "{actual}");
+        assert!(actual.contains(r#"customer."order""#), "{actual}");
+        assert!(actual.contains(r#"customer."user""#), "{actual}");
+        assert!(actual.contains("customer.c_custkey"), "{actual}");
+
+        // the backends without their own reserved words quote all the keywords
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest(DataSource::Snowflake))?
+                .with_minimal_quoting(),
+        );
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains(r#"customer."user""#), "{actual}");
+        assert!(actual.contains("customer.c_custkey"), "{actual}");
+
+        // BigQuery quotes its reserved word by the backtick
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest(DataSource::BigQuery))?
+                .with_minimal_quoting(),
+        );
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("customer.name"), "{actual}");
+        assert!(actual.contains("customer.`order`"), "{actual}");
+        assert!(actual.contains("customer.user"), "{actual}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_identifier_folding() -> Result<()> {
+        let manifest = manifest_builder()
+            .data_source(DataSource::Snowflake)
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("name", "varchar").build())
+                    .column(ColumnBuilder::new("CamelName", "varchar").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let sql = r#"SELECT c_custkey, name, "CamelName" FROM customer WHERE name = 'O''Brien'"#;
+
+        // the keyword is quoted as it is by default
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest.clone())?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains(r#"customer."name""#), "{actual}");
+
+        // the lowercase identifier is folded to uppercase like Snowflake resolves an unquoted one,
+        // and the case-sensitive identifier is preserved
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_identifier_folding());
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains(r#"customer."NAME""#), "{actual}");
+        assert!(!actual.contains(r#""name""#), "{actual}");
+        assert!(actual.contains(r#"customer."CamelName""#), "{actual}");
+        assert!(actual.contains("customer.c_custkey"), "{actual}");
+        // the literal is kept as it is
+        assert!(actual.contains("'O''Brien'"), "{actual}");
+        Ok(())
+    }
+}
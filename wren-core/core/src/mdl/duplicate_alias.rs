@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Column, DuplicateAliasPolicy, Manifest};
+
+/// Check the columns of each model resolving to the same output name, which would make the
+/// schema of the model ambiguous.
+///
+/// By default, it's an error naming the sources of both columns. With the
+/// [DuplicateAliasPolicy::Suffix] policy, the later columns are renamed with the first free
+/// numeric suffix in the order of the definition, e.g. the second `total` is renamed to
+/// `total_1`. A renamed column without an expression still reads the source column of its
+/// original name.
+pub(crate) fn resolve_duplicate_aliases(mut manifest: Manifest) -> Result<Manifest> {
+    let policy = manifest.duplicate_alias_policy.unwrap_or_default();
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            let mut names = HashSet::new();
+            if model.columns.iter().all(|c| names.insert(c.name())) {
+                return Ok(model);
+            }
+            let mut model = Arc::unwrap_or_clone(model);
+            let mut names: HashSet<String> =
+                model.columns.iter().map(|c| c.name.clone()).collect();
+            let mut resolved: Vec<Arc<Column>> = Vec::with_capacity(model.columns.len());
+            for column in &model.columns {
+                let Some(first) = resolved.iter().find(|c| c.name == column.name) else {
+                    resolved.push(Arc::clone(column));
+                    continue;
+                };
+                if policy == DuplicateAliasPolicy::Error {
+                    return plan_err!(
+                        "The column {}.{} is defined by both {} and {}",
+                        model.name,
+                        column.name,
+                        source(first),
+                        source(column)
+                    );
+                }
+                let suffixed = (1..)
+                    .map(|i| format!("{}_{i}", column.name))
+                    .find(|name| !names.contains(name))
+                    .expect("a free suffix should exist");
+                names.insert(suffixed.clone());
+                let mut renamed = Column::clone(column);
+                renamed.expression =
+                    Some(column.expression().unwrap_or(column.name()).to_string());
+                renamed.name = suffixed;
+                resolved.push(Arc::new(renamed));
+            }
+            model.columns = resolved;
+            Ok(Arc::new(model))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+fn source(column: &Column) -> String {
+    match column.expression() {
+        Some(expression) => format!("`{expression}`"),
+        None => format!("the source column {}", column.name()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{customer, manifest_builder, transform_with_mdl};
+    use crate::mdl::manifest::DuplicateAliasPolicy;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_duplicate_alias_policy() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let manifest = |policy: Option<DuplicateAliasPolicy>| {
+            let builder = manifest_builder().model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .column(
+                        ColumnBuilder::new("label", "varchar")
+                            .calculated(true)
+                            .expression("upper(c_name)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("label", "varchar")
+                            .calculated(true)
+                            .expression("lower(c_name)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            );
+            match policy {
+                Some(policy) => builder.duplicate_alias_policy(policy).build(),
+                None => builder.build(),
+            }
+        };
+
+        // the colliding aliases are rejected by default
+        let Err(err) = AnalyzedWrenMDL::analyze(manifest(None)) else {
+            panic!("expected the colliding aliases to fail");
+        };
+        assert!(err.to_string().contains(
+            "The column customer.label is defined by both `upper(c_name)` and `lower(c_name)`"
+        ));
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(Some(
+            DuplicateAliasPolicy::Suffix,
+        )))?);
+        let sql = "SELECT c_custkey, label, label_1 FROM customer ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+-------+---------+",
+                "| c_custkey | label | label_1 |",
+                "+-----------+-------+---------+",
+                "| 1         | GURA  | gura    |",
+                "| 2         | AZKI  | azki    |",
+                "| 3         | INA   | ina     |",
+                "+-----------+-------+---------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
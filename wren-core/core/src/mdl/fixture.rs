@@ -0,0 +1,144 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+use crate::mdl::manifest::Manifest;
+use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+use datafusion::arrow::array::{
+    ArrayRef, Date32Array, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
+};
+use datafusion::common::Result;
+use datafusion::prelude::SessionContext;
+
+/// Return a manifest builder of the `wren.test` catalog and schema
+pub(crate) fn manifest_builder() -> ManifestBuilder {
+    ManifestBuilder::new().catalog("wren").schema("test")
+}
+
+/// Return a model builder of the customer table with its key and name
+pub(crate) fn customer_model() -> ModelBuilder {
+    ModelBuilder::new("customer")
+        .table_reference("customer")
+        .column(ColumnBuilder::new("c_custkey", "bigint").build())
+        .column(ColumnBuilder::new("c_name", "varchar").build())
+}
+
+/// Return a model builder of the orders table with its key and customer key
+pub(crate) fn orders_model() -> ModelBuilder {
+    ModelBuilder::new("orders")
+        .table_reference("orders")
+        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+        .column(ColumnBuilder::new("o_custkey", "bigint").build())
+}
+
+/// Transform the SQL by the analyzed MDL in a new session
+pub(crate) async fn transform_with_mdl(
+    analyzed_mdl: &Arc<AnalyzedWrenMDL>,
+    sql: &str,
+) -> Result<String> {
+    transform_sql_with_ctx(&SessionContext::new(), Arc::clone(analyzed_mdl), &[], sql)
+        .await
+}
+
+/// Return the manifest of the `tests/data/mdl.json` test data
+pub(crate) fn mdl_json_manifest() -> Manifest {
+    let test_data: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+        .iter()
+        .collect();
+    let mdl_json = fs::read_to_string(test_data.as_path()).unwrap();
+    serde_json::from_str::<Manifest>(&mdl_json).unwrap()
+}
+
+/// Return a RecordBatch with made up data about customer
+pub(crate) fn customer() -> RecordBatch {
+    let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+    let name: ArrayRef = Arc::new(StringArray::from_iter_values(["Gura", "Azki", "Ina"]));
+    RecordBatch::try_from_iter(vec![("c_custkey", custkey), ("c_name", name)]).unwrap()
+}
+
+/// Return a RecordBatch with made up data about profile
+pub(crate) fn profile() -> RecordBatch {
+    let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+    let phone: ArrayRef = Arc::new(StringArray::from_iter_values([
+        "123456", "234567", "345678",
+    ]));
+    let sex: ArrayRef = Arc::new(StringArray::from_iter_values(["M", "M", "F"]));
+    RecordBatch::try_from_iter(vec![
+        ("p_custkey", custkey),
+        ("p_phone", phone),
+        ("p_sex", sex),
+    ])
+    .unwrap()
+}
+
+/// Return a RecordBatch with made up data about orders
+pub(crate) fn orders() -> RecordBatch {
+    let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+    let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+    let totalprice: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
+    RecordBatch::try_from_iter(vec![
+        ("o_orderkey", orderkey),
+        ("o_custkey", custkey),
+        ("o_totalprice", totalprice),
+    ])
+    .unwrap()
+}
+
+/// Return a RecordBatch of the orders placed by the customers, keyed from 1, with the other
+/// columns
+pub(crate) fn orders_of(custkeys: &[i64], columns: Vec<(&str, ArrayRef)>) -> RecordBatch {
+    let orderkey: ArrayRef =
+        Arc::new(Int64Array::from_iter_values(1..=custkeys.len() as i64));
+    let custkey: ArrayRef = Arc::new(Int64Array::from(custkeys.to_vec()));
+    RecordBatch::try_from_iter(
+        [("o_orderkey", orderkey), ("o_custkey", custkey)]
+            .into_iter()
+            .chain(columns),
+    )
+    .unwrap()
+}
+
+pub(crate) fn artist() -> RecordBatch {
+    let name: ArrayRef =
+        Arc::new(StringArray::from_iter_values(["Ina", "Azki", "Kaela"]));
+    let group: ArrayRef = Arc::new(StringArray::from_iter_values(["EN", "JP", "ID"]));
+    let subscribe: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
+    let debut_time: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]));
+    RecordBatch::try_from_iter(vec![
+        ("名字", name),
+        ("組別", group),
+        ("訂閱數", subscribe),
+        ("出道時間", debut_time),
+    ])
+    .unwrap()
+}
+
+/// Return a RecordBatch with made up daily snapshots of account balances
+pub(crate) fn balances() -> RecordBatch {
+    let account: ArrayRef = Arc::new(StringArray::from_iter_values([
+        "A", "A", "A", "B", "B", "B",
+    ]));
+    // 2024-01-15, 2024-01-31, 2024-02-28, 2024-01-31, 2024-02-10, 2024-02-28
+    let snapshot_date: ArrayRef = Arc::new(Date32Array::from(vec![
+        19737, 19753, 19781, 19753, 19763, 19781,
+    ]));
+    let balance: ArrayRef = Arc::new(Int64Array::from(vec![100, 150, 200, 50, 70, 80]));
+    RecordBatch::try_from_iter(vec![
+        ("account", account),
+        ("snapshot_date", snapshot_date),
+        ("balance", balance),
+    ])
+    .unwrap()
+}
+
+pub(crate) fn timestamp_table() -> RecordBatch {
+    let timestamp: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]));
+    let timestamptz: ArrayRef =
+        Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]).with_timezone("UTC"));
+    RecordBatch::try_from_iter(vec![
+        ("timestamp_col", timestamp),
+        ("timestamptz_col", timestamptz),
+    ])
+    .unwrap()
+}
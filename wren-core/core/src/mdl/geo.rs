@@ -0,0 +1,182 @@
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, Expr, FunctionArg, FunctionArgExpr, FunctionArguments,
+};
+use std::ops::ControlFlow;
+
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// The mean radius of the earth in kilometers
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// Expand the geospatial function of the calculated field into plain SQL math, so it's portable
+/// across the backends without any geo extension.
+///
+/// - `haversine(lat1, lon1, lat2, lon2)`: the great-circle distance in kilometers between two
+///   points given in degrees.
+/// - `within_bbox(lat, lon, min_lat, min_lon, max_lat, max_lon)`: true if the point is in the
+///   bounding box.
+///
+/// The other expressions are kept unchanged.
+pub(crate) fn expand_geo_functions(expr: &mut Expr) -> Result<()> {
+    match visit_expressions_mut(expr, |e| match expand_geo_function(e) {
+        Ok(()) => ControlFlow::Continue(()),
+        Err(e) => ControlFlow::Break(e),
+    }) {
+        ControlFlow::Break(e) => Err(e),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+fn expand_geo_function(expr: &mut Expr) -> Result<()> {
+    let Expr::Function(function) = expr else {
+        return Ok(());
+    };
+    let name = function.name.to_string().to_lowercase();
+    let (arity, template): (usize, fn(&[String]) -> String) = match name.as_str() {
+        "haversine" => (4, haversine),
+        "within_bbox" => (6, within_bbox),
+        _ => return Ok(()),
+    };
+    let args = match &function.args {
+        FunctionArguments::List(list) => list
+            .args
+            .iter()
+            .map(|arg| match arg {
+                FunctionArg::Unnamed(FunctionArgExpr::Expr(arg)) => Ok(arg.to_string()),
+                _ => plan_err!("{name} only accepts positional arguments: {arg}"),
+            })
+            .collect::<Result<Vec<_>>>()?,
+        _ => vec![],
+    };
+    if args.len() != arity {
+        return plan_err!(
+            "{name} expects {arity} arguments, but got {}: {function}",
+            args.len()
+        );
+    }
+    *expr = parse_expr_snippet(&template(&args))?;
+    Ok(())
+}
+
+fn haversine(args: &[String]) -> String {
+    let [lat1, lon1, lat2, lon2] = [&args[0], &args[1], &args[2], &args[3]]
+        .map(|degrees| format!("radians({degrees})"));
+    format!(
+        "2 * {EARTH_RADIUS_KM:.1} * asin(sqrt(\
+        power(sin(({lat2} - {lat1}) / 2), 2) + \
+        cos({lat1}) * cos({lat2}) * power(sin(({lon2} - {lon1}) / 2), 2)))"
+    )
+}
+
+fn within_bbox(args: &[String]) -> String {
+    let [lat, lon, min_lat, min_lon, max_lat, max_lon] =
+        [0, 1, 2, 3, 4, 5].map(|i| &args[i]);
+    format!(
+        "({lat} BETWEEN {min_lat} AND {max_lat} AND {lon} BETWEEN {min_lon} AND {max_lon})"
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_geo_distance() -> Result<()> {
+        let ctx = SessionContext::new();
+        let trip_id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let start_lat: ArrayRef = Arc::new(Float64Array::from(vec![51.5074, 40.7128]));
+        let start_lon: ArrayRef = Arc::new(Float64Array::from(vec![-0.1278, -74.0060]));
+        let end_lat: ArrayRef = Arc::new(Float64Array::from(vec![48.8566, 40.7306]));
+        let end_lon: ArrayRef = Arc::new(Float64Array::from(vec![2.3522, -73.9352]));
+        ctx.register_batch(
+            "trip",
+            RecordBatch::try_from_iter(vec![
+                ("trip_id", trip_id),
+                ("start_lat", start_lat),
+                ("start_lon", start_lon),
+                ("end_lat", end_lat),
+                ("end_lon", end_lon),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("trip")
+                    .table_reference("trip")
+                    .column(ColumnBuilder::new("trip_id", "bigint").build())
+                    .column(ColumnBuilder::new("start_lat", "double").build())
+                    .column(ColumnBuilder::new("start_lon", "double").build())
+                    .column(ColumnBuilder::new("end_lat", "double").build())
+                    .column(ColumnBuilder::new("end_lon", "double").build())
+                    .column(
+                        ColumnBuilder::new("distance_km", "double")
+                            .calculated(true)
+                            .expression(
+                                "haversine(start_lat, start_lon, end_lat, end_lon)",
+                            )
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("in_europe", "boolean")
+                            .calculated(true)
+                            .expression("within_bbox(end_lat, end_lon, 35, -10, 70, 40)")
+                            .build(),
+                    )
+                    .primary_key("trip_id")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT trip_id, round(distance_km, 2) AS distance_km, in_europe \
+            FROM trip ORDER BY trip_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("haversine"));
+        assert!(!actual.contains("within_bbox"));
+        assert!(actual.contains("asin("));
+        assert!(actual.contains("radians("));
+
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------+-------------+-----------+",
+                "| trip_id | distance_km | in_europe |",
+                "+---------+-------------+-----------+",
+                "| 1       | 343.56      | true      |",
+                "| 2       | 6.29        | false     |",
+                "+---------+-------------+-----------+",
+            ],
+            &result
+        );
+
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("trip")
+                    .table_reference("trip")
+                    .column(ColumnBuilder::new("trip_id", "bigint").build())
+                    .column(ColumnBuilder::new("start_lat", "double").build())
+                    .column(
+                        ColumnBuilder::new("distance_km", "double")
+                            .calculated(true)
+                            .expression("haversine(start_lat, start_lat)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        match transform_with_mdl(&analyzed_mdl, "SELECT distance_km FROM trip").await {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(e
+                .to_string()
+                .contains("haversine expects 4 arguments, but got 2")),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,447 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{visit_expressions_mut, Expr};
+
+use crate::mdl::manifest::{Column, Manifest, Model, Relationship};
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// Resolve the models extending another model.
+///
+/// The extending model inherits the columns of its base model, followed by the columns it adds.
+/// A column declared by both replaces the inherited one and should keep its type. The source, with
+/// its fallback sources, and the primary key are inherited if the extending model doesn't declare them.
+///
+/// The inherited relationship column gets a copy of its relationship joining the extending model
+/// instead of the base, named `{model}_{relationship}`.
+pub(crate) fn resolve_extensions(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.models.iter().all(|m| m.extends().is_none()) {
+        return Ok(manifest);
+    }
+    let mut resolved = HashMap::new();
+    let mut relationships = manifest.relationships.clone();
+    manifest.models = manifest
+        .models
+        .iter()
+        .map(|model| {
+            resolve_model(
+                &manifest,
+                model,
+                &mut resolved,
+                &mut vec![],
+                &mut relationships,
+            )
+        })
+        .collect::<Result<_>>()?;
+    manifest.relationships = relationships;
+    Ok(manifest)
+}
+
+fn resolve_model(
+    manifest: &Manifest,
+    model: &Arc<Model>,
+    resolved: &mut HashMap<String, Arc<Model>>,
+    extending: &mut Vec<String>,
+    relationships: &mut Vec<Arc<Relationship>>,
+) -> Result<Arc<Model>> {
+    let Some(base_name) = model.extends() else {
+        return Ok(Arc::clone(model));
+    };
+    if let Some(model) = resolved.get(model.name()) {
+        return Ok(Arc::clone(model));
+    }
+    if extending.iter().any(|name| name == model.name()) {
+        return plan_err!(
+            "The model {} extends itself through {}",
+            model.name(),
+            extending.join(" -> ")
+        );
+    }
+    let Some(base) = manifest.models.iter().find(|m| m.name() == base_name) else {
+        return plan_err!("The base model {base_name} of {} isn't found", model.name());
+    };
+    extending.push(model.name().to_string());
+    let base = resolve_model(manifest, base, resolved, extending, relationships)?;
+    extending.pop();
+
+    let mut columns = vec![];
+    for inherited in &base.columns {
+        match model.columns.iter().find(|c| c.name() == inherited.name()) {
+            Some(column) if !column.r#type.eq_ignore_ascii_case(&inherited.r#type) => {
+                return plan_err!(
+                    "The column {}.{} overrides {}.{} of type {} with a different type {}",
+                    model.name(),
+                    column.name(),
+                    base.name(),
+                    inherited.name(),
+                    inherited.r#type,
+                    column.r#type
+                );
+            }
+            Some(column) => columns.push(Arc::clone(column)),
+            None => columns.push(inherit_column(
+                model.name(),
+                &base,
+                inherited,
+                relationships,
+            )?),
+        }
+    }
+    columns.extend(
+        model
+            .columns
+            .iter()
+            .filter(|c| base.get_column(c.name()).is_none())
+            .cloned(),
+    );
+
+    let mut extended = Model::clone(model);
+    extended.columns = columns;
+    if extended.ref_sql.is_none()
+        && extended.base_object.is_none()
+        && extended.table_reference.is_none()
+    {
+        extended.ref_sql.clone_from(&base.ref_sql);
+        extended.base_object.clone_from(&base.base_object);
+        extended.table_reference.clone_from(&base.table_reference);
+        extended.fallback_sources.clone_from(&base.fallback_sources);
+    }
+    if extended.primary_key.is_none() {
+        extended.primary_key.clone_from(&base.primary_key);
+    }
+    extended.extends = None;
+    let extended = Arc::new(extended);
+    resolved.insert(model.name().to_string(), Arc::clone(&extended));
+    Ok(extended)
+}
+
+/// Copy the column of the base model. The relationship column joins the extending model instead.
+fn inherit_column(
+    model_name: &str,
+    base: &Model,
+    column: &Arc<Column>,
+    relationships: &mut Vec<Arc<Relationship>>,
+) -> Result<Arc<Column>> {
+    let Some(relationship_name) = column.relationship.as_ref() else {
+        return Ok(Arc::clone(column));
+    };
+    let Some(relationship) = relationships
+        .iter()
+        .find(|r| &r.name == relationship_name)
+        .cloned()
+    else {
+        return Ok(Arc::clone(column));
+    };
+    if !relationship.models.iter().any(|m| m == base.name()) {
+        return Ok(Arc::clone(column));
+    }
+    let name = format!("{model_name}_{}", relationship.name);
+    if relationships.iter().all(|r| r.name != name) {
+        let mut condition = parse_expr_snippet(&relationship.condition)?;
+        let _ = visit_expressions_mut(&mut condition, |e| {
+            if let Expr::CompoundIdentifier(ids) = e {
+                if let [.., qualifier, _] = ids.as_mut_slice() {
+                    if qualifier.value == base.name() {
+                        qualifier.value = model_name.to_string();
+                    }
+                }
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        let mut inherited = Arc::unwrap_or_clone(relationship);
+        inherited.name.clone_from(&name);
+        inherited.models = inherited
+            .models
+            .iter()
+            .map(|m| {
+                if m == base.name() {
+                    model_name.to_string()
+                } else {
+                    m.clone()
+                }
+            })
+            .collect();
+        inherited.condition = condition.to_string();
+        relationships.push(Arc::new(inherited));
+    }
+    let mut column = Column::clone(column);
+    column.relationship = Some(name);
+    Ok(Arc::new(column))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{
+        ArrayRef, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray,
+    };
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_model_extension() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "event",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "e_id",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "e_type",
+                    Arc::new(StringArray::from(vec!["click", "view", "click"]))
+                        as ArrayRef,
+                ),
+                (
+                    "e_user",
+                    Arc::new(Int64Array::from(vec![1, 2, 2])) as ArrayRef,
+                ),
+                (
+                    "x_pos",
+                    Arc::new(Int64Array::from(vec![10, 20, 30])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        ctx.register_batch("customer", customer())?;
+        let manifest = |e_type: &str| {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("event")
+                        .table_reference("event")
+                        .column(ColumnBuilder::new("e_id", "bigint").build())
+                        .column(ColumnBuilder::new("e_type", "varchar").build())
+                        .column(ColumnBuilder::new("e_user", "bigint").build())
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "user",
+                                "customer",
+                                "event_customer",
+                            )
+                            .build(),
+                        )
+                        .primary_key("e_id")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("click_event")
+                        .extends("event")
+                        .column(ColumnBuilder::new("e_type", e_type).build())
+                        .column(ColumnBuilder::new("x_pos", "bigint").build())
+                        .column(
+                            ColumnBuilder::new("user_name", "varchar")
+                                .calculated(true)
+                                .expression("user.c_name")
+                                .build(),
+                        )
+                        .build(),
+                )
+                .model(customer_model().primary_key("c_custkey").build())
+                .relationship(
+                    RelationshipBuilder::new("event_customer")
+                        .model("event")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("event.e_user = customer.c_custkey")
+                        .build(),
+                )
+                .build()
+        };
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest("varchar"))?);
+        let click_event = analyzed_mdl.wren_mdl().get_model("click_event").unwrap();
+        assert_eq!(
+            click_event
+                .columns
+                .iter()
+                .map(|c| c.name())
+                .collect::<Vec<_>>(),
+            ["e_id", "e_type", "e_user", "user", "x_pos", "user_name"]
+        );
+        assert_eq!(click_event.table_reference(), "event");
+        assert_eq!(click_event.primary_key(), Some("e_id"));
+
+        let sql = "SELECT e_id, e_type, x_pos, user_name FROM click_event \
+            WHERE e_type = 'click' ORDER BY e_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------+--------+-------+-----------+",
+                "| e_id | e_type | x_pos | user_name |",
+                "+------+--------+-------+-----------+",
+                "| 1    | click  | 10    | Gura      |",
+                "| 3    | click  | 30    | Azki      |",
+                "+------+--------+-------+-----------+",
+            ],
+            &result
+        );
+
+        // the extending model can't override the type of the inherited column
+        match AnalyzedWrenMDL::analyze(manifest("integer")) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(
+                e.to_string().contains(
+                    "The column click_event.e_type overrides event.e_type of type varchar \
+                    with a different type integer"
+                ),
+                "{e}"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fallback_sources() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orders = |keys: Vec<i64>, prices: Vec<f64>| {
+            let orderkey: ArrayRef = Arc::new(Int64Array::from(keys));
+            let totalprice: ArrayRef = Arc::new(Float64Array::from(prices));
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_totalprice", totalprice),
+            ])
+        };
+        ctx.register_batch("orders", orders(vec![1, 2], vec![100.0, 200.0])?)?;
+        ctx.register_batch("orders_backup", orders(vec![2, 3], vec![999.0, 300.0])?)?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .fallback_sources(&["orders_backup"])
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey, o_totalprice FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("orders_backup"), "{actual}");
+        // the order 2 of the backup is shadowed by the primary source
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+--------------+",
+                "| o_orderkey | o_totalprice |",
+                "+------------+--------------+",
+                "| 1          | 100.0        |",
+                "| 2          | 200.0        |",
+                "| 3          | 300.0        |",
+                "+------------+--------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_fallback_sources_super_types() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                ("o_quantity", Arc::new(Int64Array::from(vec![10, 20]))),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "orders_backup",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![2, 3])) as ArrayRef,
+                ),
+                ("o_quantity", Arc::new(Int32Array::from(vec![99, 30]))),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "orders_archive",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![4])) as ArrayRef,
+                ),
+                (
+                    "o_quantity",
+                    Arc::new(StringArray::from_iter_values(["forty"])),
+                ),
+            ])?,
+        )?;
+        let mut registers = HashMap::new();
+        for name in ["orders", "orders_backup", "orders_archive"] {
+            let provider = ctx
+                .catalog("datafusion")
+                .unwrap()
+                .schema("public")
+                .unwrap()
+                .table(name)
+                .await?
+                .unwrap();
+            registers.insert(format!("datafusion.public.{name}"), provider);
+        }
+        let manifest = |fallback: &str| {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("datafusion.public.orders")
+                        .fallback_sources(&[fallback])
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_quantity", "bigint").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT o_orderkey, o_quantity FROM orders ORDER BY o_orderkey";
+
+        // the INT column of the backup is widened to BIGINT
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_tables(
+            manifest("datafusion.public.orders_backup"),
+            registers.clone(),
+        )?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("o_quantity AS BIGINT)"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+------------+",
+                "| o_orderkey | o_quantity |",
+                "+------------+------------+",
+                "| 1          | 10         |",
+                "| 2          | 20         |",
+                "| 3          | 30         |",
+                "+------------+------------+",
+            ],
+            &result
+        );
+
+        // the text column of the archive can't be unioned with a number
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze_with_tables(
+            manifest("datafusion.public.orders_archive"),
+            registers,
+        )?);
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "Column o_quantity of model orders can't be unioned from the types Int64 and Utf8"
+            ),
+            "{err}"
+        );
+        Ok(())
+    }
+}
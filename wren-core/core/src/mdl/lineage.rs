@@ -4,15 +4,18 @@ use std::sync::Arc;
 
 use datafusion::common::{internal_err, plan_err, Column};
 use datafusion::error::Result;
+use datafusion::sql::sqlparser::ast;
 use datafusion::sql::TableReference;
 use petgraph::Graph;
 
 use crate::logical_plan::utils::from_qualified_name;
+use crate::mdl::snippet::parse_expr_snippet;
 use crate::mdl::{utils, WrenMDL};
 
-use super::manifest::{JoinType, Relationship};
+use super::manifest::{AsOf, JoinHint, JoinType, Lookup, Relationship};
 use super::utils::{
-    collect_identifiers, qualify_name_from_column_name, quoted, to_expr_queue,
+    collect_identifiers, qualify_name_from_column_name, quoted, quoted_ident,
+    self_join_alias, self_join_column_name, to_expr_queue,
 };
 use crate::mdl::Dataset;
 
@@ -116,13 +119,21 @@ impl Lineage {
 
             let mut directed_graph: Graph<Dataset, DatasetLink> = Graph::new();
             let mut node_index_map = HashMap::new();
-            let mut left_vertex = *node_index_map
+            let mut self_vertex_map = HashMap::new();
+            let root_vertex = *node_index_map
                 .entry(column_ref.dataset.clone())
                 .or_insert_with(|| directed_graph.add_node(column_ref.dataset.clone()));
 
-            for source_column in source_columns.iter() {
+            // build the graph in the declared order for the reproducible join order
+            let mut source_columns = source_columns.iter().collect::<Vec<_>>();
+            source_columns.sort_by_cached_key(|c| declared_order(&column_ref.dataset, c));
+            for source_column in source_columns {
+                // every source column is reached from the calculated field's own dataset
+                let mut left_vertex = root_vertex;
                 let mut expr_parts = to_expr_queue(source_column.clone());
                 let mut relation_ref = current_relation.clone();
+                // the alias of the self relationship instance the chain is at
+                let mut self_alias: Option<String> = None;
                 while !expr_parts.is_empty() {
                     let ident = expr_parts.pop_front().unwrap();
                     let Some(source_column_ref) = mdl.get_column_reference(&Column::new(
@@ -137,12 +148,17 @@ impl Lineage {
                                 source_column_ref.column.relationship.clone()
                             {
                                 if let Some(rs_rf) = mdl.get_relationship(&rs) {
+                                    // the related model of a self relationship is the model itself
                                     let related_model_name = rs_rf
                                         .models
                                         .iter()
                                         .find(|m| m != &relation_ref.table())
                                         .cloned()
-                                        .unwrap();
+                                        .unwrap_or_else(|| {
+                                            relation_ref.table().to_string()
+                                        });
+                                    let is_self_relationship =
+                                        related_model_name == relation_ref.table();
                                     if related_model_name
                                         != source_column_ref.column.r#type
                                     {
@@ -152,42 +168,119 @@ impl Lineage {
                                         );
                                     }
 
-                                    collect_identifiers(&rs_rf.condition)?
-                                        .iter()
-                                        .cloned()
-                                        .for_each(|ident| {
-                                            required_fields_map
-                                                .entry(column.clone())
-                                                .or_default()
-                                                .insert(Column::from_qualified_name(
-                                                    format!(
-                                                        "{}.{}.{}",
-                                                        quoted(mdl.catalog()),
-                                                        quoted(mdl.schema()),
-                                                        qualify_name_from_column_name(
-                                                            &ident
+                                    // the scope of the relationship filters the joined rows
+                                    // by the columns of both sides
+                                    let mut identifiers =
+                                        collect_identifiers(&rs_rf.condition)?;
+                                    if let Some(scope) = rs_rf.scope() {
+                                        identifiers.extend(collect_identifiers(scope)?);
+                                    }
+                                    identifiers.iter().cloned().for_each(|ident| {
+                                        required_fields_map
+                                            .entry(column.clone())
+                                            .or_default()
+                                            .insert(Column::from_qualified_name(
+                                                format!(
+                                                    "{}.{}.{}",
+                                                    quoted(mdl.catalog()),
+                                                    quoted(mdl.schema()),
+                                                    qualify_name_from_column_name(&ident)
+                                                ),
+                                            ));
+                                    });
+                                    // the lookup picks the related row by the order column, and
+                                    // the as-of join picks it by the time columns of both sides
+                                    let picked_by = rs_rf
+                                        .lookup()
+                                        .map(|lookup| vec![&lookup.order_by])
+                                        .or_else(|| {
+                                            rs_rf.as_of().map(|as_of| {
+                                                vec![
+                                                    &as_of.dimension_time,
+                                                    &as_of.fact_time,
+                                                ]
+                                            })
+                                        })
+                                        .unwrap_or_default();
+                                    for order_by in picked_by {
+                                        required_fields_map
+                                            .entry(column.clone())
+                                            .or_default()
+                                            .insert(Column::from_qualified_name(
+                                                format!(
+                                                    "{}.{}.{}",
+                                                    quoted(mdl.catalog()),
+                                                    quoted(mdl.schema()),
+                                                    qualify_name_from_column_name(
+                                                        &Column::new_unqualified(
+                                                            order_by
                                                         )
-                                                    ),
-                                                ));
-                                        });
+                                                    )
+                                                ),
+                                            ));
+                                    }
 
                                     let related_model =
                                         mdl.get_model(&related_model_name).unwrap();
 
-                                    let right_vertex = *node_index_map
-                                        .entry(Dataset::Model(Arc::clone(&related_model)))
-                                        .or_insert_with(|| {
-                                            directed_graph.add_node(Dataset::Model(
-                                                Arc::clone(&related_model),
-                                            ))
-                                        });
-                                    directed_graph.add_edge(
+                                    let owner_alias = self_alias.take();
+                                    let (right_vertex, link) = if is_self_relationship {
+                                        // The self relationship joins another instance of the model.
+                                        // Each hop is aliased by its path of the relationship columns
+                                        // to avoid the collision, e.g. `manager__manager`.
+                                        let alias = self_join_alias(
+                                            owner_alias.as_deref(),
+                                            source_column_ref.column.name(),
+                                        );
+                                        let link = DatasetLink::new(
+                                            rs_rf.name(),
+                                            rs_rf.join_type,
+                                            self_join_condition(
+                                                &rs_rf.condition,
+                                                rs_rf.join_type,
+                                                related_model.primary_key(),
+                                                owner_alias.as_deref(),
+                                                &alias,
+                                            )?,
+                                        )
+                                        .with_alias(&alias)
+                                        .with_join_hint(rs_rf.join_hint)
+                                        .with_distinct(rs_rf.distinct);
+                                        let vertex = *self_vertex_map
+                                            .entry((left_vertex, alias.clone()))
+                                            .or_insert_with(|| {
+                                                directed_graph.add_node(Dataset::Model(
+                                                    Arc::clone(&related_model),
+                                                ))
+                                            });
+                                        self_alias = Some(alias);
+                                        (vertex, link)
+                                    } else {
+                                        (
+                                            *node_index_map
+                                                .entry(Dataset::Model(Arc::clone(
+                                                    &related_model,
+                                                )))
+                                                .or_insert_with(|| {
+                                                    directed_graph.add_node(
+                                                        Dataset::Model(Arc::clone(
+                                                            &related_model,
+                                                        )),
+                                                    )
+                                                }),
+                                            get_dataset_link_revers_if_need(
+                                                source_column_ref.dataset.clone(),
+                                                rs_rf,
+                                            )
+                                            .with_single_row(
+                                                related_model.is_single_row(),
+                                            ),
+                                        )
+                                    };
+                                    directed_graph.update_edge(
                                         left_vertex,
                                         right_vertex,
-                                        get_dataset_link_revers_if_need(
-                                            source_column_ref.dataset.clone(),
-                                            rs_rf,
-                                        ),
+                                        link,
                                     );
 
                                     relation_ref = TableReference::full(
@@ -252,6 +345,19 @@ impl Lineage {
     }
 }
 
+/// The order of the source column is the position of its first part in the dataset, then its name.
+fn declared_order(dataset: &Dataset, source_column: &Column) -> (usize, String) {
+    let position = match (dataset, to_expr_queue(source_column.clone()).front()) {
+        (Dataset::Model(model), Some(first)) => model
+            .columns
+            .iter()
+            .position(|c| c.name() == first)
+            .unwrap_or(usize::MAX),
+        _ => usize::MAX,
+    };
+    (position, source_column.flat_name())
+}
+
 fn consume_pending_field(
     mdl: &WrenMDL,
     required_fields_map: &mut HashMap<Column, HashSet<Column>>,
@@ -284,16 +390,88 @@ struct RequiredInfo {
 
 #[derive(PartialEq, Eq, Hash, Debug, Clone)]
 pub struct DatasetLink {
+    /// The name of the relationship linking the datasets
+    pub relationship: String,
     pub join_type: JoinType,
     pub condition: String,
+    /// The alias of the joined model if it's joined by a self relationship
+    pub alias: Option<String>,
+    /// Pick one row of the joined model for each row of the other model
+    pub lookup: Option<Lookup>,
+    /// The distribution hint of the join with the joined model
+    pub join_hint: Option<JoinHint>,
+    /// De-duplicate the rows fanned out by the join instead of aggregating them
+    pub distinct: bool,
+    /// Pick the latest row of the joined model at or before the time of the other model
+    pub as_of: Option<AsOf>,
+    /// The predicate scoping the joined rows, bound by the parameters of the query context
+    pub scope: Option<String>,
+    /// Match the NULL keys of both sides by `IS NOT DISTINCT FROM`
+    pub null_safe: bool,
 }
 
 impl DatasetLink {
-    fn new(join_type: JoinType, condition: String) -> Self {
+    fn new(relationship: &str, join_type: JoinType, condition: String) -> Self {
         DatasetLink {
+            relationship: relationship.to_string(),
             join_type,
             condition,
+            alias: None,
+            lookup: None,
+            join_hint: None,
+            distinct: false,
+            as_of: None,
+            scope: None,
+            null_safe: false,
+        }
+    }
+
+    fn with_lookup(mut self, lookup: Option<Lookup>) -> Self {
+        self.lookup = lookup;
+        self
+    }
+
+    fn with_join_hint(mut self, join_hint: Option<JoinHint>) -> Self {
+        self.join_hint = join_hint;
+        self
+    }
+
+    fn with_as_of(mut self, as_of: Option<AsOf>) -> Self {
+        self.as_of = as_of;
+        self
+    }
+
+    fn with_scope(mut self, scope: Option<String>) -> Self {
+        self.scope = scope;
+        self
+    }
+
+    fn with_null_safe(mut self, null_safe: bool) -> Self {
+        self.null_safe = null_safe;
+        self
+    }
+
+    fn with_distinct(mut self, distinct: bool) -> Self {
+        self.distinct = distinct;
+        self
+    }
+
+    fn with_alias(mut self, alias: &str) -> Self {
+        self.alias = Some(alias.to_string());
+        self
+    }
+
+    /// The joined single-row model matches at most one row, so the join never fans out the rows
+    /// of the other model whatever the declared join type is.
+    fn with_single_row(mut self, single_row: bool) -> Self {
+        if single_row {
+            self.join_type = match self.join_type {
+                JoinType::OneToMany => JoinType::OneToOne,
+                JoinType::ManyToMany => JoinType::ManyToOne,
+                join_type => join_type,
+            };
         }
+        self
     }
 }
 
@@ -303,6 +481,53 @@ impl Display for DatasetLink {
     }
 }
 
+/// Rewrite the condition of a self relationship for the joined instance of the model.
+///
+/// Both sides of the condition are qualified by the model, so the side of the joined instance is
+/// resolved by the primary key: the many-to-one relationship joins the row the key refers to, and
+/// the one-to-many relationship joins the rows referring to the key. Otherwise, the right side is
+/// the joined one. e.g. `employees.manager_id = employees.id` with the alias `manager` will be
+/// `employees.manager_id = manager.id`.
+///
+/// If the owning side is the instance of the previous hop, its column is read by the name the
+/// relation chain outputs, e.g. `employees.manager__manager_id = manager__manager.id`.
+fn self_join_condition(
+    condition: &str,
+    join_type: JoinType,
+    primary_key: Option<&str>,
+    owner_alias: Option<&str>,
+    alias: &str,
+) -> Result<String> {
+    let mut expr = parse_expr_snippet(condition)?;
+    let ast::Expr::BinaryOp { left, right, .. } = &mut expr else {
+        return plan_err!("Invalid self relationship condition: {}", condition);
+    };
+    let (ast::Expr::CompoundIdentifier(left), ast::Expr::CompoundIdentifier(right)) =
+        (left.as_mut(), right.as_mut())
+    else {
+        return plan_err!("Invalid self relationship condition: {}", condition);
+    };
+    if left.len() != 2 || right.len() != 2 {
+        return plan_err!("Invalid self relationship condition: {}", condition);
+    }
+    let is_key = |ids: &[ast::Ident]| primary_key.is_some_and(|key| ids[1].value == key);
+    let joined_on_left = match join_type {
+        JoinType::ManyToOne => is_key(left) && !is_key(right),
+        JoinType::OneToMany => is_key(right) && !is_key(left),
+        _ => false,
+    };
+    let (owning, joined) = if joined_on_left {
+        (right, left)
+    } else {
+        (left, right)
+    };
+    joined[0] = quoted_ident(alias);
+    if let Some(owner_alias) = owner_alias {
+        owning[1] = quoted_ident(&self_join_column_name(owner_alias, &owning[1].value));
+    }
+    Ok(expr.to_string())
+}
+
 fn get_dataset_link_revers_if_need(
     source: Dataset,
     rs: Arc<Relationship>,
@@ -316,7 +541,13 @@ fn get_dataset_link_revers_if_need(
             _ => rs.join_type,
         }
     };
-    DatasetLink::new(join_type, rs.condition.clone())
+    DatasetLink::new(rs.name(), join_type, rs.condition.clone())
+        .with_lookup(rs.lookup().cloned())
+        .with_join_hint(rs.join_hint)
+        .with_distinct(rs.distinct)
+        .with_as_of(rs.as_of().cloned())
+        .with_scope(rs.scope().map(String::from))
+        .with_null_safe(rs.null_safe)
 }
 
 #[cfg(test)]
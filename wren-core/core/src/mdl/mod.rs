@@ -1,3 +1,5 @@
+use crate::logical_plan::analyze::model_generation::ModelGenerationRule;
+use crate::logical_plan::analyze::plan::ModelPlanNode;
 use crate::logical_plan::utils::{from_qualified_name_str, map_data_type};
 use crate::mdl::builder::ManifestBuilder;
 use crate::mdl::context::{create_ctx_with_mdl, WrenDataSource};
@@ -6,17 +8,21 @@ use crate::mdl::function::{
     ByPassAggregateUDF, ByPassScalarUDF, ByPassWindowFunction, FunctionType,
     RemoteFunction,
 };
-use crate::mdl::manifest::{Column, Manifest, Metric, Model, View};
+use crate::mdl::manifest::{Column, Constant, Manifest, Metric, Model, View};
+use crate::mdl::query_context::QueryContext;
 use crate::mdl::utils::to_field;
-use crate::DataFusionError;
+use crate::{DataFusionError, WrenError};
 use datafusion::arrow::datatypes::Field;
-use datafusion::common::internal_datafusion_err;
+use datafusion::common::{internal_datafusion_err, plan_err, DFSchema, SchemaError};
 use datafusion::datasource::TableProvider;
 use datafusion::error::Result;
 use datafusion::execution::context::SessionState;
-use datafusion::logical_expr::{AggregateUDF, ScalarUDF, WindowUDF};
-use datafusion::prelude::SessionContext;
-use datafusion::sql::parser::DFParser;
+use datafusion::execution::SendableRecordBatchStream;
+use datafusion::logical_expr::{
+    ident, AggregateUDF, Extension, LogicalPlan, ScalarUDF, WindowUDF,
+};
+use datafusion::prelude::{DataFrame, SessionContext};
+use datafusion::sql::parser::{DFParser, Statement as DFStatement};
 use datafusion::sql::sqlparser::ast::{Expr, ExprWithAlias, Ident};
 use datafusion::sql::sqlparser::dialect::dialect_from_str;
 use datafusion::sql::unparser::Unparser;
@@ -25,28 +31,82 @@ pub use dataset::Dataset;
 use log::{debug, info};
 use manifest::Relationship;
 use parking_lot::RwLock;
+use std::collections::{BTreeSet, HashSet};
 use std::hash::Hash;
 use std::{collections::HashMap, sync::Arc};
-use wren_core_base::mdl::DataSource;
+use wren_core_base::mdl::{DataSource, DefaultAggregation, DuplicateAliasPolicy};
 
 pub mod builder {
     pub use wren_core_base::mdl::builder::*;
 }
+pub mod cache;
+mod comment;
+mod conditional_count;
+mod constant;
 pub mod context;
+mod cross_metric;
+mod cte;
+mod curated_view;
 pub(crate) mod dataset;
 mod dialect;
+mod duplicate_alias;
+#[cfg(test)]
+pub(crate) mod fixture;
 pub mod function;
+mod geo;
+mod inheritance;
 pub mod lineage;
 pub mod manifest {
     pub use wren_core_base::mdl::manifest::*;
 }
+mod multi_grain;
+mod namespace;
+mod period_over_period;
+mod pivot;
+pub mod query_context;
+pub mod refresh;
+mod relationship_path;
+pub mod rounding;
+pub(crate) mod snippet;
+pub mod statistics;
+mod synonym;
+mod table_function;
+pub mod table_source;
+pub mod type_override;
+pub mod unused;
 pub mod utils;
+mod weighted_average;
+pub mod writeback;
 
 pub type SessionStateRef = Arc<RwLock<SessionState>>;
 
 pub struct AnalyzedWrenMDL {
     pub wren_mdl: Arc<WrenMDL>,
     pub lineage: Arc<lineage::Lineage>,
+    statistics: Arc<statistics::StatisticsStore>,
+    options: AnalyzeOptions,
+}
+
+/// The options of planning the queries against the [AnalyzedWrenMDL], set by its `with_*`
+/// builders
+#[derive(Default)]
+pub(crate) struct AnalyzeOptions {
+    pub(crate) model_cache: Option<Arc<cache::ModelCache>>,
+    pub(crate) max_relationship_depth: Option<usize>,
+    pub(crate) grain_check: bool,
+    pub(crate) non_additive_check: bool,
+    pub(crate) strict_join: bool,
+    pub(crate) cte_output: bool,
+    pub(crate) strict_functions: bool,
+    pub(crate) lineage_comment: bool,
+    pub(crate) max_result_width: Option<usize>,
+    pub(crate) minimal_quoting: bool,
+    pub(crate) identifier_folding: bool,
+    pub(crate) row_count_validation: bool,
+    pub(crate) constant_folding: bool,
+    pub(crate) projection_folding: bool,
+    pub(crate) scalar_udfs: Vec<Arc<ScalarUDF>>,
+    pub(crate) rounding_policy: Option<rounding::RoundingPolicy>,
 }
 
 impl Hash for AnalyzedWrenMDL {
@@ -58,37 +118,166 @@ impl Hash for AnalyzedWrenMDL {
 impl Default for AnalyzedWrenMDL {
     fn default() -> Self {
         let manifest = ManifestBuilder::default().build();
-        let wren_mdl = WrenMDL::new(manifest);
-        let lineage = lineage::Lineage::new(&wren_mdl).unwrap();
-        AnalyzedWrenMDL {
-            wren_mdl: Arc::new(wren_mdl),
-            lineage: Arc::new(lineage),
-        }
+        Self::new(WrenMDL::new(manifest)).unwrap()
     }
 }
 
 impl AnalyzedWrenMDL {
     pub fn analyze(manifest: Manifest) -> Result<Self> {
-        let wren_mdl = Arc::new(WrenMDL::infer_and_register_remote_table(manifest)?);
-        let lineage = Arc::new(lineage::Lineage::new(&wren_mdl)?);
-        Ok(AnalyzedWrenMDL { wren_mdl, lineage })
+        let manifest = Self::resolve_manifest(manifest)?;
+        Self::new(WrenMDL::infer_and_register_remote_table(manifest)?)
     }
 
     pub fn analyze_with_tables(
         manifest: Manifest,
         register_tables: HashMap<String, Arc<dyn TableProvider>>,
     ) -> Result<Self> {
-        let mut wren_mdl = WrenMDL::new(manifest);
+        let mut wren_mdl = WrenMDL::new(Self::resolve_manifest(manifest)?);
         for (name, table) in register_tables {
             wren_mdl.register_table(name, table);
         }
+        Self::new(wren_mdl)
+    }
+
+    fn new(wren_mdl: WrenMDL) -> Result<Self> {
         let lineage = lineage::Lineage::new(&wren_mdl)?;
         Ok(AnalyzedWrenMDL {
             wren_mdl: Arc::new(wren_mdl),
             lineage: Arc::new(lineage),
+            statistics: Default::default(),
+            options: AnalyzeOptions::default(),
         })
     }
 
+    /// Validate the snippets of the manifest, then resolve and compile its definitions into the
+    /// plain models and views
+    fn resolve_manifest(manifest: Manifest) -> Result<Manifest> {
+        let manifest = snippet::validate_snippets(manifest)?;
+        let manifest = namespace::resolve_namespaces(manifest)?;
+        let manifest = inheritance::resolve_extensions(manifest)?;
+        let manifest = table_function::resolve_table_functions(manifest)?;
+        let manifest = duplicate_alias::resolve_duplicate_aliases(manifest)?;
+        let manifest = relationship_path::resolve_relationship_paths(manifest)?;
+        let manifest = synonym::resolve_synonyms(manifest)?;
+        let manifest = weighted_average::compile_weighted_averages(manifest)?;
+        let manifest = conditional_count::compile_conditional_counts(manifest)?;
+        let manifest = pivot::expand_pivots(manifest)?;
+        let manifest = constant::resolve_constants(manifest)?;
+        let manifest = cross_metric::compile_cross_metrics(manifest)?;
+        let manifest = curated_view::compile_curated_views(manifest)?;
+        let manifest = multi_grain::compile_multi_grain_measures(manifest)?;
+        period_over_period::compile_period_over_periods(manifest)
+    }
+
+    /// Enable the in-memory cache of the model relations for the local runtime
+    pub fn with_model_cache(mut self, model_cache: Arc<cache::ModelCache>) -> Self {
+        self.options.model_cache = Some(model_cache);
+        self
+    }
+
+    /// Limit how many relationships can be traversed to expand a relation chain
+    pub fn with_max_relationship_depth(mut self, max_depth: usize) -> Self {
+        self.options.max_relationship_depth = Some(max_depth);
+        self
+    }
+
+    /// Reject the aggregations grouping a measure by a dimension of another model whose
+    /// relationship path would duplicate the rows of the measure
+    pub fn with_grain_check(mut self) -> Self {
+        self.options.grain_check = true;
+        self
+    }
+
+    /// Reject the aggregations adding up a non-additive measure across the rows duplicated by the
+    /// relationship path of a dimension
+    pub fn with_non_additive_check(mut self) -> Self {
+        self.options.non_additive_check = true;
+        self
+    }
+
+    /// Reject the cross joins generated by the relation chains. It's usually caused by a missing
+    /// or misconfigured relationship. The cross joins requested by the query are still allowed.
+    pub fn with_strict_join(mut self) -> Self {
+        self.options.strict_join = true;
+        self
+    }
+
+    /// Factor the model expansions of the generated SQL into the CTEs of a `WITH` clause instead
+    /// of the nested subqueries
+    pub fn with_cte_output(mut self) -> Self {
+        self.options.cte_output = true;
+        self
+    }
+
+    /// Reject the calculated fields calling a function that isn't registered in the session when
+    /// they're planned, instead of failing later with a less descriptive error
+    pub fn with_strict_functions(mut self) -> Self {
+        self.options.strict_functions = true;
+        self
+    }
+
+    /// Lead the generated SQL with a comment listing the models and the measures of the query, so
+    /// the queries issued to the data source can be traced back
+    pub fn with_lineage_comment(mut self) -> Self {
+        self.options.lineage_comment = true;
+        self
+    }
+
+    /// Limit how many columns the generated plan can output, e.g. a `SELECT *` over many models
+    pub fn with_max_result_width(mut self, max_columns: usize) -> Self {
+        self.options.max_result_width = Some(max_columns);
+        self
+    }
+
+    /// Quote only the identifiers of the generated SQL that are reserved by the data source or
+    /// contain special characters, instead of all the keywords, e.g. `name` is left bare
+    pub fn with_minimal_quoting(mut self) -> Self {
+        self.options.minimal_quoting = true;
+        self
+    }
+
+    /// Fold the quoted lowercase identifiers of the generated SQL like the data source folds the
+    /// unquoted ones, e.g. `"name"` is generated as `"NAME"` for Snowflake
+    pub fn with_identifier_folding(mut self) -> Self {
+        self.options.identifier_folding = true;
+        self
+    }
+
+    /// Count the rows of each group of the calculations into the hidden column
+    /// `__wren_row_count` alongside the measures, e.g. to assert the expected counts downstream
+    pub fn with_row_count_validation(mut self) -> Self {
+        self.options.row_count_validation = true;
+        self
+    }
+
+    /// Fold the projected expressions without any column into the literals of the same types,
+    /// e.g. a tenant literal computed by a column expression is generated as the literal itself
+    pub fn with_constant_folding(mut self) -> Self {
+        self.options.constant_folding = true;
+        self
+    }
+
+    /// Fold the redundant projections stacked by the model expansion, e.g. the projection of a
+    /// partial model re-selecting the columns of its model plan
+    pub fn with_projection_folding(mut self) -> Self {
+        self.options.projection_folding = true;
+        self
+    }
+
+    /// Round the calculated fields declared as decimals by the policy, e.g. the banker's rounding
+    /// of the financial calculations, instead of the default rounding of the data source
+    pub fn with_rounding_policy(mut self, policy: rounding::RoundingPolicy) -> Self {
+        self.options.rounding_policy = Some(policy);
+        self
+    }
+
+    /// Register the custom scalar function, e.g. a currency converter of the data source, so the
+    /// calculated fields calling it can be resolved. The call is generated by the function name.
+    pub fn with_scalar_udf(mut self, udf: ScalarUDF) -> Self {
+        self.options.scalar_udfs.push(Arc::new(udf));
+        self
+    }
+
     pub fn wren_mdl(&self) -> Arc<WrenMDL> {
         Arc::clone(&self.wren_mdl)
     }
@@ -96,6 +285,115 @@ impl AnalyzedWrenMDL {
     pub fn lineage(&self) -> &lineage::Lineage {
         &self.lineage
     }
+
+    pub fn model_cache(&self) -> Option<Arc<cache::ModelCache>> {
+        self.options.model_cache.as_ref().map(Arc::clone)
+    }
+
+    /// Return the custom scalar functions registered by [Self::with_scalar_udf]
+    pub fn scalar_udfs(&self) -> &[Arc<ScalarUDF>] {
+        &self.options.scalar_udfs
+    }
+
+    /// Return the statistics of the models, e.g. collected by [statistics::collect_statistics]
+    pub fn statistics(&self) -> &statistics::StatisticsStore {
+        &self.statistics
+    }
+
+    pub(crate) fn options(&self) -> &AnalyzeOptions {
+        &self.options
+    }
+
+    /// Return the output schema of the model, resolved without a query.
+    ///
+    /// The plan of the model is generated for all the visible columns except the relationship
+    /// columns, so the schema covers the types of the calculated fields, e.g. for the tools
+    /// inspecting the model. The plan isn't executed.
+    pub fn model_schema(self: &Arc<Self>, name: &str) -> Result<DFSchema> {
+        let Some(model) = self.wren_mdl.get_model(name) else {
+            return plan_err!("Model {} not found", name);
+        };
+        let required_fields = model
+            .get_physical_columns()
+            .iter()
+            .map(|column| ident(column.name()))
+            .collect();
+        let ctx = SessionContext::new();
+        for udf in self.scalar_udfs() {
+            ctx.register_udf(udf.as_ref().clone());
+        }
+        let model_plan = ModelPlanNode::new(
+            model,
+            required_fields,
+            None,
+            Arc::clone(self),
+            ctx.state_ref(),
+        )?;
+        let rule = ModelGenerationRule::new(
+            Arc::clone(self),
+            ctx.state_ref(),
+            Arc::new(QueryContext::new()),
+        );
+        let plan = rule
+            .generate_model_internal(LogicalPlan::Extension(Extension {
+                node: Arc::new(model_plan),
+            }))?
+            .data;
+        Ok(plan.schema().as_ref().clone())
+    }
+
+    /// Return the model columns depending on the physical column of the table, sorted by the
+    /// model and column names.
+    ///
+    /// The columns of the models reading the table directly are included first. Then the calculated
+    /// fields requiring any of them are included. It covers the fields of the related models and
+    /// the join keys of the relationships traversed by the calculated fields.
+    pub fn dependents(
+        &self,
+        table_ref: &str,
+        column: &str,
+    ) -> Result<Vec<(String, String)>> {
+        let mut direct = HashSet::new();
+        for model in self.wren_mdl.models() {
+            if model.table_reference.as_deref() != Some(table_ref) {
+                continue;
+            }
+            for model_column in model.get_physical_columns() {
+                if model_column.is_calculated {
+                    continue;
+                }
+                let reads_column = match model_column.expression() {
+                    Some(expression) => utils::collect_identifiers(expression)?
+                        .iter()
+                        .any(|identifier| identifier.name == column),
+                    None => model_column.name() == column,
+                };
+                if reads_column {
+                    direct.insert((
+                        model.name().to_string(),
+                        model_column.name().to_string(),
+                    ));
+                }
+            }
+        }
+
+        let mut dependents = direct.clone().into_iter().collect::<BTreeSet<_>>();
+        for (calculated_field, required_fields) in self.lineage.required_fields_map.iter()
+        {
+            let depends = required_fields.iter().any(|field| {
+                field.relation.as_ref().is_some_and(|relation| {
+                    direct.contains(&(relation.table().to_string(), field.name.clone()))
+                })
+            });
+            if let (true, Some(relation)) = (depends, &calculated_field.relation) {
+                dependents.insert((
+                    relation.table().to_string(),
+                    calculated_field.name.clone(),
+                ));
+            }
+        }
+        Ok(dependents.into_iter().collect())
+    }
 }
 
 pub type RegisterTables = HashMap<String, Arc<dyn TableProvider>>;
@@ -202,14 +500,18 @@ impl WrenMDL {
 
     /// Infer the source column from the column expression.
     ///
-    /// If the column is calculated or has a relationship, it's not a source column.
+    /// If the column is calculated, has a relationship or is bucketed, it's not a source column.
     /// If the column without expression, it's a source column.
     /// If the column has an expression, it will try to infer the source column from the expression.
     /// If the expression is a simple column reference, it's the source column name.
     /// If the expression is a complex expression, it can't be inferred.
     ///
     fn infer_source_column(column: &Column) -> Result<Option<Field>> {
-        if column.is_calculated || column.relationship.is_some() {
+        if column.is_calculated
+            || column.relationship.is_some()
+            || column.bucket.is_some()
+            || column.parent_scope.is_some()
+        {
             return Ok(None);
         }
 
@@ -220,7 +522,7 @@ impl WrenMDL {
                 Ok(Some(Field::new(
                     alias.map(|a| a.value).unwrap_or_else(|| name.value.clone()),
                     map_data_type(&column.r#type)?,
-                    column.not_null,
+                    !column.not_null,
                 )))
             } else {
                 Ok(None)
@@ -291,11 +593,47 @@ impl WrenMDL {
         self.manifest.data_source
     }
 
+    /// Return the schema of the table references of the models without a schema
+    pub fn default_table_schema(&self) -> Option<&str> {
+        self.manifest.default_table_schema.as_deref()
+    }
+
+    /// Return the namespace of the models referred to without a namespace
+    pub fn default_namespace(&self) -> Option<&str> {
+        self.manifest.default_namespace.as_deref()
+    }
+
+    /// Return how the columns of a model resolving to the same name are disambiguated
+    pub fn duplicate_alias_policy(&self) -> Option<DuplicateAliasPolicy> {
+        self.manifest.duplicate_alias_policy
+    }
+
+    /// Return the aggregation of the calculated fields traversing a to-many relationship without
+    /// an aggregate function, unless the column declares its own
+    pub fn default_aggregation(&self) -> Option<DefaultAggregation> {
+        self.manifest.default_aggregation
+    }
+
+    pub fn constants(&self) -> &[Arc<Constant>] {
+        &self.manifest.constants
+    }
+
+    /// Return the model by its name, one of its synonyms or its namespaced name, e.g.
+    /// `finance.orders`. The name takes precedence.
     pub fn get_model(&self, name: &str) -> Option<Arc<Model>> {
-        self.manifest
-            .models
+        let models = &self.manifest.models;
+        models
             .iter()
             .find(|model| model.name == name)
+            .or_else(|| {
+                models
+                    .iter()
+                    .find(|model| model.synonyms.iter().any(|s| s == name))
+            })
+            .or_else(|| {
+                let name = namespace::resolve_reference(self.default_namespace(), name)?;
+                models.iter().find(|model| model.name == name)
+            })
             .cloned()
     }
 
@@ -358,21 +696,54 @@ pub async fn transform_sql_with_ctx(
         Ok::<_, DataFusionError>(())
     })?;
     let ctx = create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), false).await?;
-    let plan = ctx.state().create_logical_plan(sql).await?;
+    let state = ctx.state();
+    let mut statement =
+        state.sql_to_statement(sql, &state.config_options().sql_parser.dialect)?;
+    if let DFStatement::Statement(statement) = &mut statement {
+        namespace::resolve_query_namespaces(&analyzed_mdl.wren_mdl(), statement);
+        synonym::resolve_query_synonyms(&analyzed_mdl.wren_mdl(), statement);
+    }
+    let plan = state
+        .statement_to_plan(statement)
+        .await
+        .map_err(|e| explain_unauthorized_column(&ctx, analyzed_mdl.wren_mdl(), e))?;
     debug!("wren-core original plan:\n {plan}");
     let analyzed = ctx.state().optimize(&plan)?;
     debug!("wren-core final planned:\n {analyzed}");
 
     let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
-    let wren_dialect = WrenDialect::new(&data_source);
+    let wren_dialect = WrenDialect::new(&data_source)
+        .with_minimal_quoting(analyzed_mdl.options().minimal_quoting);
     let unparser = Unparser::new(&wren_dialect).with_pretty(true);
     // show the planned sql
     match unparser.plan_to_sql(&analyzed) {
         Ok(sql) => {
+            let sql = table_function::bind_table_functions(
+                sql,
+                &analyzed_mdl.wren_mdl(),
+                &QueryContext::from_config(ctx.state().config()),
+                &unparser,
+            )?;
+            let sql = if analyzed_mdl.options().cte_output {
+                cte::factor_ctes(sql)?
+            } else {
+                sql
+            };
+            let sql = if analyzed_mdl.options().identifier_folding {
+                wren_dialect.fold_identifiers(sql)
+            } else {
+                sql
+            };
             // TODO: workaround to remove unnecessary catalog and schema of mdl
             let replaced = sql
                 .to_string()
                 .replace(analyzed_mdl.wren_mdl().catalog_schema_prefix(), "");
+            let replaced = if analyzed_mdl.options().lineage_comment {
+                let comment = comment::lineage_comment(&analyzed_mdl.wren_mdl(), &plan)?;
+                format!("{comment}\n{replaced}")
+            } else {
+                replaced
+            };
             info!("wren-core planned SQL: {}", replaced);
             Ok(replaced)
         }
@@ -380,6 +751,96 @@ pub async fn transform_sql_with_ctx(
     }
 }
 
+/// The column the session isn't authorized for, or its role isn't allowed to query, is pruned
+/// from the registered table. Explain the field not found error caused by requesting it.
+///
+/// The model of the field is resolved from the relations in the scope of the failed plan, i.e.
+/// the relations of the valid fields, so an unqualified field isn't explained by another model of
+/// the same column. The error is kept as it is if the model can't be resolved unambiguously.
+fn explain_unauthorized_column(
+    ctx: &SessionContext,
+    wren_mdl: Arc<WrenMDL>,
+    error: DataFusionError,
+) -> DataFusionError {
+    let DataFusionError::SchemaError(
+        SchemaError::FieldNotFound {
+            field,
+            valid_fields,
+        },
+        _,
+    ) = error.find_root()
+    else {
+        return error;
+    };
+    let relations = match &field.relation {
+        Some(relation) => BTreeSet::from([relation.table()]),
+        None => valid_fields
+            .iter()
+            .filter_map(|f| f.relation.as_ref().map(|r| r.table()))
+            .collect(),
+    };
+    let candidates = relations
+        .into_iter()
+        .filter_map(|relation| {
+            let model = wren_mdl.get_model(relation)?;
+            let column = model.get_column(&field.name)?;
+            Some((model, column))
+        })
+        .collect::<Vec<_>>();
+    let [(model, column)] = candidates.as_slice() else {
+        return error;
+    };
+    let query_context = QueryContext::from_config(ctx.state().config());
+    let (model, name) = (model.name().to_string(), column.name().to_string());
+    let explained = if !query_context.is_allowed(column) {
+        WrenError::DisallowedColumn {
+            role: query_context.role().map(String::from),
+            model,
+            column: name,
+        }
+    } else if !query_context.is_authorized(column) {
+        WrenError::UnauthorizedColumn {
+            model,
+            column: name,
+        }
+    } else {
+        return error;
+    };
+    explained.into()
+}
+
+/// Build the plan previewing the first `n` rows of the model.
+///
+/// The plan is executed by the local runtime, whose optimizer pushes the limit down to the scan
+/// of the model source if possible.
+pub async fn preview_plan(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    model_name: &str,
+    n: usize,
+) -> Result<DataFrame> {
+    if analyzed_mdl.wren_mdl().get_model(model_name).is_none() {
+        return Err(WrenError::ModelNotFound(model_name.to_string()).into());
+    }
+    let ctx = create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), true).await?;
+    ctx.sql(&format!("SELECT * FROM {}", utils::quoted(model_name)))
+        .await?
+        .limit(0, Some(n))
+}
+
+/// Stream the first `n` rows of the model, e.g. for the data preview
+pub async fn preview(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    model_name: &str,
+    n: usize,
+) -> Result<SendableRecordBatchStream> {
+    preview_plan(ctx, analyzed_mdl, model_name, n)
+        .await?
+        .execute_stream()
+        .await
+}
+
 fn register_remote_function(
     ctx: &SessionContext,
     remote_function: &RemoteFunction,
@@ -434,19 +895,37 @@ mod test {
     use std::path::PathBuf;
     use std::sync::Arc;
 
-    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::logical_plan::analyze::JOIN_HINT_METADATA_KEY;
+    use crate::logical_plan::utils::DESCRIPTION_METADATA_KEY;
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, RelationshipBuilder, ViewBuilder,
+    };
     use crate::mdl::context::create_ctx_with_mdl;
-    use crate::mdl::function::RemoteFunction;
+    use crate::mdl::fixture::{
+        artist, balances, customer, customer_model, manifest_builder, orders,
+        orders_model, orders_of, profile, timestamp_table, transform_with_mdl,
+    };
+    use crate::mdl::function::{ByPassScalarUDF, FunctionType, RemoteFunction};
+    use crate::mdl::manifest::DataSource;
     use crate::mdl::manifest::DataSource::MySQL;
-    use crate::mdl::manifest::Manifest;
+    use crate::mdl::manifest::{
+        DefaultAggregation, JoinHint, JoinType, Manifest, NonAdditiveAggregation,
+        TimeUnit,
+    };
+    use crate::mdl::query_context::QueryContext;
     use crate::mdl::{self, transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::WrenError;
     use datafusion::arrow::array::{
-        ArrayRef, Int64Array, RecordBatch, StringArray, TimestampNanosecondArray,
+        ArrayRef, Date32Array, Float64Array, Int64Array, ListArray, RecordBatch,
+        StringArray, TimestampNanosecondArray,
     };
+    use datafusion::arrow::datatypes::{DataType, Int64Type, IntervalUnit};
     use datafusion::assert_batches_eq;
     use datafusion::common::not_impl_err;
+    use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
     use datafusion::common::Result;
     use datafusion::config::ConfigOptions;
+    use datafusion::logical_expr::{Expr, LogicalPlan, ScalarUDF};
     use datafusion::prelude::{SessionConfig, SessionContext};
     use datafusion::sql::unparser::plan_to_sql;
 
@@ -952,6 +1431,33 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_filter_placeholder() -> Result<()> {
+        let test_data: PathBuf =
+            [env!("CARGO_MANIFEST_DIR"), "tests", "data", "mdl.json"]
+                .iter()
+                .collect();
+        let mdl_json = fs::read_to_string(test_data.as_path())?;
+        let mdl = match serde_json::from_str::<Manifest>(&mdl_json) {
+            Ok(mdl) => mdl,
+            Err(e) => return not_impl_err!("Failed to parse mdl json: {}", e),
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(mdl)?);
+        let ctx =
+            create_ctx_with_mdl(&SessionContext::new(), analyzed_mdl, false).await?;
+        let sql = "SELECT c_name FROM customer WHERE c_custkey + 1 > $1 AND c_name = $2";
+        let plan = ctx.state().create_logical_plan(sql).await?;
+        let analyzed = ctx.state().optimize(&plan)?;
+        assert_eq!(
+            analyzed.get_parameter_types()?,
+            HashMap::from([
+                ("$1".to_string(), Some(DataType::Int64)),
+                ("$2".to_string(), Some(DataType::Utf8)),
+            ])
+        );
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_mysql_style_interval() -> Result<()> {
         let ctx = SessionContext::new();
@@ -1367,67 +1873,2888 @@ mod test {
         Ok(())
     }
 
-    /// Return a RecordBatch with made up data about customer
-    fn customer() -> RecordBatch {
-        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
-        let name: ArrayRef =
-            Arc::new(StringArray::from_iter_values(["Gura", "Azki", "Ina"]));
-        RecordBatch::try_from_iter(vec![("c_custkey", custkey), ("c_name", name)])
-            .unwrap()
+    #[tokio::test]
+    async fn test_semi_additive_measure() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("balances", balances())?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("balances")
+                    .table_reference("balances")
+                    .column(ColumnBuilder::new("account", "varchar").build())
+                    .column(ColumnBuilder::new("snapshot_date", "date").build())
+                    .column(
+                        ColumnBuilder::new("ending_balance", "bigint")
+                            .expression("balance")
+                            .semi_additive(
+                                "snapshot_date",
+                                &["account"],
+                                NonAdditiveAggregation::Last,
+                                Some(TimeUnit::Month),
+                            )
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT date_trunc('month', snapshot_date) AS snapshot_month, \
+            sum(ending_balance) AS total FROM balances GROUP BY 1 ORDER BY 1";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------------+-------+",
+                "| snapshot_month      | total |",
+                "+---------------------+-------+",
+                "| 2024-01-01T00:00:00 | 200   |",
+                "| 2024-02-01T00:00:00 | 280   |",
+                "+---------------------+-------+",
+            ],
+            &result
+        );
+
+        // the account missing the latest snapshot of the month counts by its own last one
+        ctx.deregister_table("balances")?;
+        let sparse = balances().slice(0, 5);
+        ctx.register_batch("balances", sparse)?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------------+-------+",
+                "| snapshot_month      | total |",
+                "+---------------------+-------+",
+                "| 2024-01-01T00:00:00 | 200   |",
+                "| 2024-02-01T00:00:00 | 270   |",
+                "+---------------------+-------+",
+            ],
+            &result
+        );
+        Ok(())
     }
 
-    /// Return a RecordBatch with made up data about profile
-    fn profile() -> RecordBatch {
-        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
-        let phone: ArrayRef = Arc::new(StringArray::from_iter_values([
-            "123456", "234567", "345678",
+    #[tokio::test]
+    async fn test_relationship_count_calculation() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch("orders", orders_of(&[1, 1, 2], vec![]))?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("order_count", "bigint")
+                            .calculated(true)
+                            .expression("count(orders)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(orders_model().primary_key("o_orderkey").build())
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, order_count FROM customer ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+-------------+",
+                "| c_name | order_count |",
+                "+--------+-------------+",
+                "| Azki   | 1           |",
+                "| Gura   | 2           |",
+                "| Ina    | 0           |",
+                "+--------+-------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_self_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        let id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3, 4]));
+        let name: ArrayRef = Arc::new(StringArray::from_iter_values([
+            "Ina", "Azki", "Gura", "Kiara",
         ]));
-        let sex: ArrayRef = Arc::new(StringArray::from_iter_values(["M", "M", "F"]));
-        RecordBatch::try_from_iter(vec![
-            ("p_custkey", custkey),
-            ("p_phone", phone),
-            ("p_sex", sex),
-        ])
-        .unwrap()
-    }
-
-    /// Return a RecordBatch with made up data about orders
-    fn orders() -> RecordBatch {
-        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
-        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
-        let totalprice: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
-        RecordBatch::try_from_iter(vec![
-            ("o_orderkey", orderkey),
-            ("o_custkey", custkey),
-            ("o_totalprice", totalprice),
-        ])
-        .unwrap()
-    }
-
-    fn artist() -> RecordBatch {
-        let name: ArrayRef =
-            Arc::new(StringArray::from_iter_values(["Ina", "Azki", "Kaela"]));
-        let group: ArrayRef = Arc::new(StringArray::from_iter_values(["EN", "JP", "ID"]));
-        let subscribe: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
-        let debut_time: ArrayRef =
-            Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]));
-        RecordBatch::try_from_iter(vec![
-            ("名字", name),
-            ("組別", group),
-            ("訂閱數", subscribe),
-            ("出道時間", debut_time),
-        ])
-        .unwrap()
-    }
-
-    fn timestamp_table() -> RecordBatch {
-        let timestamp: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]));
-        let timestamptz: ArrayRef =
-            Arc::new(TimestampNanosecondArray::from(vec![1, 2, 3]).with_timezone("UTC"));
-        RecordBatch::try_from_iter(vec![
-            ("timestamp_col", timestamp),
-            ("timestamptz_col", timestamptz),
-        ])
-        .unwrap()
+        let manager_id: ArrayRef =
+            Arc::new(Int64Array::from(vec![None, Some(1), Some(2), Some(1)]));
+        ctx.register_batch(
+            "employees",
+            RecordBatch::try_from_iter(vec![
+                ("id", id),
+                ("name", name),
+                ("manager_id", manager_id),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("employees")
+                    .table_reference("employees")
+                    .column(ColumnBuilder::new("id", "bigint").build())
+                    .column(ColumnBuilder::new("name", "varchar").build())
+                    .column(ColumnBuilder::new("manager_id", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "manager",
+                            "employees",
+                            "employees_manager",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("manager_name", "varchar")
+                            .calculated(true)
+                            .expression("manager.name")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("skip_manager_name", "varchar")
+                            .calculated(true)
+                            .expression("manager.manager.name")
+                            .build(),
+                    )
+                    .primary_key("id")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("employees_manager")
+                    .model("employees")
+                    .model("employees")
+                    .join_type(JoinType::ManyToOne)
+                    // the joined side is resolved by the primary key, whatever side it's on
+                    .condition("employees.id = employees.manager_id")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT name, manager_name, skip_manager_name FROM employees ORDER BY name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // each hop joins another instance aliased by its path of the relationship columns
+        assert!(actual.contains("AS employees"));
+        assert!(actual.contains("AS manager"));
+        assert!(actual.contains("AS manager__manager"));
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-------+--------------+-------------------+",
+                "| name  | manager_name | skip_manager_name |",
+                "+-------+--------------+-------------------+",
+                "| Azki  | Ina          |                   |",
+                "| Gura  | Azki         | Ina               |",
+                "| Ina   |              |                   |",
+                "| Kiara | Ina          |                   |",
+                "+-------+--------------+-------------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_soft_deleted_model() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("orders", orders())?;
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "c_custkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "c_name",
+                    Arc::new(StringArray::from_iter_values(["Gura", "Azki", "Ina"])),
+                ),
+                (
+                    "deleted_at",
+                    Arc::new(Date32Array::from(vec![None, Some(19000), None])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                orders_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("customer_name", "varchar")
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("deleted_at", "date").build())
+                    .base_filter("deleted_at IS NULL")
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey, customer_name FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the soft-delete predicate is applied to the joined side
+        assert!(actual.contains("WHERE __source.deleted_at IS NULL"));
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------------+",
+                "| o_orderkey | customer_name |",
+                "+------------+---------------+",
+                "| 1          | Gura          |",
+                "| 2          |               |",
+                "| 3          | Ina           |",
+                "+------------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_required_partition_filter() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("events")
+                    .table_reference("events")
+                    .column(ColumnBuilder::new("event_id", "bigint").build())
+                    .column(ColumnBuilder::new("event_date", "date").build())
+                    .column(ColumnBuilder::new("kind", "varchar").build())
+                    .required_partition_filter("event_date")
+                    .primary_key("event_id")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        for sql in [
+            "SELECT event_id FROM events",
+            "SELECT event_id FROM events WHERE kind = 'click'",
+        ] {
+            let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+            assert_eq!(
+                WrenError::find_in(&err),
+                Some(&WrenError::MissingPartitionFilter {
+                    model: "events".to_string(),
+                    column: "event_date".to_string(),
+                })
+            );
+            assert!(err.to_string().contains(
+                "The query scanning the model events should filter its partition column event_date"
+            ));
+        }
+
+        let sql = "SELECT event_id FROM events e \
+            WHERE e.event_date = DATE '2024-01-01' AND kind = 'click'";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("event_date ="));
+        Ok(())
+    }
+
+    #[test]
+    fn test_dependents() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("tpch.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("total_spent", "double")
+                            .calculated(true)
+                            .expression("sum(orders.totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("tpch.orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("totalprice", "double")
+                            .expression("o_totalprice")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("price_with_tax", "double")
+                            .calculated(true)
+                            .expression("totalprice * 1.1")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("discounted_price", "double")
+                            .calculated(true)
+                            .expression("totalprice * 0.9")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = AnalyzedWrenMDL::analyze(manifest)?;
+        let pair = |model: &str, column: &str| (model.to_string(), column.to_string());
+
+        assert_eq!(
+            analyzed_mdl.dependents("tpch.orders", "o_totalprice")?,
+            vec![
+                pair("customer", "total_spent"),
+                pair("orders", "discounted_price"),
+                pair("orders", "price_with_tax"),
+                pair("orders", "totalprice"),
+            ]
+        );
+        // the join key of the relationship traversed by the calculated field
+        assert_eq!(
+            analyzed_mdl.dependents("tpch.customer", "c_custkey")?,
+            vec![
+                pair("customer", "c_custkey"),
+                pair("customer", "total_spent")
+            ]
+        );
+        assert!(analyzed_mdl
+            .dependents("tpch.lineitem", "l_orderkey")?
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_bucketed_dimension() -> Result<()> {
+        let ctx = SessionContext::new();
+        let custkey: ArrayRef = Arc::new(Int64Array::from_iter_values(1..=20));
+        let spend: ArrayRef =
+            Arc::new(Int64Array::from_iter_values((1..=20).map(|i| i * 100)));
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![("c_custkey", custkey), ("spend", spend)])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("spend", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("spend_decile", "bigint")
+                            .bucket(10, "spend")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT spend_decile, count(*) AS customers FROM customer \
+            GROUP BY spend_decile ORDER BY spend_decile";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the bucket is produced by the window function and grouped by the outer query
+        assert!(
+            actual.contains("ntile(10) OVER (ORDER BY __source.spend"),
+            "{actual}"
+        );
+        assert!(
+            actual.contains("GROUP BY customer.spend_decile"),
+            "{actual}"
+        );
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------------+-----------+",
+                "| spend_decile | customers |",
+                "+--------------+-----------+",
+                "| 1            | 2         |",
+                "| 2            | 2         |",
+                "| 3            | 2         |",
+                "| 4            | 2         |",
+                "| 5            | 2         |",
+                "| 6            | 2         |",
+                "| 7            | 2         |",
+                "| 8            | 2         |",
+                "| 9            | 2         |",
+                "| 10           | 2         |",
+                "+--------------+-----------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_filtered_measure() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let status: ArrayRef =
+            Arc::new(StringArray::from_iter_values(["F", "O", "F", "F"]));
+        let totalprice: ArrayRef =
+            Arc::new(Float64Array::from(vec![100.0, 300.0, 50.0, 150.0]));
+        ctx.register_batch(
+            "orders",
+            orders_of(
+                &[1, 1, 2, 2],
+                vec![("o_orderstatus", status), ("o_totalprice", totalprice)],
+            ),
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("finished_ratio", "double")
+                            .calculated(true)
+                            .expression(
+                                "sum(orders.o_totalprice) FILTER (WHERE orders.o_orderstatus = 'F') \
+                                / sum(orders.o_totalprice)",
+                            )
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_orderstatus", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, finished_ratio FROM customer ORDER BY c_name";
+
+        // the filtered and unfiltered sums are computed by one aggregate with the native filter
+        let wren_ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let plan = wren_ctx.state().create_logical_plan(sql).await?;
+        let plan = wren_ctx.state().optimize(&plan)?;
+        let mut aggregates = vec![];
+        plan.apply(|plan| {
+            if let LogicalPlan::Aggregate(aggregate) = plan {
+                aggregates.push(aggregate.aggr_expr.clone());
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert_eq!(aggregates.len(), 1);
+        let filters = aggregates[0]
+            .iter()
+            .map(|expr| match expr {
+                Expr::AggregateFunction(function) => function.params.filter.is_some(),
+                _ => panic!("expected an aggregate function: {expr}"),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(filters.len(), 2);
+        assert!(filters.contains(&true) && filters.contains(&false));
+
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("FILTER (WHERE"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+----------------+",
+                "| c_name | finished_ratio |",
+                "+--------+----------------+",
+                "| Azki   | 1.0            |",
+                "| Gura   | 0.25           |",
+                "| Ina    |                |",
+                "+--------+----------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_aggregation() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let totalprice: ArrayRef =
+            Arc::new(Float64Array::from(vec![100.0, 300.0, 200.0]));
+        ctx.register_batch(
+            "orders",
+            orders_of(&[1, 1, 2], vec![("o_totalprice", totalprice)]),
+        )?;
+        let manifest = |default_aggregation: Option<DefaultAggregation>| {
+            let builder = manifest_builder()
+                .model(
+                    customer_model()
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "orders",
+                                "orders",
+                                "customer_orders",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("total_price", "double")
+                                .calculated(true)
+                                .expression("orders.o_totalprice")
+                                .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("max_price", "double")
+                                .calculated(true)
+                                .expression("orders.o_totalprice")
+                                .default_aggregation(DefaultAggregation::Max)
+                                .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("order_key", "bigint")
+                                .calculated(true)
+                                .expression("orders.o_orderkey")
+                                .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    orders_model()
+                        .column(ColumnBuilder::new("o_totalprice", "double").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_orders")
+                        .model("customer")
+                        .model("orders")
+                        .join_type(JoinType::OneToMany)
+                        .condition("customer.c_custkey = orders.o_custkey")
+                        .build(),
+                );
+            match default_aggregation {
+                Some(aggregation) => builder.default_aggregation(aggregation).build(),
+                None => builder.build(),
+            }
+        };
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(Some(
+            DefaultAggregation::Sum,
+        )))?);
+
+        // the numeric field is summed by the default aggregation of the manifest, and the
+        // aggregation declared by the column takes precedence
+        let sql = "SELECT c_name, total_price, max_price FROM customer ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+-------------+-----------+",
+                "| c_name | total_price | max_price |",
+                "+--------+-------------+-----------+",
+                "| Azki   | 200.0       | 200.0     |",
+                "| Gura   | 400.0       | 300.0     |",
+                "| Ina    |             |           |",
+                "+--------+-------------+-----------+",
+            ],
+            &result
+        );
+
+        // the identifier can't be aggregated by default
+        let sql = "SELECT c_name, order_key FROM customer";
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "The calculated field order_key traverses a to-many relationship without an aggregation. \
+                orders.o_orderkey is an identifier and can't be aggregated by default"
+            ),
+            "{err}"
+        );
+
+        // the field without any declared aggregation is left as it is
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(None))?);
+        let sql = "SELECT c_name, total_price FROM customer";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("sum("), "{actual}");
+        let sql = "SELECT c_name, order_key FROM customer";
+        transform_with_mdl(&analyzed_mdl, sql).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_lookup_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let orderdate: ArrayRef = Arc::new(StringArray::from_iter_values([
+            "2024-01-01",
+            "2024-03-01",
+            "2024-02-01",
+            "2024-01-15",
+        ]));
+        let totalprice: ArrayRef =
+            Arc::new(Float64Array::from(vec![100.0, 300.0, 50.0, 150.0]));
+        ctx.register_batch(
+            "orders",
+            orders_of(
+                &[1, 1, 2, 2],
+                vec![("o_orderdate", orderdate), ("o_totalprice", totalprice)],
+            ),
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "latest_order",
+                            "orders",
+                            "customer_latest_order",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("latest_order_price", "double")
+                            .calculated(true)
+                            .expression("latest_order.o_totalprice")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_orderdate", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_latest_order")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToOne)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .lookup("orders.o_orderdate", true)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, latest_order_price FROM customer ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the orders are ranked for each customer by the order date
+        assert!(
+            actual
+                .to_lowercase()
+                .contains("row_number() over (partition by"),
+            "{actual}"
+        );
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+--------------------+",
+                "| c_name | latest_order_price |",
+                "+--------+--------------------+",
+                "| Azki   | 50.0               |",
+                "| Gura   | 300.0              |",
+                "| Ina    |                    |",
+                "+--------+--------------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_column_description_metadata() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("c_name", "varchar")
+                            .property("description", "The name of the customer")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let df = ctx.sql("SELECT c_custkey, c_name FROM customer").await?;
+        let schema = df.schema();
+        assert_eq!(
+            schema
+                .field_with_unqualified_name("c_name")?
+                .metadata()
+                .get(DESCRIPTION_METADATA_KEY)
+                .map(String::as_str),
+            Some("The name of the customer")
+        );
+        assert!(schema
+            .field_with_unqualified_name("c_custkey")?
+            .metadata()
+            .is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_case_calculated_field() -> Result<()> {
+        let ctx = SessionContext::new();
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+        let spend: ArrayRef = Arc::new(Int64Array::from(vec![5000, 500, 50]));
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![("c_custkey", custkey), ("spend", spend)])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("spend", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("tier", "varchar")
+                            .calculated(true)
+                            .expression(
+                                "CASE WHEN spend > 1000 THEN 'gold' \
+                                WHEN spend > 100 THEN 'silver' ELSE 'bronze' END",
+                            )
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_custkey, tier FROM customer ORDER BY c_custkey";
+
+        let wren_ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let plan = wren_ctx.state().create_logical_plan(sql).await?;
+        let plan = wren_ctx.state().optimize(&plan)?;
+        let mut cases = vec![];
+        plan.apply(|plan| {
+            plan.apply_expressions(|expr| {
+                expr.apply(|e| {
+                    if let Expr::Case(case) = e {
+                        cases.push(case.clone());
+                    }
+                    Ok(TreeNodeRecursion::Continue)
+                })
+            })?;
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert_eq!(cases.len(), 1);
+        assert_eq!(cases[0].when_then_expr.len(), 2);
+        assert!(cases[0].else_expr.is_some());
+
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+--------+",
+                "| c_custkey | tier   |",
+                "+-----------+--------+",
+                "| 1         | gold   |",
+                "| 2         | silver |",
+                "| 3         | bronze |",
+                "+-----------+--------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_boolean_flag_calculated_field() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch("orders", orders_of(&[1, 1, 2], vec![]))?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("is_repeat_customer", "boolean")
+                            .calculated(true)
+                            .expression("count(orders) > 1")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("has_orders", "boolean")
+                            .calculated(true)
+                            .expression("count(orders)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(orders_model().primary_key("o_orderkey").build())
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        // the flag is a filter
+        let actual = transform_with_mdl(
+            &analyzed_mdl,
+            "SELECT c_name FROM customer WHERE is_repeat_customer ORDER BY c_name",
+        )
+        .await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+",
+                "| c_name |",
+                "+--------+",
+                "| Gura   |",
+                "+--------+",
+            ],
+            &result
+        );
+
+        // the same flag is a dimension grouping the customers by the same semantics
+        let actual = transform_with_mdl(
+            &analyzed_mdl,
+            "SELECT is_repeat_customer, count(*) AS customers FROM customer \
+            GROUP BY is_repeat_customer ORDER BY is_repeat_customer",
+        )
+        .await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(result[0].schema().field(0).data_type(), &DataType::Boolean);
+        assert_batches_eq!(
+            [
+                "+--------------------+-----------+",
+                "| is_repeat_customer | customers |",
+                "+--------------------+-----------+",
+                "| false              | 2         |",
+                "| true               | 1         |",
+                "+--------------------+-----------+",
+            ],
+            &result
+        );
+
+        // the numeric flag is cast to the declared boolean type
+        let actual = transform_with_mdl(
+            &analyzed_mdl,
+            "SELECT c_name, has_orders FROM customer ORDER BY c_name",
+        )
+        .await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+------------+",
+                "| c_name | has_orders |",
+                "+--------+------------+",
+                "| Azki   | true       |",
+                "| Gura   | true       |",
+                "| Ina    | false      |",
+                "+--------+------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reproducible_relationship_join() -> Result<()> {
+        let manifest = || {
+            manifest_builder()
+                .model(
+                    orders_model()
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "customer",
+                                "customer",
+                                "orders_customer",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("customer_label", "varchar")
+                                .calculated(true)
+                                .expression(
+                                    "concat(customer.nation.n_name, ' / ', customer.c_name)",
+                                )
+                                .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .model(
+                    customer_model()
+                        .column(ColumnBuilder::new("c_nationkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "nation",
+                                "nation",
+                                "customer_nation",
+                            )
+                            .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("nation")
+                        .table_reference("nation")
+                        .column(ColumnBuilder::new("n_nationkey", "bigint").build())
+                        .column(ColumnBuilder::new("n_name", "varchar").build())
+                        .primary_key("n_nationkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("orders_customer")
+                        .model("orders")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("orders.o_custkey = customer.c_custkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_nation")
+                        .model("customer")
+                        .model("nation")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("customer.c_nationkey = nation.n_nationkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT o_orderkey, customer_label FROM orders ORDER BY o_orderkey";
+        let mut plans = vec![];
+        // every analysis starts with the new hash maps
+        for _ in 0..10 {
+            let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest())?);
+            plans.push(transform_with_mdl(&analyzed_mdl, sql).await?);
+        }
+        assert!(plans.windows(2).all(|pair| pair[0] == pair[1]));
+
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![10, 20])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "c_custkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                (
+                    "c_name",
+                    Arc::new(StringArray::from(vec!["Gura", "Azki"])) as ArrayRef,
+                ),
+                (
+                    "c_nationkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "nation",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "n_nationkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                (
+                    "n_name",
+                    Arc::new(StringArray::from(vec!["Japan", "Taiwan"])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let result = ctx.sql(&plans[0]).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+----------------+",
+                "| o_orderkey | customer_label |",
+                "+------------+----------------+",
+                "| 10         | Japan / Gura   |",
+                "| 20         | Taiwan / Azki  |",
+                "+------------+----------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_surrogate_key() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "c_custkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                (
+                    "c_name",
+                    Arc::new(StringArray::from(vec!["Gura", "Azki"])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = |data_source: DataSource| {
+            manifest_builder()
+                .data_source(data_source)
+                .model(
+                    customer_model()
+                        .column(
+                            ColumnBuilder::new("customer_sk", "varchar")
+                                .surrogate_key(&["c_custkey", "c_name"])
+                                .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT c_custkey, customer_sk FROM customer ORDER BY c_custkey";
+
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::Datafusion))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("md5(concat_ws('|', "));
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+----------------------------------+",
+                "| c_custkey | customer_sk                      |",
+                "+-----------+----------------------------------+",
+                "| 1         | a3bc670a722f0c234644890fd8e721e4 |",
+                "| 2         | a990fecbae3b97ea804c84fb6369f47a |",
+                "+-----------+----------------------------------+",
+            ],
+            &result
+        );
+
+        // BigQuery hashes the natural keys by farm_fingerprint
+        let farm_fingerprint = RemoteFunction {
+            function_type: FunctionType::Scalar,
+            name: "farm_fingerprint".to_string(),
+            return_type: "bigint".to_string(),
+            param_names: None,
+            param_types: None,
+            description: None,
+        };
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest(DataSource::BigQuery))?);
+        let actual = transform_sql_with_ctx(
+            &SessionContext::new(),
+            analyzed_mdl,
+            &[farm_fingerprint],
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("farm_fingerprint(concat_ws('|', "));
+        assert!(!actual.contains("md5("));
+
+        let invalid = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("customer_sk", "varchar")
+                            .surrogate_key(&["c_custkey", "c_nationkey"])
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(invalid)?);
+        let err = transform_with_mdl(&analyzed_mdl, "SELECT customer_sk FROM customer")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(
+            "Natural key c_nationkey of the surrogate key not found in model customer"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unnest_column() -> Result<()> {
+        let ctx = SessionContext::new();
+        let id: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3]));
+        let tags: ArrayRef =
+            Arc::new(ListArray::from_iter_primitive::<Int64Type, _, _>(vec![
+                Some(vec![Some(10), Some(20), Some(30)]),
+                Some(vec![Some(40)]),
+                Some(vec![]),
+            ]));
+        ctx.register_batch(
+            "post",
+            RecordBatch::try_from_iter(vec![("id", id), ("tags", tags)])?,
+        )?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("post")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.post".to_string(), provider);
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("post")
+                    .table_reference("datafusion.public.post")
+                    .column(ColumnBuilder::new("id", "bigint").build())
+                    .column(ColumnBuilder::new("tags", "array<bigint>").build())
+                    .column(ColumnBuilder::new("tag", "bigint").unnest("tags").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+        let ctx = create_ctx_with_mdl(&ctx, Arc::clone(&analyzed_mdl), true).await?;
+
+        let result = ctx
+            .sql("SELECT id, tag FROM wren.test.post ORDER BY id, tag")
+            .await?
+            .collect()
+            .await?;
+        assert_batches_eq!(
+            [
+                "+----+-----+",
+                "| id | tag |",
+                "+----+-----+",
+                "| 1  | 10  |",
+                "| 1  | 20  |",
+                "| 1  | 30  |",
+                "| 2  | 40  |",
+                "+----+-----+",
+            ],
+            &result
+        );
+        // the rows are fanned out by the element count even if the element isn't selected
+        let result = ctx
+            .sql("SELECT id, count(*) AS cnt FROM wren.test.post GROUP BY id ORDER BY id")
+            .await?
+            .collect()
+            .await?;
+        assert_batches_eq!(
+            [
+                "+----+-----+",
+                "| id | cnt |",
+                "+----+-----+",
+                "| 1  | 3   |",
+                "| 2  | 1   |",
+                "+----+-----+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_share_of_total() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 2, 2])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Int64Array::from(vec![100, 200, 500, 200])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let orders = orders_model()
+            .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+            .primary_key("o_orderkey")
+            .build();
+        let customer_orders = RelationshipBuilder::new("customer_orders")
+            .model("customer")
+            .model("orders")
+            .join_type(JoinType::OneToMany)
+            .condition("customer.c_custkey = orders.o_custkey")
+            .build();
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("revenue_share", "double")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .share_of_total(true)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(orders)
+            .relationship(customer_orders)
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, revenue_share FROM customer ORDER BY c_name";
+
+        // the base measure is aggregated, then divided by its window sum over all the groups
+        let wren_ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let plan = wren_ctx.state().create_logical_plan(sql).await?;
+        let plan = wren_ctx.state().optimize(&plan)?;
+        let mut has_aggregate = false;
+        let mut has_total_window = false;
+        let mut has_division = false;
+        plan.apply(|plan| {
+            match plan {
+                LogicalPlan::Aggregate(_) => has_aggregate = true,
+                LogicalPlan::Window(window) => {
+                    has_total_window |= window.window_expr.iter().any(|expr| {
+                        let expr = expr.to_string();
+                        expr.contains("sum(") && !expr.contains("PARTITION BY")
+                    })
+                }
+                LogicalPlan::Projection(projection) => {
+                    for expr in &projection.expr {
+                        expr.apply(|e| {
+                            if let Expr::BinaryExpr(binary) = e {
+                                has_division |= binary.op
+                                    == datafusion::logical_expr::Operator::Divide;
+                            }
+                            Ok(TreeNodeRecursion::Continue)
+                        })?;
+                    }
+                }
+                _ => {}
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert!(has_aggregate, "{plan}");
+        assert!(has_total_window, "{plan}");
+        assert!(has_division, "{plan}");
+
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+---------------+",
+                "| c_name | revenue_share |",
+                "+--------+---------------+",
+                "| Azki   | 0.7           |",
+                "| Gura   | 0.3           |",
+                "| Ina    |               |",
+                "+--------+---------------+",
+            ],
+            &result
+        );
+
+        // the share of total needs a base measure aggregated over a to-many relationship
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("key_share", "double")
+                            .calculated(true)
+                            .expression("c_custkey")
+                            .share_of_total(true)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        match transform_with_mdl(&analyzed_mdl, "SELECT key_share FROM customer")
+        .await
+        {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(
+                e.to_string().contains(
+                    "The share of total key_share should aggregate over a to-many relationship"
+                ),
+                "{e}"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unknown_function_in_calculated_field() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new("upper_name", "varchar")
+                            .calculated(true)
+                            .expression("UPPER(c_name)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("masked_name", "varchar")
+                            .calculated(true)
+                            .expression("concat(mask_name(c_name), '*')")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_strict_functions());
+
+        transform_with_mdl(&analyzed_mdl, "SELECT upper_name FROM customer").await?;
+
+        let err = transform_with_mdl(&analyzed_mdl, "SELECT masked_name FROM customer")
+            .await
+            .unwrap_err();
+        let Some(WrenError::UnknownFunction {
+            function,
+            model,
+            column,
+        }) = WrenError::find_in(&err)
+        else {
+            panic!("expected an unknown function error: {err}");
+        };
+        assert_eq!(function, "mask_name");
+        assert_eq!(model, "customer");
+        assert_eq!(column, "masked_name");
+        assert!(
+            err.to_string().contains(
+                "The calculated field customer.masked_name calls an unknown function mask_name"
+            ),
+            "{err}"
+        );
+
+        // the remote function registered in the session is known
+        let mask_name = RemoteFunction {
+            function_type: FunctionType::Scalar,
+            name: "mask_name".to_string(),
+            return_type: "varchar".to_string(),
+            param_names: None,
+            param_types: None,
+            description: None,
+        };
+        transform_sql_with_ctx(
+            &SessionContext::new(),
+            analyzed_mdl,
+            &[mask_name],
+            "SELECT masked_name FROM customer",
+        )
+        .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_custom_scalar_udf_in_calculated_field() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(ColumnBuilder::new("o_currency", "varchar").build())
+                    .column(
+                        ColumnBuilder::new_calculated("o_totalprice_usd", "double")
+                            .expression(
+                                "convert_currency(o_totalprice, o_currency, 'USD')",
+                            )
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let sql = "SELECT o_orderkey, o_totalprice_usd FROM orders";
+
+        // the function is unknown without the registry
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest.clone())?);
+        assert!(transform_with_mdl(&analyzed_mdl, sql).await.is_err());
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_scalar_udf(
+            ScalarUDF::new_from_impl(ByPassScalarUDF::new(
+                "convert_currency",
+                DataType::Float64,
+            )),
+        ));
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(
+            actual.contains(
+                "convert_currency(__source.o_totalprice, __source.o_currency, 'USD')"
+            ),
+            "{actual}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_preview_model() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.customer".to_string(), provider);
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_name", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+
+        let plan = mdl::preview_plan(&ctx, Arc::clone(&analyzed_mdl), "customer", 2)
+            .await?
+            .into_optimized_plan()?;
+        let mut fetches = vec![];
+        plan.apply(|plan| {
+            match plan {
+                LogicalPlan::Limit(limit) => fetches.push(limit.get_fetch_type()?),
+                LogicalPlan::TableScan(scan) => {
+                    assert_eq!(scan.fetch, Some(2), "the limit should be pushed down");
+                }
+                _ => {}
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert_eq!(
+            fetches,
+            vec![datafusion::logical_expr::FetchType::Literal(Some(2))]
+        );
+
+        let stream = mdl::preview(&ctx, Arc::clone(&analyzed_mdl), "customer", 2).await?;
+        let result = datafusion::physical_plan::common::collect(stream).await?;
+        assert_eq!(result.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+
+        let err = mdl::preview(&ctx, analyzed_mdl, "orders", 2)
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::ModelNotFound("orders".to_string()))
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_join_hint() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(customer_model().primary_key("c_custkey").build())
+            .model(
+                orders_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("customer_name", "varchar")
+                            .calculated(true)
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .join_hint(JoinHint::Broadcast)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let state = ctx.state();
+        let plan = state
+            .create_logical_plan("SELECT o_orderkey, customer_name FROM orders")
+            .await?;
+        let plan = state.analyzer().execute_and_check(
+            plan,
+            state.config_options(),
+            |_, _| {},
+        )?;
+        // the join with the customer is annotated to broadcast the customer
+        let mut hints = vec![];
+        plan.apply(|plan| {
+            if let LogicalPlan::Join(join) = plan {
+                hints.extend(join.schema.metadata().get(JOIN_HINT_METADATA_KEY).cloned());
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert_eq!(hints, vec!["BROADCAST(customer)".to_string()]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_approx_percentile_measure() -> Result<()> {
+        let manifest = |percentile: &str| {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "orders",
+                                "orders",
+                                "customer_orders",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("p95_totalprice", "double")
+                                .calculated(true)
+                                .expression(&format!(
+                                    "approx_percentile_cont(orders.o_totalprice, {percentile})"
+                                ))
+                                .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    orders_model()
+                        .column(ColumnBuilder::new("o_totalprice", "double").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_orders")
+                        .model("customer")
+                        .model("orders")
+                        .join_type(JoinType::OneToMany)
+                        .condition("customer.c_custkey = orders.o_custkey")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT c_custkey, p95_totalprice FROM customer";
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest("0.95"))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the measure is aggregated by the approximate aggregate of DataFusion
+        let actual = actual.to_lowercase();
+        assert!(actual.contains("approx_percentile_cont("), "{actual}");
+        assert!(
+            actual.contains("o_totalprice\", 0.95)")
+                || actual.contains("o_totalprice, 0.95)"),
+            "{actual}"
+        );
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest("1.5"))?);
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert!(
+            err.to_string().contains(
+                "The percentile of the calculated field customer.p95_totalprice should be in [0, 1]: 1.5"
+            ),
+            "{err}"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_interval_calculated_field() -> Result<()> {
+        let ctx = SessionContext::new();
+        let start_at: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![
+            1_704_067_200_000_000_000,
+            1_704_153_600_000_000_000,
+        ]));
+        let end_at: ArrayRef = Arc::new(TimestampNanosecondArray::from(vec![
+            1_704_072_600_000_000_000,
+            1_704_157_200_000_000_000,
+        ]));
+        ctx.register_batch(
+            "sessions",
+            RecordBatch::try_from_iter(vec![("start_at", start_at), ("end_at", end_at)])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("sessions")
+                    .table_reference("sessions")
+                    .column(ColumnBuilder::new("start_at", "timestamp").build())
+                    .column(ColumnBuilder::new("end_at", "timestamp").build())
+                    .column(
+                        ColumnBuilder::new("duration", "interval")
+                            .calculated(true)
+                            .expression("end_at - start_at")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let actual =
+            transform_with_mdl(&analyzed_mdl, "SELECT duration FROM sessions").await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_eq!(result.iter().map(|b| b.num_rows()).sum::<usize>(), 2);
+        // the difference of the timestamps is typed as an interval
+        assert_eq!(
+            result[0].schema().field(0).data_type(),
+            &DataType::Interval(IntervalUnit::MonthDayNano)
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_default_table_schema() -> Result<()> {
+        let manifest = manifest_builder()
+            .default_table_schema("tpch")
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("sales.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        // the bare table reference gets the default schema
+        let actual =
+            transform_with_mdl(&analyzed_mdl, "SELECT o_orderkey FROM orders").await?;
+        assert!(actual.contains("FROM tpch.orders AS __source"), "{actual}");
+
+        // the qualified table reference is kept
+        let actual =
+            transform_with_mdl(&analyzed_mdl, "SELECT c_custkey FROM customer").await?;
+        assert!(
+            actual.contains("FROM sales.customer AS __source"),
+            "{actual}"
+        );
+        assert!(!actual.contains("tpch"), "{actual}");
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_measure_empty_value() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let totalprice: ArrayRef = Arc::new(Int64Array::from(vec![100, 200, 300]));
+        ctx.register_batch(
+            "orders",
+            orders_of(&[1, 1, 2], vec![("o_totalprice", totalprice)]),
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("totalprice", "bigint")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .empty_value("0")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("raw_totalprice", "bigint")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT c_name, totalprice, raw_totalprice FROM customer ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // Ina has no orders, the measure with the empty value is 0 instead of NULL
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+------------+----------------+",
+                "| c_name | totalprice | raw_totalprice |",
+                "+--------+------------+----------------+",
+                "| Azki   | 300        | 300            |",
+                "| Gura   | 300        | 300            |",
+                "| Ina    | 0          |                |",
+                "+--------+------------+----------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_materialized_calculated_field() -> Result<()> {
+        let ctx = SessionContext::new();
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let price: ArrayRef = Arc::new(Int64Array::from(vec![10, 20]));
+        let quantity: ArrayRef = Arc::new(Int64Array::from(vec![2, 3]));
+        // the stored values differ from the expression to tell which one is read
+        let revenue: ArrayRef = Arc::new(Int64Array::from(vec![21, 61]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_price", price),
+                ("o_quantity", quantity),
+                ("o_revenue", revenue),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_price", "bigint").build())
+                    .column(ColumnBuilder::new("o_quantity", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("revenue", "bigint")
+                            .calculated(true)
+                            .expression("o_price * o_quantity")
+                            .materialized_column("o_revenue")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("computed_revenue", "bigint")
+                            .calculated(true)
+                            .expression("o_price * o_quantity")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey, revenue, computed_revenue FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("o_revenue"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------+------------------+",
+                "| o_orderkey | revenue | computed_revenue |",
+                "+------------+---------+------------------+",
+                "| 1          | 21      | 20               |",
+                "| 2          | 61      | 60               |",
+                "+------------+---------+------------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_distinct_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let status: ArrayRef =
+            Arc::new(StringArray::from_iter_values(["O", "O", "F", "O"]));
+        ctx.register_batch(
+            "orders",
+            orders_of(&[1, 1, 1, 2], vec![("o_orderstatus", status)]),
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("order_status", "varchar")
+                            .calculated(true)
+                            .expression("orders.o_orderstatus")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_orderstatus", "varchar").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .distinct(true)
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql =
+            "SELECT c_name, order_status FROM customer ORDER BY c_name, order_status";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // Gura has two open orders, which are collapsed into one row
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+--------------+",
+                "| c_name | order_status |",
+                "+--------+--------------+",
+                "| Azki   | O            |",
+                "| Gura   | F            |",
+                "| Gura   | O            |",
+                "| Ina    |              |",
+                "+--------+--------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_result_width() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("c_address", "varchar").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze(manifest)?.with_max_result_width(2));
+        let err = transform_with_mdl(&analyzed_mdl, "SELECT * FROM customer")
+            .await
+            .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::ResultWidthExceeded {
+                columns: 3,
+                max_columns: 2,
+            })
+        );
+        assert!(err.to_string().contains(
+            "The query outputs 3 columns, which exceeds the maximum result width 2"
+        ));
+
+        transform_with_mdl(&analyzed_mdl, "SELECT c_custkey, c_name FROM customer")
+            .await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_as_of_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "trades",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "t_id",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "t_symbol",
+                    Arc::new(StringArray::from_iter_values(["A", "A", "B", "B"])),
+                ),
+                ("t_time", Arc::new(Int64Array::from(vec![25, 30, 10, 20]))),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "quotes",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "q_id",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "q_symbol",
+                    Arc::new(StringArray::from_iter_values(["A", "A", "B", "A"])),
+                ),
+                ("q_time", Arc::new(Int64Array::from(vec![10, 20, 15, 30]))),
+                (
+                    "q_price",
+                    Arc::new(Float64Array::from(vec![1.0, 2.0, 5.0, 3.0])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("trades")
+                    .table_reference("trades")
+                    .column(ColumnBuilder::new("t_id", "bigint").build())
+                    .column(ColumnBuilder::new("t_symbol", "varchar").build())
+                    .column(ColumnBuilder::new("t_time", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "quote",
+                            "quotes",
+                            "trades_quotes",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("quote_price", "double")
+                            .calculated(true)
+                            .expression("quote.q_price")
+                            .build(),
+                    )
+                    .primary_key("t_id")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("quotes")
+                    .table_reference("quotes")
+                    .column(ColumnBuilder::new("q_id", "bigint").build())
+                    .column(ColumnBuilder::new("q_symbol", "varchar").build())
+                    .column(ColumnBuilder::new("q_time", "bigint").build())
+                    .column(ColumnBuilder::new("q_price", "double").build())
+                    .primary_key("q_id")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("trades_quotes")
+                    .model("trades")
+                    .model("quotes")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("trades.t_symbol = quotes.q_symbol")
+                    .as_of("quotes.q_time", "trades.t_time")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT t_id, quote_price FROM trades ORDER BY t_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // each trade joins the latest quote of its symbol at or before the trade time
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------+-------------+",
+                "| t_id | quote_price |",
+                "+------+-------------+",
+                "| 1    | 2.0         |",
+                "| 2    | 3.0         |",
+                "| 3    |             |",
+                "| 4    | 5.0         |",
+                "+------+-------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_model_schema() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(customer_model().primary_key("c_custkey").build())
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("discounted_price", "double")
+                            .calculated(true)
+                            .expression("o_totalprice * 0.9")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("customer_name", "varchar")
+                            .calculated(true)
+                            .expression("customer.c_name")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let schema = analyzed_mdl.model_schema("orders")?;
+        // the relationship column isn't a part of the schema
+        assert_eq!(schema.fields().len(), 5);
+        for (name, data_type) in [
+            ("o_orderkey", DataType::Int64),
+            ("o_custkey", DataType::Int64),
+            ("o_totalprice", DataType::Float64),
+            ("discounted_price", DataType::Float64),
+            ("customer_name", DataType::Utf8),
+        ] {
+            assert_eq!(
+                schema.field_with_unqualified_name(name)?.data_type(),
+                &data_type
+            );
+        }
+
+        let err = analyzed_mdl.model_schema("lineitem").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Model lineitem not found"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_parent_scoped_aggregation() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "lineitem",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "l_id",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                ("l_orderkey", Arc::new(Int64Array::from(vec![1, 1, 2]))),
+                (
+                    "l_extendedprice",
+                    Arc::new(Float64Array::from(vec![100.0, 300.0, 50.0])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("lineitem")
+                    .table_reference("lineitem")
+                    .column(ColumnBuilder::new("l_id", "bigint").build())
+                    .column(ColumnBuilder::new("l_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("l_extendedprice", "double").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "lineitem_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("order_share", "double")
+                            .expression("l_extendedprice / sum(l_extendedprice)")
+                            .parent_scope("orders")
+                            .build(),
+                    )
+                    .primary_key("l_id")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("lineitem_orders")
+                    .model("lineitem")
+                    .model("orders")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("lineitem.l_orderkey = orders.o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT l_id, order_share FROM lineitem ORDER BY l_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the order total is a window partitioned by the order key of the line item
+        assert!(
+            actual.contains("OVER (PARTITION BY __source.l_orderkey"),
+            "{actual}"
+        );
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------+-------------+",
+                "| l_id | order_share |",
+                "+------+-------------+",
+                "| 1    | 0.25        |",
+                "| 2    | 0.75        |",
+                "| 3    | 1.0         |",
+                "+------+-------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_relationship_scope() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                ("o_custkey", Arc::new(Int64Array::from(vec![1, 1, 2, 3]))),
+                (
+                    "o_totalprice",
+                    Arc::new(Float64Array::from(vec![100.0, 300.0, 50.0, 70.0])),
+                ),
+                (
+                    "o_region",
+                    Arc::new(StringArray::from_iter_values(["EU", "US", "EU", "US"])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("region_total", "double")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(ColumnBuilder::new("o_region", "varchar").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .scope("orders.o_region = $region")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_custkey, region_total FROM customer ORDER BY c_custkey";
+
+        // only the orders of the region are joined
+        let session =
+            SessionContext::new_with_config(SessionConfig::new().with_extension(
+                Arc::new(QueryContext::new().with_variable("region", "EU")),
+            ));
+        let actual =
+            transform_sql_with_ctx(&session, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(actual.contains("o_region = 'EU'"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+--------------+",
+                "| c_custkey | region_total |",
+                "+-----------+--------------+",
+                "| 1         | 100.0        |",
+                "| 2         | 50.0         |",
+                "| 3         |              |",
+                "+-----------+--------------+",
+            ],
+            &result
+        );
+
+        // the scope can't be planned without the region
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::UnboundParameter {
+                parameter: "$region".to_string(),
+                scope: "orders.o_region = $region".to_string(),
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_latest_per_key() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "prices",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "p_id",
+                    Arc::new(Int64Array::from(vec![1, 1, 2, 2, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "p_value",
+                    Arc::new(Float64Array::from(vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0])),
+                ),
+                (
+                    "p_updated_at",
+                    Arc::new(Int64Array::from(vec![10, 20, 30, 10, 20, 10])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("prices")
+                    .table_reference("prices")
+                    .column(ColumnBuilder::new("p_id", "bigint").build())
+                    .column(ColumnBuilder::new("p_value", "double").build())
+                    .column(ColumnBuilder::new("p_updated_at", "bigint").build())
+                    .latest(&["p_id"], "p_updated_at")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT p_id, p_value FROM prices ORDER BY p_id";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the order column isn't required by the query but still decides the latest row
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------+---------+",
+                "| p_id | p_value |",
+                "+------+---------+",
+                "| 1    | 2.0     |",
+                "| 2    | 3.0     |",
+                "| 3    | 6.0     |",
+                "+------+---------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_calculation_grouped_by_expression_dimension() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("balances", balances())?;
+        ctx.register_batch(
+            "months",
+            RecordBatch::try_from_iter(vec![(
+                "m_date",
+                // 2024-01-15, 2024-02-10
+                Arc::new(Date32Array::from(vec![19737, 19763])) as ArrayRef,
+            )])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("months")
+                    .table_reference("months")
+                    .column(ColumnBuilder::new("m_date", "date").build())
+                    .column(
+                        ColumnBuilder::new("m_month", "timestamp")
+                            .expression("date_trunc('month', m_date)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "balances",
+                            "balances",
+                            "months_balances",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("total_balance", "bigint")
+                            .calculated(true)
+                            .expression("sum(balances.balance)")
+                            .build(),
+                    )
+                    .primary_key("m_month")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("balances")
+                    .table_reference("balances")
+                    .column(ColumnBuilder::new("account", "varchar").build())
+                    .column(ColumnBuilder::new("snapshot_date", "date").build())
+                    .column(ColumnBuilder::new("balance", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("b_month", "timestamp")
+                            .expression("date_trunc('month', snapshot_date)")
+                            .build(),
+                    )
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("months_balances")
+                    .model("months")
+                    .model("balances")
+                    .join_type(JoinType::OneToMany)
+                    .condition("months.m_month = balances.b_month")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT m_month, total_balance FROM months ORDER BY m_month";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the aggregate is grouped by the truncated month and projected by the same column
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------------+---------------+",
+                "| m_month             | total_balance |",
+                "+---------------------+---------------+",
+                "| 2024-01-01T00:00:00 | 300           |",
+                "| 2024-02-01T00:00:00 | 350           |",
+                "+---------------------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_count_null_as_distinct() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 1, 2, 2])) as ArrayRef,
+                ),
+                (
+                    "o_status",
+                    Arc::new(StringArray::from(vec![
+                        Some("F"),
+                        None,
+                        Some("O"),
+                        None,
+                        None,
+                    ])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("status_count", "bigint")
+                            .calculated(true)
+                            .expression("count(DISTINCT orders.o_status)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("status_bucket_count", "bigint")
+                            .calculated(true)
+                            .expression("count(DISTINCT orders.o_status)")
+                            .count_null_as_distinct(true)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_status", "varchar").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, status_count, status_bucket_count FROM customer \
+            WHERE c_custkey IN (1, 2) ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the standard distinct count ignores NULL, the other counts it as one more bucket
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+--------------+---------------------+",
+                "| c_name | status_count | status_bucket_count |",
+                "+--------+--------------+---------------------+",
+                "| Azki   | 0            | 1                   |",
+                "| Gura   | 2            | 3                   |",
+                "+--------+--------------+---------------------+",
+            ],
+            &result
+        );
+
+        // only the distinct count can count NULL as a distinct value
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("order_count", "bigint")
+                            .calculated(true)
+                            .expression("count(orders.o_orderkey)")
+                            .count_null_as_distinct(true)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(orders_model().primary_key("o_orderkey").build())
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        match transform_with_mdl(&analyzed_mdl, "SELECT order_count FROM customer").await
+        {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(
+                e.to_string().contains(
+                    "counting NULL as a distinct value should have a distinct count"
+                ),
+                "{e}"
+            ),
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_single_row_model() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Float64Array::from(vec![100.0, 200.0, 50.0])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "settings",
+            RecordBatch::try_from_iter(vec![(
+                "tax_rate",
+                Arc::new(Float64Array::from(vec![0.1])) as ArrayRef,
+            )])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "settings",
+                            "settings",
+                            "orders_settings",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("o_tax", "double")
+                            .calculated(true)
+                            .expression("o_totalprice * settings.tax_rate")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("settings")
+                    .table_reference("settings")
+                    .column(ColumnBuilder::new("tax_rate", "double").build())
+                    .single_row(true)
+                    .build(),
+            )
+            .relationship(
+                // the settings apply to every order, so there isn't any join key
+                RelationshipBuilder::new("orders_settings")
+                    .model("orders")
+                    .model("settings")
+                    .join_type(JoinType::ManyToMany)
+                    .condition("true")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey, o_tax FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the single-row model is joined as a scalar, each order is kept once without aggregation
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+-------+",
+                "| o_orderkey | o_tax |",
+                "+------------+-------+",
+                "| 1          | 10.0  |",
+                "| 2          | 20.0  |",
+                "| 3          | 5.0   |",
+                "+------------+-------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_null_safe_relationship() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "c_custkey",
+                    Arc::new(Int64Array::from(vec![Some(1), None])) as ArrayRef,
+                ),
+                (
+                    "c_name",
+                    Arc::new(StringArray::from_iter_values(["Gura", "Azki"])),
+                ),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                ("o_custkey", Arc::new(Int64Array::from(vec![Some(1), None]))),
+            ])?,
+        )?;
+        let manifest = |null_safe: bool| {
+            manifest_builder()
+                .model(customer_model().build())
+                .model(
+                    orders_model()
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "customer",
+                                "customer",
+                                "orders_customer",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("customer_name", "varchar")
+                                .calculated(true)
+                                .expression("customer.c_name")
+                                .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("orders_customer")
+                        .model("orders")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("orders.o_custkey = customer.c_custkey")
+                        .null_safe(null_safe)
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT o_orderkey, customer_name FROM orders ORDER BY o_orderkey";
+
+        // the NULL key of the order matches the NULL key of the customer
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(true))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("IS NOT DISTINCT FROM"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------------+",
+                "| o_orderkey | customer_name |",
+                "+------------+---------------+",
+                "| 1          | Gura          |",
+                "| 2          | Azki          |",
+                "+------------+---------------+",
+            ],
+            &result
+        );
+
+        // the NULL keys never match under the standard equality
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(false))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(!actual.contains("IS NOT DISTINCT FROM"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------------+",
+                "| o_orderkey | customer_name |",
+                "+------------+---------------+",
+                "| 1          | Gura          |",
+                "| 2          |               |",
+                "+------------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_not_exists_relationship_filter() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                ("o_custkey", Arc::new(Int64Array::from(vec![1, 2, 2]))),
+                // 2024-06-20, 2024-03-01, 2024-05-15
+                (
+                    "o_orderdate",
+                    Arc::new(Date32Array::from(vec![19894, 19783, 19858])),
+                ),
+            ])?,
+        )?;
+        let recent_orders = "orders.o_orderdate > DATE '2024-07-01' - INTERVAL '30' DAY";
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("no_recent_orders", "boolean")
+                            .relationship_filter("orders", Some(recent_orders), true)
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("has_orders", "boolean")
+                            .relationship_filter("orders", None, false)
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_orderdate", "date").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        // the customers with the recent orders are excluded by the correlated anti-join
+        let sql = "SELECT c_name FROM customer WHERE no_recent_orders ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        assert!(actual.contains("NOT EXISTS"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+",
+                "| c_name |",
+                "+--------+",
+                "| Azki   |",
+                "| Ina    |",
+                "+--------+",
+            ],
+            &result
+        );
+
+        // the customers without any order are excluded by the semi-join
+        let sql = "SELECT c_name FROM customer WHERE has_orders ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+",
+                "| c_name |",
+                "+--------+",
+                "| Gura   |",
+                "| Azki   |",
+                "+--------+",
+            ],
+            &result
+        );
+        Ok(())
     }
 }
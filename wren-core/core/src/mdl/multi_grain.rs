@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Manifest, MultiGrainMeasure, ViewField};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::utils::{push_compiled_view, quoted};
+
+/// Compile the multi-grain measures into the views computing the measure at the detail grain and
+/// at the coarser parent grain at once.
+///
+/// The measure is aggregated twice over the model, grouped by all the dimensions and by the
+/// parent dimensions only. The parent aggregation is joined back on the parent dimensions, so
+/// each detail row carries its subtotal `<measure>_subtotal` and its share of the subtotal
+/// `<measure>_share`. The NULL parent dimensions are matched too, so the detail rows of a NULL
+/// group aren't lost. e.g. the revenue share of the statuses in each region is compiled to
+/// ```sql
+/// SELECT __detail."region", __detail."status", __detail."revenue",
+///   __parent."revenue" AS "revenue_subtotal",
+///   CAST(__detail."revenue" AS DOUBLE) / nullif(__parent."revenue", 0) AS "revenue_share"
+/// FROM (SELECT o_region AS "region", o_orderstatus AS "status", sum(o_totalprice) AS "revenue"
+///   FROM "orders" GROUP BY 1, 2) AS __detail
+/// JOIN (SELECT o_region AS "region", sum(o_totalprice) AS "revenue" FROM "orders" GROUP BY 1)
+///   AS __parent ON __detail."region" IS NOT DISTINCT FROM __parent."region"
+/// ```
+/// Without the parent dimensions, the subtotal is the grand total.
+pub(crate) fn compile_multi_grain_measures(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.multi_grain_measures.is_empty() {
+        return Ok(manifest);
+    }
+    for measure in std::mem::take(&mut manifest.multi_grain_measures) {
+        let statement = multi_grain_statement(&manifest, &measure)?;
+        push_compiled_view(&mut manifest, &measure.name, statement)?;
+    }
+    Ok(manifest)
+}
+
+fn multi_grain_statement(
+    manifest: &Manifest,
+    multi_grain: &MultiGrainMeasure,
+) -> Result<String> {
+    let name = &multi_grain.name;
+    let model = &multi_grain.model;
+    if !manifest.models.iter().any(|m| &m.name == model) {
+        return plan_err!(
+            "The model {model} of the multi-grain measure {name} isn't found"
+        );
+    }
+    if multi_grain.dimensions.is_empty() {
+        return plan_err!("The multi-grain measure {name} should have a dimension");
+    }
+    let measure = &multi_grain.measure;
+    let mut names = HashSet::from([measure.name.as_str()]);
+    for dimension in &multi_grain.dimensions {
+        if !names.insert(dimension.name.as_str()) {
+            return plan_err!(
+                "The field {} of the multi-grain measure {name} is defined more than once",
+                dimension.name
+            );
+        }
+    }
+    let parent_dimensions = multi_grain
+        .parent_dimensions
+        .iter()
+        .map(|parent| {
+            match multi_grain.dimensions.iter().find(|d| &d.name == parent) {
+                Some(dimension) => Ok(dimension),
+                None => plan_err!(
+                    "The parent dimension {parent} of the multi-grain measure {name} isn't one of its dimensions"
+                ),
+            }
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if parent_dimensions.len() == multi_grain.dimensions.len() {
+        return plan_err!(
+            "The parent grain of the multi-grain measure {name} should be coarser than its dimensions"
+        );
+    }
+
+    let dimensions = multi_grain.dimensions.iter().collect::<Vec<_>>();
+    let detail = aggregate(model, &dimensions, measure, "__detail")?;
+    let parent = aggregate(model, &parent_dimensions, measure, "__parent")?;
+    let join = if parent_dimensions.is_empty() {
+        format!("CROSS JOIN {parent}")
+    } else {
+        let on = parent_dimensions
+            .iter()
+            .map(|d| {
+                format!(
+                    "__detail.{0} IS NOT DISTINCT FROM __parent.{0}",
+                    quoted(&d.name)
+                )
+            })
+            .collect::<Vec<_>>();
+        format!("JOIN {parent} ON {}", on.join(" AND "))
+    };
+    let value = quoted(&measure.name);
+    let mut fields = dimensions
+        .iter()
+        .map(|d| format!("__detail.{}", quoted(&d.name)))
+        .collect::<Vec<_>>();
+    fields.push(format!("__detail.{value}"));
+    fields.push(format!(
+        "__parent.{value} AS {}",
+        quoted(&format!("{}_subtotal", measure.name))
+    ));
+    fields.push(format!(
+        "CAST(__detail.{value} AS DOUBLE) / nullif(__parent.{value}, 0) AS {}",
+        quoted(&format!("{}_share", measure.name))
+    ));
+    Ok(format!("SELECT {} FROM {detail} {join}", fields.join(", ")))
+}
+
+/// Aggregate the measure over the model grouped by the dimensions
+fn aggregate(
+    model: &str,
+    dimensions: &[&ViewField],
+    measure: &ViewField,
+    alias: &str,
+) -> Result<String> {
+    let mut fields = dimensions
+        .iter()
+        .map(|dimension| {
+            Ok(format!(
+                "{} AS {}",
+                parse_expr_snippet(&dimension.expression)?,
+                quoted(&dimension.name)
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    fields.push(format!(
+        "{} AS {}",
+        parse_expr_snippet(&measure.expression)?,
+        quoted(&measure.name)
+    ));
+    let mut statement = format!("SELECT {} FROM {}", fields.join(", "), quoted(model));
+    if !dimensions.is_empty() {
+        let group_by = (1..=dimensions.len())
+            .map(|i| i.to_string())
+            .collect::<Vec<_>>();
+        statement.push_str(&format!(" GROUP BY {}", group_by.join(", ")));
+    }
+    Ok(format!("({statement}) AS {alias}"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, MultiGrainMeasureBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_multi_grain_measure() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_region",
+                    Arc::new(StringArray::from_iter_values(["EU", "EU", "US"])),
+                ),
+                (
+                    "o_orderstatus",
+                    Arc::new(StringArray::from_iter_values(["F", "O", "F"])),
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Int64Array::from(vec![100, 300, 200])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_region", "varchar").build())
+                    .column(ColumnBuilder::new("o_orderstatus", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .multi_grain_measure(
+                MultiGrainMeasureBuilder::new("region_revenue_share", "orders")
+                    .dimension("region", "o_region")
+                    .dimension("status", "o_orderstatus")
+                    .parent_dimension("region")
+                    .measure("revenue", "sum(o_totalprice)")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT region, status, revenue, revenue_subtotal, revenue_share \
+            FROM region_revenue_share ORDER BY region, status";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        // the revenue of each status and the subtotal of its region
+        assert_batches_eq!(
+            [
+                "+--------+--------+---------+------------------+---------------+",
+                "| region | status | revenue | revenue_subtotal | revenue_share |",
+                "+--------+--------+---------+------------------+---------------+",
+                "| EU     | F      | 100     | 400              | 0.25          |",
+                "| EU     | O      | 300     | 400              | 0.75          |",
+                "| US     | F      | 200     | 200              | 1.0           |",
+                "+--------+--------+---------+------------------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
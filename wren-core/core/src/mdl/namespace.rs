@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, visit_relations_mut, Expr, Ident, ObjectName, Statement,
+};
+
+use crate::mdl::manifest::{Manifest, Model, Relationship};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::WrenMDL;
+
+/// The separator of the namespace and the name of a model outside the default namespace
+const NAMESPACE_SEPARATOR: &str = "__";
+
+/// Return the unique name of the model in the namespace, e.g. `finance__orders`. The models of the
+/// default namespace keep their names.
+fn namespaced_name(
+    default_namespace: Option<&str>,
+    namespace: &str,
+    name: &str,
+) -> String {
+    if default_namespace == Some(namespace) {
+        name.to_string()
+    } else {
+        format!("{namespace}{NAMESPACE_SEPARATOR}{name}")
+    }
+}
+
+/// Return the resolved name of the namespaced model reference, e.g. `finance.orders`. The bare
+/// reference isn't namespaced, so it refers to the default namespace.
+pub(crate) fn resolve_reference(
+    default_namespace: Option<&str>,
+    reference: &str,
+) -> Option<String> {
+    let (namespace, name) = reference.split_once('.')?;
+    Some(namespaced_name(default_namespace, namespace, name))
+}
+
+/// Resolve the namespaces of the models into their names, so the models of different namespaces
+/// can share a name, e.g. `finance.orders` and `ops.orders`.
+///
+/// The models outside the default namespace are renamed by [namespaced_name]. The namespaced
+/// references of the relationships, the relationship columns and the extended models are resolved
+/// to the new names, so the relationships can join the models across the namespaces. The bare
+/// references refer to the default namespace.
+pub(crate) fn resolve_namespaces(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.models.iter().all(|m| m.namespace.is_none()) {
+        return Ok(manifest);
+    }
+    let default_namespace = manifest.default_namespace.clone();
+    let default_namespace = default_namespace.as_deref();
+    let mut names: HashMap<String, String> = HashMap::new();
+    for model in &manifest.models {
+        let name = match model.namespace() {
+            Some(namespace) => {
+                namespaced_name(default_namespace, namespace, model.name())
+            }
+            None => model.name().to_string(),
+        };
+        let reference = match model.namespace() {
+            Some(namespace) => format!("{namespace}.{}", model.name()),
+            None => model.name().to_string(),
+        };
+        if let Some((other, _)) = names.iter().find(|(_, resolved)| **resolved == name) {
+            return plan_err!("The model {reference} conflicts with the model {other}");
+        }
+        names.insert(reference, name);
+    }
+    let resolve = |reference: &str| -> String {
+        match names.get(reference) {
+            Some(name) if reference.contains('.') => name.clone(),
+            _ => reference.to_string(),
+        }
+    };
+
+    manifest.models = manifest
+        .models
+        .iter()
+        .map(|model| {
+            let mut resolved = Model::clone(model);
+            if let Some(namespace) = model.namespace() {
+                resolved.name =
+                    namespaced_name(default_namespace, namespace, model.name());
+            }
+            resolved.namespace = None;
+            resolved.extends = model.extends.as_deref().map(resolve);
+            resolved.columns = model
+                .columns
+                .iter()
+                .map(|column| {
+                    if column.relationship.is_none() {
+                        return Arc::clone(column);
+                    }
+                    let mut column = column.as_ref().clone();
+                    column.r#type = resolve(&column.r#type);
+                    Arc::new(column)
+                })
+                .collect();
+            Arc::new(resolved)
+        })
+        .collect();
+    manifest.relationships = manifest
+        .relationships
+        .iter()
+        .map(|relationship| {
+            let mut resolved = Relationship::clone(relationship);
+            resolved.models = relationship.models.iter().map(|m| resolve(m)).collect();
+            resolved.condition = resolve_qualifiers(&relationship.condition, &names)?;
+            resolved.scope = relationship
+                .scope
+                .as_deref()
+                .map(|scope| resolve_qualifiers(scope, &names))
+                .transpose()?;
+            Ok(Arc::new(resolved))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+/// Resolve the namespaced qualifiers of the columns in the expression, e.g.
+/// `finance.orders.o_custkey` to `finance__orders.o_custkey`
+fn resolve_qualifiers(
+    expression: &str,
+    names: &HashMap<String, String>,
+) -> Result<String> {
+    let mut expr = parse_expr_snippet(expression)?;
+    let mut resolved = false;
+    let _ = visit_expressions_mut(&mut expr, |e| {
+        if let Expr::CompoundIdentifier(ids) = e {
+            if let [namespace, model, column] = ids.as_slice() {
+                if let Some(name) =
+                    names.get(&format!("{}.{}", namespace.value, model.value))
+                {
+                    *ids = vec![Ident::new(name), column.clone()];
+                    resolved = true;
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    Ok(if resolved {
+        expr.to_string()
+    } else {
+        expression.to_string()
+    })
+}
+
+/// Resolve the namespaced models used by the query to their names, e.g. `finance.orders` and the
+/// column `finance.orders.o_custkey`. The reference not resolving to a model isn't changed.
+pub(crate) fn resolve_query_namespaces(wren_mdl: &WrenMDL, statement: &mut Statement) {
+    let resolve = |namespace: &Ident, model: &Ident| {
+        let name = resolve_reference(
+            wren_mdl.default_namespace(),
+            &format!("{}.{}", namespace.value, model.value),
+        )?;
+        if !wren_mdl.models().iter().any(|m| m.name() == name) {
+            return None;
+        }
+        let mut resolved = model.clone();
+        resolved.value = name;
+        Some(resolved)
+    };
+    let _ = visit_relations_mut(statement, |name| {
+        if let [namespace, model] = name.0.as_slice() {
+            if let Some(resolved) = resolve(namespace, model) {
+                *name = ObjectName(vec![resolved]);
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    let _ = visit_expressions_mut(statement, |e| {
+        if let Expr::CompoundIdentifier(ids) = e {
+            if let [namespace, model, column] = ids.as_slice() {
+                if let Some(resolved) = resolve(namespace, model) {
+                    *ids = vec![resolved, column.clone()];
+                }
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_namespaced_models() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "finance_orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                ("o_custkey", Arc::new(Int64Array::from(vec![1, 1, 2]))),
+                ("o_amount", Arc::new(Int64Array::from(vec![100, 200, 50]))),
+            ])?,
+        )?;
+        ctx.register_batch(
+            "ops_orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2])) as ArrayRef,
+                ),
+                (
+                    "o_status",
+                    Arc::new(StringArray::from_iter_values(["shipped", "packed"])),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "invoices",
+                            "finance.orders",
+                            "customer_invoices",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("invoice_total", "bigint")
+                            .expression("sum(invoices.o_amount)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .namespace("finance")
+                    .table_reference("finance_orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_amount", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("orders")
+                    .namespace("ops")
+                    .table_reference("ops_orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_status", "varchar").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_invoices")
+                    .model("customer")
+                    .model("finance.orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = finance.orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+
+        // the models of the same name are resolved by their namespaces
+        let wren_mdl = analyzed_mdl.wren_mdl();
+        let finance_orders = wren_mdl.get_model("finance.orders").unwrap();
+        assert_eq!(finance_orders.table_reference(), "finance_orders");
+        let ops_orders = wren_mdl.get_model("ops.orders").unwrap();
+        assert_eq!(ops_orders.table_reference(), "ops_orders");
+        assert!(wren_mdl.get_model("orders").is_none());
+
+        let sql = "SELECT ops.orders.o_orderkey, o_status FROM ops.orders ORDER BY 1";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+----------+",
+                "| o_orderkey | o_status |",
+                "+------------+----------+",
+                "| 1          | shipped  |",
+                "| 2          | packed   |",
+                "+------------+----------+",
+            ],
+            &result
+        );
+
+        // the relationship joins the model of the default namespace to the finance one
+        let sql = "SELECT c_name, invoice_total FROM customer ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+---------------+",
+                "| c_name | invoice_total |",
+                "+--------+---------------+",
+                "| Gura   | 300           |",
+                "| Azki   | 50            |",
+                "| Ina    |               |",
+                "+--------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
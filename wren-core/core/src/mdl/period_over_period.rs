@@ -0,0 +1,163 @@
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Manifest, PeriodOverPeriod};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::utils::{push_compiled_view, quoted};
+
+/// Compile the period-over-period metrics into the views comparing the measure of each period
+/// with the prior one.
+///
+/// The measure is aggregated over the model grouped by the time dimension truncated to the grain.
+/// The aggregation is self-joined on the period shifted by the offset, so each period carries the
+/// `value` of the prior period as `prior_value`, the `delta` and the `growth` from it. The first
+/// periods without a prior one are kept with the NULL comparisons. e.g. the month-over-month
+/// revenue is compiled to
+/// ```sql
+/// SELECT __current."period", __current."value", __prior."value" AS "prior_value",
+///   __current."value" - __prior."value" AS "delta",
+///   CAST(__current."value" - __prior."value" AS DOUBLE) / nullif(__prior."value", 0) AS "growth"
+/// FROM (SELECT date_trunc('month', o_orderdate) AS "period", sum(o_totalprice) AS "value"
+///   FROM "orders" GROUP BY 1) AS __current
+/// LEFT JOIN (SELECT date_trunc('month', o_orderdate) AS "period", sum(o_totalprice) AS "value"
+///   FROM "orders" GROUP BY 1) AS __prior
+///   ON __prior."period" = __current."period" - INTERVAL '1 month'
+/// ```
+pub(crate) fn compile_period_over_periods(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.period_over_periods.is_empty() {
+        return Ok(manifest);
+    }
+    for metric in std::mem::take(&mut manifest.period_over_periods) {
+        let statement = period_over_period_statement(&manifest, &metric)?;
+        push_compiled_view(&mut manifest, &metric.name, statement)?;
+    }
+    Ok(manifest)
+}
+
+fn period_over_period_statement(
+    manifest: &Manifest,
+    metric: &PeriodOverPeriod,
+) -> Result<String> {
+    let name = &metric.name;
+    if !manifest.models.iter().any(|m| m.name == metric.model) {
+        return plan_err!(
+            "The model {} of the period-over-period metric {name} isn't found",
+            metric.model
+        );
+    }
+    let offset = metric.offset.unwrap_or(1);
+    if offset == 0 {
+        return plan_err!(
+            "The offset of the period-over-period metric {name} should be positive"
+        );
+    }
+    let aggregated = format!(
+        "(SELECT date_trunc('{}', {}) AS \"period\", {} AS \"value\" FROM {} GROUP BY 1)",
+        metric.grain,
+        parse_expr_snippet(&metric.time_dimension)?,
+        parse_expr_snippet(&metric.measure)?,
+        quoted(&metric.model)
+    );
+    Ok(format!(
+        "SELECT __current.\"period\", __current.\"value\", __prior.\"value\" AS \"prior_value\", \
+        __current.\"value\" - __prior.\"value\" AS \"delta\", \
+        CAST(__current.\"value\" - __prior.\"value\" AS DOUBLE) / nullif(__prior.\"value\", 0) AS \"growth\" \
+        FROM {aggregated} AS __current LEFT JOIN {aggregated} AS __prior \
+        ON __prior.\"period\" = __current.\"period\" - INTERVAL '{offset} {}'",
+        metric.grain
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, PeriodOverPeriodBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::manifest::TimeUnit;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Date32Array, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_period_over_period() -> Result<()> {
+        let ctx = SessionContext::new();
+        // 2024-01-10, 2024-01-20, 2024-02-05, 2024-02-25, 2024-03-15
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5])) as ArrayRef,
+                ),
+                (
+                    "o_orderdate",
+                    Arc::new(Date32Array::from(vec![19732, 19742, 19758, 19778, 19797])),
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Int64Array::from(vec![60, 40, 100, 50, 120])),
+                ),
+            ])?,
+        )?;
+        let manifest = |offset: u32| {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_orderdate", "date").build())
+                        .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .period_over_period(
+                    PeriodOverPeriodBuilder::new("revenue_growth", "orders")
+                        .time_dimension("o_orderdate", TimeUnit::Month)
+                        .offset(offset)
+                        .measure("sum(o_totalprice)")
+                        .build(),
+                )
+                .build()
+        };
+        let sql = "SELECT period, value, prior_value, delta, growth FROM revenue_growth \
+            ORDER BY period";
+
+        // month over month
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(1))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the prior periods are self-joined, so the first period is kept without one
+        assert!(actual.contains("LEFT JOIN"), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------------+-------+-------------+-------+--------+",
+                "| period              | value | prior_value | delta | growth |",
+                "+---------------------+-------+-------------+-------+--------+",
+                "| 2024-01-01T00:00:00 | 100   |             |       |        |",
+                "| 2024-02-01T00:00:00 | 150   | 100         | 50    | 0.5    |",
+                "| 2024-03-01T00:00:00 | 120   | 150         | -30   | -0.2   |",
+                "+---------------------+-------+-------------+-------+--------+",
+            ],
+            &result
+        );
+
+        // the prior period of March is January with the offset of two months
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(2))?);
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+---------------------+-------+-------------+-------+--------+",
+                "| period              | value | prior_value | delta | growth |",
+                "+---------------------+-------+-------------+-------+--------+",
+                "| 2024-01-01T00:00:00 | 100   |             |       |        |",
+                "| 2024-02-01T00:00:00 | 150   |             |       |        |",
+                "| 2024-03-01T00:00:00 | 120   | 100         | 20    | 0.2    |",
+                "+---------------------+-------+-------------+-------+--------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
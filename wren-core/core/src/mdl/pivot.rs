@@ -0,0 +1,296 @@
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, Expr, FunctionArg, FunctionArgExpr, FunctionArguments,
+};
+
+use crate::mdl::manifest::{Column, Manifest, Model, Pivot};
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// The aggregate functions that can be pivoted
+const PIVOT_AGGREGATIONS: [&str; 5] = ["sum", "count", "avg", "min", "max"];
+
+/// Expand the pivoted columns into one calculated field per pivot value.
+///
+/// e.g. `revenue = sum(orders.o_totalprice)` pivoted by `orders.o_month` across `2024-01` and
+/// `2024-02` is expanded to
+/// - `revenue_2024_01 = sum(CASE WHEN orders.o_month = '2024-01' THEN orders.o_totalprice END)`
+/// - `revenue_2024_02 = sum(CASE WHEN orders.o_month = '2024-02' THEN orders.o_totalprice END)`
+///
+/// The rows of the values out of the list are ignored.
+pub(crate) fn expand_pivots(mut manifest: Manifest) -> Result<Manifest> {
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model.columns.iter().all(|c| c.pivot().is_none()) {
+                return Ok(model);
+            }
+            let mut model = Arc::unwrap_or_clone(model);
+            model.columns = model
+                .columns
+                .iter()
+                .map(|column| match column.pivot() {
+                    Some(pivot) => pivot_columns(&model, column, pivot),
+                    None => Ok(vec![Arc::clone(column)]),
+                })
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .flatten()
+                .collect();
+            Ok(Arc::new(model))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+fn pivot_columns(
+    model: &Model,
+    column: &Column,
+    pivot: &Pivot,
+) -> Result<Vec<Arc<Column>>> {
+    let Some(expression) = column.expression().filter(|_| column.is_calculated) else {
+        return plan_err!(
+            "The pivoted column {}.{} should be a calculated field",
+            model.name(),
+            column.name()
+        );
+    };
+    if pivot.values.is_empty() {
+        return plan_err!(
+            "The pivoted column {}.{} should have at least one value",
+            model.name(),
+            column.name()
+        );
+    }
+    let dimension = parse_expr_snippet(&pivot.dimension)?;
+    let mut names = model
+        .columns
+        .iter()
+        .filter(|c| c.name() != column.name())
+        .map(|c| c.name().to_string())
+        .collect::<HashSet<_>>();
+    pivot
+        .values
+        .iter()
+        .map(|value| {
+            let name = format!("{}_{}", column.name(), normalized_name(value));
+            if !names.insert(name.clone()) {
+                return plan_err!(
+                    "The pivot value {} of {}.{} is expanded to the duplicate column {}",
+                    value,
+                    model.name(),
+                    column.name(),
+                    name
+                );
+            }
+            let condition = format!("{dimension} = '{}'", value.replace('\'', "''"));
+            let mut expr = parse_expr_snippet(expression)?;
+            let mut pivoted = false;
+            let visited = visit_expressions_mut(&mut expr, |e| {
+                if let Expr::Function(function) = e {
+                    let name = function.name.to_string().to_lowercase();
+                    if PIVOT_AGGREGATIONS.contains(&name.as_str()) {
+                        if let FunctionArguments::List(list) = &mut function.args {
+                            if let Err(e) = list
+                                .args
+                                .iter_mut()
+                                .try_for_each(|arg| filter_argument(arg, &condition))
+                            {
+                                return ControlFlow::Break(e);
+                            }
+                            pivoted = true;
+                        }
+                    }
+                }
+                ControlFlow::Continue(())
+            });
+            if let ControlFlow::Break(e) = visited {
+                return Err(e);
+            }
+            if !pivoted {
+                return plan_err!(
+                    "The pivoted column {}.{} should be an aggregation",
+                    model.name(),
+                    column.name()
+                );
+            }
+            let mut pivoted_column = column.clone();
+            pivoted_column.name = name;
+            pivoted_column.expression = Some(expr.to_string());
+            pivoted_column.pivot = None;
+            Ok(Arc::new(pivoted_column))
+        })
+        .collect()
+}
+
+/// Wrap the argument of the aggregation with the condition of the pivot value
+fn filter_argument(arg: &mut FunctionArg, condition: &str) -> Result<()> {
+    if let FunctionArg::Unnamed(arg) = arg {
+        let value = match arg {
+            FunctionArgExpr::Expr(expr) => expr.to_string(),
+            // count(*) counts the rows of the pivot value
+            _ => "1".to_string(),
+        };
+        *arg = FunctionArgExpr::Expr(parse_expr_snippet(&format!(
+            "CASE WHEN {condition} THEN {value} END"
+        ))?);
+    }
+    Ok(())
+}
+
+/// The pivot value is normalized to be a part of the column name. e.g. `2024-01` to `2024_01`
+fn normalized_name(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::pivot::expand_pivots;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+
+    #[test]
+    fn test_duplicate_pivot_column() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_month", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(
+                        ColumnBuilder::new("revenue", "double")
+                            .calculated(true)
+                            .expression("sum(o_totalprice)")
+                            .pivot("o_month", &["2024-01", "2024/01"])
+                            .build(),
+                    )
+                    .build(),
+            )
+            .build();
+        let err = expand_pivots(manifest).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: The pivot value 2024/01 of orders.revenue is expanded to \
+            the duplicate column revenue_2024_01"
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pivot() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4, 5])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 1, 2, 2])) as ArrayRef,
+                ),
+                (
+                    "o_month",
+                    Arc::new(StringArray::from(vec![
+                        "2024-01", "2024-01", "2024-03", "2024-02", "2024-04",
+                    ])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Int64Array::from(vec![100, 200, 300, 400, 500])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("revenue", "bigint")
+                            .calculated(true)
+                            .expression("sum(orders.o_totalprice)")
+                            .pivot("orders.o_month", &["2024-01", "2024-02", "2024-03"])
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_month", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let customer = analyzed_mdl.wren_mdl().get_model("customer").unwrap();
+        assert!(customer.get_column("revenue").is_none());
+        assert_eq!(
+            customer
+                .get_column("revenue_2024_02")
+                .unwrap()
+                .expression()
+                .unwrap(),
+            "sum(CASE WHEN orders.o_month = '2024-02' THEN orders.o_totalprice END)"
+        );
+
+        let sql = "SELECT c_custkey, revenue_2024_01, revenue_2024_02, revenue_2024_03 \
+            FROM customer ORDER BY c_custkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+-----------+-----------------+-----------------+-----------------+",
+                "| c_custkey | revenue_2024_01 | revenue_2024_02 | revenue_2024_03 |",
+                "+-----------+-----------------+-----------------+-----------------+",
+                "| 1         | 300             |                 | 300             |",
+                "| 2         |                 | 400             |                 |",
+                "| 3         |                 |                 |                 |",
+                "+-----------+-----------------+-----------------+-----------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
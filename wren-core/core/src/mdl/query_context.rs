@@ -0,0 +1,715 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use datafusion::common::ScalarValue;
+use datafusion::prelude::SessionConfig;
+
+use crate::mdl::manifest::Column;
+
+/// The name of the variable resolved to the tenant id of the [QueryContext]
+pub const TENANT_ID: &str = "tenant_id";
+/// The name of the variable resolved to the role of the [QueryContext]
+pub const ROLE: &str = "role";
+/// The name of the variable resolved to the seed of the [QueryContext]
+pub const SEED: &str = "seed";
+
+/// [QueryContext] carries the values of the session issuing the query, e.g. the tenant and the role
+/// of the user. They're read by the plan generation, e.g. the row-level security of the columns.
+///
+/// It's registered as an extension of the [SessionConfig] of the query and passed to
+/// [crate::logical_plan::analyze::model_generation::ModelGenerationRule] when the context is
+/// created. The variable names are case-insensitive.
+#[derive(Debug, Default, Clone)]
+pub struct QueryContext {
+    tenant_id: Option<String>,
+    role: Option<String>,
+    variables: HashMap<String, ScalarValue>,
+    environment: Environment,
+    sample_fraction: Option<f64>,
+    routing_hint: Option<String>,
+    query_tag: Option<String>,
+    now: Option<i64>,
+    timezone: Option<String>,
+    seed: Option<i64>,
+}
+
+/// The environment the query is issued in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Environment {
+    Dev,
+    #[default]
+    Prod,
+}
+
+impl QueryContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_tenant_id(mut self, tenant_id: &str) -> Self {
+        self.tenant_id = Some(tenant_id.to_string());
+        self
+    }
+
+    pub fn with_role(mut self, role: &str) -> Self {
+        self.role = Some(role.to_string());
+        self
+    }
+
+    pub fn with_variable(mut self, name: &str, value: impl Into<ScalarValue>) -> Self {
+        self.variables.insert(name.to_lowercase(), value.into());
+        self
+    }
+
+    pub fn with_environment(mut self, environment: Environment) -> Self {
+        self.environment = environment;
+        self
+    }
+
+    /// Sample the fraction of the rows of every model source. It's only applied in the dev
+    /// environment for fast iteration.
+    pub fn with_sample_fraction(mut self, fraction: f64) -> Self {
+        self.sample_fraction = Some(fraction);
+        self
+    }
+
+    /// Route the scans of the models to the connection of the hint, e.g. `replica`. It takes
+    /// precedence over the routing hints of the models.
+    pub fn with_routing_hint(mut self, routing_hint: &str) -> Self {
+        self.routing_hint = Some(routing_hint.to_string());
+        self
+    }
+
+    /// Label the remote scans of the query for the cost attribution of the warehouse, e.g.
+    /// `team:growth`. The [crate::mdl::table_source::TableSourceFactory] attaches it to the
+    /// remote query, e.g. as the query tag or a session setting.
+    pub fn with_query_tag(mut self, query_tag: &str) -> Self {
+        self.query_tag = Some(query_tag.to_string());
+        self
+    }
+
+    /// Set the reference time of the relative date filters in microseconds since the epoch. It's
+    /// the current time by default.
+    pub fn with_now(mut self, now: i64) -> Self {
+        self.now = Some(now);
+        self
+    }
+
+    /// Set the timezone the relative date filters are evaluated in, e.g. `Asia/Taipei` or
+    /// `+08:00`. It's UTC by default.
+    pub fn with_timezone(mut self, timezone: &str) -> Self {
+        self.timezone = Some(timezone.to_string());
+        self
+    }
+
+    /// Set the seed of the sampling, so the sampled rows are reproducible across the runs. It's
+    /// also resolved as the variable [SEED].
+    pub fn with_seed(mut self, seed: i64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn environment(&self) -> Environment {
+        self.environment
+    }
+
+    /// Return the sample fraction applied to the model sources. It's ignored in the prod environment.
+    pub fn sample_fraction(&self) -> Option<f64> {
+        match self.environment {
+            Environment::Dev => self.sample_fraction,
+            Environment::Prod => None,
+        }
+    }
+
+    pub fn routing_hint(&self) -> Option<&str> {
+        self.routing_hint.as_deref()
+    }
+
+    pub fn query_tag(&self) -> Option<&str> {
+        self.query_tag.as_deref()
+    }
+
+    /// Return the reference time of the relative date filters in microseconds since the epoch
+    pub fn now(&self) -> i64 {
+        self.now.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_micros() as i64)
+        })
+    }
+
+    pub fn timezone(&self) -> &str {
+        self.timezone.as_deref().unwrap_or("UTC")
+    }
+
+    pub fn seed(&self) -> Option<i64> {
+        self.seed
+    }
+
+    pub fn tenant_id(&self) -> Option<&str> {
+        self.tenant_id.as_deref()
+    }
+
+    pub fn role(&self) -> Option<&str> {
+        self.role.as_deref()
+    }
+
+    /// Return the value of the variable. [TENANT_ID], [ROLE] and [SEED] are resolved to the tenant
+    /// id, the role and the seed if they aren't set as the variables.
+    pub fn variable(&self, name: &str) -> Option<ScalarValue> {
+        let name = name.to_lowercase();
+        if let Some(value) = self.variables.get(&name) {
+            return Some(value.clone());
+        }
+        match name.as_str() {
+            TENANT_ID => self.tenant_id.clone().map(ScalarValue::from),
+            ROLE => self.role.clone().map(ScalarValue::from),
+            SEED => self.seed.map(ScalarValue::from),
+            _ => None,
+        }
+    }
+
+    /// Return the string value of the variable
+    pub fn string_variable(&self, name: &str) -> Option<String> {
+        match self.variable(name)? {
+            ScalarValue::Utf8(value)
+            | ScalarValue::LargeUtf8(value)
+            | ScalarValue::Utf8View(value) => value,
+            value => Some(value.to_string()),
+        }
+    }
+
+    /// Return the integer value of the variable if it's an integer or a string of an integer
+    pub fn int_variable(&self, name: &str) -> Option<i64> {
+        match self.variable(name)? {
+            ScalarValue::Int8(value) => value.map(i64::from),
+            ScalarValue::Int16(value) => value.map(i64::from),
+            ScalarValue::Int32(value) => value.map(i64::from),
+            ScalarValue::Int64(value) => value,
+            value => value.to_string().parse().ok(),
+        }
+    }
+
+    /// Return the boolean value of the variable if it's a boolean or a string of a boolean
+    pub fn bool_variable(&self, name: &str) -> Option<bool> {
+        match self.variable(name)? {
+            ScalarValue::Boolean(value) => value,
+            value => value.to_string().to_lowercase().parse().ok(),
+        }
+    }
+
+    /// Check if the session is authorized to access the column by its column-level security. The
+    /// column is inaccessible if the variable named by the security isn't set.
+    pub fn is_authorized(&self, column: &Column) -> bool {
+        let Some(cls) = &column.cls else {
+            return true;
+        };
+        match self.variable(&cls.name) {
+            Some(
+                ScalarValue::Utf8(Some(value))
+                | ScalarValue::LargeUtf8(Some(value))
+                | ScalarValue::Utf8View(Some(value)),
+            ) => cls.eval(&format!("'{value}'")),
+            Some(value) if !value.is_null() => cls.eval(&value.to_string()),
+            _ => false,
+        }
+    }
+
+    /// Check if the role of the session is allowed to query the column. The column without the
+    /// allowed roles is open to every role.
+    pub fn is_allowed(&self, column: &Column) -> bool {
+        let allowed_roles = column.allowed_roles();
+        allowed_roles.is_empty()
+            || self
+                .role
+                .as_ref()
+                .is_some_and(|role| allowed_roles.contains(role))
+    }
+
+    /// The key of the values the rows of the model sources depend on, e.g. the variables of the
+    /// row-level security and the sampling. The relations cached by
+    /// [crate::mdl::cache::ModelCache] are only reused by the sessions of the same key. The
+    /// query tag and the reference time don't change the rows of the sources, so they're excluded.
+    pub fn cache_scope(&self) -> String {
+        let mut variables = self
+            .variables
+            .iter()
+            .map(|(name, value)| format!("{name}={value:?}"))
+            .collect::<Vec<_>>();
+        variables.sort();
+        format!(
+            "tenant={:?};role={:?};variables=[{}];environment={:?};sample={:?};routing={:?};timezone={:?};seed={:?}",
+            self.tenant_id,
+            self.role,
+            variables.join(","),
+            self.environment,
+            self.sample_fraction(),
+            self.routing_hint,
+            self.timezone,
+            self.seed,
+        )
+    }
+
+    /// Get the query context registered in the config of the session. It's empty if not registered.
+    pub fn from_config(config: &SessionConfig) -> Arc<Self> {
+        config.get_extension::<Self>().unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::{ColumnLevelOperator, JoinType, RowLevelOperator};
+    use crate::mdl::query_context::{Environment, QueryContext};
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::WrenError;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_row_level_security_with_query_context() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_tenant",
+                    Arc::new(StringArray::from(vec!["acme", "globex", "acme"]))
+                        as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("o_tenant", "varchar")
+                            .row_level_security("TENANT_ID", RowLevelOperator::Equals)
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT o_orderkey FROM orders ORDER BY o_orderkey";
+
+        let query_context = QueryContext::new()
+            .with_tenant_id("acme")
+            .with_role("analyst")
+            .with_variable("region", "emea");
+        assert_eq!(query_context.tenant_id(), Some("acme"));
+        assert_eq!(query_context.role(), Some("analyst"));
+        assert_eq!(
+            query_context.string_variable("REGION"),
+            Some("emea".to_string())
+        );
+        let session = SessionContext::new_with_config(
+            SessionConfig::new().with_extension(Arc::new(query_context)),
+        );
+        let actual =
+            transform_sql_with_ctx(&session, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(actual.contains("__source.o_tenant = 'acme'"));
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+",
+                "| o_orderkey |",
+                "+------------+",
+                "| 1          |",
+                "| 3          |",
+                "+------------+",
+            ],
+            &result
+        );
+
+        // the query is rejected if the tenant isn't provided
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::MissingQueryVariable {
+                variable: "TENANT_ID".to_string(),
+                column: "orders.o_tenant".to_string(),
+            })
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_sample_in_dev() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(customer_model().primary_key("c_custkey").build())
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_custkey, c_name FROM customer";
+        let session = |query_context: QueryContext| {
+            SessionContext::new_with_config(
+                SessionConfig::new().with_extension(Arc::new(query_context)),
+            )
+        };
+
+        let dev = QueryContext::new()
+            .with_environment(Environment::Dev)
+            .with_sample_fraction(0.1);
+        let actual =
+            transform_sql_with_ctx(&session(dev), Arc::clone(&analyzed_mdl), &[], sql)
+                .await?;
+        assert_eq!(actual,
+                   "SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, \
+                   __source.c_name AS c_name FROM customer AS __source WHERE random() < 0.1) AS customer");
+
+        // the sampling is ignored in prod
+        let prod = QueryContext::new().with_sample_fraction(0.1);
+        let actual =
+            transform_sql_with_ctx(&session(prod), Arc::clone(&analyzed_mdl), &[], sql)
+                .await?;
+        assert!(!actual.contains("random()"));
+
+        let invalid = QueryContext::new()
+            .with_environment(Environment::Dev)
+            .with_sample_fraction(1.5);
+        let err = transform_sql_with_ctx(&session(invalid), analyzed_mdl, &[], sql)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The sample fraction should be in (0, 1]: 1.5"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_column() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new("c_phone", "varchar")
+                            .column_level_security(
+                                "ROLE",
+                                ColumnLevelOperator::Equals,
+                                "'admin'",
+                            )
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let session = |role: &str| {
+            SessionContext::new_with_config(
+                SessionConfig::new()
+                    .with_extension(Arc::new(QueryContext::new().with_role(role))),
+            )
+        };
+
+        let actual = transform_sql_with_ctx(
+            &session("admin"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT * FROM customer",
+        )
+        .await?;
+        assert!(actual.contains("c_phone"));
+
+        // the unauthorized column is excluded from the wildcard
+        let actual = transform_sql_with_ctx(
+            &session("analyst"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT * FROM customer",
+        )
+        .await?;
+        assert_eq!(actual,
+                   "SELECT customer.c_custkey, customer.c_name FROM (SELECT __source.c_custkey AS c_custkey, \
+                   __source.c_name AS c_name FROM customer AS __source) AS customer");
+
+        let err = transform_sql_with_ctx(
+            &session("analyst"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT c_name, c_phone FROM customer",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::UnauthorizedColumn {
+                model: "customer".to_string(),
+                column: "c_phone".to_string(),
+            })
+        );
+        // the column is inaccessible without the role
+        let err = transform_with_mdl(&analyzed_mdl, "SELECT c_phone FROM customer")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains(
+            "The session isn't authorized to access the column customer.c_phone"
+        ));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_column_through_calculated_field() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new("c_acctbal", "bigint")
+                            .column_level_security(
+                                "ROLE",
+                                ColumnLevelOperator::Equals,
+                                "'admin'",
+                            )
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("c_acctbal_plus", "bigint")
+                            .expression("c_acctbal + 1")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "customer",
+                            "customer",
+                            "orders_customer",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("customer_acctbal", "bigint")
+                            .expression("customer.c_acctbal")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("orders_customer")
+                    .model("orders")
+                    .model("customer")
+                    .join_type(JoinType::ManyToOne)
+                    .condition("orders.o_custkey = customer.c_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let session = |role: &str| {
+            SessionContext::new_with_config(
+                SessionConfig::new()
+                    .with_extension(Arc::new(QueryContext::new().with_role(role))),
+            )
+        };
+        for sql in [
+            "SELECT c_name, c_acctbal_plus FROM customer",
+            "SELECT o_orderkey, customer_acctbal FROM orders",
+        ] {
+            let _ = transform_sql_with_ctx(
+                &session("admin"),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                sql,
+            )
+            .await?;
+
+            // the unauthorized column can't be read through the calculated field either
+            let err = transform_sql_with_ctx(
+                &session("analyst"),
+                Arc::clone(&analyzed_mdl),
+                &[],
+                sql,
+            )
+            .await
+            .unwrap_err();
+            assert_eq!(
+                WrenError::find_in(&err),
+                Some(&WrenError::UnauthorizedColumn {
+                    model: "customer".to_string(),
+                    column: "c_acctbal".to_string(),
+                }),
+                "{sql}"
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_seeded_sample() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let manifest = manifest_builder()
+            .model(customer_model().primary_key("c_custkey").build())
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_custkey, c_name FROM customer ORDER BY c_custkey";
+        let query_context = QueryContext::new()
+            .with_environment(Environment::Dev)
+            .with_sample_fraction(0.5)
+            .with_seed(42);
+        let session = SessionContext::new_with_config(
+            SessionConfig::new().with_extension(Arc::new(query_context)),
+        );
+        let actual =
+            transform_sql_with_ctx(&session, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        // the primary key is hashed with the seed instead of random()
+        assert!(!actual.contains("random()"));
+        assert!(actual
+            .contains("md5(concat_ws('|', '42', CAST(__source.c_custkey AS VARCHAR)))"));
+        assert!(actual.contains("< '80000000'"));
+
+        // the same rows are sampled across the runs
+        for _ in 0..2 {
+            let actual =
+                transform_sql_with_ctx(&session, Arc::clone(&analyzed_mdl), &[], sql)
+                    .await?;
+            let result = ctx.sql(&actual).await?.collect().await?;
+            assert_batches_eq!(
+                [
+                    "+-----------+--------+",
+                    "| c_custkey | c_name |",
+                    "+-----------+--------+",
+                    "| 2         | Azki   |",
+                    "+-----------+--------+",
+                ],
+                &result
+            );
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_allowed_roles() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                orders_model()
+                    .column(
+                        ColumnBuilder::new("o_totalprice", "bigint")
+                            .allowed_roles(&["finance", "admin"])
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("o_totalprice_plus", "bigint")
+                            .expression("o_totalprice + 1")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("payments")
+                    .table_reference("payments")
+                    .column(ColumnBuilder::new("p_paymentkey", "bigint").build())
+                    .column(
+                        ColumnBuilder::new("o_totalprice", "bigint")
+                            .allowed_roles(&["admin"])
+                            .build(),
+                    )
+                    .primary_key("p_paymentkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let session = |role: &str| {
+            SessionContext::new_with_config(
+                SessionConfig::new()
+                    .with_extension(Arc::new(QueryContext::new().with_role(role))),
+            )
+        };
+
+        let sql = "SELECT o_custkey, sum(o_totalprice) FROM orders GROUP BY o_custkey";
+        let actual = transform_sql_with_ctx(
+            &session("finance"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            sql,
+        )
+        .await?;
+        assert!(actual.contains("sum(orders.o_totalprice)"), "{actual}");
+
+        // the measure isn't whitelisted for the role
+        let err = transform_sql_with_ctx(
+            &session("support"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            sql,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::DisallowedColumn {
+                role: Some("support".to_string()),
+                model: "orders".to_string(),
+                column: "o_totalprice".to_string(),
+            })
+        );
+        assert!(err.to_string().contains(
+            "The role support isn't allowed to query the column orders.o_totalprice"
+        ));
+
+        // the columns open to every role pass
+        let actual = transform_sql_with_ctx(
+            &session("support"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT o_custkey, count(o_orderkey) FROM orders GROUP BY o_custkey",
+        )
+        .await?;
+        assert!(actual.contains("count(orders.o_orderkey)"), "{actual}");
+
+        // the measure can't be read through a calculated field either
+        let err = transform_sql_with_ctx(
+            &session("support"),
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT o_orderkey, o_totalprice_plus FROM orders",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::DisallowedColumn {
+                role: Some("support".to_string()),
+                model: "orders".to_string(),
+                column: "o_totalprice".to_string(),
+            })
+        );
+
+        // the column is explained by the model of the query, not another one of the same name
+        let err = transform_sql_with_ctx(
+            &session("support"),
+            analyzed_mdl,
+            &[],
+            "SELECT o_totalprice FROM payments",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::DisallowedColumn {
+                role: Some("support".to_string()),
+                model: "payments".to_string(),
+                column: "o_totalprice".to_string(),
+            })
+        );
+        Ok(())
+    }
+}
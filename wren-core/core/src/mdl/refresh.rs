@@ -0,0 +1,206 @@
+use crate::mdl::context::create_ctx_with_mdl;
+use crate::mdl::dialect::WrenDialect;
+use crate::mdl::utils::quoted;
+use crate::mdl::AnalyzedWrenMDL;
+use crate::WrenError;
+use datafusion::common::{plan_err, Result, ScalarValue};
+use datafusion::logical_expr::{ident, lit, Expr, LogicalPlan};
+use datafusion::prelude::SessionContext;
+use datafusion::sql::unparser::Unparser;
+use std::sync::Arc;
+
+/// [RefreshPlan] refreshes the changed partitions of a materialized model.
+///
+/// The source only scans the changed partitions of the model. The partitions are replaced in the
+/// materialization by deleting them and inserting the rows of the source again, so the partitions
+/// that didn't change aren't touched.
+#[derive(Debug, Clone)]
+pub struct RefreshPlan {
+    pub model: String,
+    /// The table the model is materialized to
+    pub target: String,
+    pub partition_column: String,
+    pub partitions: Vec<ScalarValue>,
+    pub source: LogicalPlan,
+}
+
+impl RefreshPlan {
+    /// Generate the `DELETE` and `INSERT` statements replacing the changed partitions with the
+    /// dialect of the data source. They should be executed in order and in one transaction.
+    pub fn to_sql(&self, analyzed_mdl: &AnalyzedWrenMDL) -> Result<Vec<String>> {
+        let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
+        let dialect = WrenDialect::new(&data_source);
+        let unparser = Unparser::new(&dialect);
+        let predicate = unparser.expr_to_sql(&partition_predicate(
+            &self.partition_column,
+            &self.partitions,
+        ))?;
+        let source = unparser.plan_to_sql(&self.source)?;
+        let columns = self
+            .source
+            .schema()
+            .fields()
+            .iter()
+            .map(|f| quoted(f.name()))
+            .collect::<Vec<_>>()
+            .join(", ");
+        Ok(vec![
+            format!("DELETE FROM {} WHERE {predicate}", self.target),
+            format!("INSERT INTO {} ({columns}) {source}", self.target),
+        ])
+    }
+}
+
+/// Create the [RefreshPlan] of the materialized model for the changed partitions.
+///
+/// The source reads the physical columns of the model. The model should declare its
+/// materialization and the partition column should be one of its columns.
+pub async fn create_refresh_plan(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    model_name: &str,
+    partitions: &[ScalarValue],
+) -> Result<RefreshPlan> {
+    let Some(model) = analyzed_mdl.wren_mdl().get_model(model_name) else {
+        return Err(WrenError::ModelNotFound(model_name.to_string()).into());
+    };
+    let Some(materialization) = model.materialization() else {
+        return plan_err!("The model {model_name} isn't materialized");
+    };
+    if model
+        .get_column(&materialization.partition_column)
+        .is_none()
+    {
+        return plan_err!(
+            "The partition column {} isn't found in {model_name}",
+            materialization.partition_column
+        );
+    }
+    if partitions.is_empty() {
+        return plan_err!("No changed partition of {model_name} to refresh");
+    }
+
+    let columns = model
+        .get_physical_columns()
+        .iter()
+        .map(|c| quoted(c.name()))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let ctx = create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), false).await?;
+    let source = ctx
+        .sql(&format!("SELECT {columns} FROM {}", quoted(model_name)))
+        .await?
+        .filter(partition_predicate(
+            &materialization.partition_column,
+            partitions,
+        ))?
+        .into_optimized_plan()?;
+    Ok(RefreshPlan {
+        model: model_name.to_string(),
+        target: materialization.target.clone(),
+        partition_column: materialization.partition_column.clone(),
+        partitions: partitions.to_vec(),
+        source,
+    })
+}
+
+fn partition_predicate(partition_column: &str, partitions: &[ScalarValue]) -> Expr {
+    ident(partition_column).in_list(partitions.iter().cloned().map(lit).collect(), false)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::manifest_builder;
+    use crate::mdl::refresh::create_refresh_plan;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::common::tree_node::{TreeNode, TreeNodeRecursion};
+    use datafusion::common::{Result, ScalarValue};
+    use datafusion::logical_expr::{Expr, LogicalPlan};
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_create_refresh_plan() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_orderdate", "varchar").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .primary_key("o_orderkey")
+                    .materialization("mat.orders", "o_orderdate")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx = SessionContext::new();
+        let partitions = [
+            ScalarValue::from("2024-01-01"),
+            ScalarValue::from("2024-01-02"),
+        ];
+
+        let refresh =
+            create_refresh_plan(&ctx, Arc::clone(&analyzed_mdl), "orders", &partitions)
+                .await?;
+        assert_eq!(refresh.target, "mat.orders");
+        // the source only scans the two changed partitions
+        let mut scanned = vec![];
+        refresh.source.apply(|plan| {
+            if let LogicalPlan::Filter(filter) = plan {
+                filter.predicate.apply(|e| {
+                    if let Expr::InList(in_list) = e {
+                        scanned.extend(in_list.list.iter().map(|e| e.to_string()));
+                    }
+                    Ok(TreeNodeRecursion::Continue)
+                })?;
+            }
+            Ok(TreeNodeRecursion::Continue)
+        })?;
+        assert_eq!(
+            scanned,
+            vec![
+                "Utf8(\"2024-01-01\")".to_string(),
+                "Utf8(\"2024-01-02\")".to_string()
+            ]
+        );
+
+        let sql = refresh.to_sql(&analyzed_mdl)?;
+        assert_eq!(sql.len(), 2);
+        assert_eq!(
+            sql[0],
+            "DELETE FROM mat.orders WHERE o_orderdate IN ('2024-01-01', '2024-01-02')"
+        );
+        assert!(
+            sql[1].starts_with(
+                r#"INSERT INTO mat.orders ("o_orderkey", "o_orderdate", "o_totalprice") SELECT "#
+            ),
+            "{}",
+            sql[1]
+        );
+        assert!(
+            sql[1].contains("IN ('2024-01-01', '2024-01-02')"),
+            "{}",
+            sql[1]
+        );
+
+        // the model without the materialization can't be refreshed
+        let err =
+            create_refresh_plan(&ctx, Arc::clone(&analyzed_mdl), "customer", &partitions)
+                .await
+                .unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("The model customer isn't materialized"),
+            "{err}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Column, Manifest, Model, Relationship};
+
+/// Resolve the relationship of the relationship columns declared without one.
+///
+/// A column typed by a model without a relationship traverses the only relationship between the
+/// two models. If several relationships connect them, e.g. `orders` to `customer` by the billing
+/// and the shipping customer, the path is ambiguous and the column should name the relationship.
+pub(crate) fn resolve_relationship_paths(mut manifest: Manifest) -> Result<Manifest> {
+    let models = manifest.models.clone();
+    let relationships = manifest.relationships.clone();
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model.columns.iter().all(|c| !is_unnamed_path(&models, c)) {
+                return Ok(model);
+            }
+            let mut resolved = Model::clone(&model);
+            resolved.columns = model
+                .columns
+                .iter()
+                .map(|column| {
+                    if !is_unnamed_path(&models, column) {
+                        return Ok(Arc::clone(column));
+                    }
+                    let Some(relationship) =
+                        resolve_path(&relationships, &model, column)?
+                    else {
+                        return Ok(Arc::clone(column));
+                    };
+                    let mut column = column.as_ref().clone();
+                    column.relationship = Some(relationship.name.clone());
+                    Ok(Arc::new(column))
+                })
+                .collect::<Result<_>>()?;
+            Ok(Arc::new(resolved))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+/// Return true if the column is typed by a model but doesn't name the relationship to traverse
+fn is_unnamed_path(models: &[Arc<Model>], column: &Column) -> bool {
+    column.relationship.is_none()
+        && !column.is_calculated
+        && column.expression.is_none()
+        && models.iter().any(|m| m.name() == column.r#type)
+}
+
+fn resolve_path<'a>(
+    relationships: &'a [Arc<Relationship>],
+    model: &Model,
+    column: &Column,
+) -> Result<Option<&'a Arc<Relationship>>> {
+    let candidates = relationships
+        .iter()
+        .filter(|r| connects(r, model.name(), &column.r#type))
+        .collect::<Vec<_>>();
+    match candidates.as_slice() {
+        [] => Ok(None),
+        [relationship] => Ok(Some(relationship)),
+        _ => plan_err!(
+            "The relationship column {}.{} is ambiguous between the relationships {}, specify one of them",
+            model.name(),
+            column.name(),
+            candidates
+                .iter()
+                .map(|r| r.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn connects(relationship: &Relationship, left: &str, right: &str) -> bool {
+    match relationship.models.as_slice() {
+        [a, b] => (a == left && b == right) || (a == right && b == left),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl;
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_relationship_path() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        let orderkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let billing: ArrayRef = Arc::new(Int64Array::from(vec![1, 2]));
+        let shipping: ArrayRef = Arc::new(Int64Array::from(vec![3, 1]));
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                ("o_orderkey", orderkey),
+                ("o_billing_custkey", billing),
+                ("o_shipping_custkey", shipping),
+            ])?,
+        )?;
+        let manifest = |customer_column: Arc<mdl::manifest::Column>| {
+            manifest_builder()
+                .model(customer_model().primary_key("c_custkey").build())
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_billing_custkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new("o_shipping_custkey", "bigint").build(),
+                        )
+                        .column(customer_column)
+                        .column(
+                            ColumnBuilder::new("customer_name", "varchar")
+                                .calculated(true)
+                                .expression("customer.c_name")
+                                .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("orders_billing_customer")
+                        .model("orders")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("orders.o_billing_custkey = customer.c_custkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("orders_shipping_customer")
+                        .model("orders")
+                        .model("customer")
+                        .join_type(JoinType::ManyToOne)
+                        .condition("orders.o_shipping_custkey = customer.c_custkey")
+                        .build(),
+                )
+                .build()
+        };
+
+        // the named path is traversed
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(
+            ColumnBuilder::new_relationship(
+                "customer",
+                "customer",
+                "orders_shipping_customer",
+            )
+            .build(),
+        ))?);
+        let sql = "SELECT o_orderkey, customer_name FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------------+",
+                "| o_orderkey | customer_name |",
+                "+------------+---------------+",
+                "| 1          | Ina           |",
+                "| 2          | Gura          |",
+                "+------------+---------------+",
+            ],
+            &result
+        );
+
+        // the unnamed path is ambiguous between the two relationships
+        let Err(e) = AnalyzedWrenMDL::analyze(manifest(
+            ColumnBuilder::new("customer", "customer").build(),
+        )) else {
+            panic!("expected the ambiguous relationship path to fail");
+        };
+        assert!(
+            e.to_string().contains(
+                "The relationship column orders.customer is ambiguous between the relationships \
+                orders_billing_customer, orders_shipping_customer"
+            ),
+            "{e}"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use crate::logical_plan::utils::map_data_type;
+use datafusion::arrow::datatypes::{DataType, DECIMAL128_MAX_PRECISION};
+use datafusion::common::{Result, ScalarValue};
+use datafusion::functions::expr_fn::{abs, round};
+use datafusion::logical_expr::{cast, lit, when, Expr};
+
+/// [RoundingMode] is how the half between the two neighbors is rounded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RoundingMode {
+    /// Round the half away from zero, e.g. `0.25` to `0.3` and `-0.25` to `-0.3`
+    HalfUp,
+    /// Round the half to the even neighbor, i.e. the banker's rounding, e.g. `0.25` to `0.2` and
+    /// `0.35` to `0.4`
+    HalfEven,
+}
+
+/// [RoundingPolicy] rounds the decimal results of the calculated fields to the scale by the
+/// mode, so the financial calculations are rounded consistently by any data source.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RoundingPolicy {
+    mode: RoundingMode,
+    scale: u32,
+}
+
+impl RoundingPolicy {
+    pub fn new(mode: RoundingMode, scale: u32) -> Self {
+        Self { mode, scale }
+    }
+
+    pub fn mode(&self) -> RoundingMode {
+        self.mode
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// Round the decimal expression to the scale of the policy.
+    ///
+    /// The `round` function rounds the half away from zero. The half-even rounding is planned by
+    /// checking the fraction of the scaled value, which is rounded to the even neighbor of the
+    /// truncated value, e.g. `2.5` is rounded to `2` and `3.5` to `4`. The halves are checked in
+    /// the decimal type because a float can't represent them exactly.
+    pub(crate) fn round(&self, expr: Expr, data_type: &DataType) -> Result<Expr> {
+        let rounded = round(vec![expr.clone(), lit(self.scale as i64)]);
+        match self.mode {
+            RoundingMode::HalfUp => Ok(rounded),
+            RoundingMode::HalfEven => {
+                let scaled =
+                    cast(expr, data_type.clone()) * decimal(10_i128.pow(self.scale), 0);
+                let fraction = scaled.clone() % decimal(1, 0);
+                let truncated = scaled - fraction.clone();
+                let is_half = abs(fraction.clone()).eq(decimal(5, 1));
+                let is_even = (truncated.clone() % decimal(2, 0)).eq(decimal(0, 0));
+                let half_even = when(is_even, truncated.clone())
+                    .otherwise(truncated + fraction * decimal(2, 0))?
+                    * decimal(1, self.scale as i8);
+                Ok(cast(
+                    when(is_half, half_even)
+                        .otherwise(cast(rounded, data_type.clone()))?,
+                    data_type.clone(),
+                ))
+            }
+        }
+    }
+}
+
+fn decimal(value: i128, scale: i8) -> Expr {
+    lit(ScalarValue::Decimal128(
+        Some(value),
+        DECIMAL128_MAX_PRECISION,
+        scale,
+    ))
+}
+
+/// Round the calculated field declared as a decimal by the policy. The other types are kept.
+pub(crate) fn round_decimal(
+    expr: Expr,
+    r#type: &str,
+    policy: Option<&RoundingPolicy>,
+) -> Result<Expr> {
+    let Some(policy) = policy else {
+        return Ok(expr);
+    };
+    match map_data_type(r#type)? {
+        data_type @ (DataType::Decimal128(_, _) | DataType::Decimal256(_, _)) => {
+            policy.round(expr, &data_type)
+        }
+        _ => Ok(expr),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::rounding::{RoundingMode, RoundingPolicy};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{
+        ArrayRef, Decimal128Array, Float64Array, Int64Array, RecordBatch,
+    };
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_rounding_policy() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Float64Array::from(vec![1.0, 3.0, 5.0, -1.0])),
+                ),
+                ("o_quantity", Arc::new(Int64Array::from(vec![4, 4, 4, 4]))),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_totalprice", "double").build())
+                    .column(ColumnBuilder::new("o_quantity", "bigint").build())
+                    .column(
+                        ColumnBuilder::new_calculated("o_unit_price", "decimal")
+                            .expression("o_totalprice / o_quantity")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let sql = "SELECT o_orderkey, o_unit_price FROM orders ORDER BY o_orderkey";
+
+        // the halves are rounded away from zero
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest.clone())?
+                .with_rounding_policy(RoundingPolicy::new(RoundingMode::HalfUp, 1)),
+        );
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+--------------+",
+                "| o_orderkey | o_unit_price |",
+                "+------------+--------------+",
+                "| 1          | 0.3          |",
+                "| 2          | 0.8          |",
+                "| 3          | 1.3          |",
+                "| 4          | -0.3         |",
+                "+------------+--------------+",
+            ],
+            &result
+        );
+
+        // the halves are rounded to the even neighbors
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest)?
+                .with_rounding_policy(RoundingPolicy::new(RoundingMode::HalfEven, 1)),
+        );
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+------------+---------------+",
+                "| o_orderkey | o_unit_price  |",
+                "+------------+---------------+",
+                "| 1          | 0.2000000000  |",
+                "| 2          | 0.8000000000  |",
+                "| 3          | 1.2000000000  |",
+                "| 4          | -0.2000000000 |",
+                "+------------+---------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_rounding_decimal_column() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3, 4])) as ArrayRef,
+                ),
+                (
+                    "o_discount",
+                    Arc::new(
+                        Decimal128Array::from(vec![1005, 1015, 2675, -1005])
+                            .with_precision_and_scale(10, 3)?,
+                    ),
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .column(ColumnBuilder::new("o_discount", "decimal").build())
+                    .column(
+                        ColumnBuilder::new_calculated("o_rounded_discount", "decimal")
+                            .expression("o_discount")
+                            .build(),
+                    )
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(
+            AnalyzedWrenMDL::analyze(manifest)?
+                .with_rounding_policy(RoundingPolicy::new(RoundingMode::HalfEven, 2)),
+        );
+        let sql = "SELECT o_orderkey, o_rounded_discount FROM orders ORDER BY o_orderkey";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        // the halves like 2.675 aren't exact in a float, they're checked in the decimal type
+        assert_batches_eq!(
+            [
+                "+------------+--------------------+",
+                "| o_orderkey | o_rounded_discount |",
+                "+------------+--------------------+",
+                "| 1          | 1.0000000000       |",
+                "| 2          | 1.0200000000       |",
+                "| 3          | 2.6800000000       |",
+                "| 4          | -1.0000000000      |",
+                "+------------+--------------------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{Expr, ObjectName, Statement};
+use datafusion::sql::sqlparser::dialect::GenericDialect;
+use datafusion::sql::sqlparser::parser::Parser;
+use datafusion::sql::sqlparser::tokenizer::Token;
+
+use crate::mdl::manifest::Manifest;
+
+/// Check the raw SQL snippets of the manifest before they're planned or embedded in the
+/// generated SQL. The expressions of the columns, the base filters of the models, the conditions
+/// and the scopes of the relationships should be a single expression, and the SQL of the inline
+/// models and the statements of the views should be a single query.
+///
+/// The snippets are parsed by [parse_expr_snippet] and [parse_query_snippet] wherever they're
+/// rewritten too, so they're validated by the same rules.
+pub(crate) fn validate_snippets(manifest: Manifest) -> Result<Manifest> {
+    for model in &manifest.models {
+        if let Some(ref_sql) = &model.ref_sql {
+            parse_query_snippet(ref_sql)?;
+        }
+        for column in &model.columns {
+            if let Some(expression) = column.expression() {
+                parse_expr_snippet(expression)?;
+            }
+        }
+        if let Some(base_filter) = model.base_filter() {
+            parse_expr_snippet(base_filter)?;
+        }
+    }
+    for relationship in &manifest.relationships {
+        parse_expr_snippet(&relationship.condition)?;
+        if let Some(scope) = relationship.scope() {
+            parse_expr_snippet(scope)?;
+        }
+    }
+    for view in &manifest.views {
+        parse_query_snippet(&view.statement)?;
+    }
+    Ok(manifest)
+}
+
+/// Parse the raw SQL snippet of exactly one expression, e.g. the expression of a calculated field.
+///
+/// The parser stops at the end of the first expression, so the tokens left after it are
+/// rejected, e.g. the statement after a semicolon in `sum(amount); DROP TABLE orders` or the
+/// second expression of `a, b`.
+pub(crate) fn parse_expr_snippet(sql: &str) -> Result<Expr> {
+    let mut parser = match Parser::new(&GenericDialect {}).try_with_sql(sql) {
+        Ok(parser) => parser,
+        Err(e) => return plan_err!("Invalid SQL expression `{sql}`: {e}"),
+    };
+    let expr = match parser.parse_expr() {
+        Ok(expr) => expr,
+        Err(e) => return plan_err!("Invalid SQL expression `{sql}`: {e}"),
+    };
+    match parser.peek_token().token {
+        Token::EOF => Ok(expr),
+        token => plan_err!(
+            "Invalid SQL expression `{sql}`: only a single expression is allowed, found {token}"
+        ),
+    }
+}
+
+/// Parse the raw SQL snippet of exactly one query, e.g. the statement of a view. The other
+/// statements, e.g. `DROP TABLE`, and the statements following the query are rejected.
+pub(crate) fn parse_query_snippet(sql: &str) -> Result<Statement> {
+    let statements = match Parser::parse_sql(&GenericDialect {}, sql) {
+        Ok(statements) => statements,
+        Err(e) => return plan_err!("Invalid SQL query `{sql}`: {e}"),
+    };
+    match statements.as_slice() {
+        [statement @ Statement::Query(_)] => Ok(statement.clone()),
+        [_] => plan_err!("Invalid SQL query `{sql}`: only a query is allowed"),
+        _ => plan_err!("Invalid SQL query `{sql}`: only a single statement is allowed"),
+    }
+}
+
+/// Parse the raw SQL snippet of exactly one object name, e.g. the table reference of a model.
+pub(crate) fn parse_object_name_snippet(sql: &str) -> Result<ObjectName> {
+    let mut parser = match Parser::new(&GenericDialect {}).try_with_sql(sql) {
+        Ok(parser) => parser,
+        Err(e) => return plan_err!("Invalid SQL object name `{sql}`: {e}"),
+    };
+    let name = match parser.parse_object_name(false) {
+        Ok(name) => name,
+        Err(e) => return plan_err!("Invalid SQL object name `{sql}`: {e}"),
+    };
+    match parser.peek_token().token {
+        Token::EOF => Ok(name),
+        token => plan_err!(
+            "Invalid SQL object name `{sql}`: only a single object name is allowed, found {token}"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{
+        ColumnBuilder, ManifestBuilder, ModelBuilder, ViewBuilder,
+    };
+    use crate::mdl::fixture::customer;
+    use crate::mdl::manifest::{Manifest, Model};
+    use crate::mdl::snippet::validate_snippets;
+    use datafusion::common::Result;
+    use std::sync::Arc;
+
+    fn customer(expression: &str) -> Arc<Model> {
+        ModelBuilder::new("customer")
+            .table_reference("customer")
+            .column(ColumnBuilder::new("c_custkey", "int").build())
+            .column(
+                ColumnBuilder::new_calculated("total", "int")
+                    .expression(expression)
+                    .build(),
+            )
+            .primary_key("c_custkey")
+            .build()
+    }
+
+    fn assert_rejected(manifest: Manifest, reason: &str) {
+        match validate_snippets(manifest) {
+            Ok(_) => panic!("expected the snippet to be rejected: {reason}"),
+            Err(e) => assert!(e.to_string().contains(reason), "{e}"),
+        }
+    }
+
+    #[test]
+    fn test_reject_raw_sql_snippet() -> Result<()> {
+        let manifest = ManifestBuilder::new()
+            .model(customer("sum(c_custkey); DROP TABLE customer"))
+            .build();
+        assert_rejected(manifest, "only a single expression is allowed, found ;");
+
+        for (statement, reason) in [
+            ("DROP TABLE customer", "only a query is allowed"),
+            (
+                "SELECT * FROM customer; DROP TABLE customer",
+                "only a single statement is allowed",
+            ),
+        ] {
+            let manifest = ManifestBuilder::new()
+                .model(customer("sum(c_custkey)"))
+                .view(
+                    ViewBuilder::new("customer_view")
+                        .statement(statement)
+                        .build(),
+                )
+                .build();
+            assert_rejected(manifest, reason);
+
+            // the SQL of the inline model is a query too
+            let manifest = ManifestBuilder::new()
+                .model(
+                    ModelBuilder::new("customer")
+                        .ref_sql(statement)
+                        .column(ColumnBuilder::new("c_custkey", "int").build())
+                        .build(),
+                )
+                .build();
+            assert_rejected(manifest, reason);
+        }
+
+        let manifest = ManifestBuilder::new()
+            .model(
+                ModelBuilder::new("customer")
+                    .ref_sql("SELECT * FROM customer")
+                    .column(ColumnBuilder::new("c_custkey", "int").build())
+                    .build(),
+            )
+            .view(
+                ViewBuilder::new("customer_view")
+                    .statement("SELECT * FROM customer")
+                    .build(),
+            )
+            .build();
+        validate_snippets(manifest)?;
+        Ok(())
+    }
+}
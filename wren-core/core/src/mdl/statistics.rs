@@ -0,0 +1,307 @@
+use crate::mdl::context::create_ctx_with_mdl;
+use crate::mdl::utils::quoted;
+use crate::mdl::AnalyzedWrenMDL;
+use datafusion::arrow::array::{Array, Int64Array};
+use datafusion::common::{internal_err, plan_err, Result};
+use datafusion::prelude::SessionContext;
+use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// [ModelStatistics] is the approximate size of a model, e.g. estimated from a sample of its
+/// source by [collect_statistics].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModelStatistics {
+    row_count: usize,
+    distinct_counts: HashMap<String, usize>,
+}
+
+impl ModelStatistics {
+    pub fn new(row_count: usize) -> Self {
+        Self {
+            row_count,
+            distinct_counts: HashMap::new(),
+        }
+    }
+
+    pub fn with_distinct_count(mut self, column: &str, distinct_count: usize) -> Self {
+        self.distinct_counts
+            .insert(column.to_string(), distinct_count);
+        self
+    }
+
+    /// Return the estimated row count of the model
+    pub fn row_count(&self) -> usize {
+        self.row_count
+    }
+
+    /// Return the estimated count of the distinct values of the column
+    pub fn distinct_count(&self, column: &str) -> Option<usize> {
+        self.distinct_counts.get(column).copied()
+    }
+}
+
+/// [StatisticsStore] keeps the statistics of the models for the planning, e.g. to join the
+/// calculations over the smaller models first. It's shared by the [AnalyzedWrenMDL], so the
+/// statistics can be collected or injected after the manifest is analyzed.
+#[derive(Debug, Default)]
+pub struct StatisticsStore {
+    statistics: Mutex<HashMap<String, ModelStatistics>>,
+}
+
+impl StatisticsStore {
+    /// Put the statistics of the model. The previous statistics of the model will be replaced.
+    pub fn put(&self, model: &str, statistics: ModelStatistics) {
+        self.statistics.lock().insert(model.to_string(), statistics);
+    }
+
+    /// Get the statistics of the model
+    pub fn get(&self, model: &str) -> Option<ModelStatistics> {
+        self.statistics.lock().get(model).cloned()
+    }
+
+    /// Return the estimated row count of the model
+    pub fn row_count(&self, model: &str) -> Option<usize> {
+        self.statistics.lock().get(model).map(|s| s.row_count)
+    }
+
+    /// Return true if no statistics are collected
+    pub fn is_empty(&self) -> bool {
+        self.statistics.lock().is_empty()
+    }
+}
+
+/// Estimate the row count and the distinct counts of the stored columns of the model by
+/// sampling the fraction of its rows locally, and put them to the statistics of the
+/// [AnalyzedWrenMDL].
+///
+/// The counts of the sample are scaled by the fraction. The distinct count of a column is only
+/// scaled if every sampled value is distinct, e.g. a key. Otherwise, the sampled count is kept
+/// because the values are likely repeated beyond the sample.
+pub async fn collect_statistics(
+    ctx: &SessionContext,
+    analyzed_mdl: Arc<AnalyzedWrenMDL>,
+    model: &str,
+    fraction: f64,
+) -> Result<ModelStatistics> {
+    if fraction <= 0.0 || fraction > 1.0 {
+        return plan_err!("The sample fraction should be in (0, 1]: {fraction}");
+    }
+    let wren_mdl = analyzed_mdl.wren_mdl();
+    let Some(model_ref) = wren_mdl.get_model(model) else {
+        return plan_err!("Model {} not found", model);
+    };
+    let columns = model_ref
+        .get_physical_columns()
+        .into_iter()
+        .filter(|c| !c.is_calculated)
+        .map(|c| c.name().to_string())
+        .collect::<Vec<_>>();
+    let projection = std::iter::once("count(*)".to_string())
+        .chain(
+            columns
+                .iter()
+                .map(|c| format!("count(DISTINCT {})", quoted(c))),
+        )
+        .collect::<Vec<_>>()
+        .join(", ");
+    let filter = if fraction < 1.0 {
+        format!(" WHERE random() < {fraction}")
+    } else {
+        String::new()
+    };
+    let sql = format!(
+        "SELECT {} FROM {}.{}.{}{}",
+        projection,
+        quoted(wren_mdl.catalog()),
+        quoted(wren_mdl.schema()),
+        quoted(model),
+        filter
+    );
+    let ctx = create_ctx_with_mdl(ctx, Arc::clone(&analyzed_mdl), true).await?;
+    let batches = ctx.sql(&sql).await?.collect().await?;
+    let Some(batch) = batches.iter().find(|b| b.num_rows() > 0) else {
+        return internal_err!("The statistics query of {} returns no row", model);
+    };
+    let count = |index: usize| -> Result<usize> {
+        let Some(array) = batch.column(index).as_any().downcast_ref::<Int64Array>()
+        else {
+            return internal_err!("The count should be an Int64 array");
+        };
+        Ok(array.value(0).max(0) as usize)
+    };
+    let sampled_rows = count(0)?;
+    let scale = |n: usize| (n as f64 / fraction).round() as usize;
+    let mut statistics = ModelStatistics::new(scale(sampled_rows));
+    for (index, column) in columns.iter().enumerate() {
+        let distinct = count(index + 1)?;
+        let distinct = if distinct == sampled_rows {
+            scale(distinct)
+        } else {
+            distinct
+        };
+        statistics = statistics.with_distinct_count(column, distinct);
+    }
+    analyzed_mdl.statistics().put(model, statistics.clone());
+    Ok(statistics)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::statistics::{collect_statistics, ModelStatistics};
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch, StringArray};
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_collect_statistics() -> Result<()> {
+        let ctx = SessionContext::new();
+        let custkey: ArrayRef = Arc::new(Int64Array::from(vec![1, 2, 3, 4]));
+        let nation: ArrayRef =
+            Arc::new(StringArray::from_iter_values(["JP", "JP", "TW", "JP"]));
+        ctx.register_batch(
+            "customer",
+            RecordBatch::try_from_iter(vec![
+                ("c_custkey", custkey),
+                ("c_nation", nation),
+            ])?,
+        )?;
+        let provider = ctx
+            .catalog("datafusion")
+            .unwrap()
+            .schema("public")
+            .unwrap()
+            .table("customer")
+            .await?
+            .unwrap();
+        let mut registers = HashMap::new();
+        registers.insert("datafusion.public.customer".to_string(), provider);
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("customer")
+                    .table_reference("datafusion.public.customer")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("c_nation", "varchar").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl =
+            Arc::new(AnalyzedWrenMDL::analyze_with_tables(manifest, registers)?);
+        let statistics =
+            collect_statistics(&ctx, Arc::clone(&analyzed_mdl), "customer", 1.0).await?;
+        assert_eq!(statistics.row_count(), 4);
+        assert_eq!(statistics.distinct_count("c_custkey"), Some(4));
+        assert_eq!(statistics.distinct_count("c_nation"), Some(2));
+        assert_eq!(analyzed_mdl.statistics().get("customer"), Some(statistics));
+
+        let err = collect_statistics(&ctx, Arc::clone(&analyzed_mdl), "customer", 0.0)
+            .await
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("The sample fraction should be in (0, 1]: 0"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_statistics_change_join_order() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "tickets",
+                            "tickets",
+                            "customer_tickets",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("order_count", "bigint")
+                            .expression("count(orders.o_orderkey)")
+                            .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new_calculated("ticket_count", "bigint")
+                            .expression("count(tickets.t_ticketkey)")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(orders_model().primary_key("o_orderkey").build())
+            .model(
+                ModelBuilder::new("tickets")
+                    .table_reference("tickets")
+                    .column(ColumnBuilder::new("t_ticketkey", "bigint").build())
+                    .column(ColumnBuilder::new("t_custkey", "bigint").build())
+                    .primary_key("t_ticketkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_tickets")
+                    .model("customer")
+                    .model("tickets")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = tickets.t_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let orders_first = |analyzed_mdl: Arc<AnalyzedWrenMDL>| async move {
+            let sql = "SELECT c_name, order_count, ticket_count FROM customer";
+            let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+            let (Some(orders), Some(tickets)) =
+                (actual.find("o_orderkey"), actual.find("t_ticketkey"))
+            else {
+                panic!("both calculations should be joined: {actual}");
+            };
+            Ok::<_, datafusion::error::DataFusionError>(orders < tickets)
+        };
+
+        // the calculations are joined in the declared order without the statistics
+        let declared = orders_first(Arc::clone(&analyzed_mdl)).await?;
+
+        // the calculation over the smaller model is joined first
+        analyzed_mdl
+            .statistics()
+            .put("orders", ModelStatistics::new(10));
+        analyzed_mdl
+            .statistics()
+            .put("tickets", ModelStatistics::new(1_000_000));
+        assert_eq!(orders_first(Arc::clone(&analyzed_mdl)).await?, declared);
+
+        analyzed_mdl
+            .statistics()
+            .put("orders", ModelStatistics::new(1_000_000));
+        analyzed_mdl
+            .statistics()
+            .put("tickets", ModelStatistics::new(10));
+        assert_eq!(orders_first(Arc::clone(&analyzed_mdl)).await?, !declared);
+        Ok(())
+    }
+}
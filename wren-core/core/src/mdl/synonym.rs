@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, visit_relations_mut, Expr, Ident, Statement,
+};
+
+use crate::mdl::manifest::{Manifest, Model};
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::WrenMDL;
+
+/// Check the synonyms of the models and columns, and resolve the synonyms used by the calculated
+/// fields to the canonical names.
+///
+/// A synonym can't be the name or a synonym of another model. The column synonyms are resolved
+/// across the models by the query, so a synonym can't refer to different column names either.
+pub(crate) fn resolve_synonyms(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest
+        .models
+        .iter()
+        .all(|m| m.synonyms.is_empty() && m.columns.iter().all(|c| c.synonyms.is_empty()))
+    {
+        return Ok(manifest);
+    }
+    let mut model_synonyms: HashMap<&str, &str> = HashMap::new();
+    let mut column_synonyms: HashMap<&str, (&str, &str)> = HashMap::new();
+    for model in &manifest.models {
+        for synonym in &model.synonyms {
+            if manifest.models.iter().any(|m| &m.name == synonym) {
+                return plan_err!(
+                    "The synonym {synonym} of the model {} is the name of a model",
+                    model.name()
+                );
+            }
+            if let Some(other) = model_synonyms.insert(synonym, model.name()) {
+                return plan_err!(
+                    "The synonym {synonym} is declared by both the models {other} and {}",
+                    model.name()
+                );
+            }
+        }
+        for column in &model.columns {
+            for synonym in &column.synonyms {
+                if model.columns.iter().any(|c| &c.name == synonym) {
+                    return plan_err!(
+                        "The synonym {synonym} of the column {}.{} is the name of a column",
+                        model.name(),
+                        column.name()
+                    );
+                }
+                if let Some((other_model, other_column)) =
+                    column_synonyms.insert(synonym, (model.name(), column.name()))
+                {
+                    if other_column != column.name() {
+                        return plan_err!(
+                            "The synonym {synonym} is declared by both the columns \
+                            {other_model}.{other_column} and {}.{}",
+                            model.name(),
+                            column.name()
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    let models = manifest.models.clone();
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model
+                .columns
+                .iter()
+                .all(|c| !c.is_calculated || c.expression.is_none())
+            {
+                return Ok(model);
+            }
+            let mut resolved = Model::clone(&model);
+            resolved.columns = model
+                .columns
+                .iter()
+                .map(|column| match column.expression() {
+                    Some(expression) if column.is_calculated => {
+                        let mut expr = parse_expr_snippet(expression)?;
+                        let _ = visit_expressions_mut(&mut expr, |e| {
+                            if let Expr::CompoundIdentifier(ids) = e {
+                                resolve_column_path(&models, &model, ids);
+                            } else if let Expr::Identifier(id) = e {
+                                resolve_column_path(
+                                    &models,
+                                    &model,
+                                    std::slice::from_mut(id),
+                                );
+                            }
+                            ControlFlow::<()>::Continue(())
+                        });
+                        let mut column = column.as_ref().clone();
+                        column.expression = Some(expr.to_string());
+                        Ok(Arc::new(column))
+                    }
+                    _ => Ok(Arc::clone(column)),
+                })
+                .collect::<Result<_>>()?;
+            Ok(Arc::new(resolved))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+/// Resolve the column synonym at the end of the relationship path of the calculated field, e.g.
+/// `orders.sales` is resolved by the model the relationship column `orders` refers to.
+fn resolve_column_path(models: &[Arc<Model>], model: &Arc<Model>, ids: &mut [Ident]) {
+    let Some((last, path)) = ids.split_last_mut() else {
+        return;
+    };
+    let mut current = Arc::clone(model);
+    for id in path.iter() {
+        let Some(target) = current
+            .columns
+            .iter()
+            .find(|c| c.name == id.value && c.relationship.is_some())
+            .and_then(|c| models.iter().find(|m| m.name == c.r#type))
+        else {
+            return;
+        };
+        current = Arc::clone(target);
+    }
+    if current.columns.iter().any(|c| c.name == last.value) {
+        return;
+    }
+    if let Some(column) = current
+        .columns
+        .iter()
+        .find(|c| c.synonyms.iter().any(|s| s == &last.value))
+    {
+        last.value.clone_from(&column.name);
+    }
+}
+
+/// Resolve the synonyms of the models and columns used by the query to the canonical names.
+///
+/// The names of the models and columns take precedence over the synonyms, so the identifier
+/// matching any of them isn't changed.
+pub(crate) fn resolve_query_synonyms(wren_mdl: &WrenMDL, statement: &mut Statement) {
+    let models = &wren_mdl.manifest.models;
+    if models
+        .iter()
+        .all(|m| m.synonyms.is_empty() && m.columns.iter().all(|c| c.synonyms.is_empty()))
+    {
+        return;
+    }
+    let resolve_model = |id: &mut Ident| {
+        if models.iter().any(|m| m.name == id.value) {
+            return;
+        }
+        if let Some(model) = models
+            .iter()
+            .find(|m| m.synonyms.iter().any(|s| s == &id.value))
+        {
+            id.value.clone_from(&model.name);
+        }
+    };
+    let resolve_column = |id: &mut Ident| {
+        if models
+            .iter()
+            .any(|m| m.columns.iter().any(|c| c.name == id.value))
+        {
+            return;
+        }
+        if let Some(column) = models
+            .iter()
+            .flat_map(|m| m.columns.iter())
+            .find(|c| c.synonyms.iter().any(|s| s == &id.value))
+        {
+            id.value.clone_from(&column.name);
+        }
+    };
+    let _ = visit_relations_mut(statement, |name| {
+        if let Some(id) = name.0.last_mut() {
+            resolve_model(id);
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    let _ = visit_expressions_mut(statement, |e| {
+        match e {
+            Expr::Identifier(id) => resolve_column(id),
+            Expr::CompoundIdentifier(ids) => {
+                if let Some((last, qualifier)) = ids.split_last_mut() {
+                    resolve_column(last);
+                    if let Some(model) = qualifier.last_mut() {
+                        resolve_model(model);
+                    }
+                }
+            }
+            _ => {}
+        }
+        ControlFlow::<()>::Continue(())
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{customer, manifest_builder, transform_with_mdl};
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_synonyms() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 2])) as ArrayRef,
+                ),
+                (
+                    "o_totalprice",
+                    Arc::new(Int64Array::from(vec![100, 200, 400])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = |order_synonyms: &[&str]| {
+            manifest_builder()
+                .model(
+                    ModelBuilder::new("customer")
+                        .table_reference("customer")
+                        .synonyms(&["client"])
+                        .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new("c_name", "varchar")
+                                .synonyms(&["customer_name"])
+                                .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new_relationship(
+                                "orders",
+                                "orders",
+                                "customer_orders",
+                            )
+                            .build(),
+                        )
+                        .column(
+                            ColumnBuilder::new("revenue", "bigint")
+                                .calculated(true)
+                                .expression("sum(orders.sales)")
+                                .build(),
+                        )
+                        .primary_key("c_custkey")
+                        .build(),
+                )
+                .model(
+                    ModelBuilder::new("orders")
+                        .table_reference("orders")
+                        .synonyms(order_synonyms)
+                        .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                        .column(ColumnBuilder::new("o_custkey", "bigint").build())
+                        .column(
+                            ColumnBuilder::new("o_totalprice", "bigint")
+                                .synonyms(&["sales"])
+                                .build(),
+                        )
+                        .primary_key("o_orderkey")
+                        .build(),
+                )
+                .relationship(
+                    RelationshipBuilder::new("customer_orders")
+                        .model("customer")
+                        .model("orders")
+                        .join_type(JoinType::OneToMany)
+                        .condition("customer.c_custkey = orders.o_custkey")
+                        .build(),
+                )
+                .build()
+        };
+
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest(&["purchase"]))?);
+        let wren_mdl = analyzed_mdl.wren_mdl();
+        let customer = wren_mdl.get_model("client").unwrap();
+        assert_eq!(customer.name(), "customer");
+        assert_eq!(
+            customer.get_column("customer_name").unwrap().name(),
+            "c_name"
+        );
+        assert_eq!(
+            customer.get_column("revenue").unwrap().expression(),
+            Some("sum(orders.o_totalprice)")
+        );
+
+        let sql = "SELECT customer_name, revenue FROM client ORDER BY customer_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+---------+",
+                "| c_name | revenue |",
+                "+--------+---------+",
+                "| Azki   | 400     |",
+                "| Gura   | 300     |",
+                "| Ina    |         |",
+                "+--------+---------+",
+            ],
+            &result
+        );
+
+        // a synonym can't refer to two models
+        match AnalyzedWrenMDL::analyze(manifest(&["client"])) {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => assert!(
+                e.to_string().contains(
+                    "The synonym client is declared by both the models customer and orders"
+                ),
+                "{e}"
+            ),
+        }
+        Ok(())
+    }
+}
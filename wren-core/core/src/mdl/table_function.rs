@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+use datafusion::error::DataFusionError;
+use datafusion::logical_expr::lit;
+use datafusion::sql::sqlparser::ast::{
+    visit_expressions_mut, visit_relations, Expr, FunctionArg, FunctionArgExpr, Ident,
+    ObjectName, Statement, TableFactor, TableFunctionArgs, Value, VisitMut, VisitorMut,
+};
+use datafusion::sql::unparser::Unparser;
+
+use crate::mdl::manifest::{Manifest, Model, TableFunction};
+use crate::mdl::query_context::QueryContext;
+use crate::mdl::snippet::parse_expr_snippet;
+use crate::mdl::WrenMDL;
+use crate::WrenError;
+
+/// The prefix of the table standing for the table function of a model in the plan
+const TABLE_FUNCTION_PREFIX: &str = "__table_function_";
+
+/// Resolve the table references of the models reading a table function, e.g. `read_json(url)`.
+///
+/// The model is planned as the scan of the table `__table_function_{model}`, so its source is
+/// registered and filtered like a table. The scan is replaced by the function call when the plan
+/// is unparsed, see [bind_table_functions].
+pub(crate) fn resolve_table_functions(mut manifest: Manifest) -> Result<Manifest> {
+    if manifest.models.iter().all(|m| m.table_function().is_none()) {
+        return Ok(manifest);
+    }
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            let Some(table_function) = model.table_function() else {
+                return Ok(model);
+            };
+            if model.table_reference.is_some() {
+                return plan_err!(
+                    "The model {} can't read both the table and the table function",
+                    model.name()
+                );
+            }
+            for arg in &table_function.args {
+                parse_expr_snippet(arg)?;
+            }
+            let mut model = Arc::unwrap_or_clone(model);
+            model.table_reference =
+                Some(format!("{TABLE_FUNCTION_PREFIX}{}", model.name));
+            Ok(Arc::new(model))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+/// Replace the scans of the table functions with the function calls, e.g.
+/// `FROM read_json('https://example.com/events.json')`. The parameters of the arguments, e.g.
+/// `$url`, are bound by the variables of the [QueryContext].
+pub(crate) fn bind_table_functions(
+    mut statement: Statement,
+    wren_mdl: &WrenMDL,
+    query_context: &QueryContext,
+    unparser: &Unparser,
+) -> Result<Statement> {
+    let mut scanned = vec![];
+    let _ = visit_relations(&statement, |name| {
+        if let Some(ident) = name.0.last() {
+            if let Some(model) = ident.value.strip_prefix(TABLE_FUNCTION_PREFIX) {
+                scanned.push(model.to_string());
+            }
+        }
+        ControlFlow::<()>::Continue(())
+    });
+    let mut calls = HashMap::new();
+    for model in scanned {
+        let Some(model) = wren_mdl.get_model(&model) else {
+            continue;
+        };
+        let Some(table_function) = model.table_function() else {
+            continue;
+        };
+        let args = bind_args(&model, table_function, query_context, unparser)?;
+        let name = ObjectName(table_function.name.split('.').map(Ident::new).collect());
+        calls.insert(
+            format!("{TABLE_FUNCTION_PREFIX}{}", model.name()),
+            (name, args),
+        );
+    }
+    if calls.is_empty() {
+        return Ok(statement);
+    }
+    let _ = statement.visit(&mut TableFunctionCalls { calls });
+    Ok(statement)
+}
+
+fn bind_args(
+    model: &Model,
+    table_function: &TableFunction,
+    query_context: &QueryContext,
+    unparser: &Unparser,
+) -> Result<Vec<FunctionArg>> {
+    table_function
+        .args
+        .iter()
+        .map(|arg| {
+            let mut expr = parse_expr_snippet(arg)?;
+            if let ControlFlow::Break(e) = visit_expressions_mut(&mut expr, |e| {
+                let Expr::Value(Value::Placeholder(parameter)) = e else {
+                    return ControlFlow::Continue(());
+                };
+                let value =
+                    match query_context.variable(parameter.trim_start_matches('$')) {
+                        Some(value) => unparser.expr_to_sql(&lit(value)),
+                        None => Err(DataFusionError::from(
+                            WrenError::UnboundTableFunctionParameter {
+                                parameter: parameter.clone(),
+                                model: model.name().to_string(),
+                            },
+                        )),
+                    };
+                match value {
+                    Ok(value) => {
+                        *e = value;
+                        ControlFlow::Continue(())
+                    }
+                    Err(err) => ControlFlow::Break(err),
+                }
+            }) {
+                return Err(e);
+            }
+            Ok(FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)))
+        })
+        .collect()
+}
+
+struct TableFunctionCalls {
+    calls: HashMap<String, (ObjectName, Vec<FunctionArg>)>,
+}
+
+impl VisitorMut for TableFunctionCalls {
+    type Break = ();
+
+    fn pre_visit_table_factor(
+        &mut self,
+        table_factor: &mut TableFactor,
+    ) -> ControlFlow<Self::Break> {
+        if let TableFactor::Table { name, args, .. } = table_factor {
+            let call = name.0.last().and_then(|ident| self.calls.get(&ident.value));
+            if let Some((function, bound)) = call {
+                *name = function.clone();
+                *args = Some(TableFunctionArgs {
+                    args: bound.clone(),
+                    settings: None,
+                });
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{manifest_builder, transform_with_mdl};
+    use crate::mdl::query_context::QueryContext;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_table_function_model() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                ModelBuilder::new("events")
+                    .table_function("read_json", &["$url", "true"])
+                    .column(ColumnBuilder::new("id", "bigint").build())
+                    .column(ColumnBuilder::new("kind", "varchar").build())
+                    .primary_key("id")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT id FROM events WHERE kind = 'click'";
+
+        // the model is scanned by calling the function with the bound arguments
+        let session = SessionContext::new_with_config(
+            SessionConfig::new().with_extension(Arc::new(
+                QueryContext::new()
+                    .with_variable("url", "https://example.com/events.json"),
+            )),
+        );
+        let actual =
+            transform_sql_with_ctx(&session, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(
+            actual.contains("FROM read_json('https://example.com/events.json', true)"),
+            "{actual}"
+        );
+        assert!(!actual.contains("__table_function_"), "{actual}");
+
+        // the parameter should be bound by the query
+        let err = transform_with_mdl(&analyzed_mdl, sql).await.unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::UnboundTableFunctionParameter {
+                parameter: "$url".to_string(),
+                model: "events".to_string(),
+            })
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::mdl::manifest::Model;
+use crate::mdl::SessionStateRef;
+use datafusion::common::Result;
+use datafusion::logical_expr::TableSource;
+use std::fmt::Debug;
+use std::sync::Arc;
+
+/// [TableSourceFactory] creates the table sources of the model scans, e.g. a source picking the
+/// connection of a read-only replica by the routing hint of the scan. The query tag of the
+/// [crate::mdl::query_context::QueryContext] labels the scan, e.g. to set the query tag of the
+/// warehouse session.
+///
+/// It's registered as an extension of the [datafusion::prelude::SessionConfig] of the query by
+/// [TableSourceFactoryRef]. The factory receives the default source of the model, so it can
+/// decorate it or replace it.
+pub trait TableSourceFactory: Debug + Send + Sync {
+    fn create_table_source(
+        &self,
+        model: &Model,
+        source: Arc<dyn TableSource>,
+        routing_hint: Option<&str>,
+        query_tag: Option<&str>,
+    ) -> Result<Arc<dyn TableSource>>;
+}
+
+/// The [TableSourceFactory] registered in the config of the session
+#[derive(Debug, Clone)]
+pub struct TableSourceFactoryRef(pub Arc<dyn TableSourceFactory>);
+
+impl TableSourceFactoryRef {
+    pub fn new(factory: Arc<dyn TableSourceFactory>) -> Self {
+        Self(factory)
+    }
+
+    /// Get the factory registered in the config of the session
+    pub(crate) fn from_session(session_state: &SessionStateRef) -> Option<Arc<Self>> {
+        session_state.read().config().get_extension::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::fixture::{customer_model, manifest_builder};
+    use crate::mdl::manifest::Model;
+    use crate::mdl::query_context::QueryContext;
+    use crate::mdl::table_source::{TableSourceFactory, TableSourceFactoryRef};
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use datafusion::common::Result;
+    use datafusion::logical_expr::TableSource;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::{Arc, Mutex};
+
+    #[tokio::test]
+    async fn test_routing_hint() -> Result<()> {
+        #[derive(Debug, Default)]
+        struct RecordingFactory {
+            hints: Mutex<Vec<(String, Option<String>)>>,
+        }
+
+        impl TableSourceFactory for RecordingFactory {
+            fn create_table_source(
+                &self,
+                model: &Model,
+                source: Arc<dyn TableSource>,
+                routing_hint: Option<&str>,
+                _query_tag: Option<&str>,
+            ) -> Result<Arc<dyn TableSource>> {
+                self.hints
+                    .lock()
+                    .unwrap()
+                    .push((model.name().to_string(), routing_hint.map(String::from)));
+                Ok(source)
+            }
+        }
+
+        let manifest = manifest_builder()
+            .model(customer_model().routing_hint("replica").build())
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let context = |factory: Arc<RecordingFactory>, query_context: QueryContext| {
+            SessionContext::new_with_config(
+                SessionConfig::new()
+                    .with_extension(Arc::new(TableSourceFactoryRef::new(factory)))
+                    .with_extension(Arc::new(query_context)),
+            )
+        };
+
+        // the scan of the replica-routed model is created with its hint
+        let factory = Arc::new(RecordingFactory::default());
+        let ctx = context(Arc::clone(&factory), QueryContext::new());
+        transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT c_name FROM customer UNION ALL SELECT CAST(o_orderkey AS VARCHAR) FROM orders",
+        )
+        .await?;
+        let hints = factory.hints.lock().unwrap().clone();
+        assert!(hints.contains(&("customer".to_string(), Some("replica".to_string()))));
+        assert!(hints.contains(&("orders".to_string(), None)));
+
+        // the hint of the query takes precedence
+        let factory = Arc::new(RecordingFactory::default());
+        let ctx = context(
+            Arc::clone(&factory),
+            QueryContext::new().with_routing_hint("primary"),
+        );
+        transform_sql_with_ctx(
+            &ctx,
+            Arc::clone(&analyzed_mdl),
+            &[],
+            "SELECT c_name FROM customer",
+        )
+        .await?;
+        let hints = factory.hints.lock().unwrap().clone();
+        assert!(hints
+            .iter()
+            .all(|(_, hint)| hint.as_deref() == Some("primary")));
+        assert!(!hints.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_tag() -> Result<()> {
+        #[derive(Debug, Default)]
+        struct RecordingFactory {
+            tags: Mutex<Vec<(String, Option<String>)>>,
+        }
+
+        impl TableSourceFactory for RecordingFactory {
+            fn create_table_source(
+                &self,
+                model: &Model,
+                source: Arc<dyn TableSource>,
+                _routing_hint: Option<&str>,
+                query_tag: Option<&str>,
+            ) -> Result<Arc<dyn TableSource>> {
+                self.tags
+                    .lock()
+                    .unwrap()
+                    .push((model.name().to_string(), query_tag.map(String::from)));
+                Ok(source)
+            }
+        }
+
+        let manifest = manifest_builder()
+            .model(customer_model().build())
+            .model(
+                ModelBuilder::new("orders")
+                    .table_reference("orders")
+                    .column(ColumnBuilder::new("o_orderkey", "bigint").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let context = |factory: Arc<RecordingFactory>, query_context: QueryContext| {
+            SessionContext::new_with_config(
+                SessionConfig::new()
+                    .with_extension(Arc::new(TableSourceFactoryRef::new(factory)))
+                    .with_extension(Arc::new(query_context)),
+            )
+        };
+        let sql =
+            "SELECT c_name FROM customer UNION ALL SELECT CAST(o_orderkey AS VARCHAR) FROM orders";
+
+        // every scan of the query is labeled by the tag of the query
+        let factory = Arc::new(RecordingFactory::default());
+        let ctx = context(
+            Arc::clone(&factory),
+            QueryContext::new().with_query_tag("team:growth"),
+        );
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        let tags = factory.tags.lock().unwrap().clone();
+        let tag = Some("team:growth".to_string());
+        assert!(tags.contains(&("customer".to_string(), tag.clone())));
+        assert!(tags.contains(&("orders".to_string(), tag)));
+
+        // the scans are not labeled without a tag
+        let factory = Arc::new(RecordingFactory::default());
+        let ctx = context(Arc::clone(&factory), QueryContext::new());
+        transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        let tags = factory.tags.lock().unwrap().clone();
+        assert!(tags.iter().all(|(_, tag)| tag.is_none()));
+        assert!(!tags.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::mdl::SessionStateRef;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// [ColumnTypeOverrides] overrides the declared types of the model columns for the queries.
+///
+/// It's registered as an extension of the [datafusion::prelude::SessionConfig] of the query. The
+/// overridden column is cast to the type when the model plan is generated, so the query can assert
+/// the type of a source returning the column in different types.
+#[derive(Debug, Default, Clone)]
+pub struct ColumnTypeOverrides {
+    types: HashMap<(String, String), String>,
+}
+
+impl ColumnTypeOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Override the type of the column of the model
+    pub fn with_type(mut self, model: &str, column: &str, r#type: &str) -> Self {
+        self.types
+            .insert((model.to_string(), column.to_string()), r#type.to_string());
+        self
+    }
+
+    /// Return the overridden type of the column
+    pub fn get(&self, model: &str, column: &str) -> Option<&str> {
+        self.types
+            .get(&(model.to_string(), column.to_string()))
+            .map(String::as_str)
+    }
+
+    /// Get the overrides registered in the config of the session
+    pub(crate) fn from_session(session_state: &SessionStateRef) -> Option<Arc<Self>> {
+        session_state.read().config().get_extension::<Self>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::ColumnBuilder;
+    use crate::mdl::fixture::{customer_model, manifest_builder};
+    use crate::mdl::type_override::ColumnTypeOverrides;
+    use crate::mdl::{transform_sql_with_ctx, AnalyzedWrenMDL};
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use datafusion::prelude::{SessionConfig, SessionContext};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_column_type_override() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("c_active", "boolean").build())
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let context = |overrides: ColumnTypeOverrides| {
+            SessionContext::new_with_config(
+                SessionConfig::new().with_extension(Arc::new(overrides)),
+            )
+        };
+
+        let ctx = context(ColumnTypeOverrides::new().with_type(
+            "customer",
+            "c_custkey",
+            "varchar",
+        ));
+        let sql = "SELECT c_custkey, c_name FROM customer";
+        let actual =
+            transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql).await?;
+        assert!(actual.contains("CAST("), "{actual}");
+        assert!(actual.contains("AS VARCHAR)"), "{actual}");
+
+        // a boolean can't be cast to a timestamp
+        let ctx = context(ColumnTypeOverrides::new().with_type(
+            "customer",
+            "c_active",
+            "timestamp",
+        ));
+        let sql = "SELECT c_active FROM customer";
+        let err = transform_sql_with_ctx(&ctx, Arc::clone(&analyzed_mdl), &[], sql)
+            .await
+            .unwrap_err();
+        let Some(WrenError::IncompatibleColumnType { column, from, to }) =
+            WrenError::find_in(&err)
+        else {
+            panic!("expected an incompatible column type error: {err}");
+        };
+        assert_eq!(column, "customer.c_active");
+        assert_eq!(from, "Boolean");
+        assert_eq!(to, "timestamp");
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+use crate::logical_plan::analyze::plan::{
+    CalculationPlanNode, ModelPlanNode, ModelSourceNode, PartialModelPlanNode,
+};
+use crate::logical_plan::analyze::RelationChain;
+use crate::mdl::AnalyzedWrenMDL;
+use datafusion::common::tree_node::TreeNodeRecursion;
+use datafusion::common::Result;
+use datafusion::logical_expr::{LogicalPlan, UserDefinedLogicalNodeCore};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// The models and columns declared in the manifest but never referenced by the queries
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UnusedDefinitions {
+    pub models: BTreeSet<String>,
+    /// The unused columns of each model. All the columns of an unused model are included.
+    pub columns: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Find the models and columns that aren't referenced by any of the plans.
+///
+/// The plans should be analyzed by [crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule],
+/// so the required columns of each model are collected in the model plan nodes. The models and
+/// columns used by the calculated fields through the relationships are counted as referenced.
+/// The relationship columns aren't reported because they don't provide any value themselves.
+pub fn find_unused_definitions(
+    analyzed_mdl: &AnalyzedWrenMDL,
+    plans: &[LogicalPlan],
+) -> Result<UnusedDefinitions> {
+    let mut used: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for plan in plans {
+        collect_used_columns(plan, &mut used)?;
+    }
+
+    let mut unused = UnusedDefinitions::default();
+    for model in analyzed_mdl.wren_mdl().models() {
+        let used_columns = used.get(model.name());
+        if used_columns.is_none() {
+            unused.models.insert(model.name().to_string());
+        }
+        let unused_columns = model
+            .get_visible_columns()
+            .filter(|column| column.relationship.is_none())
+            .filter(|column| !used_columns.is_some_and(|c| c.contains(column.name())))
+            .map(|column| column.name().to_string())
+            .collect::<BTreeSet<_>>();
+        if !unused_columns.is_empty() {
+            unused
+                .columns
+                .insert(model.name().to_string(), unused_columns);
+        }
+    }
+    Ok(unused)
+}
+
+fn collect_used_columns(
+    plan: &LogicalPlan,
+    used: &mut BTreeMap<String, BTreeSet<String>>,
+) -> Result<()> {
+    plan.apply_with_subqueries(|plan| {
+        let LogicalPlan::Extension(extension) = plan else {
+            return Ok(TreeNodeRecursion::Continue);
+        };
+        let node = extension.node.as_any();
+        if let Some(model_plan) = node.downcast_ref::<ModelPlanNode>() {
+            collect_model_columns(model_plan, used)?;
+        } else if let Some(source) = node.downcast_ref::<ModelSourceNode>() {
+            used.entry(source.model_name.clone()).or_default().extend(
+                UserDefinedLogicalNodeCore::schema(source)
+                    .fields()
+                    .iter()
+                    .map(|f| f.name().clone()),
+            );
+        } else if let Some(calculation) = node.downcast_ref::<CalculationPlanNode>() {
+            collect_used_columns_in_chain(&calculation.relation_chain, used)?;
+        } else if let Some(partial_model) = node.downcast_ref::<PartialModelPlanNode>() {
+            collect_model_columns(&partial_model.model_node, used)?;
+        }
+        Ok(TreeNodeRecursion::Continue)
+    })?;
+    Ok(())
+}
+
+fn collect_model_columns(
+    model_plan: &ModelPlanNode,
+    used: &mut BTreeMap<String, BTreeSet<String>>,
+) -> Result<()> {
+    used.entry(model_plan.plan_name.clone())
+        .or_default()
+        .extend(
+            UserDefinedLogicalNodeCore::schema(model_plan)
+                .fields()
+                .iter()
+                .map(|f| f.name().clone()),
+        );
+    collect_used_columns_in_chain(&model_plan.relation_chain, used)
+}
+
+fn collect_used_columns_in_chain(
+    chain: &RelationChain,
+    used: &mut BTreeMap<String, BTreeSet<String>>,
+) -> Result<()> {
+    match chain {
+        RelationChain::Chain { plan, next, .. } => {
+            collect_used_columns(plan, used)?;
+            collect_used_columns_in_chain(next, used)
+        }
+        RelationChain::Start(plan) => collect_used_columns(plan, used),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::logical_plan::analyze::model_anlayze::ModelAnalyzeRule;
+    use crate::mdl::builder::ColumnBuilder;
+    use crate::mdl::context::create_ctx_with_mdl;
+    use crate::mdl::fixture::{customer_model, manifest_builder, orders_model};
+    use crate::mdl::unused::find_unused_definitions;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::common::Result;
+    use datafusion::config::ConfigOptions;
+    use datafusion::optimizer::AnalyzerRule;
+    use datafusion::prelude::SessionContext;
+    use parking_lot::RwLock;
+    use std::collections::{BTreeMap, BTreeSet};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_find_unused_definitions() -> Result<()> {
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(ColumnBuilder::new("c_phone", "varchar").build())
+                    .build(),
+            )
+            .model(orders_model().build())
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let ctx =
+            create_ctx_with_mdl(&SessionContext::new(), Arc::clone(&analyzed_mdl), false)
+                .await?;
+        let rule = ModelAnalyzeRule::new(
+            Arc::clone(&analyzed_mdl),
+            Arc::new(RwLock::new(ctx.state())),
+        );
+        let mut plans = vec![];
+        for sql in [
+            "SELECT c_name FROM customer",
+            "SELECT c_custkey FROM customer WHERE c_name = 'Ina'",
+        ] {
+            let plan = ctx.state().create_logical_plan(sql).await?;
+            plans.push(rule.analyze(plan, &ConfigOptions::default())?);
+        }
+
+        let unused = find_unused_definitions(&analyzed_mdl, &plans)?;
+        assert_eq!(unused.models, BTreeSet::from(["orders".to_string()]));
+        assert_eq!(
+            unused.columns,
+            BTreeMap::from([
+                (
+                    "customer".to_string(),
+                    BTreeSet::from(["c_phone".to_string()])
+                ),
+                (
+                    "orders".to_string(),
+                    BTreeSet::from(["o_custkey".to_string(), "o_orderkey".to_string()])
+                ),
+            ])
+        );
+        Ok(())
+    }
+}
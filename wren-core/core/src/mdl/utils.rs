@@ -1,8 +1,13 @@
-use datafusion::arrow::datatypes::Field;
-use datafusion::common::{plan_err, Column, DFSchema};
+use datafusion::arrow::datatypes::{DataType, Field};
+use datafusion::common::tree_node::{
+    Transformed, TransformedResult, TreeNode, TreeNodeRecursion,
+};
+use datafusion::common::{plan_err, Column, DFSchema, ScalarValue, TableReference};
 use datafusion::error::Result;
 use datafusion::execution::session_state::SessionState;
-use datafusion::logical_expr::Expr;
+use datafusion::functions::expr_fn::coalesce;
+use datafusion::logical_expr::expr::{AggregateFunction, Cast, TryCast, WindowFunction};
+use datafusion::logical_expr::{cast, lit, Expr, ExprFunctionExt, ExprSchemable};
 use datafusion::sql::sqlparser::ast::Expr::{CompoundIdentifier, Identifier};
 use datafusion::sql::sqlparser::ast::{visit_expressions, visit_expressions_mut, Ident};
 use datafusion::sql::sqlparser::dialect::GenericDialect;
@@ -13,9 +18,16 @@ use std::collections::{BTreeSet, VecDeque};
 use std::ops::ControlFlow;
 use std::sync::Arc;
 
-use crate::logical_plan::utils::{from_qualified_name, map_data_type};
-use crate::mdl::manifest::Model;
-use crate::mdl::{AnalyzedWrenMDL, ColumnReference, Dataset, SessionStateRef};
+use crate::logical_plan::utils::{from_qualified_name, map_data_type, with_description};
+use crate::mdl::dialect::get_inner_dialect;
+use crate::mdl::geo;
+use crate::mdl::manifest::{
+    Bucket, DataSource, JoinType, Manifest, Model, NonAdditiveAggregation, SemiAdditive,
+    SurrogateKey, View,
+};
+use crate::mdl::rounding::round_decimal;
+use crate::mdl::{AnalyzedWrenMDL, ColumnReference, Dataset, SessionStateRef, WrenMDL};
+use crate::WrenError;
 
 pub fn to_expr_queue(column: Column) -> VecDeque<String> {
     column.name.split('.').map(String::from).collect()
@@ -114,8 +126,32 @@ pub fn create_wren_calculated_field_expr(
         &expr,
         session_state.config_options().sql_parser.dialect.as_str(),
     )?;
+    let wren_mdl = analyzed_wren_mdl.wren_mdl();
+    // The joined instances of the self relationships are qualified by their aliases
+    let mut self_aliases = BTreeSet::new();
     visit_expressions_mut(&mut expr, |e| {
         if let CompoundIdentifier(ids) = e {
+            if let Some((alias, column)) =
+                self_relationship_path(&wren_mdl, column_rf.dataset.name(), ids)
+            {
+                *e = CompoundIdentifier(vec![quoted_ident(&alias), column]);
+                self_aliases.insert(alias);
+                return ControlFlow::<()>::Continue(());
+            }
+        }
+        let relationship_key = match e {
+            Identifier(id) => {
+                relationship_join_key(&wren_mdl, column_rf.dataset.name(), &[id.clone()])
+            }
+            CompoundIdentifier(ids) => {
+                relationship_join_key(&wren_mdl, column_rf.dataset.name(), ids)
+            }
+            _ => None,
+        };
+        if let Some(key) = relationship_key {
+            // e.g. `count(orders)` counts the rows of the related model by its join key
+            *e = CompoundIdentifier(key);
+        } else if let CompoundIdentifier(ids) = e {
             let name_size = ids.len();
             if name_size > 2 {
                 let slice = &ids[name_size - 2..name_size];
@@ -124,6 +160,15 @@ pub fn create_wren_calculated_field_expr(
         }
         ControlFlow::<()>::Continue(())
     });
+    geo::expand_geo_functions(&mut expr)?;
+    if analyzed_wren_mdl.options().strict_functions {
+        check_known_functions(
+            &expr,
+            &session_state,
+            column_rf.dataset.name(),
+            column_rf.column.name(),
+        )?;
+    }
 
     let Some(schema) = models
         .into_iter()
@@ -136,7 +181,208 @@ pub fn create_wren_calculated_field_expr(
     else {
         return plan_err!("Error for creating schemas: {}", qualified_col);
     };
-    session_state.create_logical_expr(&expr.to_string(), &schema)
+    let schema = match wren_mdl.get_model(column_rf.dataset.name()) {
+        Some(model) if !self_aliases.is_empty() => {
+            let model_schema = Dataset::Model(model).to_qualified_schema()?;
+            self_aliases.iter().try_fold(schema, |schema, alias| {
+                schema.join(&DFSchema::try_from_qualified_schema(
+                    quoted(alias),
+                    model_schema.as_arrow(),
+                )?)
+            })?
+        }
+        _ => schema,
+    };
+    let expr = session_state.create_logical_expr(&expr.to_string(), &schema)?;
+    check_percentiles(&expr, column_rf.dataset.name(), column_rf.column.name())?;
+    let expr = cast_to_interval(expr, &column_rf.column.r#type, &schema)?;
+    let expr = cast_to_boolean(expr, &column_rf.column.r#type, &schema)?;
+    let expr = round_decimal(
+        expr,
+        &column_rf.column.r#type,
+        analyzed_wren_mdl.options().rounding_policy.as_ref(),
+    )?;
+    if self_aliases.is_empty() {
+        return Ok(expr);
+    }
+    // The columns of the joined instance are renamed by the relation chain to avoid the collision
+    expr.transform_up(|e| match e {
+        Expr::Column(Column {
+            relation: Some(relation),
+            name,
+            ..
+        }) if self_aliases.iter().any(|alias| alias == relation.table()) => {
+            Ok(Transformed::yes(Expr::Column(Column::new(
+                Some(TableReference::bare(column_rf.dataset.name())),
+                self_join_column_name(relation.table(), &name),
+            ))))
+        }
+        _ => Ok(Transformed::no(e)),
+    })
+    .data()
+}
+
+/// Check that every function called by the expression is registered in the session as a scalar,
+/// aggregate or window function
+fn check_known_functions(
+    expr: &datafusion::sql::sqlparser::ast::Expr,
+    session_state: &SessionState,
+    model: &str,
+    column: &str,
+) -> Result<()> {
+    let unknown = visit_expressions(expr, |e| {
+        let datafusion::sql::sqlparser::ast::Expr::Function(function) = e else {
+            return ControlFlow::Continue(());
+        };
+        let name = function.name.to_string();
+        // the unquoted function name is case-insensitive
+        let name = match name.strip_prefix('"').and_then(|n| n.strip_suffix('"')) {
+            Some(quoted) => quoted.to_string(),
+            None => name.to_lowercase(),
+        };
+        if session_state.scalar_functions().contains_key(&name)
+            || session_state.aggregate_functions().contains_key(&name)
+            || session_state.window_functions().contains_key(&name)
+        {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(name)
+        }
+    });
+    match unknown {
+        ControlFlow::Break(function) => Err(WrenError::UnknownFunction {
+            function,
+            model: model.to_string(),
+            column: column.to_string(),
+        }
+        .into()),
+        ControlFlow::Continue(()) => Ok(()),
+    }
+}
+
+/// Check that the percentile of the approximate percentile aggregations is a literal in [0, 1],
+/// e.g. `approx_percentile_cont(orders.o_totalprice, 0.95)`
+fn check_percentiles(expr: &Expr, model: &str, column: &str) -> Result<()> {
+    expr.apply(|e| {
+        let Expr::AggregateFunction(AggregateFunction { func, params }) = e else {
+            return Ok(TreeNodeRecursion::Continue);
+        };
+        let percentile = match func.name() {
+            "approx_percentile_cont" => params.args.get(1),
+            "approx_percentile_cont_with_weight" => params.args.get(2),
+            _ => return Ok(TreeNodeRecursion::Continue),
+        };
+        // the literal could be casted by the type coercion
+        let percentile = match percentile {
+            Some(Expr::Cast(Cast { expr, .. })) | Some(Expr::TryCast(TryCast { expr, .. })) => {
+                Some(expr.as_ref())
+            }
+            percentile => percentile,
+        };
+        let Some(Expr::Literal(percentile)) = percentile else {
+            return plan_err!(
+                "The percentile of the calculated field {model}.{column} should be a literal"
+            );
+        };
+        match percentile.cast_to(&DataType::Float64)? {
+            ScalarValue::Float64(Some(p)) if (0.0..=1.0).contains(&p) => {
+                Ok(TreeNodeRecursion::Continue)
+            }
+            _ => plan_err!(
+                "The percentile of the calculated field {model}.{column} should be in [0, 1]: {percentile}"
+            ),
+        }
+    })?;
+    Ok(())
+}
+
+/// Type the difference of the timestamps by the declared interval type of the column, e.g.
+/// `end_at - start_at` is a duration in DataFusion and it's cast to an interval.
+fn cast_to_interval(expr: Expr, r#type: &str, schema: &DFSchema) -> Result<Expr> {
+    let data_type = map_data_type(r#type)?;
+    if !matches!(data_type, DataType::Interval(_)) {
+        return Ok(expr);
+    }
+    let expr_type = expr.get_type(schema)?;
+    if expr_type != data_type
+        && matches!(expr_type, DataType::Duration(_) | DataType::Interval(_))
+    {
+        Ok(cast(expr, data_type))
+    } else {
+        Ok(expr)
+    }
+}
+
+/// Type the numeric flag by the declared boolean type of the column, e.g. `count(orders)` of a
+/// boolean flag is cast to a boolean, so the flag is the same in a filter and in a dimension.
+fn cast_to_boolean(expr: Expr, r#type: &str, schema: &DFSchema) -> Result<Expr> {
+    if map_data_type(r#type)? != DataType::Boolean {
+        return Ok(expr);
+    }
+    if expr.get_type(schema)?.is_numeric() {
+        Ok(cast(expr, DataType::Boolean))
+    } else {
+        Ok(expr)
+    }
+}
+
+/// If the identifiers are a chain of the self relationships of the model ending with a column,
+/// e.g. `manager.manager.name`, return the alias of the joined instance and the column.
+fn self_relationship_path(
+    wren_mdl: &WrenMDL,
+    model_name: &str,
+    ids: &[Ident],
+) -> Option<(String, Ident)> {
+    let (column, path) = ids.split_last()?;
+    let model = wren_mdl.get_model(model_name)?;
+    path.iter()
+        .try_fold(None, |alias: Option<String>, id| {
+            let relationship = model.get_column(&id.value)?;
+            (relationship.relationship.is_some() && relationship.r#type == model.name())
+                .then(|| Some(self_join_alias(alias.as_deref(), relationship.name())))
+        })?
+        .map(|alias| (alias, column.clone()))
+}
+
+/// The alias of the joined instance of a self relationship. Each hop of a chain is aliased by its
+/// path of the relationship columns, e.g. `manager` and then `manager__manager`.
+pub(crate) fn self_join_alias(owner_alias: Option<&str>, column: &str) -> String {
+    match owner_alias {
+        Some(owner_alias) => format!("{owner_alias}__{column}"),
+        None => column.to_string(),
+    }
+}
+
+/// The output column name of the joined instance of a self relationship. e.g. `manager__name`
+pub(crate) fn self_join_column_name(alias: &str, column: &str) -> String {
+    format!("{alias}__{column}")
+}
+
+/// If the identifiers are a relationship chain ending with a relationship column, e.g. `orders` or
+/// `customer.orders`, return the join key of the last related model, e.g. `orders.o_custkey`.
+/// The join key is NULL for the unmatched rows of the outer join, so an aggregation over it
+/// like `count(orders)` counts the related rows.
+fn relationship_join_key(
+    wren_mdl: &WrenMDL,
+    model_name: &str,
+    ids: &[Ident],
+) -> Option<Vec<Ident>> {
+    let mut model = wren_mdl.get_model(model_name)?;
+    let mut last_relationship = None;
+    for id in ids {
+        let column = model.get_column(&id.value)?;
+        let relationship = wren_mdl.get_relationship(column.relationship.as_ref()?)?;
+        model = wren_mdl.get_model(&column.r#type)?;
+        last_relationship = Some(relationship);
+    }
+    let join_key = collect_identifiers(&last_relationship?.condition)
+        .ok()?
+        .into_iter()
+        .find_map(|ident| {
+            let (qualifier, name) = ident.name.split_once('.')?;
+            (qualifier == model.name()).then(|| name.to_string())
+        })?;
+    Some(vec![quoted_ident(model.name()), quoted_ident(&join_key)])
 }
 
 /// Create the Logical Expr for the remote column.
@@ -197,9 +443,281 @@ fn qualified_expr(
         }
         ControlFlow::<()>::Continue(())
     });
+    geo::expand_geo_functions(&mut expr)?;
     Ok(expr.to_string())
 }
 
+/// Rewrite the expression of a semi-additive measure (e.g. an account balance).
+///
+/// Only the rows of the last (or first) snapshot of each entity are kept, the others become NULL.
+/// The snapshots are picked per entity, so the entity missing the latest snapshot still counts by
+/// its own last one. If a time grain is specified, the snapshot is picked per time bucket too.
+/// Summing the result over the other dimensions gives the balance-style total.
+///
+/// e.g. `CASE WHEN "snapshot_date" = max("snapshot_date") OVER (PARTITION BY "account", date_trunc('month', "snapshot_date")) THEN "balance" END`
+pub(crate) fn semi_additive_expr(
+    expr: &str,
+    semi_additive: &SemiAdditive,
+    model: &Model,
+) -> Result<String> {
+    if semi_additive.entity_keys.is_empty() {
+        return plan_err!(
+            "The semi-additive measure of model {} should have at least one entity key",
+            model.name()
+        );
+    }
+    let physical_column_expr = |name: &str, role: &str| {
+        let Some(column) = model.get_column(name) else {
+            return plan_err!(
+                "{role} {name} of the semi-additive measure not found in model {}",
+                model.name()
+            );
+        };
+        if column.is_calculated || column.relationship.is_some() {
+            return plan_err!(
+                "{role} {} of the semi-additive measure should be a physical column",
+                column.name()
+            );
+        }
+        Ok(column
+            .expression()
+            .map(String::from)
+            .unwrap_or_else(|| quoted(column.name())))
+    };
+    let time_expr = physical_column_expr(&semi_additive.time_column, "Time column")?;
+    let mut partition = semi_additive
+        .entity_keys
+        .iter()
+        .map(|key| physical_column_expr(key, "Entity key"))
+        .collect::<Result<Vec<_>>>()?;
+    if let Some(grain) = &semi_additive.time_grain {
+        partition.push(format!("date_trunc('{grain}', {time_expr})"));
+    }
+    let window_function = match semi_additive.aggregation {
+        NonAdditiveAggregation::Last => "max",
+        NonAdditiveAggregation::First => "min",
+    };
+    Ok(format!(
+        "CASE WHEN {time_expr} = {window_function}({time_expr}) OVER (PARTITION BY {}) THEN {expr} END",
+        partition.join(", ")
+    ))
+}
+
+/// Build the expression of a bucketed dimension.
+///
+/// The rows are divided into the buckets ranked by the order column.
+/// e.g. `ntile(10) OVER (ORDER BY "spend")` for the deciles of the spend.
+pub(crate) fn bucket_expr(bucket: &Bucket, model: &Model) -> Result<String> {
+    if bucket.buckets == 0 {
+        return plan_err!("The number of buckets should be greater than 0");
+    }
+    let Some(order_column) = model.get_column(&bucket.order_by) else {
+        return plan_err!(
+            "Order column {} of the bucket not found in model {}",
+            bucket.order_by,
+            model.name()
+        );
+    };
+    if order_column.is_calculated || order_column.relationship.is_some() {
+        return plan_err!(
+            "Order column {} of the bucket should be a physical column",
+            order_column.name()
+        );
+    }
+    let order_expr = order_column
+        .expression()
+        .map(String::from)
+        .unwrap_or_else(|| quoted(order_column.name()));
+    Ok(format!(
+        "ntile({}) OVER (ORDER BY {order_expr})",
+        bucket.buckets
+    ))
+}
+
+/// Return the expression of the key partitioning the rows of a parent-scoped column, i.e. the
+/// join key of the model to the parent by the relationship column `scope`.
+/// e.g. `l_orderkey` for the line items scoped by their `orders`.
+pub(crate) fn parent_scope_key(
+    scope: &str,
+    model: &Model,
+    wren_mdl: &WrenMDL,
+) -> Result<String> {
+    let Some(relationship) = model
+        .get_column(scope)
+        .and_then(|column| column.relationship.clone())
+        .and_then(|relationship| wren_mdl.get_relationship(&relationship))
+    else {
+        return plan_err!(
+            "Parent scope {} should be a relationship column of model {}",
+            scope,
+            model.name()
+        );
+    };
+    let to_one = match relationship.join_type {
+        JoinType::OneToOne => true,
+        JoinType::ManyToOne => {
+            relationship.models.first().map(String::as_str) == Some(model.name())
+        }
+        JoinType::OneToMany => {
+            relationship.models.last().map(String::as_str) == Some(model.name())
+        }
+        JoinType::ManyToMany => false,
+    };
+    if !to_one {
+        return plan_err!(
+            "Parent scope {} of model {} should be a to-one relationship",
+            scope,
+            model.name()
+        );
+    }
+    let Some(key_column) = collect_identifiers(&relationship.condition)?
+        .into_iter()
+        .find_map(|ident| {
+            let (qualifier, name) = ident.name.split_once('.')?;
+            (qualifier == model.name()).then(|| model.get_column(name))?
+        })
+    else {
+        return plan_err!(
+            "Join key of model {} not found in the relationship {}",
+            model.name(),
+            relationship.name
+        );
+    };
+    Ok(key_column
+        .expression()
+        .map(String::from)
+        .unwrap_or_else(|| quoted(key_column.name())))
+}
+
+/// Compute the aggregations of a parent-scoped column over the rows sharing the same parent.
+///
+/// Each aggregation is turned into a window partitioned by the parent key, so the rows keep their
+/// grain. e.g. `l_extendedprice / sum(l_extendedprice)` is planned as
+/// `l_extendedprice / sum(l_extendedprice) OVER (PARTITION BY l_orderkey)`, the share of each
+/// line item in the order total.
+pub(crate) fn scope_aggregations(expr: Expr, partition_by: Expr) -> Result<Expr> {
+    let scoped = expr.transform_up(|e| match e {
+        Expr::AggregateFunction(AggregateFunction { func, params })
+            if !params.distinct && params.filter.is_none() =>
+        {
+            Ok(Transformed::yes(
+                Expr::WindowFunction(WindowFunction::new(func, params.args))
+                    .partition_by(vec![partition_by.clone()])
+                    .build()?,
+            ))
+        }
+        Expr::AggregateFunction(_) => {
+            plan_err!("The parent-scoped aggregation {e} can't be distinct or filtered")
+        }
+        _ => Ok(Transformed::no(e)),
+    })?;
+    if !scoped.transformed {
+        return plan_err!(
+            "The parent-scoped expression {} should aggregate over the parent",
+            scoped.data
+        );
+    }
+    Ok(scoped.data)
+}
+
+/// The sentinel replacing NULL in the distinct count counting NULL as a distinct value
+const DISTINCT_NULL_SENTINEL: &str = "__wren_null__";
+
+/// Count NULL as a distinct value in the distinct counts of the expression.
+///
+/// The standard `count(DISTINCT ...)` ignores NULL. The argument is cast to text and coalesced to
+/// a sentinel before counted, so NULL is counted as one more bucket. e.g.
+/// `count(DISTINCT coalesce(CAST(o_status AS VARCHAR), '__wren_null__'))`
+pub(crate) fn count_null_as_distinct(expr: Expr) -> Result<Expr> {
+    let counted = expr.transform_up(|e| match e {
+        Expr::AggregateFunction(AggregateFunction { func, mut params })
+            if func.name() == "count" && params.distinct && params.args.len() == 1 =>
+        {
+            let arg = params.args.remove(0);
+            params.args = vec![coalesce(vec![
+                cast(arg, DataType::Utf8),
+                lit(DISTINCT_NULL_SENTINEL),
+            ])];
+            Ok(Transformed::yes(Expr::AggregateFunction(
+                AggregateFunction { func, params },
+            )))
+        }
+        _ => Ok(Transformed::no(e)),
+    })?;
+    if !counted.transformed {
+        return plan_err!(
+            "The expression {} counting NULL as a distinct value should have a distinct count",
+            counted.data
+        );
+    }
+    Ok(counted.data)
+}
+
+/// Build the expression of a surrogate key.
+///
+/// The natural keys are cast to text and joined by `|` before hashed by the hash function of the
+/// data source. e.g. `md5(concat_ws('|', coalesce(CAST("id" AS VARCHAR), ''), ...))`
+pub(crate) fn surrogate_key_expr(
+    surrogate_key: &SurrogateKey,
+    model: &Model,
+    data_source: &DataSource,
+) -> Result<String> {
+    if surrogate_key.natural_keys.is_empty() {
+        return plan_err!("The surrogate key should have at least one natural key");
+    }
+    let natural_keys = surrogate_key
+        .natural_keys
+        .iter()
+        .map(|key| {
+            let Some(column) = model.get_column(key) else {
+                return plan_err!(
+                    "Natural key {} of the surrogate key not found in model {}",
+                    key,
+                    model.name()
+                );
+            };
+            if column.is_calculated || column.relationship.is_some() {
+                return plan_err!(
+                    "Natural key {} of the surrogate key should be a physical column",
+                    column.name()
+                );
+            }
+            let expr = column
+                .expression()
+                .map(String::from)
+                .unwrap_or_else(|| quoted(column.name()));
+            Ok(format!("coalesce(CAST({expr} AS VARCHAR), '')"))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(format!(
+        "{}(concat_ws('|', {}))",
+        get_inner_dialect(data_source).surrogate_key_function(),
+        natural_keys.join(", ")
+    ))
+}
+
+/// Build the expression of an unnested column. It's the array column to be unnested into one row
+/// per element by the model generation.
+pub(crate) fn unnest_expr(array_column: &str, model: &Model) -> Result<String> {
+    let Some(column) = model.get_column(array_column) else {
+        return plan_err!(
+            "Array column {} of the unnest not found in model {}",
+            array_column,
+            model.name()
+        );
+    };
+    if column.is_calculated || column.relationship.is_some() || column.unnest.is_some() {
+        return plan_err!(
+            "Array column {} of the unnest should be a physical column",
+            column.name()
+        );
+    }
+    Ok(column
+        .expression()
+        .map(String::from)
+        .unwrap_or_else(|| quoted(column.name())))
+}
+
 #[inline]
 pub fn quoted_ident(s: &str) -> Ident {
     Ident::with_quote('"', s)
@@ -210,10 +728,30 @@ pub fn quoted(s: &str) -> String {
     format!("\"{}\"", s)
 }
 
+/// Push the view compiled from the manifest, e.g. a cross metric or a curated view, to the views
+/// of the manifest. The name shouldn't conflict with the existing views.
+pub(crate) fn push_compiled_view(
+    manifest: &mut Manifest,
+    name: &str,
+    statement: String,
+) -> Result<()> {
+    if manifest.views.iter().any(|v| v.name == name) {
+        return plan_err!("The {name} conflicts with the view of the same name");
+    }
+    manifest.views.push(Arc::new(View {
+        name: name.to_string(),
+        statement,
+    }));
+    Ok(())
+}
+
 /// Transform the column to a datafusion field
 pub fn to_field(column: &wren_core_base::mdl::Column) -> Result<Field> {
     let data_type = map_data_type(&column.r#type)?;
-    Ok(Field::new(&column.name, data_type, column.not_null))
+    Ok(with_description(
+        Field::new(&column.name, data_type, !column.not_null),
+        column,
+    ))
 }
 
 /// Transform the column to a datafusion field for a remote table
@@ -221,7 +759,13 @@ pub fn to_remote_field(
     column: &wren_core_base::mdl::Column,
     session_state: SessionStateRef,
 ) -> Result<Vec<Field>> {
-    if column.expression().is_some() {
+    if let Some(materialized_column) = column.materialized_column() {
+        Ok(vec![Field::new(
+            materialized_column,
+            map_data_type(&column.r#type)?,
+            !column.not_null,
+        )])
+    } else if column.expression().is_some() {
         let session_state = session_state.read();
         let expr = session_state.sql_to_expr(
             column.expression().unwrap(),
@@ -260,7 +804,8 @@ mod tests {
     use datafusion::prelude::SessionContext;
 
     use crate::logical_plan::utils::from_qualified_name;
-    use crate::mdl::manifest::Manifest;
+    use crate::mdl::builder::{ColumnBuilder, ModelBuilder};
+    use crate::mdl::manifest::{Manifest, NonAdditiveAggregation, TimeUnit};
     use crate::mdl::AnalyzedWrenMDL;
 
     #[test]
@@ -360,6 +905,56 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_semi_additive_expr() -> Result<()> {
+        let model = ModelBuilder::new("balances")
+            .table_reference("balances")
+            .column(ColumnBuilder::new("snapshot_date", "date").build())
+            .column(
+                ColumnBuilder::new("ending_balance", "bigint")
+                    .expression("balance")
+                    .semi_additive(
+                        "snapshot_date",
+                        NonAdditiveAggregation::Last,
+                        Some(TimeUnit::Month),
+                    )
+                    .build(),
+            )
+            .build();
+        let column = model.get_column("ending_balance").unwrap();
+        let expr = super::semi_additive_expr(
+            "balance",
+            column.semi_additive().unwrap(),
+            &model,
+        )?;
+        assert_eq!(
+            expr,
+            "CASE WHEN \"snapshot_date\" = max(\"snapshot_date\") OVER \
+            (PARTITION BY date_trunc('month', \"snapshot_date\")) THEN balance END"
+        );
+
+        let model = ModelBuilder::new("balances")
+            .table_reference("balances")
+            .column(
+                ColumnBuilder::new("ending_balance", "bigint")
+                    .semi_additive("unknown", NonAdditiveAggregation::First, None)
+                    .build(),
+            )
+            .build();
+        let column = model.get_column("ending_balance").unwrap();
+        let err = super::semi_additive_expr(
+            "ending_balance",
+            column.semi_additive().unwrap(),
+            &model,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Error during planning: Time column unknown of the semi-additive measure not found in model balances"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_create_remote_expr_for_model() -> Result<()> {
         let test_data: PathBuf =
@@ -0,0 +1,164 @@
+use std::sync::Arc;
+
+use datafusion::common::{plan_err, Result};
+
+use crate::mdl::manifest::{Column, Manifest, WeightedAverage};
+use crate::mdl::snippet::parse_expr_snippet;
+
+/// Compile the weighted-average measures into their calculated expressions.
+///
+/// e.g. the weighted average of `orders.o_price` by `orders.o_quantity` is compiled to
+/// `CAST(sum(orders.o_price * orders.o_quantity) AS DOUBLE) / nullif(sum(orders.o_quantity), 0)`
+///
+/// The product and the weight are aggregated separately and divided after the aggregation. The
+/// measure is NULL if the weights add up to zero.
+pub(crate) fn compile_weighted_averages(mut manifest: Manifest) -> Result<Manifest> {
+    manifest.models = manifest
+        .models
+        .into_iter()
+        .map(|model| {
+            if model.columns.iter().all(|c| c.weighted_average().is_none()) {
+                return Ok(model);
+            }
+            let mut model = Arc::unwrap_or_clone(model);
+            model.columns = model
+                .columns
+                .iter()
+                .map(|column| match column.weighted_average() {
+                    Some(weighted_average) => {
+                        weighted_average_column(model.name(), column, weighted_average)
+                    }
+                    None => Ok(Arc::clone(column)),
+                })
+                .collect::<Result<_>>()?;
+            Ok(Arc::new(model))
+        })
+        .collect::<Result<_>>()?;
+    Ok(manifest)
+}
+
+fn weighted_average_column(
+    model: &str,
+    column: &Column,
+    weighted_average: &WeightedAverage,
+) -> Result<Arc<Column>> {
+    if !column.is_calculated {
+        return plan_err!(
+            "The weighted average {model}.{} should be a calculated field",
+            column.name()
+        );
+    }
+    if column.expression().is_some() {
+        return plan_err!(
+            "The weighted average {model}.{} can't have an expression",
+            column.name()
+        );
+    }
+    let WeightedAverage { value, weight } = weighted_average;
+    let value = parse_expr_snippet(value)?;
+    let weight = parse_expr_snippet(weight)?;
+    let mut compiled = column.clone();
+    compiled.expression = Some(format!(
+        "CAST(sum({value} * {weight}) AS DOUBLE) / nullif(sum({weight}), 0)"
+    ));
+    compiled.weighted_average = None;
+    Ok(Arc::new(compiled))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, RelationshipBuilder};
+    use crate::mdl::fixture::{
+        customer, customer_model, manifest_builder, orders_model, transform_with_mdl,
+    };
+    use crate::mdl::manifest::JoinType;
+    use crate::mdl::AnalyzedWrenMDL;
+    use datafusion::arrow::array::{ArrayRef, Float64Array, Int64Array, RecordBatch};
+    use datafusion::assert_batches_eq;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_weighted_average() -> Result<()> {
+        let ctx = SessionContext::new();
+        ctx.register_batch("customer", customer())?;
+        ctx.register_batch(
+            "orders",
+            RecordBatch::try_from_iter(vec![
+                (
+                    "o_orderkey",
+                    Arc::new(Int64Array::from(vec![1, 2, 3])) as ArrayRef,
+                ),
+                (
+                    "o_custkey",
+                    Arc::new(Int64Array::from(vec![1, 1, 2])) as ArrayRef,
+                ),
+                (
+                    "o_price",
+                    Arc::new(Float64Array::from(vec![10.0, 20.0, 5.0])) as ArrayRef,
+                ),
+                (
+                    "o_quantity",
+                    Arc::new(Int64Array::from(vec![1, 3, 0])) as ArrayRef,
+                ),
+            ])?,
+        )?;
+        let manifest = manifest_builder()
+            .model(
+                customer_model()
+                    .column(
+                        ColumnBuilder::new_relationship(
+                            "orders",
+                            "orders",
+                            "customer_orders",
+                        )
+                        .build(),
+                    )
+                    .column(
+                        ColumnBuilder::new("avg_price", "double")
+                            .calculated(true)
+                            .weighted_average("orders.o_price", "orders.o_quantity")
+                            .build(),
+                    )
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                orders_model()
+                    .column(ColumnBuilder::new("o_price", "double").build())
+                    .column(ColumnBuilder::new("o_quantity", "bigint").build())
+                    .primary_key("o_orderkey")
+                    .build(),
+            )
+            .relationship(
+                RelationshipBuilder::new("customer_orders")
+                    .model("customer")
+                    .model("orders")
+                    .join_type(JoinType::OneToMany)
+                    .condition("customer.c_custkey = orders.o_custkey")
+                    .build(),
+            )
+            .build();
+        let analyzed_mdl = Arc::new(AnalyzedWrenMDL::analyze(manifest)?);
+        let sql = "SELECT c_name, avg_price FROM customer \
+            WHERE c_custkey IN (1, 2) ORDER BY c_name";
+        let actual = transform_with_mdl(&analyzed_mdl, sql).await?;
+        // the product and the weight are aggregated, then divided by the non-zero weight
+        assert!(actual.contains("o_price * "), "{actual}");
+        assert!(actual.contains("nullif(sum("), "{actual}");
+        let result = ctx.sql(&actual).await?.collect().await?;
+        assert_batches_eq!(
+            [
+                "+--------+-----------+",
+                "| c_name | avg_price |",
+                "+--------+-----------+",
+                "| Azki   |           |",
+                "| Gura   | 17.5      |",
+                "+--------+-----------+",
+            ],
+            &result
+        );
+        Ok(())
+    }
+}
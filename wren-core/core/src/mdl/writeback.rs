@@ -0,0 +1,306 @@
+use crate::mdl::dialect::WrenDialect;
+use crate::mdl::manifest::DataSource;
+use crate::mdl::snippet::parse_object_name_snippet;
+use crate::mdl::AnalyzedWrenMDL;
+use crate::WrenError;
+use datafusion::common::{plan_err, Result};
+use datafusion::logical_expr::LogicalPlan;
+use datafusion::sql::sqlparser::ast::Ident;
+use datafusion::sql::sqlparser::tokenizer::Span;
+use datafusion::sql::unparser::dialect::Dialect;
+use datafusion::sql::unparser::Unparser;
+
+const TARGET_ALIAS: &str = "target";
+const SOURCE_ALIAS: &str = "source";
+
+/// [UpsertPlan] writes the rows of the source plan back to the table of a model.
+///
+/// The rows are matched by the primary key of the model. The matched rows are updated and the others
+/// are inserted. The plan is emitted as a standard `MERGE` statement for BigQuery, MSSQL, Postgres,
+/// Snowflake and Trino, or as `INSERT ... ON DUPLICATE KEY UPDATE` for MySQL, which doesn't support
+/// `MERGE`. The other data sources can't be written back.
+#[derive(Debug, Clone)]
+pub struct UpsertPlan {
+    pub model: String,
+    /// The table reference of the model
+    pub target: String,
+    pub key_columns: Vec<String>,
+    /// The non-key columns provided by the source
+    pub update_columns: Vec<String>,
+    pub source: LogicalPlan,
+}
+
+impl UpsertPlan {
+    /// Generate the upsert statement with the dialect of the data source
+    pub fn to_sql(&self, analyzed_mdl: &AnalyzedWrenMDL) -> Result<String> {
+        let data_source = analyzed_mdl.wren_mdl().data_source().unwrap_or_default();
+        if !supports_upsert(&data_source) {
+            return plan_err!(
+                "The upsert isn't supported by the data source {data_source}"
+            );
+        }
+        let dialect = WrenDialect::new(&data_source)
+            .with_minimal_quoting(analyzed_mdl.options().minimal_quoting);
+        let source = Unparser::new(&dialect).plan_to_sql(&self.source)?;
+        let ident = |name: &str| {
+            Ident {
+                value: name.to_string(),
+                quote_style: dialect.identifier_quote_style(name),
+                span: Span::empty(),
+            }
+            .to_string()
+        };
+        let qualified = |alias: &str, column: &str| format!("{alias}.{}", ident(column));
+        let mut target = parse_object_name_snippet(&self.target)?;
+        target.0.iter_mut().for_each(|part| {
+            if part.quote_style.is_none() {
+                part.quote_style = dialect.identifier_quote_style(&part.value);
+            }
+        });
+
+        let columns = self
+            .key_columns
+            .iter()
+            .chain(self.update_columns.iter())
+            .collect::<Vec<_>>();
+        let insert_columns = columns
+            .iter()
+            .map(|c| ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let insert_values = columns
+            .iter()
+            .map(|c| qualified(SOURCE_ALIAS, c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let assignments = self
+            .update_columns
+            .iter()
+            .map(|c| format!("{} = {}", ident(c), qualified(SOURCE_ALIAS, c)))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        if data_source == DataSource::MySQL {
+            // the duplicate key is kept as it is if there's nothing to update
+            let assignments = if assignments.is_empty() {
+                let key = ident(&self.key_columns[0]);
+                format!("{key} = {key}")
+            } else {
+                assignments
+            };
+            return Ok(format!(
+                "INSERT INTO {target} ({insert_columns}) SELECT {insert_values} FROM ({source}) \
+                AS {SOURCE_ALIAS} ON DUPLICATE KEY UPDATE {assignments}"
+            ));
+        }
+
+        let on = self
+            .key_columns
+            .iter()
+            .map(|c| {
+                format!(
+                    "{} = {}",
+                    qualified(TARGET_ALIAS, c),
+                    qualified(SOURCE_ALIAS, c)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(" AND ");
+        let mut sql = format!(
+            "MERGE INTO {target} AS {TARGET_ALIAS} USING ({source}) AS {SOURCE_ALIAS} ON {on}"
+        );
+        if !assignments.is_empty() {
+            sql.push_str(&format!(" WHEN MATCHED THEN UPDATE SET {assignments}"));
+        }
+        sql.push_str(&format!(
+            " WHEN NOT MATCHED THEN INSERT ({insert_columns}) VALUES ({insert_values})"
+        ));
+        Ok(sql)
+    }
+}
+
+/// The data sources supporting `MERGE`, or `INSERT ... ON DUPLICATE KEY UPDATE` for MySQL
+fn supports_upsert(data_source: &DataSource) -> bool {
+    matches!(
+        data_source,
+        DataSource::BigQuery
+            | DataSource::MSSQL
+            | DataSource::MySQL
+            | DataSource::Postgres
+            | DataSource::Snowflake
+            | DataSource::Trino
+    )
+}
+
+/// Create the [UpsertPlan] writing the output of the source plan to the table of the model.
+///
+/// The output columns of the source should be the physical columns of the model and include
+/// the primary key. The model should be based on a table reference and declare its primary key.
+pub fn create_upsert_plan(
+    analyzed_mdl: &AnalyzedWrenMDL,
+    model_name: &str,
+    source: LogicalPlan,
+) -> Result<UpsertPlan> {
+    let Some(model) = analyzed_mdl.wren_mdl().get_model(model_name) else {
+        return Err(WrenError::ModelNotFound(model_name.to_string()).into());
+    };
+    let Some(primary_key) = model.primary_key() else {
+        return Err(WrenError::MissingPrimaryKey(model_name.to_string()).into());
+    };
+    if model.table_reference.is_none() {
+        return plan_err!("The model {model_name} isn't based on a table to write back");
+    }
+
+    let mut update_columns = vec![];
+    let mut has_key = false;
+    for field in source.schema().fields() {
+        let Some(column) = model.get_column(field.name()) else {
+            return plan_err!("The column {} isn't found in {model_name}", field.name());
+        };
+        if column.is_calculated
+            || column.relationship.is_some()
+            || column.expression.is_some()
+        {
+            return plan_err!(
+                "The column {model_name}.{} isn't a physical column to write back",
+                column.name()
+            );
+        }
+        if column.name() == primary_key {
+            has_key = true;
+        } else {
+            update_columns.push(column.name().to_string());
+        }
+    }
+    if !has_key {
+        return plan_err!(
+            "The source doesn't provide the primary key {primary_key} of {model_name}"
+        );
+    }
+    Ok(UpsertPlan {
+        model: model_name.to_string(),
+        target: model.table_reference().to_string(),
+        key_columns: vec![primary_key.to_string()],
+        update_columns,
+        source,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::mdl::builder::{ColumnBuilder, ManifestBuilder, ModelBuilder};
+    use crate::mdl::manifest::{DataSource, Manifest};
+    use crate::mdl::writeback::create_upsert_plan;
+    use crate::mdl::AnalyzedWrenMDL;
+    use crate::WrenError;
+    use datafusion::common::Result;
+    use datafusion::prelude::SessionContext;
+
+    fn manifest(data_source: DataSource) -> Manifest {
+        ManifestBuilder::new()
+            .catalog("wren")
+            .schema("test")
+            .data_source(data_source)
+            .model(
+                ModelBuilder::new("customer_spend")
+                    .table_reference("analytics.customer_spend")
+                    .column(ColumnBuilder::new("c_custkey", "bigint").build())
+                    .column(ColumnBuilder::new("spend", "double").build())
+                    .column(ColumnBuilder::new("order", "bigint").build())
+                    .primary_key("c_custkey")
+                    .build(),
+            )
+            .model(
+                ModelBuilder::new("events")
+                    .table_reference("analytics.events")
+                    .column(ColumnBuilder::new("e_name", "varchar").build())
+                    .build(),
+            )
+            .build()
+    }
+
+    #[tokio::test]
+    async fn test_create_upsert_plan() -> Result<()> {
+        let analyzed_mdl = AnalyzedWrenMDL::analyze(manifest(DataSource::Postgres))?;
+        let ctx = SessionContext::new();
+        let source = ctx
+            .sql("SELECT 1 AS c_custkey, 100.0 AS spend")
+            .await?
+            .into_unoptimized_plan();
+
+        let upsert = create_upsert_plan(&analyzed_mdl, "customer_spend", source.clone())?;
+        assert_eq!(upsert.key_columns, vec!["c_custkey".to_string()]);
+        assert_eq!(upsert.update_columns, vec!["spend".to_string()]);
+        let sql = upsert.to_sql(&analyzed_mdl)?;
+        assert!(
+            sql.starts_with("MERGE INTO analytics.customer_spend AS target USING ("),
+            "{sql}"
+        );
+        assert!(
+            sql.contains("ON target.c_custkey = source.c_custkey"),
+            "{sql}"
+        );
+        assert!(
+            sql.ends_with(
+                "WHEN MATCHED THEN UPDATE SET spend = source.spend WHEN NOT MATCHED THEN \
+                INSERT (c_custkey, spend) VALUES (source.c_custkey, source.spend)"
+            ),
+            "{sql}"
+        );
+
+        // MySQL doesn't support MERGE, the duplicate keys are updated instead
+        let analyzed_mdl = AnalyzedWrenMDL::analyze(manifest(DataSource::MySQL))?;
+        let upsert = create_upsert_plan(&analyzed_mdl, "customer_spend", source)?;
+        let sql = upsert.to_sql(&analyzed_mdl)?;
+        assert!(
+            sql.starts_with(
+                "INSERT INTO analytics.customer_spend (c_custkey, spend) \
+                SELECT source.c_custkey, source.spend FROM ("
+            ),
+            "{sql}"
+        );
+        assert!(
+            sql.ends_with(") AS source ON DUPLICATE KEY UPDATE spend = source.spend"),
+            "{sql}"
+        );
+
+        // the identifiers are quoted by the quote character of the data source
+        let analyzed_mdl = AnalyzedWrenMDL::analyze(manifest(DataSource::BigQuery))?
+            .with_minimal_quoting();
+        let source = ctx
+            .sql(r#"SELECT 1 AS c_custkey, 2 AS "order""#)
+            .await?
+            .into_unoptimized_plan();
+        let upsert = create_upsert_plan(&analyzed_mdl, "customer_spend", source)?;
+        let sql = upsert.to_sql(&analyzed_mdl)?;
+        assert!(
+            sql.ends_with(
+                "WHEN MATCHED THEN UPDATE SET `order` = source.`order` WHEN NOT MATCHED THEN \
+                INSERT (c_custkey, `order`) VALUES (source.c_custkey, source.`order`)"
+            ),
+            "{sql}"
+        );
+
+        // the data source without MERGE can't be written back
+        let analyzed_mdl = AnalyzedWrenMDL::analyze(manifest(DataSource::Clickhouse))?;
+        let upsert = create_upsert_plan(&analyzed_mdl, "customer_spend", upsert.source)?;
+        let err = upsert.to_sql(&analyzed_mdl).unwrap_err();
+        assert!(
+            err.to_string()
+                .contains("The upsert isn't supported by the data source CLICKHOUSE"),
+            "{err}"
+        );
+
+        // the model without the primary key can't be upserted
+        let source = ctx
+            .sql("SELECT 'click' AS e_name")
+            .await?
+            .into_unoptimized_plan();
+        let err = create_upsert_plan(&analyzed_mdl, "events", source).unwrap_err();
+        assert_eq!(
+            WrenError::find_in(&err),
+            Some(&WrenError::MissingPrimaryKey("events".to_string()))
+        );
+        Ok(())
+    }
+}